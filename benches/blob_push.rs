@@ -0,0 +1,31 @@
+//! Shows `Blob::push` costs the same whatever the size of the blob it appends to
+//!
+//! Run with `cargo bench --bench blob_push`
+use byte_array_blob::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+
+fn time_pushes(base_bytes: usize) {
+	let chunk = vec![7u8; 64 * 1024];
+	let pushes = 10_000;
+	let mut blob = Blob::with_capacity(base_bytes + pushes * 68, base_bytes / chunk.len() + pushes);
+	while blob.as_bytes().len() < base_bytes {
+		blob.push(&chunk);
+	}
+
+	let entry = [1u8; 64];
+	let start = Instant::now();
+	for _ in 0..pushes {
+		blob.push(black_box(&entry));
+	}
+	let elapsed = start.elapsed();
+	black_box(&blob);
+	println!("push onto {:>4} MB blob {:>8.2} ns/push", base_bytes / (1024 * 1024), elapsed.as_nanos() as f64 / pushes as f64);
+}
+
+
+fn main() {
+	time_pushes(1024 * 1024);
+	time_pushes(100 * 1024 * 1024);
+}
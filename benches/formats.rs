@@ -0,0 +1,61 @@
+//! Compares encoding and decoding every `BlobVariant` on three shapes of workload, and random access through each view type
+//!
+//! The entries come from `testutil` with fixed seeds, so every run measures the same data. Run with `cargo bench --bench formats`
+use byte_array_blob::testutil::*;
+use byte_array_blob::*;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+
+/// Runs `f` until at least 200ms have passed and prints the mean time per call, and per each of the `entries` it handles
+fn time(name: &str, entries: usize, mut f: impl FnMut() -> usize) {
+	let mut iterations = 0u32;
+	let mut total = 0;
+	let start = Instant::now();
+	while start.elapsed() < Duration::from_millis(200) {
+		total += f();
+		iterations += 1;
+	}
+	black_box(total);
+	let per_call = start.elapsed().as_nanos() as f64 / iterations as f64;
+	println!("{:<36} {:>12.2} us {:>12.2} ns/entry", name, per_call / 1000.0, per_call / entries as f64);
+}
+
+
+fn main() {
+	let workloads = [
+		("tiny", GenSpec { entries: 100_000, min_len: 0, max_len: 16, compressible: false }),
+		("huge", GenSpec { entries: 8, min_len: 1 << 20, max_len: 4 << 20, compressible: true }),
+		("mixed", GenSpec { entries: 2_000, min_len: 0, max_len: 8192, compressible: true })
+	];
+
+	for (seed, (workload, spec)) in workloads.iter().enumerate() {
+		let entries = generate_entries(seed as u64, spec);
+		let slices: Vec<&[u8]> = entries.iter().map(|e| e.as_slice()).collect();
+		println!("{} ({} entries, {} payload bytes)", workload, slices.len(), slices.iter().map(|s| s.len()).sum::<usize>());
+
+		for variant in BlobVariant::ALL {
+			let blob = variant.encode(&slices);
+			time(&format!("  encode {}", variant.name()), slices.len(), || variant.encode(black_box(&slices)).len());
+			time(&format!("  decode {}", variant.name()), slices.len(), || variant.decode(black_box(&blob)).unwrap().len());
+		}
+
+		// 100 indexes scattered over the blob, in no particular order
+		let indices: Vec<usize> = (0..100).map(|i| i * 7919 % slices.len()).collect();
+		let blob = byte_arrays_to_blob(&slices);
+		let view = BlobView::new(&blob).unwrap();
+		let validated = ValidatedBlob::validate(&blob).unwrap();
+		let (first, second) = slices.split_at(slices.len() / 2);
+		let parts = [byte_arrays_to_blob(first), byte_arrays_to_blob(second)];
+		let multi = MultiBlobView::new(parts.iter().map(|part| part.as_slice()).collect()).unwrap();
+		let compressed_blob = byte_arrays_to_compressed_blob(&slices);
+		let compressed = CompressedBlobView::new(&compressed_blob).unwrap();
+
+		time("  100 x BlobView::get", indices.len(), || indices.iter().filter_map(|&i| black_box(&view).get(i)).count());
+		time("  100 x ValidatedBlob::get", indices.len(), || indices.iter().filter_map(|&i| black_box(&validated).get(i)).count());
+		time("  100 x MultiBlobView::get", indices.len(), || indices.iter().filter_map(|&i| black_box(&multi).get(i)).count());
+		time("  100 x CompressedBlobView read", indices.len(), || {
+			indices.iter().map(|&i| black_box(&compressed).get(i).unwrap().read().unwrap().len()).sum()
+		});
+	}
+}
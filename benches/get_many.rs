@@ -0,0 +1,28 @@
+//! Compares fetching scattered slices of a large blob one at a time and all at once with `blob_get_many`
+//!
+//! Run with `cargo bench --bench get_many`
+use byte_array_blob::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+
+fn time(name: &str, iterations: usize, mut f: impl FnMut() -> usize) {
+	let start = Instant::now();
+	let mut total = 0;
+	for _ in 0..iterations {
+		total += f();
+	}
+	black_box(total);
+	println!("{:<24} {:>12.2} us per 50 lookups", name, start.elapsed().as_nanos() as f64 / iterations as f64 / 1000.0);
+}
+
+
+fn main() {
+	let entries: Vec<Vec<u8>> = (0..100_000u32).map(|i| i.to_le_bytes().repeat(1 + i as usize % 8)).collect();
+	let blob = byte_arrays_to_blob(&entries.iter().map(|e| e.as_slice()).collect::<Vec<_>>());
+	// 50 indexes scattered over the blob, in no particular order
+	let indices: Vec<usize> = (0..50).map(|i| i * 7919 % entries.len()).collect();
+
+	time("repeated nth", 20, || indices.iter().filter_map(|&i| blob_iter(black_box(&blob)).nth(i)).count());
+	time("blob_get_many", 20, || blob_get_many(black_box(&blob), &indices).unwrap().len());
+}
@@ -0,0 +1,43 @@
+//! Compares decoding a 2 slice blob into a `Vec` and into a stack only collection
+//!
+//! Run with `cargo bench --bench small_decode`
+use byte_array_blob::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+
+/// Up to 4 slices stored inline, like `ArrayVec<[&[u8]; 4]>`
+#[derive(Default)]
+struct FourSlices<'a> {
+	items: [&'a [u8]; 4],
+	len: usize
+}
+
+impl<'a> Extend<&'a [u8]> for FourSlices<'a> {
+	fn extend<I: IntoIterator<Item = &'a [u8]>>(&mut self, iter: I) {
+		for item in iter {
+			self.items[self.len] = item;
+			self.len += 1;
+		}
+	}
+}
+
+
+fn time(name: &str, iterations: usize, mut f: impl FnMut() -> usize) {
+	let start = Instant::now();
+	let mut total = 0;
+	for _ in 0..iterations {
+		total += f();
+	}
+	black_box(total);
+	println!("{:<32} {:>8.2} ns/decode", name, start.elapsed().as_nanos() as f64 / iterations as f64);
+}
+
+
+fn main() {
+	let blob = byte_arrays_to_blob(&[b"header", b"a short message body"]);
+
+	time("blob_to_byte_arrays", 10_000_000, || blob_to_byte_arrays(black_box(&blob)).unwrap().len());
+	time("blob_to_collection::<Vec>", 10_000_000, || blob_to_collection::<Vec<&[u8]>>(black_box(&blob)).unwrap().len());
+	time("blob_to_collection::<FourSlices>", 10_000_000, || blob_to_collection::<FourSlices>(black_box(&blob)).unwrap().len);
+}
@@ -0,0 +1,30 @@
+//! Compares random access through `ValidatedBlob`, `BlobView` and walking the indexes with `blob_iter`
+//!
+//! Run with `cargo bench --bench validated_get`
+use byte_array_blob::*;
+use std::hint::black_box;
+use std::time::Instant;
+
+
+fn time(name: &str, iterations: usize, mut f: impl FnMut(usize) -> usize) {
+	let start = Instant::now();
+	let mut total = 0;
+	for i in 0..iterations {
+		total += f(i);
+	}
+	black_box(total);
+	println!("{:<24} {:>8.2} ns/get", name, start.elapsed().as_nanos() as f64 / iterations as f64);
+}
+
+
+fn main() {
+	let entries: Vec<Vec<u8>> = (0..4096).map(|i| vec![i as u8; i % 64]).collect();
+	let blob = byte_arrays_to_blob(&entries.iter().map(|e| e.as_slice()).collect::<Vec<_>>());
+	let validated = ValidatedBlob::validate(&blob).unwrap();
+	let view = BlobView::new(&blob).unwrap();
+	let index = |i: usize| (i * 2654435761) % entries.len();
+
+	time("ValidatedBlob::get", 10_000_000, |i| validated.get(black_box(index(i))).unwrap().len());
+	time("BlobView::get", 10_000_000, |i| view.get(black_box(index(i))).unwrap().len());
+	time("blob_iter().nth()", 10_000, |i| blob_iter(&blob).nth(black_box(index(i))).unwrap().unwrap().len());
+}
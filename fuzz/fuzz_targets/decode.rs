@@ -0,0 +1,154 @@
+#![no_main]
+//! Feeds raw bytes to every public decode function. Run with `cargo fuzz run decode` from the repository root.
+use libfuzzer_sys::fuzz_target;
+use byte_array_blob::*;
+
+fn assert_within(data: &[u8], slice: &[u8]) {
+	let range = data.as_ptr_range();
+	assert!(range.start <= slice.as_ptr() && slice.as_ptr_range().end <= range.end);
+}
+
+fuzz_target!(|data: &[u8]| {
+	if let Ok(slices) = blob_to_byte_arrays(data) {
+		assert!(looks_like_blob(data));
+		for slice in slices {
+			assert_within(data, slice);
+		}
+	}
+
+	for slice in blob_iter(data).flatten() {
+		assert_within(data, slice);
+	}
+
+	let _ = blob_to_byte_vecs(data);
+	let _ = blob_to_byte_vecs_bounded(data, 64);
+	for entry in BlobReader::new(data).with_budget(1 << 16) {
+		if entry.is_err() {
+			break;
+		}
+	}
+
+	let mut decoder = BlobDecoder::new().with_budget(1 << 16);
+	for chunk in data.chunks(3) {
+		if decoder.push(chunk).is_err() {
+			break;
+		}
+		while decoder.next_entry().is_some() {}
+	}
+	let _ = decoder.finish();
+
+	if let Ok(entries) = blob_to_typed_byte_arrays(data) {
+		for (tag, payload) in entries {
+			assert_within(data, tag.as_bytes());
+			assert_within(data, payload);
+		}
+	}
+
+	if let Ok(entries) = blob_to_archive(data) {
+		for entry in entries {
+			assert_within(data, entry.name);
+			assert_within(data, entry.data);
+		}
+	}
+
+	if let Ok(view) = BlobView::new(data) {
+		for slice in view.iter().rev() {
+			assert_within(data, slice);
+		}
+	}
+	let _ = blob_last_array(data);
+
+	if let Ok(view) = CompressedBlobView::new(data) {
+		for handle in view.iter() {
+			if handle.decompressed_len() <= 1 << 16 {
+				let _ = handle.read();
+				let _ = std::io::copy(&mut handle.reader(), &mut std::io::sink());
+			}
+		}
+	}
+
+	let _ = decode_any(data);
+	for format in BlobFormat::ALL {
+		let _ = blob_to_byte_arrays_with_format(data, format);
+	}
+
+	// Use a short prefix of the input as the needle for the search functions
+	let needle = &data[..data.len().min(2)];
+	let _ = blob_position_of(data, needle);
+	let _ = blob_contains(data, needle);
+	let _ = blob_positions_of(data, needle);
+	let _ = recover_blob(data);
+	let _ = collect_frames(data);
+	let _ = blob_find_named(data, "");
+	if let Ok(keys) = blob_named_keys(data) {
+		let _ = keys.count();
+	}
+	let _ = blob_to_fixed_arrays::<8>(data);
+	assert_eq!(count_arrays(data).ok(), blob_to_byte_arrays(data).ok().map(|arrays| arrays.len()));
+	let _ = blob_skip_padding(data);
+	let _ = blob_to_numeric_arrays::<f64>(data, Endianness::Big);
+	let _ = verify_blob_stream(&mut &data[..]);
+	let _ = blob_sparse_to_byte_arrays_with_options(data, &DecodeOptions::new().with_max_entries(1 << 16));
+	if let Ok(view) = NamedBlobView::new_unchecked_order(data) {
+		let _ = view.is_sorted();
+		let _ = view.get("");
+	}
+	if let Ok(view) = MultiBlobView::new(vec![data, data]) {
+		let _ = view.get(view.len() / 2);
+	}
+	let mut cursor = BlobCursor::new(data);
+	while let Ok(Some(_)) = cursor.peek_len() {
+		let _ = cursor.skip();
+	}
+	if let Ok(validated) = ValidatedBlob::validate(data) {
+		let _ = validated.iter().rev().count();
+	}
+	if let Ok(overlay) = BlobOverlay::load_overlay(data, data) {
+		let _ = overlay.materialize();
+	}
+	if let Ok(json) = std::str::from_utf8(data) {
+		let _ = canonical_json_to_blob(json);
+	}
+	let _ = blob_to_canonical_json(data);
+	if let Ok(Some(location)) = blob_entry_at_offset(data, data.len() / 2) {
+		assert!(location.payload_range.end <= data.len());
+	}
+	if let Ok(sections) = Sections::parse(data) {
+		let _ = sections.get(SectionId(0));
+	}
+	if let Ok(strs) = blob_to_interned_strs(data) {
+		for s in strs {
+			assert_within(data, s.as_bytes());
+		}
+	}
+	if let Ok(byte_arrays) = blob_to_byte_arrays(data) {
+		assert_eq!(blob_content_hash(data), Ok(entries_content_hash(&byte_arrays)));
+	}
+	if let Ok(streams) = blob_deinterleave(data, 3) {
+		assert_eq!(streams.len(), 3);
+	}
+	if let Ok(found) = blob_get_many(data, &[0, 3, 1, usize::MAX]) {
+		for slice in found.into_iter().flatten() {
+			assert_within(data, slice);
+		}
+	}
+	let _ = blob_to_arena(data);
+	if let Ok(entries) = blob_to_aligned_entries(data, 64) {
+		assert!(entries.iter().all(|entry| entry.as_ptr() as usize % 64 == 0));
+	}
+	let _ = blob_to_byte_vecs_with_codec(data, &PackBitsCodec);
+	if let Ok(view) = SplitBlobView::from_blob(data) {
+		let _ = view.iter().count();
+	}
+	let sniffed = blob_sniff(data, 8);
+	assert!(sniffed.bytes_covered <= data.len());
+	if let Ok(Some(tag)) = blob_schema_tag(data) {
+		let _ = blob_to_byte_arrays_tagged(data, tag);
+	}
+	let _ = blob_to_byte_arrays_redacted(data);
+	let _ = blob_redact(data);
+	if let Ok(view) = EncryptedBlobView::new(data) {
+		assert!(view.get(view.len(), &HmacSha256Cipher::new(b"key")).unwrap().is_none());
+		let _ = view.decrypt_range(0..view.len(), &HmacSha256Cipher::new(b"key"));
+	}
+});
@@ -0,0 +1,191 @@
+//! `AlignedEntry`, an owned copy of an entry in an allocation with a chosen alignment, for APIs such as GPU uploads that need more than a `Vec<u8>` gives
+use crate::*;
+use std::alloc::{self, Layout};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+
+
+/// An owned entry whose bytes start at a multiple of a chosen alignment, created by `blob_to_aligned_entries`
+///
+/// The bytes live in their own allocation made with that alignment, which is freed when the entry is dropped.
+/// An empty entry allocates nothing, and its pointer is a dangling one that is still aligned.
+pub struct AlignedEntry {
+	ptr: NonNull<u8>,
+	len: usize,
+	align: usize
+}
+
+
+// SAFETY: `AlignedEntry` owns its allocation and nothing else points into it, the same as a `Box<[u8]>`
+unsafe impl Send for AlignedEntry {}
+unsafe impl Sync for AlignedEntry {}
+
+
+impl AlignedEntry {
+	/// Copies `bytes` into a new allocation aligned to `align`, or returns `None` if the layout is too large for the platform
+	fn copy_from(bytes: &[u8], align: usize) -> Option<Self> {
+		let layout = Layout::from_size_align(bytes.len(), align).ok()?;
+		if bytes.is_empty() {
+			// `align` is a non-zero power of two, so a pointer to address `align` is aligned and never null
+			let ptr = NonNull::new(ptr::null_mut::<u8>().wrapping_add(align))?;
+			return Some(AlignedEntry { ptr, len: 0, align });
+		}
+
+		// SAFETY: the layout has a non-zero size
+		let ptr = match NonNull::new(unsafe { alloc::alloc(layout) }) {
+			Some(ptr) => ptr,
+			None => alloc::handle_alloc_error(layout)
+		};
+		// SAFETY: the allocation is `bytes.len()` bytes long and new, so it can't overlap `bytes`
+		unsafe { ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len()) };
+		Some(AlignedEntry { ptr, len: bytes.len(), align })
+	}
+
+	/// Returns a pointer to the first byte, which is always a multiple of `align()`, even for an empty entry
+	pub fn as_ptr(&self) -> *const u8 {
+		self.ptr.as_ptr()
+	}
+
+	/// Returns a mutable pointer to the first byte. See `as_ptr`
+	pub fn as_mut_ptr(&mut self) -> *mut u8 {
+		self.ptr.as_ptr()
+	}
+
+	/// Returns the alignment the entry was allocated with
+	pub fn align(&self) -> usize {
+		self.align
+	}
+}
+
+
+impl Deref for AlignedEntry {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		// SAFETY: `ptr` is valid for `len` initialised bytes that this entry owns, or dangling but aligned and non-null when `len` is 0
+		unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+	}
+}
+
+impl DerefMut for AlignedEntry {
+	fn deref_mut(&mut self) -> &mut [u8] {
+		// SAFETY: as for `deref`, and `&mut self` means nothing else is borrowing the bytes
+		unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+	}
+}
+
+impl Drop for AlignedEntry {
+	fn drop(&mut self) {
+		if self.len == 0 {
+			return;
+		}
+		// SAFETY: a non-empty entry was allocated in `copy_from` with exactly this size and alignment, which were checked to make a valid layout then
+		unsafe { alloc::dealloc(self.ptr.as_ptr(), Layout::from_size_align_unchecked(self.len, self.align)) }
+	}
+}
+
+impl Clone for AlignedEntry {
+	fn clone(&self) -> Self {
+		AlignedEntry::copy_from(self, self.align).expect("the same size and alignment already made a valid layout")
+	}
+}
+
+impl PartialEq for AlignedEntry {
+	fn eq(&self, other: &Self) -> bool {
+		**self == **other
+	}
+}
+
+impl Eq for AlignedEntry {}
+
+impl fmt::Debug for AlignedEntry {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		formatter.debug_struct("AlignedEntry").field("align", &self.align).field("bytes", &&**self).finish()
+	}
+}
+
+impl AsRef<[u8]> for AlignedEntry {
+	fn as_ref(&self) -> &[u8] {
+		self
+	}
+}
+
+
+/// Converts a blob u8 slice to a Vec of owned entries, each copied into its own allocation aligned to `align` bytes
+///
+/// # Panics
+///
+/// Panics if `align` is not a power of two
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::TooLarge)` if an entry rounded up to `align` would be too large to allocate, otherwise the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"vertices", b""]);
+/// let entries = blob_to_aligned_entries(&blob, 256).unwrap();
+///
+/// assert_eq!(&entries[0][..], b"vertices");
+/// assert!(entries.iter().all(|entry| entry.as_ptr() as usize % 256 == 0));
+/// ```
+pub fn blob_to_aligned_entries(blob: &[u8], align: usize) -> Result<Vec<AlignedEntry>, BlobReadError> {
+	assert!(align.is_power_of_two(), "alignment must be a power of two");
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	byte_arrays.into_iter().map(|byte_arr| AlignedEntry::copy_from(byte_arr, align).ok_or(BlobReadError::TooLarge)).collect()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Kept small so the tests also run quickly under `cargo +nightly miri test aligned`
+	#[test]
+	fn entries_are_aligned() {
+		let blob = byte_arrays_to_blob(&[b"one", &[], &[7u8; 300], b"x"]);
+		for align in [1, 2, 8, 64, 256, 4096] {
+			let entries = blob_to_aligned_entries(&blob, align).unwrap();
+			assert_eq!(entries.len(), 4);
+			for (entry, byte_arr) in entries.iter().zip(blob_iter(&blob)) {
+				assert_eq!(entry.as_ptr() as usize % align, 0, "align {}", align);
+				assert_eq!(entry.align(), align);
+				assert_eq!(&entry[..], byte_arr.unwrap());
+			}
+		}
+	}
+
+	#[test]
+	fn entries_own_their_bytes() {
+		let blob = byte_arrays_to_blob(&[b"abc", &[]]);
+		let mut entries = blob_to_aligned_entries(&blob, 256).unwrap();
+		let copy = entries.clone();
+		entries[0][1] = b'X';
+		assert_eq!(&entries[0][..], b"aXc");
+		assert_eq!(&copy[0][..], b"abc");
+		assert_ne!(entries[0], copy[0]);
+		assert_eq!(entries[1], copy[1]);
+
+		// An empty entry is still dangling but aligned after a clone, and dropping it frees nothing
+		assert!(copy[1].is_empty());
+		assert_eq!(copy[1].as_ptr() as usize % 256, 0);
+		assert_eq!(format!("{:?}", entries[1]), "AlignedEntry { align: 256, bytes: [] }");
+		drop(entries);
+	}
+
+	#[test]
+	fn invalid_blob() {
+		assert_eq!(blob_to_aligned_entries(&[255u8; 4], 16).unwrap_err(), BlobReadError::NotABlob { first_header: u32::MAX });
+		assert!(blob_to_aligned_entries(&[], 16).unwrap().is_empty());
+	}
+
+	#[test]
+	#[should_panic(expected = "power of two")]
+	fn alignment_must_be_a_power_of_two() {
+		let _ = blob_to_aligned_entries(&[], 24);
+	}
+}
@@ -0,0 +1,490 @@
+//! Archive blobs, which store a name and optional file metadata alongside each payload
+//!
+//! # Layout
+//!
+//! An archive blob is an ordinary blob holding three slices per entry: the name, the metadata, and the payload.
+//!
+//! Names are raw bytes, so file names that aren't valid UTF-8 survive a round trip. On unix the bytes are the file name exactly, as given by `OsStrExt`.
+//! Elsewhere names are UTF-8: a Windows file name is stored as the WTF-8 form of its UTF-16, which is plain UTF-8 unless the name holds
+//! an unpaired surrogate, and names with unpaired surrogates are rejected when packing rather than being stored in a form other platforms can't read.
+//!
+//! The metadata slice starts with a flags byte. If bit 0 is set, a little-endian u64 modification time (seconds since the unix epoch) follows.
+//! If bit 1 is set, a little-endian u32 permission mode follows that.
+use crate::*;
+use std::borrow::Cow;
+use std::ffi::OsStr;
+
+#[cfg(feature = "fs")]
+use std::{fs, io, path::{Component, Path, PathBuf}, time::{Duration, UNIX_EPOCH}};
+
+
+const HAS_MTIME: u8 = 1;
+const HAS_MODE: u8 = 2;
+
+
+/// Errors that can occur when turning an entry name into an `OsStr`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NameError {
+	/// The name is not valid UTF-8, and this platform's file names are not raw bytes
+	NotUnicode
+}
+
+
+impl std::error::Error for NameError {}
+
+impl fmt::Display for NameError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// An owned entry to be written to an archive blob
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ArchiveEntry {
+	/// Name of the entry, as raw bytes. When unpacking to a directory this is a relative path using `/` as the separator
+	pub name: Vec<u8>,
+	/// Modification time in seconds since the unix epoch
+	pub mtime: Option<u64>,
+	/// Unix permission bits
+	pub mode: Option<u32>,
+	pub data: Vec<u8>
+}
+
+
+/// An entry read from an archive blob, borrowing the blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArchiveEntryRef<'a> {
+	pub name: &'a [u8],
+	pub mtime: Option<u64>,
+	pub mode: Option<u32>,
+	pub data: &'a [u8]
+}
+
+
+impl ArchiveEntry {
+	/// Borrows the entry as an `ArchiveEntryRef`
+	pub fn as_ref(&self) -> ArchiveEntryRef<'_> {
+		ArchiveEntryRef { name: &self.name, mtime: self.mtime, mode: self.mode, data: &self.data }
+	}
+}
+
+
+impl<'a> ArchiveEntryRef<'a> {
+	/// Returns the name as an `OsStr`, which never fails on unix
+	///
+	/// # Errors
+	///
+	/// Returns `Err(NameError::NotUnicode)` on platforms other than unix if the name is not valid UTF-8
+	pub fn name_os(&self) -> Result<&'a OsStr, NameError> {
+		name_to_os(self.name)
+	}
+
+	/// Returns the name as a string, with anything that isn't valid UTF-8 replaced by `U+FFFD`
+	pub fn name_lossy(&self) -> Cow<'a, str> {
+		String::from_utf8_lossy(self.name)
+	}
+}
+
+
+fn name_to_os(name: &[u8]) -> Result<&OsStr, NameError> {
+	#[cfg(unix)]
+	return Ok(std::os::unix::ffi::OsStrExt::from_bytes(name));
+	#[cfg(not(unix))]
+	return std::str::from_utf8(name).map(OsStr::new).map_err(|_| NameError::NotUnicode);
+}
+
+
+/// Turns a file name into the bytes stored in an archive, or returns `None` if it can't be stored losslessly
+#[cfg(feature = "fs")]
+fn os_to_name(name: &OsStr) -> Option<&[u8]> {
+	#[cfg(unix)]
+	return Some(std::os::unix::ffi::OsStrExt::as_bytes(name));
+	#[cfg(not(unix))]
+	return name.to_str().map(str::as_bytes);
+}
+
+
+fn encode_metadata(mtime: Option<u64>, mode: Option<u32>) -> Vec<u8> {
+	let mut metadata = vec![0u8];
+	if let Some(mtime) = mtime {
+		metadata[0] |= HAS_MTIME;
+		metadata.extend(mtime.to_le_bytes());
+	}
+	if let Some(mode) = mode {
+		metadata[0] |= HAS_MODE;
+		metadata.extend(mode.to_le_bytes());
+	}
+	metadata
+}
+
+
+fn decode_metadata(metadata: &[u8]) -> Option<(Option<u64>, Option<u32>)> {
+	let (&flags, mut rest) = metadata.split_first()?;
+	if flags & !(HAS_MTIME | HAS_MODE) != 0 {
+		return None;
+	}
+
+	let mut mtime = None;
+	if flags & HAS_MTIME != 0 {
+		let (bytes, tail) = rest.split_first_chunk::<8>()?;
+		mtime = Some(u64::from_le_bytes(*bytes));
+		rest = tail;
+	}
+
+	let mut mode = None;
+	if flags & HAS_MODE != 0 {
+		let (bytes, tail) = rest.split_first_chunk::<4>()?;
+		mode = Some(u32::from_le_bytes(*bytes));
+		rest = tail;
+	}
+
+	rest.is_empty().then_some((mtime, mode))
+}
+
+
+/// Converts a list of archive entries to an archive blob. See the module layout above
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let entries = [
+/// 	ArchiveEntry { name: b"run.sh".to_vec(), mtime: Some(1700000000), mode: Some(0o755), data: b"echo hi".to_vec() },
+/// 	ArchiveEntry { name: b"notes.txt".to_vec(), mtime: None, mode: None, data: Vec::new() }
+/// ];
+///
+/// let blob = archive_to_blob(&entries);
+/// let read = blob_to_archive(&blob).unwrap();
+/// assert_eq!(read[0], entries[0].as_ref());
+/// assert_eq!(read[1], entries[1].as_ref());
+/// assert_eq!(read[1].name_lossy(), "notes.txt");
+/// ```
+pub fn archive_to_blob(entries: &[ArchiveEntry]) -> Vec<u8> {
+	let metadata: Vec<Vec<u8>> = entries.iter().map(|entry| encode_metadata(entry.mtime, entry.mode)).collect();
+	let byte_arrays: Vec<&[u8]> = entries.iter().zip(&metadata)
+		.flat_map(|(entry, metadata)| [entry.name.as_slice(), metadata, &entry.data])
+		.collect();
+	byte_arrays_to_blob(&byte_arrays)
+}
+
+
+/// Converts an archive blob to a Vec of entries borrowing the blob
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InvalidMetadata)` with the entry index for a bad metadata slice,
+/// `Err(BlobReadError::UnpairedEntry)` if the last entry is incomplete, or any error from reading the underlying blob
+pub fn blob_to_archive(blob: &[u8]) -> Result<Vec<ArchiveEntryRef<'_>>, BlobReadError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	if byte_arrays.len() % 3 != 0 {
+		return Err(BlobReadError::UnpairedEntry(byte_arrays.len() / 3));
+	}
+
+	byte_arrays.chunks_exact(3).enumerate().map(|(i, group)| {
+		let (mtime, mode) = decode_metadata(group[1]).ok_or(BlobReadError::InvalidMetadata(i))?;
+		Ok(ArchiveEntryRef { name: group[0], mtime, mode, data: group[2] })
+	}).collect()
+}
+
+
+/// Reads every file under `dir` into archive entries, with names relative to `dir`.
+///
+/// Entries are sorted by name so the same tree always produces the same archive. Anything that is not a regular file or directory (such as a symlink) is skipped.
+/// Permission bits are only recorded on unix.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Io)` if the tree can not be read, or `Err(BlobFileError::UnsafePath)` if a file name can't be stored losslessly,
+/// which only happens off unix for a name that isn't valid Unicode
+#[cfg(feature = "fs")]
+pub fn pack_dir(dir: &Path) -> Result<Vec<ArchiveEntry>, BlobFileError> {
+	let mut entries = Vec::new();
+	pack_dir_recursive(dir, Vec::new(), &mut entries)?;
+	entries.sort_by(|a, b| a.name.cmp(&b.name));
+	Ok(entries)
+}
+
+
+#[cfg(feature = "fs")]
+fn pack_dir_recursive(dir: &Path, prefix: Vec<u8>, entries: &mut Vec<ArchiveEntry>) -> Result<(), BlobFileError> {
+	for dir_entry in fs::read_dir(dir)? {
+		let dir_entry = dir_entry?;
+		let file_name = dir_entry.file_name();
+		let file_name = os_to_name(&file_name).ok_or_else(|| BlobFileError::UnsafePath(file_name.to_string_lossy().into_owned()))?;
+		let name = [prefix.as_slice(), file_name].concat();
+
+		let metadata = fs::symlink_metadata(dir_entry.path())?;
+		if metadata.is_dir() {
+			pack_dir_recursive(&dir_entry.path(), [name.as_slice(), b"/"].concat(), entries)?;
+		}
+		else if metadata.is_file() {
+			let mtime = metadata.modified().ok()
+				.and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+				.map(|duration| duration.as_secs());
+
+			#[cfg(unix)]
+			let mode = Some(std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777);
+			#[cfg(not(unix))]
+			let mode = None;
+
+			entries.push(ArchiveEntry { name, mtime, mode, data: fs::read(dir_entry.path())? });
+		}
+	}
+	Ok(())
+}
+
+
+/// Turns an entry name into a path inside `dir`, rejecting names that could escape it
+///
+/// Each `/` separated part must be exactly one normal path component, so a part holding another separator for this platform
+/// (such as `\` on Windows), a drive prefix, or a NUL byte is rejected too.
+/// Long paths on Windows need no special handling here, since the standard library adds the `\\?\` prefix itself when a path is too long.
+#[cfg(feature = "fs")]
+fn safe_join(dir: &Path, name: &[u8]) -> Result<PathBuf, BlobFileError> {
+	let unsafe_path = || BlobFileError::UnsafePath(String::from_utf8_lossy(name).into_owned());
+	let mut path = dir.to_path_buf();
+	for part in name.split(|&byte| byte == b'/') {
+		let part = name_to_os(part).map_err(|_| unsafe_path())?;
+		let mut components = Path::new(part).components();
+		match (components.next(), components.next()) {
+			(Some(Component::Normal(component)), None) if component == part && os_to_name(part).is_some_and(|part| !part.contains(&0)) => path.push(component),
+			_ => return Err(unsafe_path())
+		}
+	}
+	Ok(path)
+}
+
+
+/// Writes every entry of an archive blob to a file under `dir`, creating directories as needed, and restores modification times and (on unix) permission bits.
+///
+/// Every name and modification time is checked before anything is written. Names that are absolute, contain `..` or `.` parts, or are otherwise not plain relative paths are rejected.
+/// Only the read, write and execute bits of a mode are restored, so an archive can't create setuid, setgid or sticky files.
+/// A symlink already under `dir` is never written through: a file or directory on the way to an entry that is a symlink is rejected as an unsafe path.
+/// This is checked just before each file is written, so it doesn't guard against another process adding symlinks to `dir` during the unpack.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Read)` if the blob is invalid or `Err(BlobFileError::Read(BlobReadError::InvalidMetadata))` with the entry index
+/// if a modification time can't be represented, `Err(BlobFileError::UnsafePath)` for a name that could escape `dir` or leads through a symlink,
+/// or `Err(BlobFileError::Io)` if writing fails
+#[cfg(feature = "fs")]
+pub fn unpack_archive_to_dir(blob: &[u8], dir: &Path) -> Result<(), BlobFileError> {
+	unpack_archive_to_dir_with_progress(blob, dir, |_| {})
+}
+
+
+/// Returns an error if anything under `dir` on the way to `path`, or `path` itself, is a symlink
+#[cfg(feature = "fs")]
+fn refuse_symlinks(dir: &Path, path: &Path, name: &[u8]) -> Result<(), BlobFileError> {
+	let mut checked = dir.to_path_buf();
+	// `path` was made by `safe_join`, so it is `dir` followed by normal components
+	for component in path.strip_prefix(dir).unwrap_or(path).components() {
+		checked.push(component);
+		match fs::symlink_metadata(&checked) {
+			Ok(metadata) if metadata.file_type().is_symlink() => return Err(BlobFileError::UnsafePath(String::from_utf8_lossy(name).into_owned())),
+			Ok(_) => {},
+			// Nothing further along can exist yet
+			Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+			Err(e) => return Err(e.into())
+		}
+	}
+	Ok(())
+}
+
+
+/// Unpacks an archive blob in the same way as `unpack_archive_to_dir`, calling `on_progress` after each file is written
+///
+/// Both totals are known, and `bytes_done` is how far through the blob the last written file ends.
+#[cfg(feature = "fs")]
+pub fn unpack_archive_to_dir_with_progress(blob: &[u8], dir: &Path, mut on_progress: impl FnMut(Progress)) -> Result<(), BlobFileError> {
+	let entries = blob_to_archive(blob)?;
+	let paths = entries.iter().map(|entry| safe_join(dir, entry.name)).collect::<Result<Vec<_>, _>>()?;
+	let mtimes = entries.iter().enumerate().map(|(i, entry)| {
+		entry.mtime.map(|mtime| UNIX_EPOCH.checked_add(Duration::from_secs(mtime)).ok_or(BlobReadError::InvalidMetadata(i))).transpose()
+	}).collect::<Result<Vec<_>, _>>()?;
+	let view = BlobView::new(blob)?;
+
+	for (i, ((entry, path), mtime)) in entries.iter().zip(paths).zip(mtimes).enumerate() {
+		refuse_symlinks(dir, &path, entry.name)?;
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+		let file = fs::File::create(&path)?;
+		io::Write::write_all(&mut &file, entry.data)?;
+
+		if let Some(mtime) = mtime {
+			file.set_modified(mtime)?;
+		}
+
+		#[cfg(unix)]
+		if let Some(mode) = entry.mode {
+			fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(mode & 0o777))?;
+		}
+
+		on_progress(Progress {
+			entries_done: i + 1,
+			entries_total: Some(entries.len()),
+			bytes_done: view.ends()[3 * i + 2],
+			bytes_total: Some(blob.len())
+		});
+	}
+	Ok(())
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn metadata_round_trip() {
+		for (mtime, mode) in [(None, None), (Some(0), None), (None, Some(0o644)), (Some(u64::MAX), Some(0o755))] {
+			assert_eq!(decode_metadata(&encode_metadata(mtime, mode)), Some((mtime, mode)));
+		}
+	}
+
+	#[test]
+	fn invalid_metadata() {
+		let blob = byte_arrays_to_blob(&[b"a", &[0u8], b"", b"b", &[HAS_MTIME, 1u8, 2u8], b""]);
+		assert!(matches!(blob_to_archive(&blob), Err(BlobReadError::InvalidMetadata(1))));
+
+		let blob = byte_arrays_to_blob(&[b"a", &[0u8], b"", b"b"]);
+		assert!(matches!(blob_to_archive(&blob), Err(BlobReadError::UnpairedEntry(1))));
+	}
+
+	#[cfg(feature = "fs")]
+	fn temp_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("byte-array-blob-{}-{}", std::process::id(), name));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		dir
+	}
+
+	#[cfg(feature = "fs")]
+	#[test]
+	fn pack_and_unpack_dir() {
+		let src = temp_dir("archive-src");
+		let dst = temp_dir("archive-dst");
+
+		fs::create_dir_all(src.join("sub/deeper")).unwrap();
+		fs::write(src.join("top.txt"), b"top").unwrap();
+		fs::write(src.join("sub/empty"), b"").unwrap();
+		fs::write(src.join("sub/deeper/run.sh"), b"#!/bin/sh\necho hi\n").unwrap();
+
+		let old = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+		fs::File::options().write(true).open(src.join("top.txt")).unwrap().set_modified(old).unwrap();
+		#[cfg(unix)]
+		fs::set_permissions(src.join("sub/deeper/run.sh"), std::os::unix::fs::PermissionsExt::from_mode(0o755)).unwrap();
+
+		let packed = pack_dir(&src).unwrap();
+		let blob = archive_to_blob(&packed);
+		let mut events = Vec::new();
+		unpack_archive_to_dir_with_progress(&blob, &dst, |progress| events.push(progress)).unwrap();
+		assert_eq!(events.iter().map(|p| p.entries_done).collect::<Vec<_>>(), vec![1, 2, 3]);
+		assert_eq!(events.last().unwrap().bytes_done, blob.len());
+
+		// Packing the unpacked tree gives back exactly the same entries
+		let repacked = pack_dir(&dst).unwrap();
+		assert_eq!(packed, repacked);
+		assert_eq!(packed.iter().map(|entry| entry.name.as_slice()).collect::<Vec<_>>(), vec![&b"sub/deeper/run.sh"[..], b"sub/empty", b"top.txt"]);
+		assert_eq!(packed[2].mtime, Some(1_000_000_000));
+		#[cfg(unix)]
+		assert_eq!(packed[0].mode, Some(0o755));
+
+		fs::remove_dir_all(src).unwrap();
+		fs::remove_dir_all(dst).unwrap();
+	}
+
+	#[cfg(feature = "fs")]
+	#[test]
+	fn unpack_rejects_traversal() {
+		let dst = temp_dir("archive-traversal");
+
+		for name in ["../escape", "a/../../escape", "/etc/passwd", "a//b", "./a", "", "a\0b"] {
+			let entries = [
+				ArchiveEntry { name: b"fine".to_vec(), ..Default::default() },
+				ArchiveEntry { name: name.into(), ..Default::default() }
+			];
+			match unpack_archive_to_dir(&archive_to_blob(&entries), &dst) {
+				Err(BlobFileError::UnsafePath(rejected)) => assert_eq!(rejected, name),
+				other => panic!("Expected BlobFileError::UnsafePath for {:?}, got {:?}", name, other)
+			}
+		}
+
+		// Names are checked before writing, so not even the valid entry was created
+		assert!(fs::read_dir(&dst).unwrap().next().is_none());
+		fs::remove_dir_all(dst).unwrap();
+	}
+
+	#[cfg(feature = "fs")]
+	#[test]
+	fn unpack_rejects_unrepresentable_mtime() {
+		let dst = temp_dir("archive-mtime");
+		let entries = [
+			ArchiveEntry { name: b"fine".to_vec(), ..Default::default() },
+			ArchiveEntry { name: b"far future".to_vec(), mtime: Some(u64::MAX), ..Default::default() }
+		];
+		assert!(matches!(unpack_archive_to_dir(&archive_to_blob(&entries), &dst), Err(BlobFileError::Read(BlobReadError::InvalidMetadata(1)))));
+		assert!(fs::read_dir(&dst).unwrap().next().is_none());
+		fs::remove_dir_all(dst).unwrap();
+	}
+
+	#[cfg(all(feature = "fs", unix))]
+	#[test]
+	fn unpack_masks_modes_and_refuses_symlinks() {
+		use std::os::unix::fs::{symlink, PermissionsExt};
+
+		let dst = temp_dir("archive-modes");
+		let outside = temp_dir("archive-outside");
+		let entries = [ArchiveEntry { name: b"setuid".to_vec(), mode: Some(0o4755), data: b"x".to_vec(), ..Default::default() }];
+		unpack_archive_to_dir(&archive_to_blob(&entries), &dst).unwrap();
+		assert_eq!(fs::metadata(dst.join("setuid")).unwrap().permissions().mode() & 0o7777, 0o755);
+
+		// Neither a symlinked file nor a symlinked directory is written through
+		symlink(outside.join("target"), dst.join("link")).unwrap();
+		symlink(&outside, dst.join("linked_dir")).unwrap();
+		for name in ["link", "linked_dir/file"] {
+			let entries = [ArchiveEntry { name: name.into(), data: b"overwritten".to_vec(), ..Default::default() }];
+			match unpack_archive_to_dir(&archive_to_blob(&entries), &dst) {
+				Err(BlobFileError::UnsafePath(rejected)) => assert_eq!(rejected, name),
+				other => panic!("Expected BlobFileError::UnsafePath for {:?}, got {:?}", name, other)
+			}
+		}
+		assert!(fs::read_dir(&outside).unwrap().next().is_none());
+
+		fs::remove_dir_all(dst).unwrap();
+		fs::remove_dir_all(outside).unwrap();
+	}
+
+	#[cfg(all(feature = "fs", unix))]
+	#[test]
+	fn non_utf8_names() {
+		use std::os::unix::ffi::OsStrExt;
+
+		let src = temp_dir("archive-non-utf8-src");
+		let dst = temp_dir("archive-non-utf8-dst");
+		let name: &[u8] = b"caf\xE9 \xFF.txt";
+		// Some filesystems (such as on macOS) refuse names that aren't UTF-8, so there is nothing to test there
+		if fs::write(src.join(OsStr::from_bytes(name)), b"latin-1").is_err() {
+			return;
+		}
+
+		let packed = pack_dir(&src).unwrap();
+		assert_eq!(packed[0].name, name);
+		let blob = archive_to_blob(&packed);
+		let entries = blob_to_archive(&blob).unwrap();
+		assert_eq!(entries[0].name_os().unwrap().as_bytes(), name);
+		assert_eq!(entries[0].name_lossy(), "caf\u{FFFD} \u{FFFD}.txt");
+
+		unpack_archive_to_dir(&blob, &dst).unwrap();
+		assert_eq!(fs::read(dst.join(OsStr::from_bytes(name))).unwrap(), b"latin-1");
+		assert_eq!(pack_dir(&dst).unwrap(), packed);
+
+		fs::remove_dir_all(src).unwrap();
+		fs::remove_dir_all(dst).unwrap();
+	}
+}
@@ -0,0 +1,147 @@
+//! `EntryArena`, an owned decode that keeps every payload in one buffer instead of one Vec per entry
+use crate::*;
+use crate::decode::try_reserve_exact;
+use std::ops::Range;
+
+
+/// The payloads of a blob copied into one contiguous buffer, with the range of each payload in it, created by `blob_to_arena`
+///
+/// Owns its data like `blob_to_byte_vecs` but takes exactly two allocations however many entries there are,
+/// so decoding many small entries doesn't fragment the allocator.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EntryArena {
+	bytes: Vec<u8>,
+	ranges: Vec<Range<u32>>
+}
+
+
+impl EntryArena {
+	/// Returns the number of entries
+	pub fn len(&self) -> usize {
+		self.ranges.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ranges.is_empty()
+	}
+
+	/// Returns entry `index`, or `None` if it is out of range
+	pub fn get(&self, index: usize) -> Option<&[u8]> {
+		self.ranges.get(index).map(|range| &self.bytes[range.start as usize..range.end as usize])
+	}
+
+	/// Returns an iterator over the entries in order
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = &[u8]> + DoubleEndedIterator + '_ {
+		self.ranges.iter().map(|range| &self.bytes[range.start as usize..range.end as usize])
+	}
+
+	/// Returns the payload buffer and the range of each entry in it
+	pub fn into_parts(self) -> (Vec<u8>, Vec<Range<u32>>) {
+		(self.bytes, self.ranges)
+	}
+
+	/// Converts a blob u8 slice to an `EntryArena` like `blob_to_arena`, but returns an error instead of aborting if memory runs out
+	///
+	/// Both allocations are reserved with `Vec::try_reserve_exact` once the blob has been validated.
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::AllocationFailed)` with the size of the allocation that failed, otherwise the same as `blob_to_byte_arrays`
+	pub fn try_from_blob(blob: &[u8]) -> Result<Self, BlobReadError> {
+		let (count, total_len) = measure(blob)?;
+		let mut arena = EntryArena::default();
+		try_reserve_exact(&mut arena.bytes, total_len)?;
+		try_reserve_exact(&mut arena.ranges, count)?;
+		arena.fill(blob);
+		Ok(arena)
+	}
+
+	/// Copies the payloads of a blob that has already been validated, into buffers that already have room for them
+	fn fill(&mut self, blob: &[u8]) {
+		// The payloads are shorter than the blob, which a valid blob keeps within u32
+		for byte_arr in blob_iter(blob).flatten() {
+			let start = self.bytes.len() as u32;
+			self.bytes.extend_from_slice(byte_arr);
+			self.ranges.push(start..self.bytes.len() as u32);
+		}
+	}
+}
+
+
+/// Validates a blob, returning the number of entries and the total length of their payloads
+fn measure(blob: &[u8]) -> Result<(usize, usize), BlobReadError> {
+	blob_iter(blob).try_fold((0usize, 0usize), |(count, total_len), byte_arr| {
+		byte_arr.map(|byte_arr| (count + 1, total_len + byte_arr.len()))
+	})
+}
+
+
+/// Converts a blob u8 slice to an `EntryArena`, copying every slice into one buffer
+///
+/// The blob is read twice, once to validate it and size both allocations exactly, and once to copy the payloads.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`, and nothing is allocated if the blob is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"one", b"", b"three"]);
+/// let arena = blob_to_arena(&blob).unwrap();
+///
+/// assert_eq!(arena.get(2), Some(&b"three"[..]));
+/// assert!(arena.iter().eq(blob_to_byte_arrays(&blob).unwrap()));
+///
+/// let (bytes, ranges) = arena.into_parts();
+/// assert_eq!(bytes, b"onethree");
+/// assert_eq!(ranges, [0..3, 3..3, 3..8]);
+/// ```
+pub fn blob_to_arena(blob: &[u8]) -> Result<EntryArena, BlobReadError> {
+	let (count, total_len) = measure(blob)?;
+	let mut arena = EntryArena { bytes: Vec::with_capacity(total_len), ranges: Vec::with_capacity(count) };
+	arena.fill(blob);
+	Ok(arena)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn matches_byte_vecs() {
+		let entries: Vec<Vec<u8>> = (0..500).map(|i| vec![i as u8; i % 13]).collect();
+		let blob = entries_to_blob(&entries);
+		let arena = blob_to_arena(&blob).unwrap();
+
+		assert_eq!(arena.len(), entries.len());
+		assert!(arena.iter().eq(blob_to_byte_vecs(&blob).unwrap().iter().map(|e| e.as_slice())));
+		assert!(arena.iter().rev().eq(entries.iter().rev().map(|e| e.as_slice())));
+		assert_eq!(arena.get(12), Some(&entries[12][..]));
+		assert_eq!(arena.get(500), None);
+
+		let (bytes, ranges) = arena.clone().into_parts();
+		assert_eq!(bytes, entries.concat());
+		assert_eq!(bytes.capacity(), bytes.len());
+		assert_eq!(ranges.capacity(), entries.len());
+
+		assert_eq!(blob_to_arena(&[]).unwrap(), EntryArena::default());
+		assert!(blob_to_arena(&blob[..blob.len() - 1]).is_err());
+	}
+
+	#[test]
+	fn try_from_blob() {
+		let blob = byte_arrays_to_blob(&[b"one", b"", &[7u8; 200]]);
+		assert_eq!(EntryArena::try_from_blob(&blob).unwrap(), blob_to_arena(&blob).unwrap());
+		assert_eq!(EntryArena::try_from_blob(&[]).unwrap(), EntryArena::default());
+
+		crate::decode::ALLOCATION_LIMIT.set(100);
+		assert_eq!(EntryArena::try_from_blob(&blob), Err(BlobReadError::AllocationFailed { requested: 203 }));
+		crate::decode::ALLOCATION_LIMIT.set(usize::MAX);
+	}
+}
@@ -0,0 +1,431 @@
+//! `Blob`, an owned blob that caches the position of every slice
+use crate::*;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+
+/// An owned, validated blob. The encoded bytes are exactly those `byte_arrays_to_blob` produces, and the end of each slice is cached for random access
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut blob = Blob::from_byte_arrays(&[&[1, 2], &[3]]);
+/// blob.push(&[4, 5, 6]);
+///
+/// assert_eq!(blob.len(), 3);
+/// assert_eq!(blob.get(2), Some(&[4, 5, 6][..]));
+/// assert_eq!(blob.as_bytes(), byte_arrays_to_blob(&[&[1, 2], &[3], &[4, 5, 6]]));
+/// ```
+///
+/// Equality, hashing and ordering all work on the encoded bytes, matching `[u8]`, so `Blob` can be a map key and looked up by `&[u8]` through `Borrow`.
+/// Ordering is lexicographic over the encoded bytes including the indexes, so it is consistent but is *not* the order of the slices compared one by one.
+///
+/// `Blob` deliberately doesn't implement `Deref<Target = [u8]>`. `len`, `get` and `iter` mean slices here, and the `[u8]` methods of the same names
+/// (or `first`, `contains` and the rest) meaning bytes would be easy to mix up. Use `as_bytes` or `as_ref` to get the bytes.
+#[derive(Debug, Clone, Default)]
+pub struct Blob {
+	bytes: Vec<u8>,
+	ends: Vec<usize>
+}
+
+
+impl Blob {
+	/// Creates an empty blob
+	pub fn new() -> Self {
+		Blob::default()
+	}
+
+	/// Creates an empty blob with room for `bytes` encoded bytes and `entries` slices before it needs to reallocate
+	pub fn with_capacity(bytes: usize, entries: usize) -> Self {
+		Blob { bytes: Vec::with_capacity(bytes), ends: Vec::with_capacity(entries) }
+	}
+
+	/// Encodes slices into a new blob
+	pub fn from_byte_arrays(bytes_2d: &[&[u8]]) -> Self {
+		let mut blob = Blob { bytes: Vec::with_capacity(encoded_len(bytes_2d)), ends: Vec::with_capacity(bytes_2d.len()) };
+		for byte_arr in bytes_2d {
+			blob.push(byte_arr);
+		}
+		blob
+	}
+
+	/// Takes ownership of encoded blob bytes, reading every index to check they are valid
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`
+	pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, BlobReadError> {
+		let ends = BlobView::new(&bytes)?.ends().to_vec();
+		Ok(Blob { bytes, ends })
+	}
+
+	/// Rebuilds a blob from the parts returned by `into_parts`, for example after storing them elsewhere
+	///
+	/// The slice ends are untrusted, so every one is checked against the index stored in `bytes`.
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InvalidEncodedIndex)` with the offending end if `ends` does not exactly match the indexes in `bytes`,
+	/// otherwise the same as `blob_to_byte_arrays`
+	pub fn from_parts(bytes: Vec<u8>, ends: Vec<usize>) -> Result<Self, BlobReadError> {
+		let mut iter = blob_iter(&bytes);
+		for &end in &ends {
+			match iter.next() {
+				Some(byte_arr) => {
+					byte_arr?;
+					if iter.position() != end {
+						return Err(BlobReadError::InvalidEncodedIndex(end));
+					}
+				},
+				None => return Err(BlobReadError::InvalidEncodedIndex(end))
+			}
+		}
+
+		// Any slices left over mean `ends` is too short
+		if let Some(byte_arr) = iter.next() {
+			byte_arr?;
+			return Err(BlobReadError::InvalidEncodedIndex(iter.position()));
+		}
+		Ok(Blob { bytes, ends })
+	}
+
+	/// Splits the blob into its encoded bytes and the cached end of each slice
+	pub fn into_parts(self) -> (Vec<u8>, Vec<usize>) {
+		(self.bytes, self.ends)
+	}
+
+	/// Returns the encoded bytes
+	pub fn into_bytes(self) -> Vec<u8> {
+		self.bytes
+	}
+
+	/// Returns the encoded bytes
+	pub fn as_bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+
+	/// Borrows the blob as a `BlobView` without reading it again
+	pub fn view(&self) -> BlobView<'_> {
+		BlobView::from_validated(&self.bytes, &self.ends)
+	}
+
+	/// Borrows just the slices in `range` as a `BlobView`, without copying. See `BlobView::slice`
+	///
+	/// # Panics
+	///
+	/// Panics if the range is inverted or ends past `len()`, in the same way as slicing a `[T]`
+	pub fn slice(&self, range: Range<usize>) -> BlobView<'_> {
+		BlobView::from_validated_range(&self.bytes, 0, &self.ends, range)
+	}
+
+	/// Returns the number of slices in the blob
+	pub fn len(&self) -> usize {
+		self.ends.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ends.is_empty()
+	}
+
+	/// Returns slice `index`, or `None` if it is out of range
+	pub fn get(&self, index: usize) -> Option<&[u8]> {
+		self.view().range(index).map(|range| &self.bytes[range])
+	}
+
+	/// Returns an iterator over the slices
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = &[u8]> + ExactSizeIterator + '_ {
+		(0..self.len()).map(|index| self.get(index).unwrap())
+	}
+
+	/// Appends a slice to the end of the blob
+	///
+	/// Only the new index and payload are written, and nothing already in the blob is read or moved unless the buffer has to grow.
+	///
+	/// # Panics
+	///
+	/// Panics if the encoded blob would become larger than `u32::MAX` bytes, since its indexes could no longer be stored
+	pub fn push(&mut self, byte_arr: &[u8]) {
+		let idx_end = self.bytes.len() + 4 + byte_arr.len();
+		let write_end_idx = u32::try_from(idx_end).expect("blob larger than u32::MAX bytes");
+
+		self.bytes.extend(write_end_idx.to_ne_bytes());
+		self.bytes.extend(byte_arr);
+		self.ends.push(idx_end);
+	}
+
+	/// Appends every slice of `other` to the end of the blob
+	///
+	/// The bytes of `other` are copied in one go and only its indexes are rewritten, so this is O(`other`) whatever the size of `self`.
+	///
+	/// # Panics
+	///
+	/// Panics if the encoded blob would become larger than `u32::MAX` bytes
+	pub fn extend_from_blob(&mut self, other: &Blob) {
+		let offset = self.bytes.len();
+		u32::try_from(offset + other.bytes.len()).expect("blob larger than u32::MAX bytes");
+
+		self.bytes.extend_from_slice(&other.bytes);
+		self.ends.reserve(other.ends.len());
+		let mut idx_start = offset;
+		for &end in &other.ends {
+			let end = end + offset;
+			self.write_index(idx_start, end);
+			self.ends.push(end);
+			idx_start = end;
+		}
+	}
+
+	/// Inserts a slice so it becomes slice `index`, moving every later slice along in a single copy
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is greater than `len()`, or if the encoded blob would become larger than `u32::MAX` bytes
+	pub fn insert(&mut self, index: usize, byte_arr: &[u8]) {
+		assert!(index <= self.len(), "insertion index {} is past the end of the blob ({} slices)", index, self.len());
+		let added = 4 + byte_arr.len();
+		let old_len = self.bytes.len();
+		u32::try_from(old_len + added).expect("blob larger than u32::MAX bytes");
+
+		let idx_start = self.idx_start(index);
+		self.bytes.resize(old_len + added, 0);
+		self.bytes.copy_within(idx_start..old_len, idx_start + added);
+		self.bytes[idx_start+4..idx_start+added].copy_from_slice(byte_arr);
+		self.write_index(idx_start, idx_start + added);
+
+		self.shift_ends(index, idx_start + added, |end| end + added);
+		self.ends.insert(index, idx_start + added);
+	}
+
+	/// Removes slice `index` and returns it, moving every later slice back in a single copy
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is out of range
+	pub fn remove(&mut self, index: usize) -> Vec<u8> {
+		assert!(index < self.len(), "removal index {} is out of range for a blob of {} slices", index, self.len());
+		let idx_start = self.idx_start(index);
+		let idx_end = self.ends[index];
+		let removed = idx_end - idx_start;
+
+		let byte_arr = self.bytes[idx_start+4..idx_end].to_vec();
+		self.bytes.copy_within(idx_end.., idx_start);
+		self.bytes.truncate(self.bytes.len() - removed);
+
+		self.ends.remove(index);
+		self.shift_ends(index, idx_start, |end| end - removed);
+		byte_arr
+	}
+
+	/// Returns where the index of slice `index` starts, which is where the previous slice ends
+	fn idx_start(&self, index: usize) -> usize {
+		match index {
+			0 => 0,
+			_ => self.ends[index - 1]
+		}
+	}
+
+	fn write_index(&mut self, idx_start: usize, idx_end: usize) {
+		self.bytes[idx_start..idx_start+4].copy_from_slice(&(idx_end as u32).to_ne_bytes());
+	}
+
+	/// Moves the ends of slice `from` onwards with `shift`, rewriting their indexes. The index of slice `from` is at `idx_start`
+	fn shift_ends(&mut self, from: usize, mut idx_start: usize, shift: impl Fn(usize) -> usize) {
+		for i in from..self.ends.len() {
+			let end = shift(self.ends[i]);
+			self.ends[i] = end;
+			self.write_index(idx_start, end);
+			idx_start = end;
+		}
+	}
+}
+
+
+impl TryFrom<Vec<u8>> for Blob {
+	type Error = BlobReadError;
+
+	fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+		Blob::from_bytes(bytes)
+	}
+}
+
+
+impl From<Blob> for Vec<u8> {
+	fn from(blob: Blob) -> Self {
+		blob.into_bytes()
+	}
+}
+
+
+impl AsRef<[u8]> for Blob {
+	fn as_ref(&self) -> &[u8] {
+		&self.bytes
+	}
+}
+
+
+impl Borrow<[u8]> for Blob {
+	fn borrow(&self) -> &[u8] {
+		&self.bytes
+	}
+}
+
+
+// The ends are derived from the bytes, so only the bytes need comparing
+impl PartialEq for Blob {
+	fn eq(&self, other: &Self) -> bool {
+		self.bytes == other.bytes
+	}
+}
+
+
+impl Eq for Blob {}
+
+
+impl PartialEq<[u8]> for Blob {
+	fn eq(&self, other: &[u8]) -> bool {
+		self.bytes == other
+	}
+}
+
+
+// Must hash the same as `[u8]` for `Borrow` lookups to work
+impl Hash for Blob {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.bytes.as_slice().hash(state);
+	}
+}
+
+
+impl PartialOrd for Blob {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+
+impl Ord for Blob {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.bytes.cmp(&other.bytes)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parts_round_trip() {
+		let blob = Blob::from_byte_arrays(&[&[1u8], &[], &[2u8, 3u8]]);
+		let (bytes, ends) = blob.clone().into_parts();
+		assert_eq!(ends, vec![5, 9, 15]);
+
+		let rebuilt = Blob::from_parts(bytes, ends).unwrap();
+		assert_eq!(rebuilt.iter().collect::<Vec<_>>(), blob.iter().collect::<Vec<_>>());
+	}
+
+	#[test]
+	fn from_parts_rejects_invalid_ends() {
+		let bytes = byte_arrays_to_blob(&[&[1u8], &[], &[2u8, 3u8]]);
+
+		// Wrong value, too few, and too many ends are all rejected
+		assert!(matches!(Blob::from_parts(bytes.clone(), vec![5, 10, 15]), Err(BlobReadError::InvalidEncodedIndex(10))));
+		assert!(matches!(Blob::from_parts(bytes.clone(), vec![5, 9]), Err(BlobReadError::InvalidEncodedIndex(15))));
+		assert!(matches!(Blob::from_parts(bytes.clone(), vec![5, 9, 15, 19]), Err(BlobReadError::InvalidEncodedIndex(19))));
+
+		// Valid ends can't cover for invalid bytes
+		let mut corrupt = bytes.clone();
+		corrupt[4] = 0u8;
+		corrupt[5] = 0u8;
+		assert!(Blob::from_parts(corrupt, vec![5, 9, 15]).is_err());
+	}
+
+	#[test]
+	fn from_bytes_and_push() {
+		let mut blob = Blob::from_bytes(byte_arrays_to_blob(&[&[1u8]])).unwrap();
+		blob.push(&[]);
+		blob.push(&[2u8]);
+		assert_eq!(blob.as_bytes(), byte_arrays_to_blob(&[&[1u8], &[], &[2u8]]));
+		assert_eq!(blob.view().iter().next_back(), Some(&[2u8][..]));
+		assert!(Blob::from_bytes(vec![1u8]).is_err());
+	}
+
+	#[test]
+	fn hash_map_lookup_by_bytes() {
+		let first = Blob::from_byte_arrays(&[&[1u8], &[2u8]]);
+		let second = Blob::from_byte_arrays(&[&[3u8]]);
+
+		let mut map = std::collections::HashMap::new();
+		map.insert(first.clone(), "first");
+		map.insert(second.clone(), "second");
+
+		let bytes = byte_arrays_to_blob(&[&[1u8], &[2u8]]);
+		assert_eq!(map.get(bytes.as_slice()), Some(&"first"));
+		assert_eq!(map.get(second.as_bytes()), Some(&"second"));
+		assert_eq!(map.get(&[0u8][..]), None);
+
+		// A blob equal to one already in the map replaces its value
+		map.insert(Blob::from_bytes(bytes).unwrap(), "again");
+		assert_eq!(map.len(), 2);
+		assert_eq!(map[&first], "again");
+	}
+
+	#[test]
+	fn btree_set_orders_by_encoded_bytes() {
+		let blobs = [
+			Blob::from_byte_arrays(&[&[2u8]]),
+			Blob::from_byte_arrays(&[&[1u8], &[9u8]]),
+			Blob::new(),
+			Blob::from_byte_arrays(&[&[1u8]])
+		];
+		let set: std::collections::BTreeSet<Blob> = blobs.iter().cloned().collect();
+
+		let mut expected: Vec<Vec<u8>> = blobs.iter().map(|blob| blob.as_bytes().to_vec()).collect();
+		expected.sort();
+		assert_eq!(set.iter().map(|blob| blob.as_bytes().to_vec()).collect::<Vec<_>>(), expected);
+
+		assert!(set.contains(byte_arrays_to_blob(&[&[2u8]]).as_slice()));
+		assert!(set.contains(&[][..]));
+		assert!(!set.contains(byte_arrays_to_blob(&[&[3u8]]).as_slice()));
+		assert_eq!(blobs[0], *byte_arrays_to_blob(&[&[2u8]]).as_slice());
+	}
+
+	#[test]
+	fn push_reuses_buffer() {
+		let mut blob = Blob::with_capacity(1024, 16);
+		let ptr = blob.as_bytes().as_ptr();
+		for i in 0..16u8 {
+			blob.push(&[i; 20]);
+			assert_eq!(blob.as_bytes().as_ptr(), ptr);
+		}
+		assert_eq!(blob.len(), 16);
+	}
+
+	#[test]
+	fn extend_insert_and_remove() {
+		let mut blob = Blob::from_byte_arrays(&[&[1u8], &[]]);
+		blob.extend_from_blob(&Blob::from_byte_arrays(&[&[2u8, 2u8], &[3u8]]));
+		blob.extend_from_blob(&Blob::new());
+		assert_eq!(blob.as_bytes(), byte_arrays_to_blob(&[&[1u8], &[], &[2u8, 2u8], &[3u8]]));
+
+		blob.insert(0, &[0u8; 3]);
+		blob.insert(3, &[9u8]);
+		blob.insert(blob.len(), &[]);
+		let expected: Vec<&[u8]> = vec![&[0u8; 3], &[1u8], &[], &[9u8], &[2u8, 2u8], &[3u8], &[]];
+		assert_eq!(blob.as_bytes(), byte_arrays_to_blob(&expected));
+
+		assert_eq!(blob.remove(3), vec![9u8]);
+		assert_eq!(blob.remove(0), vec![0u8; 3]);
+		assert_eq!(blob.remove(blob.len() - 1), Vec::<u8>::new());
+		assert_eq!(blob.as_bytes(), byte_arrays_to_blob(&[&[1u8], &[], &[2u8, 2u8], &[3u8]]));
+
+		// The cached ends stay in step with the bytes
+		let (bytes, ends) = blob.into_parts();
+		assert!(Blob::from_parts(bytes, ends).is_ok());
+	}
+}
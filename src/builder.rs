@@ -0,0 +1,290 @@
+//! `BlobBuilder`, for building a blob one slice at a time
+use crate::*;
+
+
+/// Builds a blob one slice at a time, optionally checking that an expected number of slices was pushed
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut builder = BlobBuilder::with_expected_entries(2);
+/// builder.push(b"one").unwrap();
+/// assert_eq!(builder.remaining_expected(), Some(1));
+/// builder.push(b"two").unwrap();
+///
+/// assert_eq!(builder.finish().unwrap(), byte_arrays_to_blob(&[b"one", b"two"]));
+/// ```
+///
+/// # Reuse
+///
+/// A builder can build one blob after another without allocating once its buffer is big enough. `clear` and `finish_into` keep the capacity
+/// of the buffer, and `push` and `reserve_entries` only ever grow it. `shrink_to_fit` gives spare capacity back, and `finish` hands the buffer over.
+///
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut builder = BlobBuilder::new();
+/// let mut frame = Vec::new();
+/// for tick in 0..3u8 {
+/// 	builder.push(&[tick]).unwrap();
+/// 	builder.finish_into(&mut frame).unwrap();
+/// 	assert_eq!(frame, byte_arrays_to_blob(&[&[tick]]));
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BlobBuilder {
+	bytes: Vec<u8>,
+	entries: usize,
+	expected: Option<usize>,
+	flagged: bool
+}
+
+
+impl BlobBuilder {
+	pub fn new() -> Self {
+		BlobBuilder::default()
+	}
+
+	/// Creates a builder that expects exactly `n` slices, reserving room for their indexes
+	///
+	/// `finish` returns `Err(BlobWriteError::EntryCountMismatch)` if a different number were pushed.
+	pub fn with_expected_entries(n: usize) -> Self {
+		let mut builder = BlobBuilder { expected: Some(n), ..BlobBuilder::default() };
+		builder.reserve_entries(n);
+		builder
+	}
+
+	/// Creates a builder for a flagged blob, in which entries pushed with `push_sensitive` can later be removed by `blob_redact`
+	///
+	/// Every entry is stored with a flags byte before it, see the flagged blob layout in `blob_to_flagged_entries`.
+	pub fn flagged() -> Self {
+		let mut builder = BlobBuilder { flagged: true, ..BlobBuilder::default() };
+		write_flagged_header(&mut builder.bytes);
+		builder
+	}
+
+	/// Reserves room for the indexes of `additional` more slices. Payload space is not reserved, since its size isn't known
+	pub fn reserve_entries(&mut self, additional: usize) {
+		self.bytes.reserve(additional.saturating_mul(4));
+	}
+
+	/// Appends a slice
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobWriteError::TooLarge)` without changing the builder if the blob would grow past `u32::MAX` bytes
+	pub fn push(&mut self, byte_arr: &[u8]) -> Result<(), BlobWriteError> {
+		match self.flagged {
+			true => self.push_flags(entry_flags(false), byte_arr),
+			false => self.push_parts(&[byte_arr])
+		}
+	}
+
+	/// Appends a slice flagged as sensitive, so `blob_redact` and `blob_to_byte_arrays_redacted` leave its payload out
+	///
+	/// # Panics
+	///
+	/// Panics if the builder wasn't made with `BlobBuilder::flagged`, since a plain blob has nowhere to store the flag
+	///
+	/// # Errors
+	///
+	/// The same as `push`
+	pub fn push_sensitive(&mut self, byte_arr: &[u8]) -> Result<(), BlobWriteError> {
+		assert!(self.flagged, "push_sensitive needs a builder made with BlobBuilder::flagged");
+		self.push_flags(entry_flags(true), byte_arr)
+	}
+
+	/// Appends an entry of a flagged blob with the given flags byte
+	pub(crate) fn push_flags(&mut self, flags: u8, byte_arr: &[u8]) -> Result<(), BlobWriteError> {
+		self.push_parts(&[&[flags], byte_arr])
+	}
+
+	/// Appends one slice made of `parts` joined together, without joining them into a buffer first
+	pub(crate) fn push_parts(&mut self, parts: &[&[u8]]) -> Result<(), BlobWriteError> {
+		let len = parts.iter().try_fold(0usize, |len, part| len.checked_add(part.len())).ok_or(BlobWriteError::TooLarge)?;
+		let idx_end = self.bytes.len().checked_add(4).and_then(|end| end.checked_add(len)).ok_or(BlobWriteError::TooLarge)?;
+		let write_end_idx = u32::try_from(idx_end).map_err(|_| BlobWriteError::TooLarge)?;
+
+		self.bytes.extend(write_end_idx.to_ne_bytes());
+		for part in parts {
+			self.bytes.extend_from_slice(part);
+		}
+		self.entries += 1;
+		Ok(())
+	}
+
+	/// Removes every slice, keeping the capacity of the buffer and the expected count, if there is one
+	pub fn clear(&mut self) {
+		self.bytes.clear();
+		self.entries = 0;
+		if self.flagged {
+			write_flagged_header(&mut self.bytes);
+		}
+	}
+
+	/// Returns how many encoded bytes the buffer can hold before it needs to reallocate
+	pub fn capacity(&self) -> usize {
+		self.bytes.capacity()
+	}
+
+	/// Shrinks the buffer to fit the slices pushed so far
+	pub fn shrink_to_fit(&mut self) {
+		self.bytes.shrink_to_fit();
+	}
+
+	/// Returns the number of slices pushed so far
+	pub fn len(&self) -> usize {
+		self.entries
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries == 0
+	}
+
+	/// Returns how many more slices are expected, or `None` if the builder was not given an expected count.
+	/// This is 0 once the expected count has been reached or passed
+	pub fn remaining_expected(&self) -> Option<usize> {
+		self.expected.map(|expected| expected.saturating_sub(self.entries))
+	}
+
+	/// Returns the encoded blob
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobWriteError::EntryCountMismatch)` if the builder expected a different number of slices than were pushed
+	pub fn finish(self) -> Result<Vec<u8>, BlobWriteError> {
+		match self.expected {
+			Some(expected) if expected != self.entries => Err(BlobWriteError::EntryCountMismatch { expected, actual: self.entries }),
+			_ => Ok(self.bytes)
+		}
+	}
+
+	/// Replaces the contents of `out` with the encoded blob and clears the builder, so it can build the next blob
+	///
+	/// The buffers are swapped rather than copied: `out` gets the builder's buffer and the builder keeps the old buffer of `out`, emptied.
+	/// Calling this in a loop with the same `out` passes two buffers back and forth, and once both are big enough nothing is allocated.
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobWriteError::EntryCountMismatch)` without changing the builder or `out` if the builder expected a different number of slices than were pushed
+	pub fn finish_into(&mut self, out: &mut Vec<u8>) -> Result<(), BlobWriteError> {
+		if let Some(expected) = self.expected.filter(|&expected| expected != self.entries) {
+			return Err(BlobWriteError::EntryCountMismatch { expected, actual: self.entries });
+		}
+		std::mem::swap(&mut self.bytes, out);
+		self.clear();
+		Ok(())
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	fn build(expected: usize, pushed: usize) -> Result<Vec<u8>, BlobWriteError> {
+		let mut builder = BlobBuilder::with_expected_entries(expected);
+		for i in 0..pushed {
+			builder.push(&vec![i as u8; i]).unwrap();
+		}
+		builder.finish()
+	}
+
+	#[test]
+	fn expected_entry_counts() {
+		let blob = build(3, 3).unwrap();
+		assert_eq!(blob, byte_arrays_to_blob(&[&[], &[1u8], &[2u8, 2u8]]));
+
+		assert!(matches!(build(3, 2), Err(BlobWriteError::EntryCountMismatch { expected: 3, actual: 2 })));
+		assert!(matches!(build(3, 4), Err(BlobWriteError::EntryCountMismatch { expected: 3, actual: 4 })));
+		assert_eq!(build(0, 0).unwrap(), Vec::<u8>::new());
+		assert!(matches!(build(0, 1), Err(BlobWriteError::EntryCountMismatch { expected: 0, actual: 1 })));
+	}
+
+	#[test]
+	fn remaining_expected() {
+		let mut builder = BlobBuilder::with_expected_entries(2);
+		assert!(builder.is_empty());
+		assert_eq!(builder.remaining_expected(), Some(2));
+		builder.push(&[]).unwrap();
+		builder.push(&[]).unwrap();
+		assert_eq!(builder.remaining_expected(), Some(0));
+		builder.push(&[]).unwrap();
+		assert_eq!(builder.remaining_expected(), Some(0));
+		assert_eq!(builder.len(), 3);
+
+		let mut unchecked = BlobBuilder::new();
+		unchecked.push(&[1u8]).unwrap();
+		assert_eq!(unchecked.remaining_expected(), None);
+		assert_eq!(unchecked.finish().unwrap(), byte_arrays_to_blob(&[&[1u8]]));
+	}
+
+	#[test]
+	fn capacity_stabilizes() {
+		let mut builder = BlobBuilder::new();
+		let mut frame = Vec::new();
+		let mut warm_capacity = 0;
+
+		for tick in 0..1000 {
+			// Frames repeat every 91 ticks, so the largest has been built by the end of the warm-up
+			let entries: Vec<Vec<u8>> = (0..tick % 7).map(|i| vec![i as u8; tick % 13]).collect();
+			for byte_arr in &entries {
+				builder.push(byte_arr).unwrap();
+			}
+			builder.finish_into(&mut frame).unwrap();
+			assert_eq!(frame, entries_to_blob(&entries));
+			assert!(builder.is_empty());
+
+			let capacity = builder.capacity().max(frame.capacity());
+			match tick < 100 {
+				true => warm_capacity = warm_capacity.max(capacity),
+				false => assert!(capacity <= warm_capacity, "capacity grew to {} after the warm-up at tick {}", capacity, tick)
+			}
+		}
+
+		builder.push(&[1u8; 10]).unwrap();
+		builder.clear();
+		assert!(builder.capacity() >= 14);
+		builder.shrink_to_fit();
+		assert_eq!(builder.capacity(), 0);
+	}
+
+	#[test]
+	fn finish_into_checks_count() {
+		let mut builder = BlobBuilder::with_expected_entries(1);
+		let mut out = vec![9u8];
+		assert!(matches!(builder.finish_into(&mut out), Err(BlobWriteError::EntryCountMismatch { expected: 1, actual: 0 })));
+		assert_eq!(out, vec![9u8]);
+
+		builder.push(&[]).unwrap();
+		builder.finish_into(&mut out).unwrap();
+		assert_eq!(out, byte_arrays_to_blob(&[&[]]));
+		// The expected count is kept for the next blob
+		assert_eq!(builder.remaining_expected(), Some(1));
+	}
+
+	#[test]
+	fn flagged_builder_reuse() {
+		let mut builder = BlobBuilder::flagged();
+		let mut out = Vec::new();
+		for round in 0..3u8 {
+			builder.push_sensitive(&[round]).unwrap();
+			builder.finish_into(&mut out).unwrap();
+			// Every blob gets the magic again, and only the entries pushed since are counted
+			assert!(builder.is_empty());
+			assert_eq!(blob_to_flagged_entries(&out).unwrap(), [FlaggedEntry { data: &[round], sensitive: true, redacted: false }]);
+		}
+		builder.clear();
+		assert!(blob_to_flagged_entries(&builder.finish().unwrap()).unwrap().is_empty());
+	}
+
+	#[test]
+	#[should_panic(expected = "BlobBuilder::flagged")]
+	fn sensitive_needs_flagged_builder() {
+		let _ = BlobBuilder::new().push_sensitive(b"secret");
+	}
+}
@@ -0,0 +1,103 @@
+//! `spawn_blob_reader`, which decodes a blob on a background thread and hands the entries over through a bounded channel
+use crate::*;
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
+
+
+/// The handle of the thread started by `spawn_blob_reader`, which returns whether the whole blob was read
+pub type BlobReaderHandle = JoinHandle<Result<(), BlobStreamError>>;
+
+
+/// Reads the slices of a blob from `reader` on a new thread, sending each with its index through a channel that holds at most `bound` of them
+///
+/// When the channel is full the thread waits, so a slow consumer holds back the decode rather than letting entries pile up in memory.
+/// A `bound` of 0 hands each entry over directly. Once the blob has been read, or an error found, the channel is closed and the thread
+/// returns the result, so a consumer can drain the receiver and then join the handle to learn whether the blob was read to the end.
+/// If the receiver is dropped early the thread stops at the next entry and returns `Ok(())`.
+///
+/// # Errors
+///
+/// The handle returns the same errors as `BlobReader::read_entry`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"one", b"two", b"three"]);
+/// let (handle, entries) = spawn_blob_reader(std::io::Cursor::new(blob), 2);
+///
+/// let lengths: Vec<(usize, usize)> = entries.iter().map(|(index, byte_arr)| (index, byte_arr.len())).collect();
+/// assert_eq!(lengths, [(0, 3), (1, 3), (2, 5)]);
+/// assert!(handle.join().unwrap().is_ok());
+/// ```
+pub fn spawn_blob_reader<R: Read + Send + 'static>(reader: R, bound: usize) -> (BlobReaderHandle, Receiver<(usize, Vec<u8>)>) {
+	let (sender, receiver) = mpsc::sync_channel(bound);
+	let handle = thread::spawn(move || {
+		for (index, byte_arr) in BlobReader::new(reader).enumerate() {
+			if sender.send((index, byte_arr?)).is_err() {
+				// The receiver is gone, so nobody wants the rest
+				break;
+			}
+		}
+		Ok(())
+	});
+	(handle, receiver)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::{self, Cursor};
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	/// Counts how many bytes have been read, to see how far ahead of the consumer the thread got
+	struct CountingReader {
+		inner: Cursor<Vec<u8>>,
+		read: Arc<AtomicUsize>
+	}
+
+	impl Read for CountingReader {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let n = self.inner.read(buf)?;
+			self.read.fetch_add(n, Ordering::SeqCst);
+			Ok(n)
+		}
+	}
+
+	#[test]
+	fn dropping_receiver_stops_reader() {
+		let entries: Vec<[u8; 16]> = (0..100u8).map(|i| [i; 16]).collect();
+		let slices: Vec<&[u8]> = entries.iter().map(|entry| &entry[..]).collect();
+		let blob = byte_arrays_to_blob(&slices);
+		let read = Arc::new(AtomicUsize::new(0));
+
+		let (handle, receiver) = spawn_blob_reader(CountingReader { inner: Cursor::new(blob.clone()), read: Arc::clone(&read) }, 4);
+		for (expected, (index, byte_arr)) in receiver.iter().take(50).enumerate() {
+			assert_eq!((index, byte_arr), (expected, vec![expected as u8; 16]));
+		}
+		drop(receiver);
+
+		assert!(handle.join().unwrap().is_ok());
+		// The bound kept the thread from reading far past what was taken
+		assert!(read.load(Ordering::SeqCst) < 60 * 20, "read {} bytes", read.load(Ordering::SeqCst));
+	}
+
+	#[test]
+	fn errors_arrive_after_the_last_good_entry() {
+		let mut blob = byte_arrays_to_blob(&[b"a", b"b"]);
+		blob.extend([1u8, 2]);
+		let (handle, receiver) = spawn_blob_reader(Cursor::new(blob), 0);
+
+		assert_eq!(receiver.iter().collect::<Vec<_>>(), [(0, b"a".to_vec()), (1, b"b".to_vec())]);
+		assert!(matches!(handle.join().unwrap(), Err(BlobStreamError::Read(_))));
+
+		let (handle, receiver) = spawn_blob_reader(io::empty(), 1);
+		assert_eq!(receiver.recv().ok(), None);
+		assert!(handle.join().unwrap().is_ok());
+	}
+}
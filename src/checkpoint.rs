@@ -0,0 +1,216 @@
+//! `BlobCheckpoint`, a saved position in a blob that decoding can resume from without walking the indexes before it
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+use crate::crc32::Crc32;
+
+
+/// How many bytes before a checkpoint's offset its digest covers
+const DIGEST_WINDOW: usize = 64;
+
+
+/// A position between two entries of a blob, taken by `BlobIter::checkpoint` or `BlobCursor::checkpoint` and resumed by `resume_blob_iter`
+///
+/// The fields are public so a checkpoint can be saved however suits the job doing the saving.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlobCheckpoint {
+	/// The number of the next entry to be read
+	pub entry_index: usize,
+	/// Where in the blob the index of that entry starts
+	pub byte_offset: usize,
+	/// The CRC-32 of the 64 bytes before `byte_offset`, or all of them if there are fewer, to notice a checkpoint being used with the wrong blob
+	pub digest: u32
+}
+
+
+impl BlobCheckpoint {
+	pub(crate) fn new(blob: &[u8], entry_index: usize, byte_offset: usize) -> Self {
+		BlobCheckpoint { entry_index, byte_offset, digest: window_digest(blob, byte_offset) }
+	}
+}
+
+
+fn window_digest(blob: &[u8], byte_offset: usize) -> u32 {
+	let mut crc = Crc32::new();
+	crc.update(blob.get(byte_offset.saturating_sub(DIGEST_WINDOW)..byte_offset).unwrap_or_default());
+	crc.finish()
+}
+
+
+/// Errors that can occur when resuming from a `BlobCheckpoint`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResumeError {
+	/// The checkpoint's offset is past the end of a blob that is `len` bytes long
+	OffsetOutOfBounds {
+		offset: usize,
+		len: usize
+	},
+	/// The index at the checkpoint's offset is invalid
+	InvalidHeader(BlobReadError),
+	/// The checkpoint was taken from a different blob: its digest doesn't match, or its entry index can't be at its offset
+	Mismatch
+}
+
+
+impl std::error::Error for ResumeError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ResumeError::InvalidHeader(e) => Some(e),
+			ResumeError::OffsetOutOfBounds { .. } | ResumeError::Mismatch => None
+		}
+	}
+}
+
+impl fmt::Display for ResumeError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+impl BlobIter<'_> {
+	/// Returns a checkpoint for the next entry to be read, which `resume_blob_iter` can carry on from later
+	pub fn checkpoint(&self) -> BlobCheckpoint {
+		BlobCheckpoint::new(self.blob, self.entry, self.idx_start)
+	}
+}
+
+
+/// Returns an iterator over the slices of a blob from a checkpoint on, after checking the checkpoint belongs to the blob
+///
+/// The index at the checkpoint must be valid and the bytes just before it must match the checkpoint's digest, so resuming
+/// against a different or changed blob fails rather than reading from the middle of an entry. Only the 64 bytes before the checkpoint
+/// are compared, so a change further back that leaves every length the same isn't noticed.
+/// Later checkpoints from the returned iterator carry on counting entries from the checkpoint's `entry_index`.
+///
+/// # Errors
+///
+/// Returns `Err(ResumeError::OffsetOutOfBounds)` if the checkpoint is past the end of the blob, `Err(ResumeError::Mismatch)` if it was taken from
+/// a different blob, or `Err(ResumeError::InvalidHeader)` if the index at the checkpoint is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"done", b"also done", b"not yet"]);
+/// let mut iter = blob_iter(&blob);
+/// iter.next();
+/// iter.next();
+/// let checkpoint = iter.checkpoint();
+/// assert_eq!(checkpoint.entry_index, 2);
+///
+/// // After a restart
+/// let mut resumed = resume_blob_iter(&blob, checkpoint).unwrap();
+/// assert_eq!(resumed.next(), Some(Ok(&b"not yet"[..])));
+///
+/// let other = byte_arrays_to_blob(&[b"some", b"other blob", b"entirely"]);
+/// assert_eq!(resume_blob_iter(&other, checkpoint).err(), Some(ResumeError::Mismatch));
+/// ```
+pub fn resume_blob_iter(blob: &[u8], checkpoint: BlobCheckpoint) -> Result<BlobIter<'_>, ResumeError> {
+	resume_blob_iter_with_format(blob, checkpoint, BlobFormat::NativeEndian)
+}
+
+
+/// The same as `resume_blob_iter` for a blob in `format`
+///
+/// # Errors
+///
+/// The same as `resume_blob_iter`
+pub fn resume_blob_iter_with_format(blob: &[u8], checkpoint: BlobCheckpoint, format: BlobFormat) -> Result<BlobIter<'_>, ResumeError> {
+	let BlobCheckpoint { entry_index, byte_offset, digest } = checkpoint;
+	if byte_offset > blob.len() {
+		return Err(ResumeError::OffsetOutOfBounds { offset: byte_offset, len: blob.len() });
+	}
+	// Every entry before the checkpoint has an index, and only the first entry starts at 0
+	let too_many_entries = entry_index.checked_mul(format.header_len()).is_none_or(|min_offset| min_offset > byte_offset);
+	if too_many_entries || (entry_index == 0) != (byte_offset == 0) || window_digest(blob, byte_offset) != digest {
+		return Err(ResumeError::Mismatch);
+	}
+
+	let iter = BlobIter { blob, format, idx_start: byte_offset, entry: entry_index, done: false };
+	if let Some(Err(e)) = iter.clone().next() {
+		return Err(ResumeError::InvalidHeader(e));
+	}
+	Ok(iter)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn resume_mid_blob_and_at_end() {
+		let entries = sample_entries();
+		let slices = as_slices(&entries);
+		for format in [BlobFormat::NativeEndian, BlobFormat::LittleEndian64, BlobFormat::LengthPrefixed] {
+			let blob = byte_arrays_to_blob_with_format(&slices, format);
+			let mut iter = blob_iter_with_format(&blob, format);
+			assert_eq!(iter.checkpoint(), BlobCheckpoint { entry_index: 0, byte_offset: 0, digest: 0 });
+
+			for k in 0..=slices.len() {
+				let checkpoint = iter.checkpoint();
+				assert_eq!(checkpoint.entry_index, k);
+				let mut resumed = resume_blob_iter_with_format(&blob, checkpoint, format).unwrap();
+				assert_eq!(resumed.clone().collect::<Result<Vec<_>, _>>().unwrap(), slices[k..]);
+
+				// Checkpoints taken after resuming still count from the start of the blob
+				if k < slices.len() {
+					resumed.next();
+					iter.next();
+					assert_eq!(resumed.checkpoint(), iter.checkpoint());
+				}
+			}
+			assert_eq!(iter.checkpoint().byte_offset, blob.len());
+		}
+
+		let blob = byte_arrays_to_blob(&slices);
+		let mut cursor = BlobCursor::new(&blob);
+		for _ in 0..4 {
+			cursor.skip().unwrap();
+		}
+		let checkpoint = cursor.checkpoint();
+		assert_eq!(checkpoint.entry_index, 4);
+		assert_eq!(resume_blob_iter(&blob, checkpoint).unwrap().next(), Some(Ok(slices[4])));
+		cursor.rewind();
+		assert_eq!(cursor.checkpoint(), blob_iter(&blob).checkpoint());
+	}
+
+	#[test]
+	fn resume_against_modified_blob() {
+		let entries = sample_entries();
+		let slices = as_slices(&entries);
+		let blob = byte_arrays_to_blob(&slices);
+		let mut iter = blob_iter(&blob);
+		iter.nth(5);
+		let checkpoint = iter.checkpoint();
+
+		// A payload byte in the entry just before the checkpoint
+		let mut changed = blob.clone();
+		changed[checkpoint.byte_offset - 1] ^= 1;
+		assert_eq!(resume_blob_iter(&changed, checkpoint).err(), Some(ResumeError::Mismatch));
+
+		// An entry made longer earlier in the blob, which moves everything after it
+		let mut longer = entries.clone();
+		longer[3].push(0);
+		let longer = entries_to_blob(&longer);
+		assert!(resume_blob_iter(&longer, checkpoint).is_err());
+
+		let truncated = &blob[..checkpoint.byte_offset - 1];
+		assert_eq!(resume_blob_iter(truncated, checkpoint).err(), Some(ResumeError::OffsetOutOfBounds { offset: checkpoint.byte_offset, len: truncated.len() }));
+
+		// The bytes before the checkpoint are fine but the index at it is not
+		let mut bad_header = blob.clone();
+		bad_header[checkpoint.byte_offset..checkpoint.byte_offset + 4].copy_from_slice(&u32::MAX.to_ne_bytes());
+		assert!(matches!(resume_blob_iter(&bad_header, checkpoint), Err(ResumeError::InvalidHeader(_))));
+
+		// An entry index that can't fit before the offset
+		let wrong_count = BlobCheckpoint { entry_index: checkpoint.byte_offset, ..checkpoint };
+		assert_eq!(resume_blob_iter(&blob, wrong_count).err(), Some(ResumeError::Mismatch));
+		let wrong_start = BlobCheckpoint { entry_index: 1, byte_offset: 0, digest: 0 };
+		assert_eq!(resume_blob_iter(&blob, wrong_start).err(), Some(ResumeError::Mismatch));
+	}
+}
@@ -0,0 +1,271 @@
+//! Coalescing runs of small entries into one entry per group, with a map to find each original entry within the merged ones
+//!
+//! # Layout
+//!
+//! The map is stored as one blob entry holding three little-endian u32s per original entry: the index of the coalesced entry holding it,
+//! the offset of its payload within that entry, and its length. A blob is at most `u32::MAX` bytes, so every value fits.
+use crate::*;
+use std::ops::Range;
+
+
+/// Where an entry of the original blob is after `blob_coalesce`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoalescedSlice {
+	/// The index of the coalesced entry holding it
+	pub entry: usize,
+	/// The offset of its payload within the coalesced entry
+	pub start: usize,
+	/// The length of its payload
+	pub len: usize
+}
+
+
+impl CoalescedSlice {
+	/// Returns the range of the payload within the coalesced entry
+	pub fn range(&self) -> Range<usize> {
+		self.start..self.start + self.len
+	}
+}
+
+
+/// The original entries of a blob made by `blob_coalesce`, by original index
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoalesceMap {
+	slices: Vec<CoalescedSlice>
+}
+
+
+impl CoalesceMap {
+	/// Returns the number of entries in the original blob
+	pub fn len(&self) -> usize {
+		self.slices.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.slices.is_empty()
+	}
+
+	/// Returns where entry `original_index` of the original blob is, or `None` if it is out of range
+	pub fn get(&self, original_index: usize) -> Option<CoalescedSlice> {
+		self.slices.get(original_index).copied()
+	}
+
+	/// Returns where every entry of the original blob is, by original index
+	pub fn slices(&self) -> &[CoalescedSlice] {
+		&self.slices
+	}
+
+	/// Returns the map as a single blob entry, so it can be stored along with the coalesced blob. See the module layout above
+	pub fn to_entry(&self) -> Vec<u8> {
+		let mut entry = Vec::with_capacity(self.slices.len() * 12);
+		for slice in &self.slices {
+			// `blob_coalesce` only makes slices within a blob, which fit in u32s
+			for value in [slice.entry, slice.start, slice.len] {
+				entry.extend((value as u32).to_le_bytes());
+			}
+		}
+		entry
+	}
+
+	/// Reads a map made by `to_entry`
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::TruncatedIndex)` with the position of the last slice if the entry isn't a whole number of slices,
+	/// or `Err(BlobReadError::InvalidMetadata)` with the original index of the first slice that ends past `u32::MAX` or isn't where `blob_coalesce` puts it,
+	/// straight after the slice before it in the same coalesced entry or at the start of the next one
+	pub fn from_entry(entry: &[u8]) -> Result<Self, BlobReadError> {
+		if !entry.len().is_multiple_of(12) {
+			return Err(BlobReadError::TruncatedIndex(entry.len() - entry.len() % 12));
+		}
+		let mut slices: Vec<CoalescedSlice> = Vec::with_capacity(entry.len() / 12);
+		for (i, row) in entry.chunks_exact(12).enumerate() {
+			let [entry, start, len] = [0, 4, 8].map(|at| u32::from_le_bytes(row[at..at + 4].try_into().unwrap()));
+			if start.checked_add(len).is_none() {
+				return Err(BlobReadError::InvalidMetadata(i));
+			}
+			let slice = CoalescedSlice { entry: index_to_usize(entry)?, start: index_to_usize(start)?, len: index_to_usize(len)? };
+			let in_place = match slices.last() {
+				Some(last) => (slice.entry == last.entry && slice.start == last.range().end) || (slice.entry == last.entry + 1 && slice.start == 0),
+				None => slice.entry == 0 && slice.start == 0
+			};
+			if !in_place {
+				return Err(BlobReadError::InvalidMetadata(i));
+			}
+			slices.push(slice);
+		}
+		Ok(CoalesceMap { slices })
+	}
+
+	/// Returns the length the map expects coalesced entry `entry` to have, from the end of the last slice in it
+	fn entry_len(&self, entry: usize) -> Option<usize> {
+		// Slices are in order of their coalesced entry, which both `blob_coalesce` and `from_entry` ensure
+		let after = self.slices.partition_point(|slice| slice.entry <= entry);
+		self.slices.get(after.checked_sub(1)?).filter(|slice| slice.entry == entry).map(|slice| slice.range().end)
+	}
+}
+
+
+/// Merges each run of consecutive entries that `group_of` gives the same key into one entry, their payloads concatenated in order,
+/// and returns the merged blob with a map back to the original entries
+///
+/// `group_of` is called with each original index and payload in order. Only consecutive entries are merged, so a key that comes back after
+/// a different one starts a new entry. Every merged entry saves its 4 byte index, and one read fetches the whole group.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"mesh:a", b"mesh:b", b"tex:a", b"mesh:c"]);
+/// let (coalesced, map) = blob_coalesce(&blob, |_, byte_arr| byte_arr[0] as u64).unwrap();
+///
+/// assert_eq!(blob_to_byte_arrays(&coalesced).unwrap(), [&b"mesh:amesh:b"[..], b"tex:a", b"mesh:c"]);
+/// assert_eq!(resolve_coalesced(&map, 1, &coalesced), Some(&b"mesh:b"[..]));
+///
+/// // The map fits in an entry, so it can be stored next to the blob
+/// let stored = byte_arrays_to_blob(&[&map.to_entry(), &coalesced]);
+/// let [map, coalesced] = blob_to_byte_arrays(&stored).unwrap()[..] else { unreachable!() };
+/// assert_eq!(resolve_coalesced(&CoalesceMap::from_entry(map).unwrap(), 3, coalesced), Some(&b"mesh:c"[..]));
+/// ```
+pub fn blob_coalesce(blob: &[u8], mut group_of: impl FnMut(usize, &[u8]) -> u64) -> Result<(Vec<u8>, CoalesceMap), BlobReadError> {
+	let mut coalesced = Vec::with_capacity(blob.len());
+	let mut slices = Vec::new();
+	// The key of the entry being filled, and where its index is
+	let mut current: Option<(u64, usize)> = None;
+	let mut entries = 0;
+
+	for (i, byte_arr) in blob_iter(blob).enumerate() {
+		let byte_arr = byte_arr?;
+		let key = group_of(i, byte_arr);
+		let idx_pos = match current {
+			Some((current_key, idx_pos)) if current_key == key => idx_pos,
+			_ => {
+				if let Some((_, idx_pos)) = current {
+					end_entry(&mut coalesced, idx_pos);
+				}
+				current = Some((key, coalesced.len()));
+				coalesced.extend([0; 4]);
+				entries += 1;
+				coalesced.len() - 4
+			}
+		};
+		slices.push(CoalescedSlice { entry: entries - 1, start: coalesced.len() - idx_pos - 4, len: byte_arr.len() });
+		coalesced.extend_from_slice(byte_arr);
+	}
+	if let Some((_, idx_pos)) = current {
+		end_entry(&mut coalesced, idx_pos);
+	}
+	Ok((coalesced, CoalesceMap { slices }))
+}
+
+
+/// Writes the end of the entry whose index is at `idx_pos`, which is the end of `coalesced` so far. The result is never longer than the original blob
+fn end_entry(coalesced: &mut [u8], idx_pos: usize) {
+	let idx_end = coalesced.len() as u32;
+	coalesced[idx_pos..idx_pos + 4].copy_from_slice(&idx_end.to_ne_bytes());
+}
+
+
+/// Returns entry `original_index` of the blob that `coalesced_blob` was coalesced from, exactly as it was,
+/// or `None` if it is out of range or the map doesn't fit `coalesced_blob`
+///
+/// The coalesced entry must be as long as the map expects, which catches most maps used with the wrong blob.
+/// Each call reads the indexes up to the coalesced entry. To resolve many entries, look up `CoalesceMap::get` in a `BlobView` instead.
+pub fn resolve_coalesced<'a>(map: &CoalesceMap, original_index: usize, coalesced_blob: &'a [u8]) -> Option<&'a [u8]> {
+	let slice = map.get(original_index)?;
+	let coalesced = blob_iter(coalesced_blob).nth(slice.entry)?.ok()?;
+	// An entry of another length can't be the one the map was made for
+	if Some(coalesced.len()) != map.entry_len(slice.entry) {
+		return None;
+	}
+	coalesced.get(slice.range())
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn every_entry_resolves() {
+		// Groups of tiny entries, with an empty entry and a group that comes back after another
+		let entries: Vec<Vec<u8>> = (0..300u32).map(|i| vec![i as u8; (i % 7) as usize]).collect();
+		let slices = as_slices(&entries);
+		let group = |i: usize| match i {
+			0..100 => 0,
+			100..150 => 1,
+			_ => (i / 25) as u64 % 2
+		};
+		let blob = byte_arrays_to_blob(&slices);
+		let (coalesced, map) = blob_coalesce(&blob, |i, _| group(i)).unwrap();
+
+		assert_eq!(map.len(), entries.len());
+		let view = BlobView::new(&coalesced).unwrap();
+		assert_eq!(view.len(), 2 + 6);
+		for (i, entry) in entries.iter().enumerate() {
+			assert_eq!(resolve_coalesced(&map, i, &coalesced), Some(&entry[..]));
+			let slice = map.get(i).unwrap();
+			assert_eq!(&view.get(slice.entry).unwrap()[slice.range()], &entry[..]);
+		}
+		assert_eq!(resolve_coalesced(&map, entries.len(), &coalesced), None);
+
+		// Concatenating each coalesced entry gives back every payload in order
+		assert_eq!(view.iter().flatten().copied().collect::<Vec<_>>(), entries.concat());
+		assert_eq!(CoalesceMap::from_entry(&map.to_entry()).unwrap(), map);
+	}
+
+	#[test]
+	fn header_overhead_drops() {
+		let entries: Vec<Vec<u8>> = (0..1000u32).map(|i| i.to_le_bytes().repeat(10)).collect();
+		let slices = as_slices(&entries);
+		let blob = byte_arrays_to_blob(&slices);
+		let (coalesced, _) = blob_coalesce(&blob, |i, _| i as u64 / 100).unwrap();
+
+		// 1000 entries become 10, saving 990 indexes of 4 bytes
+		assert_eq!(blob_to_byte_arrays(&coalesced).unwrap().len(), 10);
+		assert_eq!(blob.len() - coalesced.len(), 990 * 4);
+
+		// A key for every entry changes nothing
+		let (unchanged, map) = blob_coalesce(&blob, |i, _| i as u64).unwrap();
+		assert_eq!(unchanged, blob);
+		assert!(map.slices().iter().enumerate().all(|(i, slice)| slice.entry == i && slice.start == 0));
+	}
+
+	#[test]
+	fn edge_cases() {
+		let (coalesced, map) = blob_coalesce(&[], |_, _| 0).unwrap();
+		assert!(coalesced.is_empty() && map.is_empty());
+
+		let blob = byte_arrays_to_blob(&[b"", b""]);
+		let (coalesced, map) = blob_coalesce(&blob, |_, _| 0).unwrap();
+		assert_eq!(blob_to_byte_arrays(&coalesced).unwrap(), [b""]);
+		assert_eq!(resolve_coalesced(&map, 1, &coalesced), Some(&b""[..]));
+		assert_eq!(resolve_coalesced(&map, 0, &blob_coalesce(&byte_arrays_to_blob(&[b"x"]), |_, _| 0).unwrap().0), None);
+
+		assert!(blob_coalesce(&blob[..blob.len() - 1], |_, _| 0).is_err());
+		assert_eq!(CoalesceMap::from_entry(&[0; 13]), Err(BlobReadError::TruncatedIndex(12)));
+		assert_eq!(CoalesceMap::from_entry(&[0; 5]), Err(BlobReadError::TruncatedIndex(0)));
+		let mut overflowing = [0u8; 24];
+		overflowing[16..].copy_from_slice(&[255; 8]);
+		assert_eq!(CoalesceMap::from_entry(&overflowing), Err(BlobReadError::InvalidMetadata(1)));
+
+		// Slices that overlap, leave gaps or skip a coalesced entry aren't a map `blob_coalesce` made
+		let row = |values: [u32; 3]| values.map(u32::to_le_bytes).concat();
+		for bad in [[0, 1, 2], [0, 0, 1], [0, 5, 1], [2, 0, 1]] {
+			let entry = [row([0, 0, 2]), row(bad)].concat();
+			assert_eq!(CoalesceMap::from_entry(&entry), Err(BlobReadError::InvalidMetadata(1)), "{:?}", bad);
+		}
+		assert_eq!(CoalesceMap::from_entry(&row([1, 0, 0])), Err(BlobReadError::InvalidMetadata(0)));
+
+		// A map for a different blob resolves nothing rather than the wrong bytes
+		let (_, long_map) = blob_coalesce(&byte_arrays_to_blob(&[b"longer entry"]), |_, _| 0).unwrap();
+		assert_eq!(resolve_coalesced(&long_map, 0, &coalesced), None);
+	}
+}
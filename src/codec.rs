@@ -0,0 +1,321 @@
+//! `EntryCodec`, one extension point for transforms such as compression and obfuscation that change each payload on the way in and out
+use crate::*;
+
+
+/// Errors that can occur when encoding or decoding a blob through an `EntryCodec`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CodecError {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// The encoded entries would make a blob larger than `u32::MAX` bytes
+	TooLarge,
+	/// The codec could not encode an entry. The enum yields the index of the entry
+	EncodeFailed(usize),
+	/// The codec could not decode an entry, usually because it was encoded by a different codec or has been changed since.
+	/// The enum yields the index of the entry
+	DecodeFailed(usize)
+}
+
+
+impl CodecError {
+	/// Returns the index of the entry the codec failed on, if it was the codec that failed
+	pub fn entry(&self) -> Option<usize> {
+		match self {
+			CodecError::EncodeFailed(entry) | CodecError::DecodeFailed(entry) => Some(*entry),
+			CodecError::Read(_) | CodecError::TooLarge => None
+		}
+	}
+}
+
+
+impl std::error::Error for CodecError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			CodecError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for CodecError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for CodecError {
+	fn from(e: BlobReadError) -> Self {
+		CodecError::Read(e)
+	}
+}
+
+
+/// A reversible transform of the payloads of a blob, given the index of each entry so it can vary from entry to entry
+///
+/// `decode_entry` must undo `encode_entry` for the same index.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// /// Stores each entry reversed
+/// struct Reverse;
+///
+/// impl EntryCodec for Reverse {
+/// 	fn encode_entry(&self, _index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+/// 		Ok(data.iter().rev().copied().collect())
+/// 	}
+///
+/// 	fn decode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+/// 		self.encode_entry(index, data)
+/// 	}
+/// }
+///
+/// let blob = byte_arrays_to_blob_with_codec(&[b"abc"], &Reverse).unwrap();
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap(), [b"cba"]);
+/// assert_eq!(blob_to_byte_vecs_with_codec(&blob, &Reverse).unwrap(), [b"abc"]);
+/// ```
+pub trait EntryCodec {
+	/// Returns the bytes to store for entry `index`
+	///
+	/// # Errors
+	///
+	/// Returns `Err(CodecError::EncodeFailed(index))` if the entry can't be encoded
+	fn encode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError>;
+
+	/// Returns entry `index` from the bytes stored for it
+	///
+	/// # Errors
+	///
+	/// Returns `Err(CodecError::DecodeFailed(index))` if the stored bytes can't be decoded
+	fn decode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError>;
+}
+
+
+impl<C: EntryCodec + ?Sized> EntryCodec for &C {
+	fn encode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		(**self).encode_entry(index, data)
+	}
+
+	fn decode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		(**self).decode_entry(index, data)
+	}
+}
+
+
+/// A codec that stores entries as they are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdentityCodec;
+
+
+impl EntryCodec for IdentityCodec {
+	fn encode_entry(&self, _index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		Ok(data.to_vec())
+	}
+
+	fn decode_entry(&self, _index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		Ok(data.to_vec())
+	}
+}
+
+
+/// A codec that compresses each entry with PackBits, storing it the same way as `byte_arrays_to_compressed_blob`
+///
+/// A blob encoded with this codec can be read by `CompressedBlobView`, and a compressed blob can be decoded with it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PackBitsCodec;
+
+
+impl EntryCodec for PackBitsCodec {
+	fn encode_entry(&self, _index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		Ok(compress_entry(data))
+	}
+
+	fn decode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		EntryHandle::parse(index, data)
+			.and_then(|handle| handle.read_to_vec().ok())
+			.ok_or(CodecError::DecodeFailed(index))
+	}
+}
+
+
+/// A codec that XORs each entry with a repeating key, to keep entries from being read at a glance. This is obfuscation, not encryption
+///
+/// Byte `i` of every entry is XORed with byte `i % key.len()` of the key. An empty key leaves entries as they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XorCodec {
+	key: Vec<u8>
+}
+
+
+impl XorCodec {
+	pub fn new(key: &[u8]) -> Self {
+		XorCodec { key: key.to_vec() }
+	}
+
+	fn apply(&self, data: &[u8]) -> Vec<u8> {
+		match self.key.is_empty() {
+			true => data.to_vec(),
+			false => data.iter().zip(self.key.iter().cycle()).map(|(byte, key)| byte ^ key).collect()
+		}
+	}
+}
+
+
+impl EntryCodec for XorCodec {
+	fn encode_entry(&self, _index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		Ok(self.apply(data))
+	}
+
+	fn decode_entry(&self, _index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		Ok(self.apply(data))
+	}
+}
+
+
+/// A codec that encodes with `first` and then `second`, and decodes in the opposite order
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let codec = ChainCodec::new(PackBitsCodec, XorCodec::new(b"key"));
+/// let blob = byte_arrays_to_blob_with_codec(&[&[0u8; 1000]], &codec).unwrap();
+///
+/// assert!(blob.len() < 100);
+/// assert_eq!(blob_to_byte_vecs_with_codec(&blob, &codec).unwrap(), [vec![0u8; 1000]]);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChainCodec<A, B> {
+	first: A,
+	second: B
+}
+
+
+impl<A: EntryCodec, B: EntryCodec> ChainCodec<A, B> {
+	pub fn new(first: A, second: B) -> Self {
+		ChainCodec { first, second }
+	}
+
+	/// Returns the two codecs, in the order they encode
+	pub fn into_inner(self) -> (A, B) {
+		(self.first, self.second)
+	}
+}
+
+
+impl<A: EntryCodec, B: EntryCodec> EntryCodec for ChainCodec<A, B> {
+	fn encode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		self.second.encode_entry(index, &self.first.encode_entry(index, data)?)
+	}
+
+	fn decode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+		self.first.decode_entry(index, &self.second.decode_entry(index, data)?)
+	}
+}
+
+
+/// Converts a slice of u8 slices to a blob, storing each slice as `codec` encodes it
+///
+/// # Errors
+///
+/// Returns the first error from `codec`, or `Err(CodecError::TooLarge)` if the encoded slices would make a blob larger than `u32::MAX` bytes
+pub fn byte_arrays_to_blob_with_codec(bytes_2d: &[&[u8]], codec: &impl EntryCodec) -> Result<Vec<u8>, CodecError> {
+	let mut blob = Vec::with_capacity(encoded_len(bytes_2d));
+	for (index, byte_arr) in bytes_2d.iter().enumerate() {
+		let encoded = codec.encode_entry(index, byte_arr)?;
+		let idx_end = u32::try_from(blob.len() + 4 + encoded.len()).map_err(|_| CodecError::TooLarge)?;
+		blob.extend(idx_end.to_ne_bytes());
+		blob.extend_from_slice(&encoded);
+	}
+	Ok(blob)
+}
+
+
+/// Converts a blob u8 slice to a Vec of owned u8 Vecs, decoding each slice with `codec`
+///
+/// # Errors
+///
+/// Returns the first error from `codec`, otherwise the same as `blob_to_byte_vecs` for the slices up to it
+pub fn blob_to_byte_vecs_with_codec(blob: &[u8], codec: &impl EntryCodec) -> Result<Vec<Vec<u8>>, CodecError> {
+	blob_iter(blob).enumerate().map(|(index, byte_arr)| codec.decode_entry(index, byte_arr?)).collect()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	/// Refuses entries that are too long, and checks on decode that no entry could have come from a longer one
+	struct MaxLen(usize);
+
+	impl EntryCodec for MaxLen {
+		fn encode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+			match data.len() <= self.0 {
+				true => Ok(data.to_vec()),
+				false => Err(CodecError::EncodeFailed(index))
+			}
+		}
+
+		fn decode_entry(&self, index: usize, data: &[u8]) -> Result<Vec<u8>, CodecError> {
+			match data.len() <= self.0 {
+				true => Ok(data.to_vec()),
+				false => Err(CodecError::DecodeFailed(index))
+			}
+		}
+	}
+
+	#[test]
+	fn compress_then_xor_round_trip() {
+		let entries = sample_entries();
+		let slices = as_slices(&entries);
+		let codec = ChainCodec::new(PackBitsCodec, XorCodec::new(b"obfuscate"));
+
+		let blob = byte_arrays_to_blob_with_codec(&slices, &codec).unwrap();
+		assert!(blob.len() < byte_arrays_to_blob(&slices).len() / 10);
+		assert_eq!(blob_to_byte_vecs_with_codec(&blob, &codec).unwrap(), entries);
+
+		// Only the XOR is undone, so what is left is exactly the compressed blob
+		let stored = blob_to_byte_vecs_with_codec(&blob, &XorCodec::new(b"obfuscate")).unwrap();
+		assert_eq!(entries_to_blob(&stored), byte_arrays_to_compressed_blob(&slices));
+
+		// Decoding in the wrong order fails on the first entry whose header doesn't survive
+		let backwards = ChainCodec::new(XorCodec::new(b"obfuscate"), PackBitsCodec);
+		assert_eq!(blob_to_byte_vecs_with_codec(&blob, &backwards), Err(CodecError::DecodeFailed(0)));
+	}
+
+	#[test]
+	fn built_in_codecs() {
+		let entries = sample_entries();
+		let slices = as_slices(&entries);
+		assert_eq!(byte_arrays_to_blob_with_codec(&slices, &IdentityCodec).unwrap(), byte_arrays_to_blob(&slices));
+		assert_eq!(byte_arrays_to_blob_with_codec(&slices, &XorCodec::new(&[])).unwrap(), byte_arrays_to_blob(&slices));
+
+		let compressed = byte_arrays_to_compressed_blob(&slices);
+		assert_eq!(byte_arrays_to_blob_with_codec(&slices, &PackBitsCodec).unwrap(), compressed);
+		assert_eq!(blob_to_byte_vecs_with_codec(&compressed, &PackBitsCodec).unwrap(), entries);
+
+		let xored = byte_arrays_to_blob_with_codec(&[b"abcd"], &XorCodec::new(&[1, 2])).unwrap();
+		assert_eq!(blob_to_byte_arrays(&xored).unwrap(), [&[b'a' ^ 1, b'b' ^ 2, b'c' ^ 1, b'd' ^ 2][..]]);
+	}
+
+	#[test]
+	fn errors_carry_entry_index() {
+		let slices: [&[u8]; 3] = [b"ok", b"too long", b"ok"];
+		let encode_error = byte_arrays_to_blob_with_codec(&slices, &ChainCodec::new(IdentityCodec, MaxLen(4))).unwrap_err();
+		assert_eq!(encode_error, CodecError::EncodeFailed(1));
+		assert_eq!(encode_error.entry(), Some(1));
+
+		let blob = byte_arrays_to_blob(&slices);
+		assert_eq!(blob_to_byte_vecs_with_codec(&blob, &MaxLen(4)), Err(CodecError::DecodeFailed(1)));
+		assert_eq!(blob_to_byte_vecs_with_codec(&blob, &PackBitsCodec), Err(CodecError::DecodeFailed(0)));
+
+		let error = blob_to_byte_vecs_with_codec(&blob[..blob.len() - 1], &IdentityCodec).unwrap_err();
+		assert!(matches!(error, CodecError::Read(_)));
+		assert_eq!(error.entry(), None);
+	}
+}
@@ -0,0 +1,173 @@
+//! Functions for joining blobs together and splitting them apart
+use crate::*;
+
+
+/// Joins several blobs into one, in order. Every index is rewritten, since indexes are positions within the whole blob
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` if any of the blobs can not be read
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let a = byte_arrays_to_blob(&[&[1], &[2]]);
+/// let b = byte_arrays_to_blob(&[&[3]]);
+///
+/// let joined = blob_concat(&[&a, &b]).unwrap();
+/// assert_eq!(joined, byte_arrays_to_blob(&[&[1], &[2], &[3]]));
+/// ```
+pub fn blob_concat(blobs: &[&[u8]]) -> Result<Vec<u8>, BlobReadError> {
+	let mut byte_arrays = Vec::new();
+	for blob in blobs {
+		byte_arrays.extend(blob_to_byte_arrays(blob)?);
+	}
+	Ok(byte_arrays_to_blob(&byte_arrays))
+}
+
+
+/// Errors that can occur when partitioning a blob
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PartitionError {
+	/// A single entry, including its 4 byte index, is larger than the size of a partition
+	EntryTooLargeForPartition {
+		entry: usize,
+		size: usize
+	},
+	/// The blob being partitioned is invalid
+	Read(BlobReadError)
+}
+
+
+impl std::error::Error for PartitionError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			PartitionError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for PartitionError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for PartitionError {
+	fn from(e: BlobReadError) -> Self {
+		PartitionError::Read(e)
+	}
+}
+
+
+/// Splits a blob into consecutive blobs that are each no larger than `max_bytes`, such as for sending in size limited frames
+///
+/// Each piece is a complete blob that can be decoded by itself, and entries are never split between pieces.
+/// Pieces are filled greedily, and an empty blob gives no pieces.
+///
+/// # Errors
+///
+/// Returns `Err(PartitionError::EntryTooLargeForPartition)` if one entry can not fit in a piece by itself,
+/// or `Err(PartitionError::Read)` if the blob can not be read
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1; 4], &[2; 4], &[3; 4]]);
+///
+/// // Each entry takes 8 bytes, so only two fit in 20
+/// let pieces = blob_partition_by_size(&blob, 20).unwrap();
+/// assert_eq!(pieces.len(), 2);
+///
+/// let pieces: Vec<&[u8]> = pieces.iter().map(|piece| piece.as_slice()).collect();
+/// assert_eq!(reassemble_partitions(&pieces).unwrap(), blob);
+/// ```
+pub fn blob_partition_by_size(blob: &[u8], max_bytes: usize) -> Result<Vec<Vec<u8>>, PartitionError> {
+	let mut pieces = Vec::new();
+	let mut current: Vec<&[u8]> = Vec::new();
+	let mut current_size = 0;
+
+	for (i, byte_arr) in blob_iter(blob).enumerate() {
+		let byte_arr = byte_arr?;
+		let size = 4 + byte_arr.len();
+		if size > max_bytes {
+			return Err(PartitionError::EntryTooLargeForPartition { entry: i, size });
+		}
+
+		if current_size + size > max_bytes {
+			pieces.push(byte_arrays_to_blob(&current));
+			current.clear();
+			current_size = 0;
+		}
+		current.push(byte_arr);
+		current_size += size;
+	}
+
+	if !current.is_empty() {
+		pieces.push(byte_arrays_to_blob(&current));
+	}
+	Ok(pieces)
+}
+
+
+/// Joins the pieces made by `blob_partition_by_size` back into the original blob. This is the same as `blob_concat`
+pub fn reassemble_partitions(pieces: &[&[u8]]) -> Result<Vec<u8>, BlobReadError> {
+	blob_concat(pieces)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pieces_as_slices(pieces: &[Vec<u8>]) -> Vec<&[u8]> {
+		pieces.iter().map(|piece| piece.as_slice()).collect()
+	}
+
+	#[test]
+	fn one_entry_per_piece() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 10], &[2u8; 10], &[3u8; 9]]);
+
+		// 14 fits any single entry but never two
+		let pieces = blob_partition_by_size(&blob, 14).unwrap();
+		assert_eq!(pieces.len(), 3);
+		for (piece, expected) in pieces.iter().zip([&[1u8; 10][..], &[2u8; 10], &[3u8; 9]]) {
+			assert_eq!(blob_to_byte_arrays(piece).unwrap(), vec![expected]);
+		}
+		assert_eq!(reassemble_partitions(&pieces_as_slices(&pieces)).unwrap(), blob);
+	}
+
+	#[test]
+	fn empty_entries_share_pieces() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 10], &[], &[2u8; 10]]);
+
+		let pieces = blob_partition_by_size(&blob, 18).unwrap();
+		assert_eq!(blob_to_byte_arrays(&pieces[0]).unwrap(), vec![&[1u8; 10][..], &[]]);
+		assert_eq!(pieces.len(), 2);
+		assert_eq!(reassemble_partitions(&pieces_as_slices(&pieces)).unwrap(), blob);
+	}
+
+	#[test]
+	fn budget_smaller_than_first_entry() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 10], &[2u8]]);
+		match blob_partition_by_size(&blob, 13) {
+			Err(PartitionError::EntryTooLargeForPartition { entry, size }) => assert_eq!((entry, size), (0, 14)),
+			other => panic!("Expected PartitionError::EntryTooLargeForPartition, got {:?}", other)
+		}
+	}
+
+	#[test]
+	fn empty_and_whole() {
+		assert!(blob_partition_by_size(&[], 0).unwrap().is_empty());
+
+		let blob = byte_arrays_to_blob(&[&[1u8], &[2u8]]);
+		assert_eq!(blob_partition_by_size(&blob, blob.len()).unwrap(), vec![blob.clone()]);
+		assert!(blob_concat(&[&blob, &[1u8]]).is_err());
+	}
+}
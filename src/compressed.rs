@@ -0,0 +1,281 @@
+//! Compressed blobs, where each entry is compressed separately so it can be read without decompressing the others
+//!
+//! # Layout
+//!
+//! A compressed blob is an ordinary blob with one slice per entry. Each slice starts with a flags byte and the little-endian u32 decompressed length, followed by the entry data.
+//! If bit 0 of the flags is set the data is PackBits compressed, otherwise it is stored as is.
+//! Entries that would not get smaller are stored uncompressed.
+use crate::*;
+use crate::packbits::{self, Decompressor};
+use std::borrow::Cow;
+use std::io;
+
+
+const COMPRESSED: u8 = 1;
+const ENTRY_HEADER_LEN: usize = 5;
+
+
+/// Converts slices to a compressed blob. See the module layout above
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let data = [7u8; 1000];
+/// let blob = byte_arrays_to_compressed_blob(&[&data, b"short"]);
+/// assert!(blob.len() < 100);
+///
+/// let view = CompressedBlobView::new(&blob).unwrap();
+/// assert_eq!(view.get(0).unwrap().read_to_vec().unwrap(), data);
+/// assert_eq!(view.get(1).unwrap().read().unwrap(), &b"short"[..]);
+/// ```
+pub fn byte_arrays_to_compressed_blob(bytes_2d: &[&[u8]]) -> Vec<u8> {
+	let entries: Vec<Vec<u8>> = bytes_2d.iter().map(|byte_arr| compress_entry(byte_arr)).collect();
+	byte_arrays_to_blob(&entries.iter().map(|entry| entry.as_slice()).collect::<Vec<_>>())
+}
+
+
+/// Returns the slice a compressed blob stores for `byte_arr`, with its header
+pub(crate) fn compress_entry(byte_arr: &[u8]) -> Vec<u8> {
+	let compressed = packbits::compress(byte_arr);
+	let (flags, data) = match compressed.len() < byte_arr.len() {
+		true => (COMPRESSED, compressed.as_slice()),
+		false => (0, byte_arr)
+	};
+
+	let mut entry = Vec::with_capacity(ENTRY_HEADER_LEN + data.len());
+	entry.push(flags);
+	entry.extend((byte_arr.len() as u32).to_le_bytes());
+	entry.extend(data);
+	entry
+}
+
+
+/// A borrowed compressed blob with random access to entries. Entries are only decompressed when they are read
+#[derive(Debug, Clone)]
+pub struct CompressedBlobView<'a> {
+	view: BlobView<'a>
+}
+
+
+impl<'a> CompressedBlobView<'a> {
+	/// Reads every index and entry header in `blob`. No entry is decompressed
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InvalidMetadata)` with the entry index if an entry header is malformed, otherwise the same as `blob_to_byte_arrays`
+	pub fn new(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		let view = BlobView::new(blob)?;
+		for (i, byte_arr) in view.iter().enumerate() {
+			if EntryHandle::parse(i, byte_arr).is_none() {
+				return Err(BlobReadError::InvalidMetadata(i));
+			}
+		}
+		Ok(CompressedBlobView { view })
+	}
+
+	/// Returns the number of entries
+	pub fn len(&self) -> usize {
+		self.view.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.view.is_empty()
+	}
+
+	/// Returns a handle to entry `index` without decompressing it, or `None` if it is out of range
+	pub fn get(&self, index: usize) -> Option<EntryHandle<'a>> {
+		EntryHandle::parse(index, self.view.get(index)?)
+	}
+
+	/// Returns handles to every entry, in order
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = EntryHandle<'a>> + ExactSizeIterator + '_ {
+		(0..self.len()).map(|index| self.get(index).unwrap())
+	}
+}
+
+
+/// An entry of a `CompressedBlobView`. Nothing is decompressed until the data is read
+#[derive(Debug, Clone, Copy)]
+pub struct EntryHandle<'a> {
+	index: usize,
+	compressed: bool,
+	decompressed_len: usize,
+	data: &'a [u8]
+}
+
+
+impl<'a> EntryHandle<'a> {
+	/// Reads the header of slice `index` of a compressed blob, or returns `None` if it is malformed
+	pub(crate) fn parse(index: usize, byte_arr: &'a [u8]) -> Option<Self> {
+		match byte_arr.first() {
+			Some(flags) if byte_arr.len() >= ENTRY_HEADER_LEN && flags & !COMPRESSED == 0 => Some(EntryHandle {
+				index,
+				compressed: flags & COMPRESSED != 0,
+				decompressed_len: u32::from_le_bytes(byte_arr[1..ENTRY_HEADER_LEN].try_into().unwrap()) as usize,
+				data: &byte_arr[ENTRY_HEADER_LEN..]
+			}),
+			_ => None
+		}
+	}
+
+	/// Returns true if the entry is stored compressed
+	pub fn is_compressed(&self) -> bool {
+		self.compressed
+	}
+
+	/// Returns the number of bytes the entry takes in the blob, not including its header
+	pub fn compressed_len(&self) -> usize {
+		self.data.len()
+	}
+
+	/// Returns the length of the entry once decompressed, as recorded when it was encoded
+	pub fn decompressed_len(&self) -> usize {
+		self.decompressed_len
+	}
+
+	/// Returns the entry data. Entries stored uncompressed are borrowed from the blob without copying
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InvalidCompressedData)` with the entry index if the data is malformed or does not match the recorded length
+	pub fn read(&self) -> Result<Cow<'a, [u8]>, BlobReadError> {
+		if !self.compressed {
+			if self.data.len() != self.decompressed_len {
+				return Err(BlobReadError::InvalidCompressedData(self.index));
+			}
+			return Ok(Cow::Borrowed(self.data));
+		}
+		packbits::decompress(self.data, self.decompressed_len)
+			.map(Cow::Owned)
+			.map_err(|_| BlobReadError::InvalidCompressedData(self.index))
+	}
+
+	/// Returns an owned copy of the entry data. See `read`
+	pub fn read_to_vec(&self) -> Result<Vec<u8>, BlobReadError> {
+		self.read().map(Cow::into_owned)
+	}
+
+	/// Returns a reader that decompresses the entry as it is read
+	///
+	/// Malformed data, or data that does not match the recorded length, is reported as an `io::Error` of kind `InvalidData`
+	pub fn reader(&self) -> impl io::Read + 'a {
+		EntryReader::new(*self)
+	}
+}
+
+
+/// Reader returned by `EntryHandle::reader`
+struct EntryReader<'a> {
+	inner: Result<Decompressor<'a>, &'a [u8]>,
+	/// Set for a stored entry whose data is not the recorded length, which `read` checks up front
+	length_mismatch: bool
+}
+
+
+impl<'a> EntryReader<'a> {
+	fn new(handle: EntryHandle<'a>) -> Self {
+		let inner = match handle.compressed {
+			true => Ok(Decompressor::new(handle.data, handle.decompressed_len)),
+			false => Err(handle.data)
+		};
+		let length_mismatch = !handle.compressed && handle.data.len() != handle.decompressed_len;
+		EntryReader { inner, length_mismatch }
+	}
+}
+
+
+impl io::Read for EntryReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.length_mismatch {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "stored entry does not match its recorded length"));
+		}
+		match &mut self.inner {
+			Ok(decompressor) => {
+				let n = decompressor.read(buf)?;
+				if n == 0 && !buf.is_empty() && decompressor.remaining() > 0 {
+					return Err(io::Error::new(io::ErrorKind::InvalidData, "compressed entry ended early"));
+				}
+				Ok(n)
+			},
+			Err(stored) => stored.read(buf)
+		}
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+	use std::io::Read;
+
+	#[test]
+	fn lazy_and_eager_reads_agree() {
+		let entries = sample_entries();
+		let blob = byte_arrays_to_compressed_blob(&as_slices(&entries));
+		let view = CompressedBlobView::new(&blob).unwrap();
+
+		for (handle, entry) in view.iter().zip(&entries) {
+			assert_eq!(handle.decompressed_len(), entry.len());
+			assert_eq!(&handle.read_to_vec().unwrap(), entry);
+
+			// Read through a small buffer so runs are split across reads
+			let mut streamed: Vec<u8> = Vec::new();
+			let mut buf = [0u8; 7];
+			let mut reader = handle.reader();
+			loop {
+				match reader.read(&mut buf).unwrap() {
+					0 => break,
+					n => streamed.extend(&buf[..n])
+				}
+			}
+			assert_eq!(&streamed, entry);
+		}
+	}
+
+	#[test]
+	fn stored_entries_are_borrowed() {
+		let blob = byte_arrays_to_compressed_blob(&[b"abc", &[1u8; 100]]);
+		let view = CompressedBlobView::new(&blob).unwrap();
+
+		let stored = view.get(0).unwrap();
+		assert!(!stored.is_compressed());
+		assert!(matches!(stored.read().unwrap(), Cow::Borrowed(b"abc")));
+
+		let compressed = view.get(1).unwrap();
+		assert!(compressed.is_compressed());
+		assert!(compressed.compressed_len() < compressed.decompressed_len());
+		assert!(matches!(compressed.read().unwrap(), Cow::Owned(_)));
+	}
+
+	#[test]
+	fn corrupt_entries() {
+		let blob = byte_arrays_to_blob(&[&[0u8, 1u8, 0u8, 0u8]]);
+		assert_eq!(CompressedBlobView::new(&blob).unwrap_err(), BlobReadError::InvalidMetadata(0));
+
+		// The recorded length says 50 bytes, but the data only holds 10
+		let mut entry = vec![COMPRESSED];
+		entry.extend(50u32.to_le_bytes());
+		entry.extend(packbits::compress(&[1u8; 10]));
+		let blob = byte_arrays_to_blob(&[&entry]);
+		let view = CompressedBlobView::new(&blob).unwrap();
+
+		let handle = view.get(0).unwrap();
+		assert_eq!(handle.read().unwrap_err(), BlobReadError::InvalidCompressedData(0));
+		assert_eq!(handle.reader().read_to_end(&mut Vec::new()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+		// A stored entry with a recorded length of 5 but only 3 bytes of data
+		let mut entry = vec![0u8];
+		entry.extend(5u32.to_le_bytes());
+		entry.extend(b"abc");
+		let blob = byte_arrays_to_blob(&[&entry]);
+		let view = CompressedBlobView::new(&blob).unwrap();
+
+		let handle = view.get(0).unwrap();
+		assert_eq!(handle.read().unwrap_err(), BlobReadError::InvalidCompressedData(0));
+		assert_eq!(handle.reader().read_to_end(&mut Vec::new()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+	}
+}
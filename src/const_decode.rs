@@ -0,0 +1,144 @@
+//! `const fn` checks of a blob, so a blob embedded with `include_bytes!` can be checked when the crate is compiled
+//!
+//! These only use loops and integer operations that are allowed in `const fn`, and never allocate. They work on every Rust version the crate
+//! supports, which is 1.87 and later.
+use crate::*;
+
+
+/// Errors that can occur when reading a blob in a `const fn`. These are the errors of `blob_to_byte_arrays`, without any that need
+/// a formatted layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConstBlobError {
+	/// An index points outside the blob or before the data it follows. The enum yields the offending index
+	InvalidEncodedIndex(usize),
+	/// The blob ends part way through an index. The enum yields the position of the truncated index
+	TruncatedIndex(usize),
+	/// The blob is larger than `u32::MAX` bytes
+	TooLarge,
+	/// An index is too large for the platform's usize. The enum yields the index as it was encoded
+	IndexUnrepresentable {
+		encoded: u32
+	},
+	/// The first index doesn't fit the blob, so the data is very likely not a blob. The enum yields the first index as it was encoded
+	NotABlob {
+		first_header: u32
+	}
+}
+
+
+impl std::error::Error for ConstBlobError {}
+
+impl fmt::Display for ConstBlobError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<ConstBlobError> for BlobReadError {
+	fn from(e: ConstBlobError) -> Self {
+		match e {
+			ConstBlobError::InvalidEncodedIndex(idx) => BlobReadError::InvalidEncodedIndex(idx),
+			ConstBlobError::TruncatedIndex(pos) => BlobReadError::TruncatedIndex(pos),
+			ConstBlobError::TooLarge => BlobReadError::TooLarge,
+			ConstBlobError::IndexUnrepresentable { encoded } => BlobReadError::IndexUnrepresentable { encoded },
+			ConstBlobError::NotABlob { first_header } => BlobReadError::NotABlob { first_header }
+		}
+	}
+}
+
+
+/// Returns the number of slices in a blob, checking every index in the same way as `count_arrays`
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`, as a `ConstBlobError`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// const BLOB: &[u8] = &[5, 0, 0, 0, 42, 9, 0, 0, 0];
+/// # #[cfg(target_endian = "little")]
+/// const COUNT: usize = match const_count_entries(BLOB) {
+/// 	Ok(count) => count,
+/// 	Err(_) => panic!("embedded blob is invalid")
+/// };
+/// # #[cfg(target_endian = "little")]
+/// assert_eq!(COUNT, 2);
+/// ```
+pub const fn const_count_entries(blob: &[u8]) -> Result<usize, ConstBlobError> {
+	if blob.len() > MAX_U32_LEN {
+		return Err(ConstBlobError::TooLarge);
+	}
+
+	let mut count = 0;
+	let mut idx_start = 0;
+	while idx_start < blob.len() {
+		if blob.len() - idx_start < 4 {
+			return Err(ConstBlobError::TruncatedIndex(idx_start));
+		}
+		let encoded = u32::from_ne_bytes([blob[idx_start], blob[idx_start + 1], blob[idx_start + 2], blob[idx_start + 3]]);
+		let idx_end = match checked_index(encoded, usize::MAX as u64) {
+			Some(idx_end) => idx_end,
+			None => return Err(ConstBlobError::IndexUnrepresentable { encoded })
+		};
+		if idx_end > blob.len() || idx_end < idx_start + 4 {
+			if idx_start == 0 {
+				return Err(ConstBlobError::NotABlob { first_header: encoded });
+			}
+			return Err(ConstBlobError::InvalidEncodedIndex(idx_end));
+		}
+		idx_start = idx_end;
+		count += 1;
+	}
+	Ok(count)
+}
+
+
+/// Returns true if every index in a blob is valid, so `blob_to_byte_arrays` would succeed
+///
+/// # Examples
+/// A `const` item fails to compile if an embedded blob is invalid.
+/// ```compile_fail
+/// use byte_array_blob::*;
+///
+/// const BLOB: &[u8] = &[255, 255, 255, 255];
+/// const _: () = assert!(const_validate(BLOB));
+/// ```
+/// An empty blob is valid
+/// ```
+/// use byte_array_blob::*;
+///
+/// const _: () = assert!(const_validate(&[]));
+/// ```
+pub const fn const_validate(blob: &[u8]) -> bool {
+	const_count_entries(blob).is_ok()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matches_count_arrays() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 9], &[], &[2u8]]);
+		assert_eq!(const_count_entries(&blob), Ok(3));
+		assert_eq!(const_count_entries(&[]), Ok(0));
+
+		for len in 0..blob.len() {
+			let prefix = &blob[..len];
+			assert_eq!(const_count_entries(prefix).map_err(BlobReadError::from), count_arrays(prefix));
+			assert_eq!(const_validate(prefix), blob_to_byte_arrays(prefix).is_ok());
+		}
+		assert_eq!(const_count_entries(&[255u8; 4]), Err(ConstBlobError::NotABlob { first_header: u32::MAX }));
+
+		let mut blob = blob;
+		blob[13..17].copy_from_slice(&u32::MAX.to_ne_bytes());
+		assert_eq!(const_count_entries(&blob), Err(ConstBlobError::InvalidEncodedIndex(u32::MAX as usize)));
+		assert_eq!(const_count_entries(&blob).map_err(BlobReadError::from), count_arrays(&blob));
+	}
+}
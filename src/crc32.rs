@@ -0,0 +1,72 @@
+//! CRC-32 (the IEEE polynomial used by zip, gzip and PNG), for digests that can be updated a chunk at a time
+
+const TABLE: [u32; 256] = make_table();
+
+const fn make_table() -> [u32; 256] {
+	let mut table = [0u32; 256];
+	let mut i = 0;
+	while i < 256 {
+		let mut crc = i as u32;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = match crc & 1 {
+				1 => (crc >> 1) ^ 0xEDB8_8320,
+				_ => crc >> 1
+			};
+			bit += 1;
+		}
+		table[i] = crc;
+		i += 1;
+	}
+	table
+}
+
+
+/// A running CRC-32
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Crc32(u32);
+
+
+impl Crc32 {
+	pub(crate) fn new() -> Self {
+		Crc32(0xFFFF_FFFF)
+	}
+
+	pub(crate) fn update(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 = TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+		}
+	}
+
+	pub(crate) fn finish(self) -> u32 {
+		!self.0
+	}
+}
+
+
+/// Returns the CRC-32 of `bytes`
+#[cfg(test)]
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+	let mut crc = Crc32::new();
+	crc.update(bytes);
+	crc.finish()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn known_values() {
+		assert_eq!(crc32(b""), 0);
+		assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+		let mut crc = Crc32::new();
+		crc.update(b"1234");
+		crc.update(b"56789");
+		assert_eq!(crc.finish(), 0xCBF4_3926);
+	}
+}
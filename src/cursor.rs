@@ -0,0 +1,162 @@
+//! `BlobCursor`, for reading a blob one entry at a time by hand
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+
+
+/// A position in a blob that can look at the next entry's length before deciding to read or skip it
+///
+/// Errors never move the cursor: after any method returns `Err`, the cursor stays at the index that failed,
+/// so calling it again returns the same error. `rewind` goes back to the start.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1; 1000], &[2, 2]]);
+/// let mut cursor = BlobCursor::new(&blob);
+///
+/// // Skip anything too large to be interesting
+/// while let Some(len) = cursor.peek_len().unwrap() {
+/// 	if len > 100 {
+/// 		cursor.skip().unwrap();
+/// 	} else {
+/// 		assert_eq!(cursor.next().unwrap(), Some(&[2, 2][..]));
+/// 	}
+/// }
+/// assert_eq!(cursor.remaining_bytes(), 0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlobCursor<'a> {
+	blob: &'a [u8],
+	position: usize,
+	/// The number of the next entry to be read
+	entry: usize
+}
+
+
+impl<'a> BlobCursor<'a> {
+	/// Creates a cursor at the start of `blob`
+	pub fn new(blob: &'a [u8]) -> Self {
+		BlobCursor { blob, position: 0, entry: 0 }
+	}
+
+	/// Reads the entry at the cursor without moving it, returning the entry and where the next index starts
+	fn read_at(&self) -> Result<Option<(&'a [u8], usize)>, BlobReadError> {
+		let mut iter = BlobIter { blob: self.blob, format: BlobFormat::NativeEndian, idx_start: self.position, entry: 0, done: false };
+		match iter.next() {
+			Some(byte_arr) => Ok(Some((byte_arr?, iter.position()))),
+			None => Ok(None)
+		}
+	}
+
+	/// Returns the length of the next entry, or `None` at the end of the blob. The cursor does not move
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`, for the next index only
+	pub fn peek_len(&self) -> Result<Option<usize>, BlobReadError> {
+		Ok(self.read_at()?.map(|(byte_arr, _)| byte_arr.len()))
+	}
+
+	/// Returns the next entry and moves past it, or `None` at the end of the blob
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`, for the next index only. The cursor does not move
+	#[allow(clippy::should_implement_trait)]
+	pub fn next(&mut self) -> Result<Option<&'a [u8]>, BlobReadError> {
+		Ok(self.read_at()?.map(|(byte_arr, next)| {
+			self.position = next;
+			self.entry += 1;
+			byte_arr
+		}))
+	}
+
+	/// Moves past the next entry, returning false if the cursor was already at the end of the blob
+	///
+	/// # Errors
+	///
+	/// The same as `next`
+	pub fn skip(&mut self) -> Result<bool, BlobReadError> {
+		Ok(self.next()?.is_some())
+	}
+
+	/// Returns the number of bytes after the cursor
+	pub fn remaining_bytes(&self) -> usize {
+		self.blob.len() - self.position
+	}
+
+	/// Returns the position in the blob of the next index to be read
+	pub fn position(&self) -> usize {
+		self.position
+	}
+
+	/// Returns a checkpoint for the next entry to be read, which `resume_blob_iter` can carry on from later
+	pub fn checkpoint(&self) -> BlobCheckpoint {
+		BlobCheckpoint::new(self.blob, self.entry, self.position)
+	}
+
+	/// Moves the cursor back to the start of the blob
+	pub fn rewind(&mut self) {
+		self.position = 0;
+		self.entry = 0;
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn interleaved_calls_match_full_decode() {
+		let entries: Vec<Vec<u8>> = (0..30).map(|i| vec![i as u8; i % 7]).collect();
+		let blob = entries_to_blob(&entries);
+		let decoded = blob_to_byte_arrays(&blob).unwrap();
+
+		let mut cursor = BlobCursor::new(&blob);
+		let mut collected = Vec::new();
+		let mut skipped = 0;
+		for step in 0.. {
+			let Some(len) = cursor.peek_len().unwrap() else { break };
+			assert_eq!(cursor.peek_len().unwrap(), Some(len));
+			if step % 3 == 0 {
+				assert!(cursor.skip().unwrap());
+				skipped += 1;
+			} else {
+				collected.push(cursor.next().unwrap().unwrap());
+			}
+		}
+		assert_eq!(cursor.next(), Ok(None));
+		assert_eq!(cursor.skip(), Ok(false));
+		assert_eq!(cursor.remaining_bytes(), 0);
+		assert_eq!(collected.len() + skipped, decoded.len());
+
+		// Reading everything after a rewind gives the full decode
+		cursor.rewind();
+		assert_eq!(cursor.remaining_bytes(), blob.len());
+		let all: Vec<&[u8]> = std::iter::from_fn(|| cursor.next().unwrap()).collect();
+		assert_eq!(all, decoded);
+	}
+
+	#[test]
+	fn errors_leave_cursor_in_place() {
+		let mut blob = byte_arrays_to_blob(&[&[1u8], &[2u8, 2u8]]);
+		blob.truncate(blob.len() - 1);
+
+		let mut cursor = BlobCursor::new(&blob);
+		assert_eq!(cursor.next(), Ok(Some(&[1u8][..])));
+		let position = cursor.position();
+
+		let error = cursor.peek_len().unwrap_err();
+		assert_eq!(cursor.next(), Err(error.clone()));
+		assert_eq!(cursor.skip(), Err(error));
+		assert_eq!(cursor.position(), position);
+
+		cursor.rewind();
+		assert_eq!(cursor.peek_len(), Ok(Some(1)));
+	}
+}
@@ -0,0 +1,532 @@
+//! The core decoders, which read the native layout straight from the blob
+//!
+//! Nothing in this module indexes or unwraps: every read from the blob goes through `get`, so an index that doesn't fit becomes an error instead of a panic.
+//! The lints below keep it that way.
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+use std::iter::FusedIterator;
+#[cfg(test)]
+use std::cell::Cell;
+
+
+/// Converts a blob u8 slice to a Vec of u8 slices
+///
+/// Every slice takes at least 4 bytes of the blob, so there are never more than `blob.len() / 4` of them. On top of that the decode is capped at
+/// `DecodeOptions::DEFAULT_MAX_ENTRIES` slices, so a blob made of nothing but empty slices can't make the result Vec many times larger than the input.
+/// Use `blob_to_byte_arrays_with_options` to raise or lower the cap.
+///
+/// # Errors
+///
+/// The function returns `Err(BlobReadError)` when an index in the data is invalid or cut short, or when the length of the data is more than `u32::MAX`.
+/// Returns `Err(BlobReadError::TooManyEntries)` if the blob holds more than `DecodeOptions::DEFAULT_MAX_ENTRIES` slices
+///
+/// # Examples
+///
+/// ```
+/// use byte_array_blob::blob_to_byte_arrays;
+///
+/// // The first four bytes in the blob point to the end of the slice (index 8)
+/// let blob = [8u8, 0u8, 0u8, 0u8, 255u8, 0u8, 0u8, 0u8];
+/// let read_blob = byte_array_blob::blob_to_byte_arrays(&blob);
+///
+/// assert!(read_blob.is_ok());
+/// if let Ok(data) = read_blob {
+/// 	assert_eq!(data[0], &[255, 0, 0, 0]);
+/// }
+/// ```
+pub fn blob_to_byte_arrays(blob: &[u8]) -> Result<Vec<&[u8]>, BlobReadError> {
+	blob_to_byte_arrays_with_options(blob, &DecodeOptions::new())
+}
+
+
+/// Converts a blob u8 slice to a Vec of u8 slices, within the limits of `options`
+///
+/// The indexes are walked once to count and check the slices before the result is allocated, so the Vec is allocated exactly once at its final size.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::TooManyEntries)` if the blob holds more slices than `options.max_entries()`,
+/// `Err(BlobReadError::AllocationFailed)` if the Vec can't be allocated, otherwise the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[], &[], &[]]);
+/// let options = DecodeOptions::new().with_max_entries(2);
+///
+/// assert_eq!(blob_to_byte_arrays_with_options(&blob, &options), Err(BlobReadError::TooManyEntries(2)));
+/// ```
+pub fn blob_to_byte_arrays_with_options<'a>(blob: &'a [u8], options: &DecodeOptions) -> Result<Vec<&'a [u8]>, BlobReadError> {
+	let mut count = 0;
+	for byte_arr in blob_iter(blob) {
+		byte_arr?;
+		if count == options.max_entries() {
+			return Err(BlobReadError::TooManyEntries(options.max_entries()));
+		}
+		count += 1;
+	}
+
+	let mut byte_arrays = Vec::new();
+	try_reserve_exact(&mut byte_arrays, count)?;
+	// The first pass already checked every index, so there are no errors to drop
+	byte_arrays.extend(blob_iter(blob).flatten());
+	Ok(byte_arrays)
+}
+
+
+/// Converts a blob u8 slice to any collection of u8 slices, such as a `VecDeque` or a fixed capacity stack vector
+///
+/// Each slice is added with `Extend` as it is read, so a collection that stores a few items inline can skip the heap entirely for small blobs.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+/// use std::collections::VecDeque;
+///
+/// let blob = byte_arrays_to_blob(&[&[1], &[2]]);
+/// let mut queue: VecDeque<&[u8]> = blob_to_collection(&blob).unwrap();
+/// assert_eq!(queue.pop_back(), Some(&[2][..]));
+/// ```
+pub fn blob_to_collection<'a, C: Default + Extend<&'a [u8]>>(blob: &'a [u8]) -> Result<C, BlobReadError> {
+	let mut collection = C::default();
+	for byte_arr in blob_iter(blob) {
+		collection.extend(std::iter::once(byte_arr?));
+	}
+	Ok(collection)
+}
+
+
+/// Converts a blob u8 slice to a Vec of owned u8 Vecs, copying each slice
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+pub fn blob_to_byte_vecs(blob: &[u8]) -> Result<Vec<Vec<u8>>, BlobReadError> {
+	blob_iter(blob).map(|byte_arr| byte_arr.map(|byte_arr| byte_arr.to_vec())).collect()
+}
+
+
+#[cfg(test)]
+thread_local! {
+	/// The most bytes `try_reserve_exact` will reserve at once, so tests can make allocations fail without using up memory
+	pub(crate) static ALLOCATION_LIMIT: Cell<usize> = const { Cell::new(usize::MAX) };
+}
+
+
+/// Reserves room for exactly `additional` more items in `vec`, returning `Err(BlobReadError::AllocationFailed)` instead of aborting if the allocator can't
+pub(crate) fn try_reserve_exact<T>(vec: &mut Vec<T>, additional: usize) -> Result<(), BlobReadError> {
+	let requested = additional.saturating_mul(size_of::<T>());
+	#[cfg(test)]
+	if requested > ALLOCATION_LIMIT.get() {
+		return Err(BlobReadError::AllocationFailed { requested });
+	}
+	vec.try_reserve_exact(additional).map_err(|_| BlobReadError::AllocationFailed { requested })
+}
+
+
+/// Converts a blob u8 slice to a Vec of owned u8 Vecs like `blob_to_byte_vecs`, but returns an error instead of aborting if memory runs out
+///
+/// The indexes are checked before anything is allocated, and every allocation is reserved at its final size with `Vec::try_reserve_exact`,
+/// so a server can turn away a decode that won't fit rather than be killed by it. Memory already reserved is freed when an allocation fails.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::AllocationFailed)` with the size of the first allocation that failed, otherwise the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"one", b"two"]);
+/// assert_eq!(blob_to_byte_vecs_try(&blob).unwrap(), blob_to_byte_vecs(&blob).unwrap());
+/// ```
+pub fn blob_to_byte_vecs_try(blob: &[u8]) -> Result<Vec<Vec<u8>>, BlobReadError> {
+	let count = blob_iter(blob).try_fold(0usize, |count, byte_arr| byte_arr.map(|_| count + 1))?;
+	let mut byte_vecs = Vec::new();
+	try_reserve_exact(&mut byte_vecs, count)?;
+	// The first pass already checked every index, so there are no errors to drop
+	for byte_arr in blob_iter(blob).flatten() {
+		let mut byte_vec = Vec::new();
+		try_reserve_exact(&mut byte_vec, byte_arr.len())?;
+		byte_vec.extend_from_slice(byte_arr);
+		byte_vecs.push(byte_vec);
+	}
+	Ok(byte_vecs)
+}
+
+
+/// Converts a blob u8 slice to a Vec of owned u8 Vecs, copying no more than `max_bytes` of slice data in total
+///
+/// The budget is checked before each slice is copied, so an error means nothing past the budget was allocated.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::BudgetExceeded)` when copying a slice would take the total past `max_bytes`, otherwise the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[0; 10], &[0; 10]]);
+/// assert!(blob_to_byte_vecs_bounded(&blob, 20).is_ok());
+///
+/// match blob_to_byte_vecs_bounded(&blob, 15).unwrap_err() {
+/// 	BlobReadError::BudgetExceeded { at_entry, needed, budget } => assert_eq!((at_entry, needed, budget), (1, 20, 15)),
+/// 	_ => panic!("This should not happen")
+/// }
+/// ```
+pub fn blob_to_byte_vecs_bounded(blob: &[u8], max_bytes: usize) -> Result<Vec<Vec<u8>>, BlobReadError> {
+	let mut budget = Budget::new(max_bytes);
+	blob_iter(blob).enumerate().map(|(i, byte_arr)| {
+		let byte_arr = byte_arr?;
+		budget.spend(i, byte_arr.len())?;
+		Ok(byte_arr.to_vec())
+	}).collect()
+}
+
+
+/// Tracks the bytes copied so far against a limit, shared by the owned decoders
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+	used: usize,
+	max_bytes: usize
+}
+
+
+impl Budget {
+	pub(crate) fn new(max_bytes: usize) -> Self {
+		Budget { used: 0, max_bytes }
+	}
+
+	/// Records `len` more bytes for entry `at_entry`, or errors without recording them if that would exceed the limit
+	pub(crate) fn spend(&mut self, at_entry: usize, len: usize) -> Result<(), BlobReadError> {
+		let needed = self.used.saturating_add(len);
+		if needed > self.max_bytes {
+			return Err(BlobReadError::BudgetExceeded { at_entry, needed, budget: self.max_bytes });
+		}
+		self.used = needed;
+		Ok(())
+	}
+}
+
+
+/// Iterator over the slices in a blob, created by `blob_iter`
+///
+/// Each slice is read as it is reached, so nothing is allocated and an error is only found once iteration gets to it.
+/// After yielding an `Err` the iterator is finished.
+#[derive(Debug, Clone)]
+pub struct BlobIter<'a> {
+	pub(crate) blob: &'a [u8],
+	pub(crate) format: BlobFormat,
+	pub(crate) idx_start: usize,
+	/// The number of the next entry to be read
+	pub(crate) entry: usize,
+	pub(crate) done: bool
+}
+
+
+impl<'a> Iterator for BlobIter<'a> {
+	type Item = Result<&'a [u8], BlobReadError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done || self.idx_start >= self.blob.len() {
+			return None;
+		}
+
+		let item = self.read_next();
+		if item.is_err() {
+			self.done = true;
+		}
+		Some(item)
+	}
+
+	/// The count isn't known without reading every index, but an unfinished iterator yields at least one more item, an entry or an error,
+	/// and every entry takes at least one index worth of bytes
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		match self.is_finished() {
+			true => (0, Some(0)),
+			false => (1, Some(self.blob.len().saturating_sub(self.idx_start).div_ceil(self.format.header_len())))
+		}
+	}
+}
+
+
+impl FusedIterator for BlobIter<'_> {}
+
+
+impl<'a> BlobIter<'a> {
+	/// Returns the position in the blob of the next index to be read, which is also where the last slice read ended
+	pub fn position(&self) -> usize {
+		self.idx_start
+	}
+
+	/// Returns true once every slice has been read, or an error has been returned
+	pub fn is_finished(&self) -> bool {
+		self.done || self.idx_start >= self.blob.len()
+	}
+
+	/// Returns an iterator over at most the next `max_entries` slices, leaving the rest for a later call
+	///
+	/// Each slice read is one index checked and no copying, so a call does work in proportion to `max_entries` however large the blob is,
+	/// and allocates nothing. This suits loops with a deadline, such as an audio callback, which can keep the `BlobIter` and carry on in the next one.
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::*;
+	///
+	/// let blob = byte_arrays_to_blob(&[b"a", b"b", b"c"]);
+	/// let mut iter = blob_iter(&blob);
+	///
+	/// assert_eq!(iter.by_budget(2).count(), 2);
+	/// assert!(!iter.is_finished());
+	/// assert_eq!(iter.by_budget(2).collect::<Result<Vec<_>, _>>().unwrap(), [b"c"]);
+	/// assert!(iter.is_finished());
+	/// ```
+	pub fn by_budget(&mut self, max_entries: usize) -> impl Iterator<Item = Result<&'a [u8], BlobReadError>> + '_ {
+		self.take(max_entries)
+	}
+
+	fn read_next(&mut self) -> Result<&'a [u8], BlobReadError> {
+		// It is difficult for this case to occur
+		if self.blob.len() > self.format.max_len() {
+			return Err(BlobReadError::TooLarge);
+		}
+
+		// Get the encoded index, the slice may end before all of its bytes are present
+		let idx_end = self.format.read_index(self.blob, self.idx_start)?;
+		let idx_start = self.idx_start.saturating_add(self.format.header_len());
+
+		// The range is only in the blob if the index end is no more than the blob length, and no less than the slice start
+		match self.blob.get(idx_start..idx_end) {
+			Some(byte_arr) => {
+				self.idx_start = idx_end;
+				self.entry += 1;
+				Ok(byte_arr)
+			},
+			None if self.idx_start == 0 => Err(BlobReadError::NotABlob { first_header: u32::try_from(idx_end).unwrap_or(u32::MAX) }),
+			None => Err(BlobReadError::InvalidEncodedIndex(idx_end))
+		}
+	}
+}
+
+
+/// Checks whether a blob's first index is plausible, without decoding anything else
+///
+/// The first index must point at or past its own end and no further than the end of the blob. Most files that aren't blobs,
+/// such as images or archives, start with magic bytes that fail this, and decoding them returns `Err(BlobReadError::NotABlob)`.
+/// A `true` result doesn't mean the rest of the blob is valid. An empty blob holds no slices, so it looks like a blob
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// assert!(looks_like_blob(&byte_arrays_to_blob(&[b"one", b"two"])));
+/// assert!(!looks_like_blob(b"\x89PNG\r\n\x1a\n"));
+/// ```
+pub fn looks_like_blob(blob: &[u8]) -> bool {
+	match *blob {
+		[] => true,
+		[a, b, c, d, ..] => {
+			let first = u32::from_ne_bytes([a, b, c, d]);
+			first >= 4 && u64::from(first) <= blob.len() as u64
+		},
+		_ => false
+	}
+}
+
+
+/// Returns an iterator over the slices in a blob, without allocating a Vec for them
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1, 2], &[3]]);
+///
+/// let mut iter = blob_iter(&blob);
+/// assert_eq!(iter.next().unwrap().unwrap(), &[1, 2]);
+/// assert_eq!(iter.next().unwrap().unwrap(), &[3]);
+/// assert!(iter.next().is_none());
+/// ```
+pub fn blob_iter(blob: &[u8]) -> BlobIter<'_> {
+	blob_iter_with_format(blob, BlobFormat::NativeEndian)
+}
+
+
+/// Returns the number of slices in a blob, reading every index but copying nothing
+///
+/// Zero length slices are always counted: an empty blob holds 0 slices, and every `[4, 0, 0, 0]` style index is one empty slice,
+/// wherever it appears in the blob.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// assert_eq!(count_arrays(&byte_arrays_to_blob(&[])).unwrap(), 0);
+/// assert_eq!(count_arrays(&byte_arrays_to_blob(&[&[]])).unwrap(), 1);
+/// assert_eq!(count_arrays(&byte_arrays_to_blob(&[&[], &[1], &[]])).unwrap(), 3);
+/// ```
+pub fn count_arrays(blob: &[u8]) -> Result<usize, BlobReadError> {
+	let mut count = 0;
+	for byte_arr in blob_iter(blob) {
+		byte_arr?;
+		count += 1;
+	}
+	Ok(count)
+}
+
+
+/// Cuts a blob down to its first `keep` slices in place, without re-encoding them, and returns its new length in bytes
+///
+/// Only the first `keep` indexes are read, so whatever follows them, such as a half written slice, is cut off without being checked.
+/// If the blob holds `keep` slices or fewer it is left as it is and its full length is returned.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` without changing the blob if one of the indexes before the cut is invalid, the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut blob = byte_arrays_to_blob(&[b"good", b"also good"]);
+/// blob.extend([200, 0, 0, 0, 1, 2]);
+///
+/// assert_eq!(blob_truncate_entries(&mut blob, 2).unwrap(), 21);
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap(), [&b"good"[..], b"also good"]);
+/// ```
+pub fn blob_truncate_entries(blob: &mut Vec<u8>, keep: usize) -> Result<usize, BlobReadError> {
+	let mut iter = blob_iter(blob);
+	for byte_arr in iter.by_ref().take(keep) {
+		byte_arr?;
+	}
+	let end = iter.position();
+	blob.truncate(end);
+	Ok(blob.len())
+}
+
+
+/// Returns the slices at `indices` in one pass over the blob, in the same order as `indices`
+///
+/// Looking up each index with `blob_iter(blob).nth(i)` reads every index before it again each time, where this reads each index at most once.
+/// An index may appear more than once, and an index past the last slice gives `None`.
+/// The walk stops at the largest index that is in range, so slices after it aren't checked, unless an index is out of range and the whole blob has to be read to know it.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` if an invalid index is reached before the walk stops, the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"zero", b"one", b"two"]);
+/// let found = blob_get_many(&blob, &[2, 0, 7, 2]).unwrap();
+/// assert_eq!(found, [Some(&b"two"[..]), Some(b"zero"), None, Some(b"two")]);
+/// ```
+pub fn blob_get_many<'a>(blob: &'a [u8], indices: &[usize]) -> Result<Vec<Option<&'a [u8]>>, BlobReadError> {
+	let mut found = vec![None; indices.len()];
+	// Each wanted index with its position in `indices`, sorted by index
+	let mut order: Vec<(usize, usize)> = indices.iter().copied().zip(0..).collect();
+	order.sort_unstable();
+
+	let mut wanted = order.iter().peekable();
+	for (index, byte_arr) in blob_iter(blob).enumerate() {
+		if wanted.peek().is_none() {
+			break;
+		}
+		let byte_arr = byte_arr?;
+		while let Some(&(_, position)) = wanted.next_if(|&&(wanted_index, _)| wanted_index == index) {
+			if let Some(slot) = found.get_mut(position) {
+				*slot = Some(byte_arr);
+			}
+		}
+	}
+	Ok(found)
+}
+
+
+/// Returns the index of the first slice in the blob that is byte-equal to `needle`
+///
+/// The search stops at the first match, so slices after it are not checked for errors.
+/// A zero length `needle` only matches zero length slices.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` if an invalid index is reached before a match is found
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1, 2, 3], &[1, 2], &[]]);
+///
+/// assert_eq!(blob_position_of(&blob, &[1, 2]).unwrap(), Some(1));
+/// assert_eq!(blob_position_of(&blob, &[]).unwrap(), Some(2));
+/// assert_eq!(blob_position_of(&blob, &[4]).unwrap(), None);
+/// ```
+pub fn blob_position_of(blob: &[u8], needle: &[u8]) -> Result<Option<usize>, BlobReadError> {
+	for (i, byte_arr) in blob_iter(blob).enumerate() {
+		if entry_matches(byte_arr?, needle) {
+			return Ok(Some(i));
+		}
+	}
+	Ok(None)
+}
+
+
+/// Returns true if any slice in the blob is byte-equal to `needle`. See `blob_position_of`
+pub fn blob_contains(blob: &[u8], needle: &[u8]) -> Result<bool, BlobReadError> {
+	Ok(blob_position_of(blob, needle)?.is_some())
+}
+
+
+/// Returns the indexes of every slice in the blob that is byte-equal to `needle`
+///
+/// Unlike `blob_position_of` the whole blob is read, so any invalid index is an error.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[7], &[8], &[7]]);
+/// assert_eq!(blob_positions_of(&blob, &[7]).unwrap(), vec![0, 2]);
+/// ```
+pub fn blob_positions_of(blob: &[u8], needle: &[u8]) -> Result<Vec<usize>, BlobReadError> {
+	let mut positions = Vec::new();
+	for (i, byte_arr) in blob_iter(blob).enumerate() {
+		if entry_matches(byte_arr?, needle) {
+			positions.push(i);
+		}
+	}
+	Ok(positions)
+}
+
+
+/// Splits off the first `n` bytes, or all of them if there are fewer, for the streaming decoders
+pub(crate) fn split_at_most(bytes: &[u8], n: usize) -> (&[u8], &[u8]) {
+	bytes.split_at(n.min(bytes.len()))
+}
+
+
+/// Copies `bytes` into `data` from position `filled`. The streaming decoders never take more than the rest of an index, so all of `bytes` fits
+pub(crate) fn fill_from(data: &mut [u8], filled: usize, bytes: &[u8]) {
+	for (slot, &byte) in data.iter_mut().skip(filled).zip(bytes) {
+		*slot = byte;
+	}
+}
+
+
+// Length is compared first, so slices of a different size are never compared byte by byte
+fn entry_matches(byte_arr: &[u8], needle: &[u8]) -> bool {
+	byte_arr.len() == needle.len() && byte_arr == needle
+}
@@ -0,0 +1,258 @@
+//! `BlobDecoder`, a push based decoder that does no IO of its own
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+use std::collections::VecDeque;
+
+
+#[derive(Debug)]
+enum DecodeState {
+	/// Collecting the bytes of an index
+	Index {
+		data: [u8; 4],
+		filled: usize
+	},
+	/// Collecting the payload of an entry, which ends at `idx_end`
+	Payload {
+		data: Vec<u8>,
+		idx_end: usize
+	},
+	/// An error was returned, and no more input will be accepted
+	Failed(BlobReadError)
+}
+
+
+/// A decoder that is given the bytes of a blob as they arrive, and hands back each entry once it is complete
+///
+/// Input can be split anywhere, including part way through an index. The decoder does no IO, so it can be driven by any sync or async source.
+/// `BlobReader` is a wrapper around it for `io::Read`.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1, 2], &[3]]);
+/// let mut decoder = BlobDecoder::new();
+///
+/// decoder.push(&blob[..3]).unwrap();
+/// assert_eq!(decoder.next_entry(), None);
+///
+/// decoder.push(&blob[3..]).unwrap();
+/// assert_eq!(decoder.next_entry(), Some(vec![1, 2]));
+/// assert_eq!(decoder.next_entry(), Some(vec![3]));
+/// decoder.finish().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct BlobDecoder {
+	state: DecodeState,
+	position: usize,
+	entries_started: usize,
+	completed: VecDeque<Vec<u8>>,
+	budget: Option<Budget>
+}
+
+
+impl Default for BlobDecoder {
+	fn default() -> Self {
+		BlobDecoder::new()
+	}
+}
+
+
+impl BlobDecoder {
+	pub fn new() -> Self {
+		BlobDecoder {
+			state: DecodeState::Index { data: [0u8; 4], filled: 0 },
+			position: 0,
+			entries_started: 0,
+			completed: VecDeque::new(),
+			budget: None
+		}
+	}
+
+	/// Limits the total number of payload bytes the decoder will allocate, in the same way as `blob_to_byte_vecs_bounded`
+	pub fn with_budget(mut self, max_bytes: usize) -> Self {
+		self.budget = Some(Budget::new(max_bytes));
+		self
+	}
+
+	/// Returns the number of bytes of the blob pushed so far
+	pub fn position(&self) -> usize {
+		self.position
+	}
+
+	/// Returns how many more bytes are needed to finish the index or entry currently being read
+	///
+	/// Pushing exactly this many bytes never reads past the current entry. This is 0 once the decoder has failed.
+	pub fn wanted(&self) -> usize {
+		match &self.state {
+			DecodeState::Index { filled, .. } => 4 - filled,
+			DecodeState::Payload { idx_end, .. } => idx_end - self.position,
+			DecodeState::Failed(_) => 0
+		}
+	}
+
+	/// Feeds more bytes of the blob into the decoder. Completed entries are queued for `next_entry`
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError)` as soon as an invalid index or exceeded budget is found, with a first index that points before its own end
+	/// reported as `NotABlob` as `blob_to_byte_arrays` does. After an error the decoder accepts no more input and returns the same error
+	pub fn push(&mut self, mut bytes: &[u8]) -> Result<(), BlobReadError> {
+		while !bytes.is_empty() {
+			let (taken, rest) = match &mut self.state {
+				DecodeState::Index { data, filled } => {
+					let (taken, rest) = split_at_most(bytes, 4 - *filled);
+					fill_from(data, *filled, taken);
+					*filled += taken.len();
+					(taken.len(), rest)
+				},
+				DecodeState::Payload { data, idx_end } => {
+					let (taken, rest) = split_at_most(bytes, *idx_end - self.position);
+					data.extend_from_slice(taken);
+					(taken.len(), rest)
+				},
+				DecodeState::Failed(e) => return Err(e.clone())
+			};
+			self.position += taken;
+			bytes = rest;
+
+			if let Err(e) = self.advance() {
+				self.state = DecodeState::Failed(e.clone());
+				return Err(e);
+			}
+		}
+		Ok(())
+	}
+
+	/// Moves to the next state once the current index or entry is complete
+	fn advance(&mut self) -> Result<(), BlobReadError> {
+		match &mut self.state {
+			DecodeState::Index { data, filled: 4 } => {
+				let encoded = u32::from_ne_bytes(*data);
+				let idx_end = index_to_usize(encoded)?;
+				if idx_end < self.position {
+					// The same check `looks_like_blob` makes, which is all a stream can know about its first index
+					if self.entries_started == 0 {
+						return Err(BlobReadError::NotABlob { first_header: encoded });
+					}
+					return Err(BlobReadError::InvalidEncodedIndex(idx_end));
+				}
+				if let Some(budget) = &mut self.budget {
+					budget.spend(self.entries_started, idx_end - self.position)?;
+				}
+				self.entries_started += 1;
+				self.state = DecodeState::Payload { data: Vec::new(), idx_end };
+			},
+			DecodeState::Payload { .. } => {},
+			_ => return Ok(())
+		}
+
+		// A zero length entry is already complete, so this also runs straight after an index
+		if let DecodeState::Payload { data, idx_end } = &mut self.state {
+			if *idx_end == self.position {
+				self.completed.push_back(std::mem::take(data));
+				self.state = DecodeState::Index { data: [0u8; 4], filled: 0 };
+			}
+		}
+		Ok(())
+	}
+
+	/// Takes the next completed entry, in order
+	pub fn next_entry(&mut self) -> Option<Vec<u8>> {
+		self.completed.pop_front()
+	}
+
+	/// Checks the blob ended between entries. Any entries not taken with `next_entry` are dropped
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::TruncatedIndex)` if input stopped part way through an index, `Err(BlobReadError::InvalidEncodedIndex)` if it stopped part way through an entry,
+	/// or `Err(BlobReadError::NotABlob)` if that entry was the first, the same errors `blob_to_byte_arrays` gives for the bytes pushed. If the decoder had already failed, returns the error from `push`
+	pub fn finish(self) -> Result<(), BlobReadError> {
+		self.check_finished()
+	}
+
+	/// The check done by `finish`, without consuming the decoder
+	pub(crate) fn check_finished(&self) -> Result<(), BlobReadError> {
+		match &self.state {
+			DecodeState::Index { filled: 0, .. } => Ok(()),
+			DecodeState::Index { filled, .. } => Err(BlobReadError::TruncatedIndex(self.position - filled)),
+			DecodeState::Payload { idx_end, .. } if self.entries_started == 1 => Err(BlobReadError::NotABlob { first_header: u32::try_from(*idx_end).unwrap_or(u32::MAX) }),
+			DecodeState::Payload { idx_end, .. } => Err(BlobReadError::InvalidEncodedIndex(*idx_end)),
+			DecodeState::Failed(e) => Err(e.clone())
+		}
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	fn decode_in_chunks(blob: &[u8], mut chunk_len: impl FnMut() -> usize) -> Vec<Vec<u8>> {
+		let mut decoder = BlobDecoder::new();
+		let mut decoded = Vec::new();
+		let mut rest = blob;
+		while !rest.is_empty() {
+			let (chunk, tail) = rest.split_at(chunk_len().min(rest.len()));
+			decoder.push(chunk).unwrap();
+			decoded.extend(std::iter::from_fn(|| decoder.next_entry()));
+			rest = tail;
+		}
+		decoder.finish().unwrap();
+		decoded
+	}
+
+	#[test]
+	fn any_chunk_size_gives_same_entries() {
+		let entries = sample_entries();
+		let blob = entries_to_blob(&entries);
+
+		assert_eq!(decode_in_chunks(&blob, || 1), entries);
+		assert_eq!(decode_in_chunks(&blob, || 3), entries);
+		assert_eq!(decode_in_chunks(&blob, || blob.len()), entries);
+
+		let mut rng = XorShift32::new(0x2545_F491);
+		assert_eq!(decode_in_chunks(&blob, || 1 + (rng.next_u32() % 50) as usize), entries);
+	}
+
+	#[test]
+	fn finish_mid_entry() {
+		let blob = byte_arrays_to_blob(&[&[1u8], &[2u8; 10]]);
+
+		let mut decoder = BlobDecoder::new();
+		decoder.push(&blob[..7]).unwrap();
+		assert_eq!(decoder.finish(), Err(BlobReadError::TruncatedIndex(5)));
+
+		let mut decoder = BlobDecoder::new();
+		decoder.push(&blob[..12]).unwrap();
+		assert_eq!(decoder.next_entry(), Some(vec![1u8]));
+		assert_eq!(decoder.finish(), Err(BlobReadError::InvalidEncodedIndex(19)));
+
+		// Stopping inside the first entry means its index is past the end
+		let mut decoder = BlobDecoder::new();
+		decoder.push(&blob[..4]).unwrap();
+		assert_eq!(decoder.finish(), Err(BlobReadError::NotABlob { first_header: 5 }));
+		assert_eq!(blob_to_byte_arrays(&blob[..4]), Err(BlobReadError::NotABlob { first_header: 5 }));
+	}
+
+	#[test]
+	fn invalid_index_poisons() {
+		let mut decoder = BlobDecoder::new();
+		let blob = 2u32.to_ne_bytes();
+		assert_eq!(decoder.push(&blob), Err(BlobReadError::NotABlob { first_header: 2 }));
+		assert_eq!(decoder.push(&[1u8]), Err(BlobReadError::NotABlob { first_header: 2 }));
+		assert_eq!(decoder.wanted(), 0);
+		assert_eq!(blob_to_byte_arrays(&blob), Err(BlobReadError::NotABlob { first_header: 2 }));
+
+		// Later indexes that point backwards are invalid indexes, as in the slice decoder
+		let mut blob = byte_arrays_to_blob(&[&[1u8], &[2u8]]);
+		blob[5..9].copy_from_slice(&3u32.to_ne_bytes());
+		let mut decoder = BlobDecoder::new();
+		assert_eq!(decoder.push(&blob), Err(BlobReadError::InvalidEncodedIndex(3)));
+		assert_eq!(blob_to_byte_arrays(&blob), Err(BlobReadError::InvalidEncodedIndex(3)));
+	}
+}
@@ -0,0 +1,257 @@
+//! Delta packs, which store only the entries missing from one or more shared base blobs
+//!
+//! # Layout
+//!
+//! A delta pack is an ordinary blob. Its first slice is the manifest, and every slice after it is an entry that was not found in the base blobs.
+//!
+//! The manifest has one record per entry, in order. A record is a single `0` byte for the next literal slice of the delta pack,
+//! or a `1` byte followed by a little-endian u32 base blob index and a little-endian u32 entry index within that blob.
+use crate::*;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+
+const LITERAL: u8 = 0;
+const REFERENCE: u8 = 1;
+
+
+pub(crate) fn content_hash(data: &[u8]) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	hasher.write(data);
+	hasher.finish()
+}
+
+
+/// Builds a delta pack against one or more base blobs. Entries equal to an entry of a base blob are stored as a reference to it
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let base = byte_arrays_to_blob(&[b"shared texture", b"shared mesh"]);
+///
+/// let mut builder = DedupPackBuilder::new(&[&base]).unwrap();
+/// builder.push(b"shared mesh");
+/// builder.push(b"new level data");
+/// let delta = builder.finish();
+///
+/// let entries = resolve(&[&base], &delta).unwrap();
+/// assert_eq!(entries, vec![b"shared mesh".to_vec(), b"new level data".to_vec()]);
+/// ```
+#[derive(Debug)]
+pub struct DedupPackBuilder<'a> {
+	bases: Vec<Vec<&'a [u8]>>,
+	index: HashMap<u64, Vec<(u32, u32)>>,
+	hash: fn(&[u8]) -> u64,
+	manifest: Vec<u8>,
+	literals: Vec<Vec<u8>>
+}
+
+
+impl<'a> DedupPackBuilder<'a> {
+	/// Reads and indexes every entry of the base blobs
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError)` if a base blob can not be read
+	pub fn new(base_blobs: &[&'a [u8]]) -> Result<Self, BlobReadError> {
+		DedupPackBuilder::with_hash(base_blobs, content_hash)
+	}
+
+	fn with_hash(base_blobs: &[&'a [u8]], hash: fn(&[u8]) -> u64) -> Result<Self, BlobReadError> {
+		let bases = base_blobs.iter().map(|blob| blob_to_byte_arrays(blob)).collect::<Result<Vec<_>, _>>()?;
+
+		let mut index: HashMap<u64, Vec<(u32, u32)>> = HashMap::new();
+		for (j, base) in bases.iter().enumerate() {
+			for (k, byte_arr) in base.iter().enumerate() {
+				index.entry(hash(byte_arr)).or_default().push((j as u32, k as u32));
+			}
+		}
+		Ok(DedupPackBuilder { bases, index, hash, manifest: Vec::new(), literals: Vec::new() })
+	}
+
+	/// Finds a base entry byte-equal to `data`. Equal hashes are not trusted alone, since different entries can collide
+	fn find(&self, data: &[u8]) -> Option<(u32, u32)> {
+		self.index.get(&(self.hash)(data))?.iter().copied()
+			.find(|&(j, k)| self.bases[j as usize][k as usize] == data)
+	}
+
+	/// Adds the next entry, returning true if it was found in a base blob
+	pub fn push(&mut self, data: &[u8]) -> bool {
+		match self.find(data) {
+			Some((j, k)) => {
+				self.manifest.push(REFERENCE);
+				self.manifest.extend(j.to_le_bytes());
+				self.manifest.extend(k.to_le_bytes());
+				true
+			},
+			None => {
+				self.manifest.push(LITERAL);
+				self.literals.push(data.to_vec());
+				false
+			}
+		}
+	}
+
+	/// Returns the delta pack. See the module layout above
+	pub fn finish(self) -> Vec<u8> {
+		let mut byte_arrays: Vec<&[u8]> = vec![&self.manifest];
+		byte_arrays.extend(self.literals.iter().map(|literal| literal.as_slice()));
+		byte_arrays_to_blob(&byte_arrays)
+	}
+}
+
+
+/// Errors that can occur when resolving a delta pack
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ResolveError {
+	/// The delta pack or a base blob can not be read
+	Read(BlobReadError),
+	/// The delta pack has no manifest, or the manifest is malformed at the given byte
+	InvalidManifest(usize),
+	/// Entry `entry` refers to an entry that does not exist in the given base blobs
+	UnknownReference {
+		entry: usize,
+		blob: usize,
+		index: usize
+	},
+	/// The manifest and the delta pack disagree on the number of literal entries
+	LiteralCountMismatch {
+		manifest: usize,
+		delta: usize
+	}
+}
+
+
+impl std::error::Error for ResolveError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ResolveError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for ResolveError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for ResolveError {
+	fn from(e: BlobReadError) -> Self {
+		ResolveError::Read(e)
+	}
+}
+
+
+/// Rebuilds the full list of entries from a delta pack made by `DedupPackBuilder` and the same base blobs, in the same order
+///
+/// # Errors
+///
+/// Returns `Err(ResolveError)` if any blob can not be read, or the manifest does not match the delta pack and base blobs
+pub fn resolve(base_blobs: &[&[u8]], delta: &[u8]) -> Result<Vec<Vec<u8>>, ResolveError> {
+	let bases = base_blobs.iter().map(|blob| blob_to_byte_arrays(blob)).collect::<Result<Vec<_>, _>>()?;
+	let delta = blob_to_byte_arrays(delta)?;
+	let (manifest, literals) = delta.split_first().ok_or(ResolveError::InvalidManifest(0))?;
+
+	let mut entries = Vec::new();
+	let mut next_literal = 0;
+	let mut position = 0;
+	while position < manifest.len() {
+		let entry = entries.len();
+		match manifest[position] {
+			LITERAL => {
+				let literal = literals.get(next_literal).ok_or(ResolveError::LiteralCountMismatch { manifest: next_literal + 1, delta: literals.len() })?;
+				entries.push(literal.to_vec());
+				next_literal += 1;
+				position += 1;
+			},
+			REFERENCE => {
+				let record = manifest.get(position+1..position+9).ok_or(ResolveError::InvalidManifest(position))?;
+				let blob = u32::from_le_bytes(record[..4].try_into().unwrap()) as usize;
+				let index = u32::from_le_bytes(record[4..].try_into().unwrap()) as usize;
+				let byte_arr = bases.get(blob).and_then(|base| base.get(index)).ok_or(ResolveError::UnknownReference { entry, blob, index })?;
+				entries.push(byte_arr.to_vec());
+				position += 9;
+			},
+			_ => return Err(ResolveError::InvalidManifest(position))
+		}
+	}
+
+	if next_literal != literals.len() {
+		return Err(ResolveError::LiteralCountMismatch { manifest: next_literal, delta: literals.len() });
+	}
+	Ok(entries)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	fn entry(i: usize) -> Vec<u8> {
+		(0..200).map(|b| (b * 7 + i * 13) as u8).collect()
+	}
+
+	#[test]
+	fn delta_is_smaller_than_full_pack() {
+		let base_entries: Vec<Vec<u8>> = (0..100).map(entry).collect();
+		let base = entries_to_blob(&base_entries);
+
+		// Most of the level is shared with the base, with a few new entries
+		let level: Vec<Vec<u8>> = (0..95).chain(1000..1005).map(entry).collect();
+		let mut builder = DedupPackBuilder::new(&[&base]).unwrap();
+		for data in &level {
+			builder.push(data);
+		}
+		let delta = builder.finish();
+
+		let full = entries_to_blob(&level);
+		assert!(delta.len() * 10 < full.len(), "delta of {} bytes vs full pack of {} bytes", delta.len(), full.len());
+		assert_eq!(resolve(&[&base], &delta).unwrap(), level);
+	}
+
+	#[test]
+	fn multiple_bases() {
+		let a = byte_arrays_to_blob(&[b"a0", b"a1"]);
+		let b = byte_arrays_to_blob(&[b"b0"]);
+
+		let mut builder = DedupPackBuilder::new(&[&a, &b]).unwrap();
+		assert!(builder.push(b"b0"));
+		assert!(!builder.push(b"new"));
+		assert!(builder.push(b"a1"));
+		let delta = builder.finish();
+
+		assert_eq!(resolve(&[&a, &b], &delta).unwrap(), vec![b"b0".to_vec(), b"new".to_vec(), b"a1".to_vec()]);
+		assert!(matches!(resolve(&[&a], &delta), Err(ResolveError::UnknownReference { entry: 0, blob: 1, index: 0 })));
+	}
+
+	#[test]
+	fn hash_collisions_are_checked() {
+		// Every entry has the same hash, so only the byte comparison tells them apart
+		let base = byte_arrays_to_blob(&[b"one", b"two"]);
+		let mut builder = DedupPackBuilder::with_hash(&[&base], |_| 0).unwrap();
+
+		assert!(!builder.push(b"six"));
+		assert!(builder.push(b"two"));
+		let delta = builder.finish();
+		assert_eq!(resolve(&[&base], &delta).unwrap(), vec![b"six".to_vec(), b"two".to_vec()]);
+	}
+
+	#[test]
+	fn invalid_manifest() {
+		assert!(matches!(resolve(&[], &[]), Err(ResolveError::InvalidManifest(0))));
+
+		let delta = byte_arrays_to_blob(&[&[REFERENCE, 0u8, 0u8]]);
+		assert!(matches!(resolve(&[], &delta), Err(ResolveError::InvalidManifest(0))));
+
+		let delta = byte_arrays_to_blob(&[&[LITERAL], b"x", b"extra"]);
+		assert!(matches!(resolve(&[], &delta), Err(ResolveError::LiteralCountMismatch { manifest: 1, delta: 2 })));
+	}
+}
@@ -0,0 +1,106 @@
+//! Digests of the entries of a blob rather than of its bytes, so the same entries hash the same whatever format they are encoded in
+use crate::*;
+use crate::sha256::Sha256;
+
+
+/// Hashes the canonical form of a list of entries: the count, then the length and bytes of each entry, with lengths as little-endian u64s
+fn hash_entries<'a>(count: usize, byte_arrays: impl IntoIterator<Item = Result<&'a [u8], BlobReadError>>) -> Result<[u8; 32], BlobReadError> {
+	let mut hasher = Sha256::new();
+	hasher.update(&(count as u64).to_le_bytes());
+	for byte_arr in byte_arrays {
+		let byte_arr = byte_arr?;
+		hasher.update(&(byte_arr.len() as u64).to_le_bytes());
+		hasher.update(byte_arr);
+	}
+	Ok(hasher.finish())
+}
+
+
+/// Returns a SHA-256 digest of the entries of a blob, for use as a cache key that doesn't change when the format does
+///
+/// The digest covers the number of entries and the length and bytes of each, not the encoded blob, so it is the same as
+/// `entries_content_hash` of the decoded entries and the same for any `BlobFormat` holding them.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let entries: [&[u8]; 2] = [b"mesh", b"texture"];
+/// let native = byte_arrays_to_blob(&entries);
+/// let prefixed = byte_arrays_to_blob_with_format(&entries, BlobFormat::LengthPrefixed);
+///
+/// assert_ne!(native, prefixed);
+/// assert_eq!(blob_content_hash(&native).unwrap(), entries_content_hash(&entries));
+/// assert_eq!(blob_content_hash_with_format(&prefixed, BlobFormat::LengthPrefixed).unwrap(), entries_content_hash(&entries));
+/// ```
+pub fn blob_content_hash(blob: &[u8]) -> Result<[u8; 32], BlobReadError> {
+	blob_content_hash_with_format(blob, BlobFormat::NativeEndian)
+}
+
+
+/// The same as `blob_content_hash` for a blob in `format`
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays_with_format`
+pub fn blob_content_hash_with_format(blob: &[u8], format: BlobFormat) -> Result<[u8; 32], BlobReadError> {
+	// The count comes first in the digest, so walk the blob once to validate and count it before hashing
+	let count = blob_iter_with_format(blob, format).try_fold(0usize, |count, byte_arr| byte_arr.map(|_| count + 1))?;
+	hash_entries(count, blob_iter_with_format(blob, format))
+}
+
+
+/// Returns the digest `blob_content_hash` gives for a blob holding `bytes_2d`, without encoding one
+pub fn entries_content_hash(bytes_2d: &[&[u8]]) -> [u8; 32] {
+	match hash_entries(bytes_2d.len(), bytes_2d.iter().map(|&byte_arr| Ok(byte_arr))) {
+		Ok(digest) => digest,
+		Err(_) => unreachable!("entries that are already decoded can't fail to read")
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn same_entries_any_format() {
+		let entries: [&[u8]; 4] = [b"first", &[], &[0xAB; 300], b"x"];
+		let expected = entries_content_hash(&entries);
+		for format in [BlobFormat::NativeEndian, BlobFormat::LittleEndian, BlobFormat::LittleEndian64, BlobFormat::LengthPrefixed] {
+			let blob = byte_arrays_to_blob_with_format(&entries, format);
+			assert_eq!(blob_content_hash_with_format(&blob, format).unwrap(), expected, "{:?}", format);
+		}
+		assert_eq!(blob_content_hash(&byte_arrays_to_blob(&entries)).unwrap(), expected);
+		assert_eq!(blob_content_hash(&[]).unwrap(), entries_content_hash(&[]));
+	}
+
+	#[test]
+	fn different_entries() {
+		let digests = [
+			entries_content_hash(&[]),
+			entries_content_hash(&[b""]),
+			entries_content_hash(&[b"", b""]),
+			entries_content_hash(&[b"ab"]),
+			entries_content_hash(&[b"a", b"b"]),
+			entries_content_hash(&[b"b", b"a"]),
+			entries_content_hash(&[b"ab", b""]),
+			entries_content_hash(&[b"", b"ab"])
+		];
+		for (i, a) in digests.iter().enumerate() {
+			for b in &digests[i + 1..] {
+				assert_ne!(a, b);
+			}
+		}
+
+		let mut blob = byte_arrays_to_blob(&[b"one", b"two"]);
+		blob.pop();
+		assert!(blob_content_hash(&blob).is_err());
+	}
+}
@@ -0,0 +1,107 @@
+//! `EntryDisplay`, a bounded escaped rendering of an entry for logs
+use crate::*;
+
+
+/// Displays an entry as ASCII, with every other byte escaped, and cut short if it is long
+///
+/// Printable ASCII is written as it is, apart from `\`, which is written as `\\` so the output can't be confused with an escape.
+/// Every other byte is written as `\xNN` in lowercase hex. Only the first `DEFAULT_LIMIT` bytes are shown, followed by `… (+N bytes)`
+/// for the rest. A precision such as `{:.8}` sets the limit instead, and the alternate form `{:#}` shows the whole entry.
+/// Nothing is allocated: the output is written straight to the formatter.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// assert_eq!(EntryDisplay(b"ok\x00\xFF").to_string(), r"ok\x00\xff");
+/// assert_eq!(format!("{:.4}", EntryDisplay(b"truncated")), "trun… (+5 bytes)");
+/// assert_eq!(format!("{:#.4}", EntryDisplay(b"truncated")), "truncated");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryDisplay<'a>(pub &'a [u8]);
+
+
+impl EntryDisplay<'_> {
+	/// The number of bytes shown when no precision is given
+	pub const DEFAULT_LIMIT: usize = 64;
+}
+
+
+impl fmt::Display for EntryDisplay<'_> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let limit = if formatter.alternate() { usize::MAX } else { formatter.precision().unwrap_or(EntryDisplay::DEFAULT_LIMIT) };
+		let (shown, hidden) = self.0.split_at(self.0.len().min(limit));
+
+		let mut rest = shown;
+		while !rest.is_empty() {
+			// Write runs of printable bytes in one go, then escape the byte that ended the run
+			let run = rest.iter().position(|&byte| !(0x20..=0x7E).contains(&byte) || byte == b'\\').unwrap_or(rest.len());
+			// The run is all printable ASCII, so it is valid UTF-8
+			formatter.write_str(std::str::from_utf8(&rest[..run]).map_err(|_| fmt::Error)?)?;
+			match rest.get(run) {
+				Some(b'\\') => formatter.write_str(r"\\")?,
+				Some(byte) => write!(formatter, "\\x{:02x}", byte)?,
+				None => break
+			}
+			rest = &rest[run + 1..];
+		}
+
+		match hidden.len() {
+			0 => Ok(()),
+			1 => formatter.write_str("… (+1 byte)"),
+			n => write!(formatter, "… (+{} bytes)", n)
+		}
+	}
+}
+
+
+impl Blob {
+	/// Returns slice `index` wrapped in an `EntryDisplay`, or `None` if it is out of range
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::*;
+	///
+	/// let blob = Blob::from_byte_arrays(&[b"name", &[1, 2]]);
+	/// assert_eq!(blob.display_entry(1).unwrap().to_string(), r"\x01\x02");
+	/// ```
+	pub fn display_entry(&self, index: usize) -> Option<EntryDisplay<'_>> {
+		self.get(index).map(EntryDisplay)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn text_entry() {
+		assert_eq!(EntryDisplay(b"level_01.map").to_string(), "level_01.map");
+		assert_eq!(EntryDisplay(b"C:\\saves \"slot 1\"\n").to_string(), r#"C:\\saves "slot 1"\x0a"#);
+		assert_eq!(EntryDisplay(b"").to_string(), "");
+		assert_eq!(format!("{:.0}", EntryDisplay(b"")), "");
+		assert_eq!(format!("[{}]", Blob::from_byte_arrays(&[&[]]).display_entry(0).unwrap()), "[]");
+		assert_eq!(Blob::new().display_entry(0), None);
+	}
+
+	#[test]
+	fn binary_entry() {
+		let entry = [0x00, 0x7F, 0x80, 0xFF, b'A', 0x1B, b'['];
+		assert_eq!(EntryDisplay(&entry).to_string(), r"\x00\x7f\x80\xffA\x1b[");
+		assert_eq!(format!("{:.4}", EntryDisplay(&entry)), r"\x00\x7f\x80\xff… (+3 bytes)");
+		assert_eq!(format!("{:.6}", EntryDisplay(&entry)), r"\x00\x7f\x80\xffA\x1b… (+1 byte)");
+	}
+
+	#[test]
+	fn long_entry() {
+		let entry: Vec<u8> = (0..100u8).map(|i| b'a' + i % 26).collect();
+		let shown = format!("{}", EntryDisplay(&entry));
+		assert_eq!(shown, "abcdefghijklmnopqrstuvwxyzabcdefghijklmnopqrstuvwxyzabcdefghijkl… (+36 bytes)");
+		assert_eq!(format!("{:#}", EntryDisplay(&entry)).len(), 100);
+		assert_eq!(format!("{:.100}", EntryDisplay(&entry)), format!("{:#}", EntryDisplay(&entry)));
+		assert_eq!(format!("{:.0}", EntryDisplay(&entry)), "… (+100 bytes)");
+	}
+}
@@ -0,0 +1,383 @@
+//! Encrypted blobs, where each entry is sealed on its own so one entry can be decrypted without decrypting the rest
+//!
+//! # Layout
+//!
+//! Slice 0 is the 4 magic bytes `BABE` followed by the 8 byte pack salt. Every later slice is one entry, sealed by an `EntryCipher` as the ciphertext followed by the tag.
+//! Entry `i` is sealed with the nonce `salt || i`, with `i` as a little-endian u32, and the associated data `BABE || salt || i || count`,
+//! with `i` and the number of entries as little-endian u64s. An entry moved to another index, copied in from another pack, or left after entries were dropped fails authentication.
+//!
+//! # Overhead
+//!
+//! Each entry costs its 4 byte index and the tag, 20 bytes with `HmacSha256Cipher`, and the header slice costs 16 bytes.
+//! Encrypting a whole blob as one message costs a single nonce and tag, but every read has to decrypt and authenticate all of it first.
+//! Sealing each entry is the better trade for large packs read an entry at a time, and sealing the whole blob for many small entries that are always read together.
+use crate::*;
+use crate::sha256::hmac_sha256;
+
+
+const ENCRYPTED_MAGIC: [u8; 4] = *b"BABE";
+const HMAC_TAG_LEN: usize = 16;
+
+
+/// Errors that can occur when encrypting or decrypting the entries of an encrypted blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EncryptedBlobError {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// The first slice of the blob isn't the encrypted blob header
+	NotEncrypted,
+	/// The encrypted blob would be larger than `u32::MAX` bytes, or have more than `u32::MAX` entries
+	TooLarge,
+	/// Entry `index` was changed, moved, or sealed with a different key or for a different pack
+	AuthenticationFailed(usize),
+	/// There is no entry `index` in a blob of `len` entries
+	IndexOutOfRange {
+		index: usize,
+		len: usize
+	}
+}
+
+
+impl std::error::Error for EncryptedBlobError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			EncryptedBlobError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for EncryptedBlobError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for EncryptedBlobError {
+	fn from(e: BlobReadError) -> Self {
+		EncryptedBlobError::Read(e)
+	}
+}
+
+
+/// An authenticated cipher holding its key, which seals and opens the entries of an encrypted blob
+///
+/// An AEAD such as ChaCha20-Poly1305 or AES-GCM from another crate can be used by implementing this for it.
+/// `open` must fail for anything `seal` didn't return for the same nonce and associated data.
+pub trait EntryCipher {
+	/// Returns the number of bytes `seal` adds to each payload
+	fn tag_len(&self) -> usize;
+
+	/// Returns `plaintext` encrypted, followed by a tag covering it and `aad`. The result must be `tag_len` bytes longer than `plaintext`
+	fn seal(&self, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8>;
+
+	/// Returns the plaintext of `sealed`, or `None` if it or `aad` doesn't match the tag
+	fn open(&self, nonce: &[u8; 12], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>>;
+}
+
+
+impl<C: EntryCipher + ?Sized> EntryCipher for &C {
+	fn tag_len(&self) -> usize {
+		(**self).tag_len()
+	}
+
+	fn seal(&self, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+		(**self).seal(nonce, aad, plaintext)
+	}
+
+	fn open(&self, nonce: &[u8; 12], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+		(**self).open(nonce, aad, sealed)
+	}
+}
+
+
+/// An `EntryCipher` built from HMAC-SHA256, for when adding a dependency for an AEAD isn't an option
+///
+/// Payloads are XORed with a keystream of HMAC-SHA256 blocks of the nonce and a counter, then tagged with HMAC-SHA256 of the nonce,
+/// associated data and ciphertext, truncated to 16 bytes. The encryption and tag keys are derived from `key` with HMAC-SHA256, so `key`
+/// should be at least 32 random bytes. The comparison of tags takes the same time wherever they differ.
+#[derive(Clone)]
+pub struct HmacSha256Cipher {
+	stream_key: [u8; 32],
+	tag_key: [u8; 32]
+}
+
+
+impl HmacSha256Cipher {
+	/// Makes a cipher from `key`, which should be at least 32 bytes from a secure random source or a key derivation function.
+	/// A key of any length is accepted, but one that is short or guessable gives no more security than its own length
+	pub fn new(key: &[u8]) -> Self {
+		HmacSha256Cipher { stream_key: hmac_sha256(key, &[b"byte-array-blob stream"]), tag_key: hmac_sha256(key, &[b"byte-array-blob tag"]) }
+	}
+
+	fn apply_keystream(&self, nonce: &[u8; 12], data: &mut [u8]) {
+		for (counter, chunk) in data.chunks_mut(32).enumerate() {
+			let block = hmac_sha256(&self.stream_key, &[nonce, &(counter as u64).to_le_bytes()]);
+			for (byte, key) in chunk.iter_mut().zip(block) {
+				*byte ^= key;
+			}
+		}
+	}
+
+	fn tag(&self, nonce: &[u8; 12], aad: &[u8], ciphertext: &[u8]) -> [u8; HMAC_TAG_LEN] {
+		// The length of the associated data keeps the boundary between it and the ciphertext from moving
+		let digest = hmac_sha256(&self.tag_key, &[nonce, &(aad.len() as u64).to_le_bytes(), aad, ciphertext]);
+		digest[..HMAC_TAG_LEN].try_into().unwrap()
+	}
+}
+
+
+/// Leaves out the keys
+impl fmt::Debug for HmacSha256Cipher {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		formatter.debug_struct("HmacSha256Cipher").finish_non_exhaustive()
+	}
+}
+
+
+impl EntryCipher for HmacSha256Cipher {
+	fn tag_len(&self) -> usize {
+		HMAC_TAG_LEN
+	}
+
+	fn seal(&self, nonce: &[u8; 12], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+		let mut sealed = plaintext.to_vec();
+		self.apply_keystream(nonce, &mut sealed);
+		let tag = self.tag(nonce, aad, &sealed);
+		sealed.extend(tag);
+		sealed
+	}
+
+	fn open(&self, nonce: &[u8; 12], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+		let (ciphertext, tag) = sealed.split_at_checked(sealed.len().checked_sub(HMAC_TAG_LEN)?)?;
+		let differences = self.tag(nonce, aad, ciphertext).iter().zip(tag).fold(0, |acc, (a, b)| acc | (a ^ b));
+		if differences != 0 {
+			return None;
+		}
+		let mut plaintext = ciphertext.to_vec();
+		self.apply_keystream(nonce, &mut plaintext);
+		Some(plaintext)
+	}
+}
+
+
+/// Returns the nonce for entry `index` of the pack with `salt`
+fn entry_nonce(salt: &[u8; 8], index: u32) -> [u8; 12] {
+	let mut nonce = [0u8; 12];
+	nonce[..8].copy_from_slice(salt);
+	nonce[8..].copy_from_slice(&index.to_le_bytes());
+	nonce
+}
+
+
+/// Returns the associated data for entry `index` of the pack with `salt` and `count` entries
+fn entry_aad(salt: &[u8; 8], index: u32, count: usize) -> [u8; 28] {
+	let mut aad = [0u8; 28];
+	aad[..4].copy_from_slice(&ENCRYPTED_MAGIC);
+	aad[4..12].copy_from_slice(salt);
+	aad[12..20].copy_from_slice(&u64::from(index).to_le_bytes());
+	aad[20..].copy_from_slice(&(count as u64).to_le_bytes());
+	aad
+}
+
+
+/// Converts a slice of u8 slices to an encrypted blob, sealing each slice on its own with `cipher`
+///
+/// `salt` goes into every nonce, so it must be different for every pack sealed with the same key, for example 8 random bytes or a counter that is never reused.
+/// Reusing a salt with the same key reuses every nonce, which with most ciphers reveals the XOR of the entries at each index of the two packs.
+///
+/// # Errors
+///
+/// Returns `Err(EncryptedBlobError::TooLarge)` if the sealed slices would make a blob larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let cipher = HmacSha256Cipher::new(b"a key of at least 32 random bytes!");
+/// let blob = byte_arrays_to_encrypted_blob(&[b"first", b"second", b"third"], &cipher, *b"pack0001").unwrap();
+///
+/// let view = EncryptedBlobView::new(&blob).unwrap();
+/// assert_eq!(view.get(1, &cipher).unwrap(), Some(b"second".to_vec()));
+/// assert_eq!(view.decrypt_range([2, 0], &cipher).unwrap(), [b"third".to_vec(), b"first".to_vec()]);
+/// assert_eq!(view.get(0, &HmacSha256Cipher::new(b"another key")), Err(EncryptedBlobError::AuthenticationFailed(0)));
+/// ```
+pub fn byte_arrays_to_encrypted_blob(bytes_2d: &[&[u8]], cipher: &impl EntryCipher, salt: [u8; 8]) -> Result<Vec<u8>, EncryptedBlobError> {
+	let mut builder = BlobBuilder::with_expected_entries(bytes_2d.len() + 1);
+	builder.push(&[&ENCRYPTED_MAGIC[..], &salt].concat()).map_err(|_| EncryptedBlobError::TooLarge)?;
+	for (index, byte_arr) in bytes_2d.iter().enumerate() {
+		let index = u32::try_from(index).map_err(|_| EncryptedBlobError::TooLarge)?;
+		let sealed = cipher.seal(&entry_nonce(&salt, index), &entry_aad(&salt, index, bytes_2d.len()), byte_arr);
+		builder.push(&sealed).map_err(|_| EncryptedBlobError::TooLarge)?;
+	}
+	builder.finish().map_err(|_| EncryptedBlobError::TooLarge)
+}
+
+
+/// A view of an encrypted blob that decrypts one entry at a time
+///
+/// Only the indexes are checked when the view is made. Each entry is authenticated when it is decrypted, so the view can't say whether an entry
+/// it hasn't decrypted is intact.
+#[derive(Debug, Clone)]
+pub struct EncryptedBlobView<'a> {
+	/// Every slice of the blob, including the header
+	view: BlobView<'a>,
+	salt: [u8; 8]
+}
+
+
+impl<'a> EncryptedBlobView<'a> {
+	/// Validates the indexes of an encrypted blob and reads its header
+	///
+	/// # Errors
+	///
+	/// Returns `Err(EncryptedBlobError::NotEncrypted)` if the first slice isn't the encrypted blob header, otherwise the same as `BlobView::new`
+	pub fn new(blob: &'a [u8]) -> Result<Self, EncryptedBlobError> {
+		let view = BlobView::new(blob)?;
+		let header = view.get(0).ok_or(EncryptedBlobError::NotEncrypted)?;
+		let Some((&ENCRYPTED_MAGIC, salt)) = header.split_first_chunk::<4>() else {
+			return Err(EncryptedBlobError::NotEncrypted);
+		};
+		let salt = salt.try_into().map_err(|_| EncryptedBlobError::NotEncrypted)?;
+		Ok(EncryptedBlobView { view, salt })
+	}
+
+	/// Returns the number of entries, not counting the header
+	pub fn len(&self) -> usize {
+		self.view.len() - 1
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns the salt the entries were sealed with
+	pub fn salt(&self) -> [u8; 8] {
+		self.salt
+	}
+
+	/// Decrypts entry `index` with `cipher`, or returns `Ok(None)` if there is no such entry. No other entry is read
+	///
+	/// # Errors
+	///
+	/// Returns `Err(EncryptedBlobError::AuthenticationFailed(index))` if the entry doesn't open with `cipher` at this index of this pack
+	pub fn get(&self, index: usize, cipher: &impl EntryCipher) -> Result<Option<Vec<u8>>, EncryptedBlobError> {
+		let Some(sealed) = index.checked_add(1).and_then(|slice| self.view.get(slice)) else {
+			return Ok(None);
+		};
+		// The view has no more than `u32::MAX` slices, since each takes 4 bytes of a blob no larger than that
+		let entry = index as u32;
+		cipher.open(&entry_nonce(&self.salt, entry), &entry_aad(&self.salt, entry, self.len()), sealed)
+			.map(Some)
+			.ok_or(EncryptedBlobError::AuthenticationFailed(index))
+	}
+
+	/// Decrypts the entries at `indices` with `cipher`, in the order given, reading no others
+	///
+	/// # Errors
+	///
+	/// Returns `Err(EncryptedBlobError::IndexOutOfRange)` if an index is past the end, otherwise the same as `get`, for the first index that fails
+	pub fn decrypt_range(&self, indices: impl IntoIterator<Item = usize>, cipher: &impl EntryCipher) -> Result<Vec<Vec<u8>>, EncryptedBlobError> {
+		indices.into_iter()
+			.map(|index| self.get(index, cipher)?.ok_or(EncryptedBlobError::IndexOutOfRange { index, len: self.len() }))
+			.collect()
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	const KEY: &[u8] = b"0123456789abcdef0123456789abcdef";
+
+	fn encrypted(entries: &[Vec<u8>], salt: [u8; 8]) -> Vec<u8> {
+		let slices = as_slices(entries);
+		byte_arrays_to_encrypted_blob(&slices, &HmacSha256Cipher::new(KEY), salt).unwrap()
+	}
+
+	#[test]
+	fn round_trip() {
+		let entries = sample_entries();
+		let blob = encrypted(&entries, *b"saltsalt");
+		let cipher = HmacSha256Cipher::new(KEY);
+		let view = EncryptedBlobView::new(&blob).unwrap();
+		assert_eq!(view.len(), 9);
+		assert_eq!(view.salt(), *b"saltsalt");
+		for (index, entry) in entries.iter().enumerate() {
+			assert_eq!(view.get(index, &cipher).unwrap().as_ref(), Some(entry));
+		}
+		assert_eq!(view.get(9, &cipher), Ok(None));
+		assert_eq!(view.get(usize::MAX, &cipher), Ok(None));
+		assert_eq!(view.decrypt_range([usize::MAX], &cipher), Err(EncryptedBlobError::IndexOutOfRange { index: usize::MAX, len: 9 }));
+		assert_eq!(view.decrypt_range(0..9, &cipher).unwrap(), entries);
+		assert_eq!(view.decrypt_range(7..11, &cipher), Err(EncryptedBlobError::IndexOutOfRange { index: 9, len: 9 }));
+
+		// No payload is stored in the clear
+		let stored = blob_to_byte_arrays(&blob).unwrap();
+		assert_ne!(stored[2], b"alpha");
+		assert!(!stored[3].iter().all(|&byte| byte == 0));
+
+		let empty = byte_arrays_to_encrypted_blob(&[], &cipher, [0; 8]).unwrap();
+		assert!(EncryptedBlobView::new(&empty).unwrap().is_empty());
+	}
+
+	#[test]
+	fn overhead_per_entry() {
+		let entries = sample_entries();
+		let slices = as_slices(&entries);
+		let payload: usize = entries.iter().map(Vec::len).sum();
+		assert_eq!(encrypted(&entries, [0; 8]).len(), 16 + payload + 20 * entries.len());
+
+		// Over a plain blob with the same entries, each costs just its tag
+		let plain = byte_arrays_to_blob(&slices);
+		assert_eq!(encrypted(&entries, [0; 8]).len() - plain.len(), 16 + HMAC_TAG_LEN * entries.len());
+	}
+
+	#[test]
+	fn swapped_entries_fail_authentication() {
+		let entries = vec![b"same length 1".to_vec(), b"same length 2".to_vec(), b"other".to_vec()];
+		let blob = encrypted(&entries, *b"saltsalt");
+		let cipher = HmacSha256Cipher::new(KEY);
+
+		let mut stored = blob_to_byte_vecs(&blob).unwrap();
+		stored.swap(1, 2);
+		let swapped = entries_to_blob(&stored);
+		assert_eq!(swapped.len(), blob.len());
+		let view = EncryptedBlobView::new(&swapped).unwrap();
+		assert_eq!(view.get(0, &cipher), Err(EncryptedBlobError::AuthenticationFailed(0)));
+		assert_eq!(view.get(1, &cipher), Err(EncryptedBlobError::AuthenticationFailed(1)));
+		assert_eq!(view.get(2, &cipher).unwrap(), Some(b"other".to_vec()));
+
+		// An entry from another pack, or left after the last entry was dropped, fails too
+		let other = encrypted(&entries, *b"another!");
+		stored = blob_to_byte_vecs(&blob).unwrap();
+		stored[3] = blob_to_byte_vecs(&other).unwrap().remove(3);
+		let substituted = entries_to_blob(&stored);
+		assert_eq!(EncryptedBlobView::new(&substituted).unwrap().get(2, &cipher), Err(EncryptedBlobError::AuthenticationFailed(2)));
+
+		let truncated = entries_to_blob(&stored[..3]);
+		assert_eq!(EncryptedBlobView::new(&truncated).unwrap().get(0, &cipher), Err(EncryptedBlobError::AuthenticationFailed(0)));
+	}
+
+	#[test]
+	fn tampering_and_bad_headers() {
+		let blob = encrypted(&sample_entries(), [1; 8]);
+		let cipher = HmacSha256Cipher::new(KEY);
+		let mut tampered = blob.clone();
+		let last = tampered.len() - 1;
+		tampered[last] ^= 1;
+		assert_eq!(EncryptedBlobView::new(&tampered).unwrap().get(8, &cipher), Err(EncryptedBlobError::AuthenticationFailed(8)));
+		assert!(EncryptedBlobView::new(&tampered).unwrap().get(7, &cipher).is_ok());
+
+		assert!(cipher.open(&[0; 12], &[], &[0; HMAC_TAG_LEN - 1]).is_none());
+		assert_eq!(EncryptedBlobView::new(&[]).err(), Some(EncryptedBlobError::NotEncrypted));
+		assert_eq!(EncryptedBlobView::new(&byte_arrays_to_blob(&[b"BABEsalt"])).err(), Some(EncryptedBlobError::NotEncrypted));
+		assert!(matches!(EncryptedBlobView::new(&blob[..blob.len() - 1]), Err(EncryptedBlobError::Read(_))));
+		assert_eq!(format!("{:?}", cipher), "HmacSha256Cipher { .. }");
+	}
+}
@@ -0,0 +1,498 @@
+//! `Error`, a single error type every error in the crate converts into
+use crate::*;
+use std::io;
+
+
+/// Any error returned by this crate
+///
+/// Functions return their own specific error type, which keeps the possible failures precise.
+/// Every one of those types converts into `Error` with `?`, for application code that doesn't need to tell them apart.
+/// `MigrateError` converts with the callback's error boxed in a `CallbackError`. `FilteredDecodeError`, `VerifyTransformError` and `EntryParseError` have no
+/// conversion: their error parameter is the caller's own type, and the caller wants it back rather than boxed inside `Error`.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// // Copies a blob from a reader to a writer, using two different error types
+/// fn copy_blob(blob: &[u8], out: &mut Vec<u8>) -> Result<(), byte_array_blob::Error> {
+/// 	let mut writer = BlobWriter::new(out);
+/// 	for entry in BlobReader::new(blob) {
+/// 		writer.write_entry(&entry?)?;
+/// 	}
+/// 	Ok(())
+/// }
+///
+/// let error = copy_blob(&[255, 0, 0, 0], &mut Vec::new()).unwrap_err();
+/// assert!(error.is_corruption());
+/// assert!(!error.is_io());
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+	/// A blob is invalid
+	Read(BlobReadError),
+	/// A blob could not be written
+	Write(BlobWriteError),
+	/// An error from a reader, writer or the filesystem
+	Io(io::Error),
+	/// A filesystem operation on a blob failed
+	#[cfg(feature = "fs")]
+	File(BlobFileError),
+	/// A blob could not be partitioned
+	Partition(PartitionError),
+	/// A delta pack could not be resolved
+	Resolve(ResolveError),
+	/// An entry does not hold a whole number of numbers
+	Numeric(NumericEntryError),
+	/// A blob stream failed verification
+	Verify(VerifyError),
+	/// A named blob is invalid or out of order
+	Named(NamedBlobError),
+	/// Two blobs could not be read side by side
+	Zip(ZipError),
+	/// A length prefixed frame could not be read
+	Frame(FrameError),
+	/// A saved overlay could not be loaded
+	Overlay(OverlayError),
+	/// A sectioned blob could not be built
+	Section(SectionError),
+	/// The slices of a blob could not be swapped or reordered
+	Edit(BlobEditError),
+	/// Decoding could not resume from a checkpoint
+	Resume(ResumeError),
+	/// An entry codec failed
+	Codec(CodecError),
+	/// Streams could not be interleaved into a blob
+	Interleave(InterleaveError),
+	/// An entry does not hold records of a `RecordLayout`
+	Layout(LayoutError),
+	/// Entries could not be normalized to a fixed size
+	Normalize(NormalizeError),
+	/// An entry of an encrypted blob could not be decrypted
+	Encrypted(EncryptedBlobError),
+	/// Canonical JSON could not be turned back into a blob
+	#[cfg(feature = "interop")]
+	Interop(InteropError),
+	/// A blob could not be published to a shared region
+	Publish(PublishError),
+	/// A blob could not be read in a `const fn`
+	Const(ConstBlobError),
+	/// An entry name could not be turned into an `OsStr`
+	Name(NameError),
+	/// A versioned blob could not be migrated. The callback's error is boxed
+	Migrate(MigrateError<CallbackError>)
+}
+
+
+impl Error {
+	/// Returns true if the error was caused by invalid blob data, rather than by IO or by a request that can't be met
+	pub fn is_corruption(&self) -> bool {
+		match self {
+			Error::Read(_) => true,
+			Error::Write(_) | Error::Io(_) => false,
+			#[cfg(feature = "fs")]
+			Error::File(e) => matches!(e, BlobFileError::Read(_)),
+			Error::Partition(e) => matches!(e, PartitionError::Read(_)),
+			Error::Resolve(_) => true,
+			Error::Numeric(_) => true,
+			Error::Verify(e) => matches!(e, VerifyError::Invalid { .. }),
+			Error::Named(_) => true,
+			Error::Zip(e) => matches!(e, ZipError::Read(_)),
+			Error::Frame(e) => matches!(e, FrameError::Truncated(_)),
+			Error::Overlay(_) => true,
+			Error::Section(_) => false,
+			Error::Edit(e) => matches!(e, BlobEditError::Read(_)),
+			Error::Resume(e) => matches!(e, ResumeError::InvalidHeader(_)),
+			Error::Codec(e) => matches!(e, CodecError::Read(_) | CodecError::DecodeFailed(_)),
+			Error::Interleave(_) => false,
+			Error::Layout(e) => matches!(e, LayoutError::TooShort { .. } | LayoutError::TrailingBytes { .. }),
+			Error::Normalize(e) => matches!(e, NormalizeError::Read(_)),
+			Error::Encrypted(e) => !matches!(e, EncryptedBlobError::TooLarge | EncryptedBlobError::IndexOutOfRange { .. }),
+			#[cfg(feature = "interop")]
+			Error::Interop(_) => true,
+			Error::Publish(_) => false,
+			Error::Const(_) => true,
+			Error::Name(_) => false,
+			Error::Migrate(e) => matches!(e, MigrateError::Read(_))
+		}
+	}
+
+	/// Returns true if the error was caused by an `io::Error`, however deeply it is wrapped
+	pub fn is_io(&self) -> bool {
+		self.io_error().is_some()
+	}
+
+	/// Returns the `io::Error` that caused the error, if there is one
+	pub fn io_error(&self) -> Option<&io::Error> {
+		match self {
+			Error::Io(e) => Some(e),
+			Error::Write(BlobWriteError::Io(e)) => Some(e),
+			#[cfg(feature = "fs")]
+			Error::File(BlobFileError::Io(e)) => Some(e),
+			Error::Verify(VerifyError::Io(e)) => Some(e),
+			Error::Frame(FrameError::Io(e)) => Some(e),
+			_ => None
+		}
+	}
+}
+
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Read(e) => Some(e),
+			Error::Write(e) => Some(e),
+			Error::Io(e) => Some(e),
+			#[cfg(feature = "fs")]
+			Error::File(e) => Some(e),
+			Error::Partition(e) => Some(e),
+			Error::Resolve(e) => Some(e),
+			Error::Numeric(e) => Some(e),
+			Error::Verify(e) => Some(e),
+			Error::Named(e) => Some(e),
+			Error::Zip(e) => Some(e),
+			Error::Frame(e) => Some(e),
+			Error::Overlay(e) => Some(e),
+			Error::Section(e) => Some(e),
+			Error::Edit(e) => Some(e),
+			Error::Resume(e) => Some(e),
+			Error::Codec(e) => Some(e),
+			Error::Interleave(e) => Some(e),
+			Error::Layout(e) => Some(e),
+			Error::Normalize(e) => Some(e),
+			Error::Encrypted(e) => Some(e),
+			#[cfg(feature = "interop")]
+			Error::Interop(e) => Some(e),
+			Error::Publish(e) => Some(e),
+			Error::Const(e) => Some(e),
+			Error::Name(e) => Some(e),
+			Error::Migrate(e) => Some(e)
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Error::Read(_) => write!(formatter, "invalid blob"),
+			Error::Write(_) => write!(formatter, "failed to write blob"),
+			Error::Io(_) => write!(formatter, "io error"),
+			#[cfg(feature = "fs")]
+			Error::File(_) => write!(formatter, "blob file operation failed"),
+			Error::Partition(_) => write!(formatter, "failed to partition blob"),
+			Error::Resolve(_) => write!(formatter, "failed to resolve delta pack"),
+			Error::Numeric(_) => write!(formatter, "invalid numeric entry"),
+			Error::Verify(_) => write!(formatter, "blob verification failed"),
+			Error::Named(_) => write!(formatter, "invalid named blob"),
+			Error::Zip(_) => write!(formatter, "failed to read blobs side by side"),
+			Error::Frame(_) => write!(formatter, "failed to read frame"),
+			Error::Overlay(_) => write!(formatter, "invalid saved overlay"),
+			Error::Section(_) => write!(formatter, "failed to build sectioned blob"),
+			Error::Edit(_) => write!(formatter, "failed to reorder blob"),
+			Error::Resume(_) => write!(formatter, "failed to resume from checkpoint"),
+			Error::Codec(_) => write!(formatter, "entry codec failed"),
+			Error::Interleave(_) => write!(formatter, "failed to interleave streams"),
+			Error::Layout(_) => write!(formatter, "entry does not match the record layout"),
+			Error::Normalize(_) => write!(formatter, "failed to normalize entries"),
+			Error::Encrypted(_) => write!(formatter, "failed to decrypt entry"),
+			#[cfg(feature = "interop")]
+			Error::Interop(_) => write!(formatter, "invalid canonical json"),
+			Error::Publish(_) => write!(formatter, "failed to publish blob"),
+			Error::Const(_) => write!(formatter, "invalid blob"),
+			Error::Name(_) => write!(formatter, "invalid entry name"),
+			Error::Migrate(_) => write!(formatter, "failed to migrate blob")
+		}
+	}
+}
+
+
+/// The error a callback returned, boxed so that `Error` doesn't need a type parameter for it
+#[derive(Debug)]
+pub struct CallbackError(Box<dyn std::error::Error + Send + Sync>);
+
+
+impl CallbackError {
+	/// Returns the error the callback returned
+	pub fn get_ref(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+		&*self.0
+	}
+
+	/// Returns the error the callback returned, taking ownership of it
+	pub fn into_inner(self) -> Box<dyn std::error::Error + Send + Sync> {
+		self.0
+	}
+}
+
+
+impl std::error::Error for CallbackError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		self.0.source()
+	}
+}
+
+impl fmt::Display for CallbackError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.fmt(formatter)
+	}
+}
+
+
+impl From<BlobReadError> for Error {
+	fn from(e: BlobReadError) -> Self {
+		Error::Read(e)
+	}
+}
+
+impl From<BlobWriteError> for Error {
+	fn from(e: BlobWriteError) -> Self {
+		Error::Write(e)
+	}
+}
+
+impl From<io::Error> for Error {
+	fn from(e: io::Error) -> Self {
+		Error::Io(e)
+	}
+}
+
+/// `BlobStreamError` is only ever an IO error or a read error, so it is unwrapped rather than kept as another layer
+impl From<BlobStreamError> for Error {
+	fn from(e: BlobStreamError) -> Self {
+		match e {
+			BlobStreamError::Io(e) => Error::Io(e),
+			BlobStreamError::Read(e) => Error::Read(e)
+		}
+	}
+}
+
+#[cfg(feature = "fs")]
+impl From<BlobFileError> for Error {
+	fn from(e: BlobFileError) -> Self {
+		Error::File(e)
+	}
+}
+
+impl From<PartitionError> for Error {
+	fn from(e: PartitionError) -> Self {
+		Error::Partition(e)
+	}
+}
+
+impl From<ResolveError> for Error {
+	fn from(e: ResolveError) -> Self {
+		Error::Resolve(e)
+	}
+}
+
+impl From<NumericEntryError> for Error {
+	fn from(e: NumericEntryError) -> Self {
+		Error::Numeric(e)
+	}
+}
+
+impl From<VerifyError> for Error {
+	fn from(e: VerifyError) -> Self {
+		Error::Verify(e)
+	}
+}
+
+impl From<NamedBlobError> for Error {
+	fn from(e: NamedBlobError) -> Self {
+		Error::Named(e)
+	}
+}
+
+impl From<ZipError> for Error {
+	fn from(e: ZipError) -> Self {
+		Error::Zip(e)
+	}
+}
+
+impl From<FrameError> for Error {
+	fn from(e: FrameError) -> Self {
+		Error::Frame(e)
+	}
+}
+
+impl From<OverlayError> for Error {
+	fn from(e: OverlayError) -> Self {
+		Error::Overlay(e)
+	}
+}
+
+impl From<SectionError> for Error {
+	fn from(e: SectionError) -> Self {
+		Error::Section(e)
+	}
+}
+
+impl From<BlobEditError> for Error {
+	fn from(e: BlobEditError) -> Self {
+		Error::Edit(e)
+	}
+}
+
+impl From<ResumeError> for Error {
+	fn from(e: ResumeError) -> Self {
+		Error::Resume(e)
+	}
+}
+
+impl From<CodecError> for Error {
+	fn from(e: CodecError) -> Self {
+		Error::Codec(e)
+	}
+}
+
+impl From<InterleaveError> for Error {
+	fn from(e: InterleaveError) -> Self {
+		Error::Interleave(e)
+	}
+}
+
+impl From<LayoutError> for Error {
+	fn from(e: LayoutError) -> Self {
+		Error::Layout(e)
+	}
+}
+
+impl From<NormalizeError> for Error {
+	fn from(e: NormalizeError) -> Self {
+		Error::Normalize(e)
+	}
+}
+
+impl From<EncryptedBlobError> for Error {
+	fn from(e: EncryptedBlobError) -> Self {
+		Error::Encrypted(e)
+	}
+}
+
+#[cfg(feature = "interop")]
+impl From<InteropError> for Error {
+	fn from(e: InteropError) -> Self {
+		Error::Interop(e)
+	}
+}
+
+impl From<PublishError> for Error {
+	fn from(e: PublishError) -> Self {
+		Error::Publish(e)
+	}
+}
+
+impl From<ConstBlobError> for Error {
+	fn from(e: ConstBlobError) -> Self {
+		Error::Const(e)
+	}
+}
+
+impl From<NameError> for Error {
+	fn from(e: NameError) -> Self {
+		Error::Name(e)
+	}
+}
+
+impl<E: Into<Box<dyn std::error::Error + Send + Sync>>> From<MigrateError<E>> for Error {
+	fn from(e: MigrateError<E>) -> Self {
+		Error::Migrate(match e {
+			MigrateError::Read(e) => MigrateError::Read(e),
+			MigrateError::NewerThanTarget { entry, version } => MigrateError::NewerThanTarget { entry, version },
+			MigrateError::Migrate { entry, from, to, error } => MigrateError::Migrate { entry, from, to, error: CallbackError(error.into()) },
+			MigrateError::TooLarge => MigrateError::TooLarge
+		})
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::error::Error as _;
+
+	fn chain_depth(e: &dyn std::error::Error) -> usize {
+		let mut depth = 1;
+		let mut source = e.source();
+		while let Some(e) = source {
+			depth += 1;
+			source = e.source();
+		}
+		depth
+	}
+
+	struct FailingWriter;
+
+	impl io::Write for FailingWriter {
+		fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+			Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+		}
+
+		fn flush(&mut self) -> io::Result<()> {
+			Ok(())
+		}
+	}
+
+	fn write_one() -> Result<(), Error> {
+		BlobWriter::new(FailingWriter).write_entry(&[1u8])?;
+		Ok(())
+	}
+
+	#[test]
+	fn wrapped_io_error() {
+		let e = write_one().unwrap_err();
+		assert!(e.is_io());
+		assert!(!e.is_corruption());
+		assert_eq!(e.io_error().unwrap().kind(), io::ErrorKind::BrokenPipe);
+
+		// Error -> BlobWriteError -> io::Error
+		assert_eq!(chain_depth(&e), 3);
+		assert!(e.source().unwrap().downcast_ref::<BlobWriteError>().is_some());
+	}
+
+	#[test]
+	fn stream_errors_are_unwrapped() {
+		let e: Error = BlobReader::new(&[255u8, 0u8, 0u8, 0u8][..]).read_entry().unwrap_err().into();
+		assert!(matches!(e, Error::Read(BlobReadError::NotABlob { first_header: 255 })));
+		assert!(e.is_corruption());
+		assert_eq!(chain_depth(&e), 2);
+
+		let e: Error = BlobStreamError::Io(io::Error::other("gone")).into();
+		assert!(e.is_io());
+		assert_eq!(chain_depth(&e), 2);
+	}
+
+	#[test]
+	fn classification() {
+		let e: Error = PartitionError::EntryTooLargeForPartition { entry: 0, size: 10 }.into();
+		assert!(!e.is_corruption() && !e.is_io());
+
+		let e: Error = PartitionError::Read(BlobReadError::TooLarge).into();
+		assert!(e.is_corruption());
+		assert_eq!(chain_depth(&e), 3);
+
+		let e: Error = PublishError::Busy.into();
+		assert!(!e.is_corruption() && !e.is_io());
+		let e: Error = ConstBlobError::TruncatedIndex(0).into();
+		assert!(e.is_corruption());
+		let e: Error = NameError::NotUnicode.into();
+		assert!(!e.is_corruption());
+
+		let e: Error = MigrateError::<String>::Read(BlobReadError::TooLarge).into();
+		assert!(e.is_corruption());
+		let e: Error = MigrateError::Migrate { entry: 0, from: 1, to: 2, error: String::from("bad field") }.into();
+		assert!(!e.is_corruption() && !e.is_io());
+		// Error -> MigrateError -> the boxed callback error
+		assert_eq!(chain_depth(&e), 3);
+		assert_eq!(e.source().unwrap().source().unwrap().to_string(), "bad field");
+
+		#[cfg(feature = "fs")]
+		{
+			let e: Error = BlobFileError::Io(io::Error::other("disk")).into();
+			assert!(e.is_io() && !e.is_corruption());
+			assert_eq!(chain_depth(&e), 3);
+		}
+	}
+}
@@ -0,0 +1,674 @@
+//! Functions that work on blob files without loading them into memory
+use crate::*;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+/// Size of the buffer used when copying payloads between files
+const COPY_CHUNK: usize = 64 * 1024;
+
+
+/// Reports what `convert_blob_in_file` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConversionReport {
+	/// Number of entries converted
+	pub entries: usize,
+	/// Number of bytes written. When converted in place this is only the rewritten indexes
+	pub bytes_written: u64,
+	/// True if the file was converted in place rather than rewritten
+	pub in_place: bool
+}
+
+
+/// Converts the blob file at `path` from one index layout to another
+///
+/// When both layouts have the same index width (such as `NativeEndian` to `LittleEndian`) only the indexes are rewritten, in place.
+/// Otherwise the file is copied in bounded chunks to a temporary file next to it, which is then renamed over the original.
+/// In both cases every index is checked first, so an invalid file is left untouched.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Read)` if the file is not a valid blob in the `from` layout, or would be too large for the `to` layout,
+/// or `Err(BlobFileError::Io)` if reading or writing fails
+pub fn convert_blob_in_file(path: &Path, from: BlobFormat, to: BlobFormat) -> Result<ConversionReport, BlobFileError> {
+	if from.header_len() == to.header_len() {
+		convert_in_place(path, from, to)
+	}
+	else {
+		let temp_path = temp_path_for(path, ".convert-tmp");
+		let result = convert_by_rewrite(path, &temp_path, from, to);
+		if result.is_err() {
+			let _ = fs::remove_file(&temp_path);
+		}
+		result
+	}
+}
+
+
+pub(crate) fn temp_path_for(path: &Path, suffix: &str) -> PathBuf {
+	let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+	file_name.push(suffix);
+	path.with_file_name(file_name)
+}
+
+
+/// Returns a path next to `path` ending in `suffix`, the process id and a counter, so merges running at the same time never share it
+fn unique_temp_path_for(path: &Path, suffix: &str) -> PathBuf {
+	static NEXT: AtomicUsize = AtomicUsize::new(0);
+	temp_path_for(path, &format!("{}.{}.{}", suffix, std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)))
+}
+
+
+/// Converts a position in a file to a usize for an error, where a position that doesn't fit reads as `usize::MAX`
+fn error_position(position: u64) -> usize {
+	usize::try_from(position).unwrap_or(usize::MAX)
+}
+
+
+/// Reads the index at the current position of `reader`, returning `None` at a clean end of file
+fn read_file_index(reader: &mut impl Read, position: u64, format: BlobFormat) -> Result<Option<u64>, BlobFileError> {
+	let header_len = format.header_len();
+	let mut data = [0u8; 8];
+	let mut filled = 0;
+	while filled < header_len {
+		match reader.read(&mut data[filled..header_len]) {
+			Ok(0) => break,
+			Ok(n) => filled += n,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e.into())
+		}
+	}
+
+	match filled {
+		0 => Ok(None),
+		n if n < header_len => Err(BlobReadError::TruncatedIndex(error_position(position)).into()),
+		_ => Ok(Some(format.read_index_u64(&data[..header_len], position)))
+	}
+}
+
+
+/// Checks an index read at `position` in a file of `file_len` bytes, in the same way as `blob_to_byte_arrays`,
+/// so a bad first index is reported as `NotABlob`
+fn check_file_index(idx_end: u64, position: u64, header_len: usize, file_len: u64) -> Result<(), BlobReadError> {
+	if idx_end > file_len || idx_end < position + header_len as u64 {
+		return Err(match position {
+			0 => BlobReadError::NotABlob { first_header: u32::try_from(idx_end).unwrap_or(u32::MAX) },
+			_ => BlobReadError::InvalidEncodedIndex(error_position(idx_end))
+		});
+	}
+	Ok(())
+}
+
+
+/// Walks the indexes of a blob through `Read + Seek`, seeking past each payload rather than reading it
+///
+/// Positions are u64 throughout, so a file larger than the address space can be walked on a 32-bit target
+/// as long as its layout can describe it. This is separate from the slice based decoders, which stay the fast path for blobs in memory.
+pub(crate) struct FileHeaders {
+	format: BlobFormat,
+	position: u64,
+	len: u64
+}
+
+
+impl FileHeaders {
+	/// Finds the length of the blob by seeking to the end of `reader`
+	pub(crate) fn new(reader: &mut impl Seek, format: BlobFormat) -> Result<Self, BlobFileError> {
+		let len = reader.seek(SeekFrom::End(0))?;
+		if len > format.max_len_u64() {
+			return Err(BlobReadError::TooLarge.into());
+		}
+		Ok(FileHeaders { format, position: 0, len })
+	}
+
+	/// Returns the position of the next index, which is also where the last slice read ended
+	fn position(&self) -> u64 {
+		self.position
+	}
+
+	/// Reads and checks the next index, returning the range of its payload, or `None` at the end of the blob
+	pub(crate) fn next_range(&mut self, reader: &mut (impl Read + Seek)) -> Result<Option<Range<u64>>, BlobFileError> {
+		if self.position >= self.len {
+			return Ok(None);
+		}
+		reader.seek(SeekFrom::Start(self.position))?;
+		let truncated = BlobReadError::TruncatedIndex(error_position(self.position));
+		let idx_end = read_file_index(reader, self.position, self.format)?.ok_or(truncated)?;
+		check_file_index(idx_end, self.position, self.format.header_len(), self.len)?;
+
+		let range = self.position + self.format.header_len() as u64..idx_end;
+		self.position = idx_end;
+		Ok(Some(range))
+	}
+}
+
+
+/// Reads the payload ranges of every slice of a blob through `Read + Seek`
+pub(crate) fn entry_ranges(reader: &mut (impl Read + Seek), format: BlobFormat) -> Result<Vec<Range<u64>>, BlobFileError> {
+	let mut headers = FileHeaders::new(reader, format)?;
+	let mut ranges = Vec::new();
+	while let Some(range) = headers.next_range(reader)? {
+		ranges.push(range);
+	}
+	Ok(ranges)
+}
+
+
+/// Returns the position in the file of the payload of every slice of the blob file at `path`
+///
+/// Only the indexes are read, by seeking from one to the next with u64 positions, so the file never has to be mapped or loaded
+/// and can be larger than the address space.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`, as `Err(BlobFileError::Read)`, where a position past `usize::MAX` is reported as `usize::MAX`.
+/// Returns `Err(BlobFileError::Io)` if reading the file fails
+pub fn blob_file_entry_ranges(path: &Path) -> Result<Vec<Range<u64>>, BlobFileError> {
+	entry_ranges(&mut BufReader::new(File::open(path)?), BlobFormat::NativeEndian)
+}
+
+
+/// Reads slice `index` of the blob file at `path`, returning `None` if the file has fewer slices
+///
+/// Only the indexes before the slice and the slice itself are read. See `blob_file_entry_ranges`
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Read)` if one of the indexes up to the slice is invalid, or `Err(BlobFileError::Read(BlobReadError::TooLarge))`
+/// if the slice is too large to hold in memory. Returns `Err(BlobFileError::Io)` if reading the file fails
+///
+/// # Examples
+/// ```no_run
+/// use byte_array_blob::*;
+/// use std::path::Path;
+///
+/// let texture = read_file_entry(Path::new("assets.blob"), 12).unwrap().expect("no such entry");
+/// ```
+pub fn read_file_entry(path: &Path, index: usize) -> Result<Option<Vec<u8>>, BlobFileError> {
+	let mut reader = BufReader::new(File::open(path)?);
+	read_entry_at(&mut reader, BlobFormat::NativeEndian, index)
+}
+
+
+fn read_entry_at(reader: &mut (impl Read + Seek), format: BlobFormat, index: usize) -> Result<Option<Vec<u8>>, BlobFileError> {
+	let mut headers = FileHeaders::new(reader, format)?;
+	for _ in 0..index {
+		if headers.next_range(reader)?.is_none() {
+			return Ok(None);
+		}
+	}
+	let range = match headers.next_range(reader)? {
+		Some(range) => range,
+		None => return Ok(None)
+	};
+
+	let len = usize::try_from(range.end - range.start).map_err(|_| BlobReadError::TooLarge)?;
+	let mut byte_arr = vec![0u8; len];
+	reader.seek(SeekFrom::Start(range.start))?;
+	reader.read_exact(&mut byte_arr)?;
+	Ok(Some(byte_arr))
+}
+
+
+fn convert_in_place(path: &Path, from: BlobFormat, to: BlobFormat) -> Result<ConversionReport, BlobFileError> {
+	let mut file = File::options().read(true).write(true).open(path)?;
+	let file_len = usize::try_from(file.metadata()?.len()).map_err(|_| BlobReadError::TooLarge)?;
+	if file_len > from.max_len() || file_len > to.max_len() {
+		return Err(BlobReadError::TooLarge.into());
+	}
+	let header_len = from.header_len();
+
+	// Check every index before changing any of them, so an invalid file is not left half converted
+	let ranges = entry_ranges(&mut file, from)?;
+
+	for range in &ranges {
+		// The file fits in usize, so every position in it does too
+		let (position, idx_end) = (range.start as usize - header_len, range.end as usize);
+		file.seek(SeekFrom::Start(position as u64))?;
+		file.write_all(&to.encode_index(position, idx_end)[..header_len])?;
+	}
+	file.sync_all()?;
+
+	Ok(ConversionReport { entries: ranges.len(), bytes_written: (ranges.len() * header_len) as u64, in_place: true })
+}
+
+
+fn convert_by_rewrite(path: &Path, temp_path: &Path, from: BlobFormat, to: BlobFormat) -> Result<ConversionReport, BlobFileError> {
+	let input = File::open(path)?;
+	let file_len = input.metadata()?.len();
+	if file_len > from.max_len_u64() {
+		return Err(BlobReadError::TooLarge.into());
+	}
+	let mut reader = BufReader::with_capacity(COPY_CHUNK, input);
+	let mut writer = BufWriter::with_capacity(COPY_CHUNK, File::create(temp_path)?);
+
+	let mut entries = 0;
+	let mut position = 0;
+	let mut written: usize = 0;
+	while let Some(idx_end) = read_file_index(&mut reader, position, from)? {
+		check_file_index(idx_end, position, from.header_len(), file_len)?;
+		let len = usize::try_from(idx_end - position - from.header_len() as u64).map_err(|_| BlobReadError::TooLarge)?;
+
+		let idx_start = written;
+		written = written.checked_add(to.header_len()).and_then(|written| written.checked_add(len))
+			.filter(|&written| written <= to.max_len()).ok_or(BlobReadError::TooLarge)?;
+		writer.write_all(&to.encode_index(idx_start, written)[..to.header_len()])?;
+
+		// The length was checked against the file size, so a short copy means the file changed while being read
+		let copied = io::copy(&mut (&mut reader).take(len as u64), &mut writer)?;
+		if copied != len as u64 {
+			return Err(BlobReadError::InvalidEncodedIndex(error_position(idx_end)).into());
+		}
+
+		entries += 1;
+		position = idx_end;
+	}
+
+	let output = writer.into_inner().map_err(|e| e.into_error())?;
+	output.sync_all()?;
+	drop(output);
+	fs::rename(temp_path, path)?;
+
+	Ok(ConversionReport { entries, bytes_written: written as u64, in_place: false })
+}
+
+
+/// Cuts the blob file at `path` down to its first `keep` slices, and returns its new length in bytes. See `blob_truncate_entries`
+///
+/// Only the first `keep` indexes are read and the file is cut with `File::set_len`, so no payload is read or rewritten.
+/// If the file holds `keep` slices or fewer it is left as it is and its full length is returned.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Read)` without changing the file if one of the indexes before the cut is invalid,
+/// or `Err(BlobFileError::Io)` if reading or resizing the file fails
+pub fn truncate_blob_file(path: &Path, keep: usize) -> Result<u64, BlobFileError> {
+	let mut file = File::options().read(true).write(true).open(path)?;
+	let mut headers = FileHeaders::new(&mut file, BlobFormat::NativeEndian)?;
+	for _ in 0..keep {
+		if headers.next_range(&mut file)?.is_none() {
+			break;
+		}
+	}
+
+	let position = headers.position();
+	if position < file.metadata()?.len() {
+		file.set_len(position)?;
+		file.sync_all()?;
+	}
+	Ok(position)
+}
+
+
+/// Reports what `merge_blob_files` did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeStats {
+	/// Number of input files read
+	pub files: usize,
+	/// Number of entries written to the output
+	pub entries: usize,
+	/// Number of bytes written to the output
+	pub bytes_written: u64,
+	/// Number of entries left out because an equal entry had already been written
+	pub duplicates_skipped: usize
+}
+
+
+/// Options for `merge_blob_files_with_options`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let options = MergeOptions::new().with_dedup(true);
+/// assert!(options.dedup());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MergeOptions {
+	dedup: bool
+}
+
+
+impl MergeOptions {
+	/// Creates options that keep every entry
+	pub fn new() -> Self {
+		MergeOptions::default()
+	}
+
+	/// Leaves out any entry equal to one already written
+	///
+	/// Entries are matched by hash and then compared against the copy in the output, so a hash collision never drops an entry.
+	/// A hash and position are kept for each distinct entry, so memory grows with the number of distinct entries but not their size.
+	pub fn with_dedup(mut self, dedup: bool) -> Self {
+		self.dedup = dedup;
+		self
+	}
+
+	/// Returns whether entries equal to one already written are left out
+	pub fn dedup(&self) -> bool {
+		self.dedup
+	}
+}
+
+
+/// Concatenates the blob files at `inputs` into one blob file at `output`, keeping the entries in order. See `merge_blob_files_with_options`
+pub fn merge_blob_files(inputs: &[&Path], output: &Path) -> Result<MergeStats, BlobFileError> {
+	merge_blob_files_with_options(inputs, output, &MergeOptions::new())
+}
+
+
+/// Concatenates the blob files at `inputs` into one blob file at `output`, keeping the entries in order
+///
+/// Each input is streamed through a `BlobReader` and each entry written with a `BlobWriter`, so only one entry is in memory at a time.
+/// The output is written to a temporary file next to it which is renamed into place at the end, so `output` may also be one of the inputs,
+/// and a failed merge leaves any existing `output` untouched.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Read(BlobReadError::InPart))` with the position of the input in `inputs` if an input is not a valid blob,
+/// `Err(BlobFileError::Read(BlobReadError::TooLarge))` if the output would be larger than `u32::MAX` bytes,
+/// or `Err(BlobFileError::Io)` if reading or writing fails
+///
+/// # Examples
+/// ```no_run
+/// use byte_array_blob::*;
+/// use std::path::Path;
+///
+/// let inputs = [Path::new("job-0.blob"), Path::new("job-1.blob")];
+/// let stats = merge_blob_files_with_options(&inputs, Path::new("pack.blob"), &MergeOptions::new().with_dedup(true)).unwrap();
+/// println!("{} entries, {} duplicates skipped", stats.entries, stats.duplicates_skipped);
+/// ```
+pub fn merge_blob_files_with_options(inputs: &[&Path], output: &Path, options: &MergeOptions) -> Result<MergeStats, BlobFileError> {
+	let temp_path = unique_temp_path_for(output, ".merge-tmp");
+	match merge_into(inputs, &temp_path, options) {
+		Ok(stats) => {
+			fs::rename(&temp_path, output)?;
+			Ok(stats)
+		},
+		Err(e) => {
+			let _ = fs::remove_file(&temp_path);
+			Err(e)
+		}
+	}
+}
+
+
+fn from_write_error(e: BlobWriteError) -> BlobFileError {
+	match e {
+		BlobWriteError::Io(e) => BlobFileError::Io(e),
+		BlobWriteError::TooLarge => BlobFileError::Read(BlobReadError::TooLarge),
+		other => BlobFileError::Io(io::Error::other(other))
+	}
+}
+
+
+fn merge_into(inputs: &[&Path], temp_path: &Path, options: &MergeOptions) -> Result<MergeStats, BlobFileError> {
+	let mut writer = BlobWriter::new(BufWriter::with_capacity(COPY_CHUNK, File::create_new(temp_path)?));
+	// Only opened when deduplicating, to read back earlier entries whose hash matches
+	let mut written = if options.dedup { Some(File::open(temp_path)?) } else { None };
+	let mut seen: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+	let mut scratch = Vec::new();
+	let mut stats = MergeStats { files: inputs.len(), ..MergeStats::default() };
+
+	for (part, input) in inputs.iter().enumerate() {
+		let mut reader = BlobReader::new(BufReader::with_capacity(COPY_CHUNK, File::open(input)?));
+		loop {
+			let byte_arr = match reader.read_entry() {
+				Ok(Some(byte_arr)) => byte_arr,
+				Ok(None) => break,
+				Err(BlobStreamError::Io(e)) => return Err(e.into()),
+				Err(BlobStreamError::Read(error)) => return Err(BlobReadError::InPart { part, error: Box::new(error) }.into())
+			};
+
+			if let Some(written) = written.as_mut() {
+				let candidates = seen.entry(content_hash(&byte_arr)).or_default();
+				let mut duplicate = false;
+				for &(position, len) in candidates.iter().filter(|&&(_, len)| len == byte_arr.len()) {
+					writer.get_mut().flush()?;
+					written.seek(SeekFrom::Start((position + 4) as u64))?;
+					scratch.resize(len, 0);
+					written.read_exact(&mut scratch)?;
+					if scratch == byte_arr {
+						duplicate = true;
+						break;
+					}
+				}
+				if duplicate {
+					stats.duplicates_skipped += 1;
+					continue;
+				}
+				candidates.push((writer.position(), byte_arr.len()));
+			}
+
+			writer.write_entry(&byte_arr).map_err(from_write_error)?;
+			stats.entries += 1;
+		}
+	}
+
+	stats.bytes_written = writer.position() as u64;
+	let output = writer.finish().map_err(from_write_error)?.into_inner().map_err(|e| e.into_error())?;
+	output.sync_all()?;
+	Ok(stats)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("byte-array-blob-{}-{}", std::process::id(), name));
+		fs::write(&path, contents).unwrap();
+		path
+	}
+
+	/// Returns the temporary files a merge into `output` has left behind
+	fn merge_temp_files(output: &Path) -> Vec<PathBuf> {
+		let prefix = temp_path_for(output, ".merge-tmp").file_name().unwrap().to_os_string();
+		fs::read_dir(output.parent().unwrap()).unwrap()
+			.map(|entry| entry.unwrap().path())
+			.filter(|path| path.file_name().unwrap().as_encoded_bytes().starts_with(prefix.as_encoded_bytes()))
+			.collect()
+	}
+
+	#[test]
+	fn convert_in_place_same_width() {
+		let entries = sample_entries();
+		let path = temp_file("convert-in-place", &entries_to_blob(&entries));
+
+		let report = convert_blob_in_file(&path, BlobFormat::NativeEndian, BlobFormat::LittleEndian).unwrap();
+		assert_eq!(report, ConversionReport { entries: 9, bytes_written: 36, in_place: true });
+
+		let converted = fs::read(&path).unwrap();
+		assert_eq!(blob_to_byte_arrays_with_format(&converted, BlobFormat::LittleEndian).unwrap(), as_slices(&entries));
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn convert_by_rewrite_wider() {
+		let entries = sample_entries();
+		let path = temp_file("convert-rewrite", &entries_to_blob(&entries));
+
+		let report = convert_blob_in_file(&path, BlobFormat::NativeEndian, BlobFormat::LittleEndian64).unwrap();
+		let converted = fs::read(&path).unwrap();
+		assert_eq!(report, ConversionReport { entries: 9, bytes_written: converted.len() as u64, in_place: false });
+		assert_eq!(blob_to_byte_arrays_with_format(&converted, BlobFormat::LittleEndian64).unwrap(), as_slices(&entries));
+
+		// And back again
+		convert_blob_in_file(&path, BlobFormat::LittleEndian64, BlobFormat::NativeEndian).unwrap();
+		assert_eq!(blob_to_byte_arrays(&fs::read(&path).unwrap()).unwrap(), as_slices(&entries));
+		assert!(!temp_path_for(&path, ".convert-tmp").exists());
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn invalid_file_is_untouched() {
+		let mut blob = entries_to_blob(&sample_entries());
+		blob.extend([255u8; 4]);
+
+		for (name, to) in [("convert-invalid-in-place", BlobFormat::LittleEndian), ("convert-invalid-rewrite", BlobFormat::LittleEndian64)] {
+			let path = temp_file(name, &blob);
+			assert!(matches!(convert_blob_in_file(&path, BlobFormat::NativeEndian, to), Err(BlobFileError::Read(BlobReadError::InvalidEncodedIndex(_)))));
+			assert_eq!(fs::read(&path).unwrap(), blob);
+			assert!(!temp_path_for(&path, ".convert-tmp").exists());
+			fs::remove_file(path).unwrap();
+		}
+	}
+
+	/// A file of `len` bytes that is all zeros apart from a few `chunks`, so blobs far larger than memory can be walked
+	struct SparseFile {
+		len: u64,
+		chunks: Vec<(u64, Vec<u8>)>,
+		position: u64
+	}
+
+	impl Read for SparseFile {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let n = buf.len().min(self.len.saturating_sub(self.position) as usize);
+			buf[..n].fill(0);
+			for (start, data) in &self.chunks {
+				for (i, byte) in buf[..n].iter_mut().enumerate() {
+					if let Some(offset) = (self.position + i as u64).checked_sub(*start).filter(|&offset| offset < data.len() as u64) {
+						*byte = data[offset as usize];
+					}
+				}
+			}
+			self.position += n as u64;
+			Ok(n)
+		}
+	}
+
+	impl Seek for SparseFile {
+		fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+			self.position = match pos {
+				SeekFrom::Start(position) => position,
+				SeekFrom::End(offset) => self.len.checked_add_signed(offset).unwrap(),
+				SeekFrom::Current(offset) => self.position.checked_add_signed(offset).unwrap()
+			};
+			Ok(self.position)
+		}
+	}
+
+	#[test]
+	fn walk_past_four_gigabytes() {
+		const GIB: u64 = 1 << 30;
+		let headers = [(0, 5 * GIB), (5 * GIB, 5 * GIB + 8 + 3), (5 * GIB + 11, 6 * GIB)];
+		let mut chunks: Vec<(u64, Vec<u8>)> = headers.iter().map(|&(position, idx_end)| (position, u64::to_le_bytes(idx_end).to_vec())).collect();
+		chunks.push((5 * GIB + 8, b"abc".to_vec()));
+		let mut file = SparseFile { len: 6 * GIB, chunks, position: 0 };
+
+		let ranges = entry_ranges(&mut file, BlobFormat::LittleEndian64).unwrap();
+		assert_eq!(ranges, [8..5 * GIB, 5 * GIB + 8..5 * GIB + 11, 5 * GIB + 19..6 * GIB]);
+		assert_eq!(read_entry_at(&mut file, BlobFormat::LittleEndian64, 1).unwrap(), Some(b"abc".to_vec()));
+		assert_eq!(read_entry_at(&mut file, BlobFormat::LittleEndian64, 3).unwrap(), None);
+
+		// A 4 byte layout can't describe a file this large
+		assert!(matches!(entry_ranges(&mut file, BlobFormat::NativeEndian), Err(BlobFileError::Read(BlobReadError::TooLarge))));
+
+		// An index pointing past the end is caught without reading the payloads before it
+		file.chunks[2].1 = u64::to_le_bytes(7 * GIB).to_vec();
+		match entry_ranges(&mut file, BlobFormat::LittleEndian64) {
+			Err(BlobFileError::Read(error)) => assert_eq!(error.kind(), BlobErrorKind::InvalidIndex),
+			other => panic!("Expected an invalid index, got {:?}", other)
+		}
+		assert_eq!(read_entry_at(&mut file, BlobFormat::LittleEndian64, 1).unwrap(), Some(b"abc".to_vec()));
+	}
+
+	#[test]
+	fn file_entry_ranges() {
+		let entries = sample_entries();
+		let blob = entries_to_blob(&entries);
+		let path = temp_file("entry-ranges", &blob);
+
+		let view = BlobView::new(&blob).unwrap();
+		let expected: Vec<Range<u64>> = (0..view.len()).map(|index| view.range(index).unwrap()).map(|range| range.start as u64..range.end as u64).collect();
+		assert_eq!(blob_file_entry_ranges(&path).unwrap(), expected);
+		for (index, entry) in entries.iter().enumerate() {
+			assert_eq!(read_file_entry(&path, index).unwrap().as_ref(), Some(entry));
+		}
+		assert_eq!(read_file_entry(&path, entries.len()).unwrap(), None);
+
+		// Cut part way through the last entry that isn't empty
+		fs::write(&path, &blob[..blob.len() - 5]).unwrap();
+		assert!(matches!(blob_file_entry_ranges(&path), Err(BlobFileError::Read(BlobReadError::InvalidEncodedIndex(_)))));
+		assert_eq!(read_file_entry(&path, 1).unwrap(), Some(entries[1].clone()));
+
+		// A bad first index gives the same error as the slice decoder, whether it points past the end or backwards
+		for not_a_blob in [&[255u8; 4][..], &2u32.to_ne_bytes(), &[9u32.to_ne_bytes().as_slice(), &[1]].concat()] {
+			let expected = blob_to_byte_arrays(not_a_blob).unwrap_err();
+			assert!(matches!(expected, BlobReadError::NotABlob { .. }));
+			fs::write(&path, not_a_blob).unwrap();
+			assert!(matches!(blob_file_entry_ranges(&path), Err(BlobFileError::Read(e)) if e == expected));
+			assert!(matches!(read_file_entry(&path, 0), Err(BlobFileError::Read(e)) if e == expected));
+			assert!(matches!(truncate_blob_file(&path, 1), Err(BlobFileError::Read(e)) if e == expected));
+		}
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn truncate_file() {
+		let entries = sample_entries();
+		let blob = entries_to_blob(&entries);
+		let mut torn = blob.clone();
+		torn.extend([100u8, 0, 0, 0, 7]);
+		let path = temp_file("truncate", &torn);
+
+		assert!(matches!(truncate_blob_file(&path, 10), Err(BlobFileError::Read(BlobReadError::InvalidEncodedIndex(100)))));
+		assert_eq!(fs::read(&path).unwrap(), torn);
+
+		assert_eq!(truncate_blob_file(&path, 9).unwrap(), blob.len() as u64);
+		assert_eq!(fs::read(&path).unwrap(), blob);
+		assert_eq!(truncate_blob_file(&path, 50).unwrap(), blob.len() as u64);
+
+		assert_eq!(truncate_blob_file(&path, 3).unwrap(), 4 + 9 + 10_004);
+		assert_eq!(blob_to_byte_vecs(&fs::read(&path).unwrap()).unwrap(), entries[..3]);
+		assert_eq!(truncate_blob_file(&path, 0).unwrap(), 0);
+		assert_eq!(fs::read(&path).unwrap(), Vec::<u8>::new());
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn merge_three_files() {
+		let inputs = [
+			temp_file("merge-a", &byte_arrays_to_blob(&[b"shared", b"a", &[]])),
+			temp_file("merge-b", &[]),
+			temp_file("merge-c", &byte_arrays_to_blob(&[&[7u8; 100_000], b"shared", &[], b"c"]))
+		];
+		let input_paths: Vec<&Path> = inputs.iter().map(PathBuf::as_path).collect();
+		let output = std::env::temp_dir().join(format!("byte-array-blob-{}-merge-out", std::process::id()));
+
+		// Every merge writes to its own temporary file, so merges to the same output can't clobber each other's
+		assert_ne!(unique_temp_path_for(&output, ".merge-tmp"), unique_temp_path_for(&output, ".merge-tmp"));
+
+		let stats = merge_blob_files(&input_paths, &output).unwrap();
+		let merged = fs::read(&output).unwrap();
+		assert_eq!(blob_to_byte_arrays(&merged).unwrap(), [&b"shared"[..], b"a", &[], &[7u8; 100_000], b"shared", &[], b"c"]);
+		assert_eq!(stats, MergeStats { files: 3, entries: 7, bytes_written: merged.len() as u64, duplicates_skipped: 0 });
+
+		let stats = merge_blob_files_with_options(&input_paths, &output, &MergeOptions::new().with_dedup(true)).unwrap();
+		let merged = fs::read(&output).unwrap();
+		assert_eq!(blob_to_byte_arrays(&merged).unwrap(), [&b"shared"[..], b"a", &[], &[7u8; 100_000], b"c"]);
+		assert_eq!(stats, MergeStats { files: 3, entries: 5, bytes_written: merged.len() as u64, duplicates_skipped: 2 });
+		assert!(merge_temp_files(&output).is_empty());
+
+		// A bad input names its position and leaves the previous output alone
+		fs::write(&inputs[1], [255u8; 4]).unwrap();
+		match merge_blob_files(&input_paths, &output) {
+			Err(BlobFileError::Read(error)) => assert_eq!((error.part(), error.kind()), (Some(1), BlobErrorKind::UnknownFormat)),
+			other => panic!("Expected a read error in part 1, got {:?}", other)
+		}
+		assert_eq!(fs::read(&output).unwrap(), merged);
+		assert!(merge_temp_files(&output).is_empty());
+
+		for path in inputs.iter().chain([&output]) {
+			fs::remove_file(path).unwrap();
+		}
+	}
+}
@@ -0,0 +1,187 @@
+//! Per-entry filters run while encoding or decoding, for transforms such as de-obfuscation that would otherwise need a second pass
+use crate::*;
+
+
+/// Errors that can occur when decoding a blob through a filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilteredDecodeError<E> {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// The filter failed on entry `entry`
+	Filter {
+		entry: usize,
+		error: E
+	}
+}
+
+
+impl<E: std::error::Error + 'static> std::error::Error for FilteredDecodeError<E> {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			FilteredDecodeError::Read(e) => Some(e),
+			FilteredDecodeError::Filter { error, .. } => Some(error)
+		}
+	}
+}
+
+impl<E: fmt::Debug> fmt::Display for FilteredDecodeError<E> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl<E> From<BlobReadError> for FilteredDecodeError<E> {
+	fn from(e: BlobReadError) -> Self {
+		FilteredDecodeError::Read(e)
+	}
+}
+
+
+/// Errors that can occur when encoding a blob through a filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FilteredEncodeError<E> {
+	/// The filtered entries would make a blob larger than `u32::MAX` bytes
+	TooLarge,
+	/// The filter failed on entry `entry`
+	Filter {
+		entry: usize,
+		error: E
+	}
+}
+
+
+impl<E: std::error::Error + 'static> std::error::Error for FilteredEncodeError<E> {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			FilteredEncodeError::TooLarge => None,
+			FilteredEncodeError::Filter { error, .. } => Some(error)
+		}
+	}
+}
+
+impl<E: fmt::Debug> fmt::Display for FilteredEncodeError<E> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// Converts a blob u8 slice to a Vec of owned u8 Vecs, running `filter` on each Vec before it is added
+///
+/// Each entry is copied once into its own Vec, which `filter` then changes in place, so there is no second copy or second pass.
+/// `filter` is given the index of the entry and may change its length.
+///
+/// # Errors
+///
+/// Returns `Err(FilteredDecodeError::Filter)` with the index of the entry if `filter` fails, which stops the decode.
+/// Otherwise the same as `blob_to_byte_vecs`, for the entries up to the failure
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[0x0F ^ 1, 0x0F ^ 2]]);
+/// let byte_vecs = blob_to_byte_vecs_filtered(&blob, |_, entry| {
+/// 	entry.iter_mut().for_each(|byte| *byte ^= 0x0F);
+/// 	Ok::<_, ()>(())
+/// });
+/// assert_eq!(byte_vecs.unwrap(), vec![vec![1, 2]]);
+/// ```
+pub fn blob_to_byte_vecs_filtered<E>(blob: &[u8], mut filter: impl FnMut(usize, &mut Vec<u8>) -> Result<(), E>) -> Result<Vec<Vec<u8>>, FilteredDecodeError<E>> {
+	let mut byte_vecs = Vec::new();
+	for (entry, byte_arr) in blob_iter(blob).enumerate() {
+		let mut byte_vec = byte_arr?.to_vec();
+		filter(entry, &mut byte_vec).map_err(|error| FilteredDecodeError::Filter { entry, error })?;
+		byte_vecs.push(byte_vec);
+	}
+	Ok(byte_vecs)
+}
+
+
+/// Converts a slice of u8 slices to a blob, running `filter` on a copy of each slice before it is written
+///
+/// One scratch Vec is reused for every slice, so only slices that grow past the largest one so far cause an allocation.
+///
+/// # Errors
+///
+/// Returns `Err(FilteredEncodeError::Filter)` with the index of the slice if `filter` fails, or `Err(FilteredEncodeError::TooLarge)`
+/// if the filtered slices would make a blob larger than `u32::MAX` bytes
+pub fn byte_arrays_to_blob_filtered<E>(bytes_2d: &[&[u8]], mut filter: impl FnMut(usize, &mut Vec<u8>) -> Result<(), E>) -> Result<Vec<u8>, FilteredEncodeError<E>> {
+	let mut blob = Vec::with_capacity(encoded_len(bytes_2d));
+	let mut scratch = Vec::new();
+	for (entry, byte_arr) in bytes_2d.iter().enumerate() {
+		scratch.clear();
+		scratch.extend_from_slice(byte_arr);
+		filter(entry, &mut scratch).map_err(|error| FilteredEncodeError::Filter { entry, error })?;
+
+		let idx_end = u32::try_from(blob.len() + 4 + scratch.len()).map_err(|_| FilteredEncodeError::TooLarge)?;
+		blob.extend(idx_end.to_ne_bytes());
+		blob.extend_from_slice(&scratch);
+	}
+	Ok(blob)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[derive(Debug, PartialEq, Eq)]
+	struct BadChecksum;
+
+	/// XORs every byte with the key, and adds or checks a trailing checksum byte so a wrong key is noticed
+	fn xor_filter(key: &[u8], encode: bool) -> impl FnMut(usize, &mut Vec<u8>) -> Result<(), BadChecksum> + '_ {
+		move |entry, byte_vec| {
+			if !encode {
+				let checksum = byte_vec.pop().ok_or(BadChecksum)?;
+				byte_vec.iter_mut().enumerate().for_each(|(i, byte)| *byte ^= key[(entry + i) % key.len()]);
+				if byte_vec.iter().fold(entry as u8, |sum, &byte| sum.wrapping_add(byte)) != checksum {
+					return Err(BadChecksum);
+				}
+				return Ok(());
+			}
+			let checksum = byte_vec.iter().fold(entry as u8, |sum, &byte| sum.wrapping_add(byte));
+			byte_vec.iter_mut().enumerate().for_each(|(i, byte)| *byte ^= key[(entry + i) % key.len()]);
+			byte_vec.push(checksum);
+			Ok(())
+		}
+	}
+
+	#[test]
+	fn xor_round_trip() {
+		let slices: [&[u8]; 4] = [b"first entry", &[], &[0; 40], b"x"];
+		let key = b"pack key";
+
+		let blob = byte_arrays_to_blob_filtered(&slices, xor_filter(key, true)).unwrap();
+		assert_ne!(blob_to_byte_arrays(&blob).unwrap()[0], slices[0]);
+		assert_eq!(blob_to_byte_vecs_filtered(&blob, xor_filter(key, false)).unwrap(), slices);
+
+		assert_eq!(blob_to_byte_vecs_filtered(&blob, xor_filter(b"wrong key", false)), Err(FilteredDecodeError::Filter { entry: 0, error: BadChecksum }));
+		let mut ragged = byte_arrays_to_blob_filtered(&slices, xor_filter(key, true)).unwrap();
+		ragged.truncate(ragged.len() - 1);
+		assert!(matches!(blob_to_byte_vecs_filtered(&ragged, xor_filter(key, false)), Err(FilteredDecodeError::Read(_))));
+	}
+
+	#[test]
+	fn failing_filters() {
+		let slices: [&[u8]; 3] = [b"a", b"bad", b"c"];
+		let only_short = |_: usize, byte_vec: &mut Vec<u8>| if byte_vec.len() < 2 { Ok(()) } else { Err(byte_vec.len()) };
+		assert_eq!(byte_arrays_to_blob_filtered(&slices, only_short), Err(FilteredEncodeError::Filter { entry: 1, error: 3 }));
+
+		let blob = byte_arrays_to_blob(&slices);
+		let mut seen = Vec::new();
+		let result = blob_to_byte_vecs_filtered(&blob, |entry, byte_vec| {
+			seen.push(entry);
+			only_short(entry, byte_vec)
+		});
+		assert_eq!(result, Err(FilteredDecodeError::Filter { entry: 1, error: 3 }));
+		assert_eq!(seen, vec![0, 1]);
+
+		assert_eq!(byte_arrays_to_blob_filtered(&[], only_short).unwrap(), Vec::<u8>::new());
+		assert_eq!(byte_arrays_to_blob_filtered(&slices, |_, _| Ok::<_, ()>(())).unwrap(), blob);
+	}
+}
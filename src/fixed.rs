@@ -0,0 +1,102 @@
+//! Encoding and decoding into fixed capacity storage, without allocating
+use crate::*;
+
+
+/// Converts a blob u8 slice to an array of at most `N` u8 slices, returning the array and how many of its slots are filled
+///
+/// Nothing is allocated, so this suits targets without an allocator. Unused slots hold empty slices.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::TooManyEntries(N))` if the blob holds more than `N` slices, otherwise the same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1], &[2, 2]]);
+/// let (slices, len) = blob_to_fixed_arrays::<4>(&blob).unwrap();
+/// assert_eq!(&slices[..len], [&[1][..], &[2, 2]]);
+///
+/// assert_eq!(blob_to_fixed_arrays::<1>(&blob), Err(BlobReadError::TooManyEntries(1)));
+/// ```
+pub fn blob_to_fixed_arrays<const N: usize>(blob: &[u8]) -> Result<([&[u8]; N], usize), BlobReadError> {
+	let mut slices = [&blob[..0]; N];
+	let mut len = 0;
+	for byte_arr in blob_iter(blob) {
+		let byte_arr = byte_arr?;
+		match slices.get_mut(len) {
+			Some(slot) => *slot = byte_arr,
+			None => return Err(BlobReadError::TooManyEntries(N))
+		}
+		len += 1;
+	}
+	Ok((slices, len))
+}
+
+
+/// Encodes slices into the start of `buffer`, returning the length of the blob
+///
+/// Nothing is allocated. Nothing is written unless the whole blob fits, so a too small buffer is left untouched.
+///
+/// # Errors
+///
+/// Returns `Err(BlobWriteError::BufferTooSmall)` if the blob is longer than `buffer`, or `Err(BlobWriteError::TooLarge)` if it would be larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut buffer = [0u8; 16];
+/// let len = byte_arrays_to_buffer(&[&[1, 2], &[3]], &mut buffer).unwrap();
+/// assert_eq!(&buffer[..len], byte_arrays_to_blob(&[&[1, 2], &[3]]));
+///
+/// assert!(byte_arrays_to_buffer(&[&[0; 13]], &mut buffer).is_err());
+/// ```
+pub fn byte_arrays_to_buffer(bytes_2d: &[&[u8]], buffer: &mut [u8]) -> Result<usize, BlobWriteError> {
+	let needed = encoded_len(bytes_2d);
+	if needed > MAX_U32_LEN {
+		return Err(BlobWriteError::TooLarge);
+	}
+	if needed > buffer.len() {
+		return Err(BlobWriteError::BufferTooSmall { needed, capacity: buffer.len() });
+	}
+
+	let mut position = 0;
+	for byte_arr in bytes_2d {
+		let idx_end = position + 4 + byte_arr.len();
+		// `needed` fits in u32, and so does every index before it
+		buffer[position..position+4].copy_from_slice(&(idx_end as u32).to_ne_bytes());
+		buffer[position+4..idx_end].copy_from_slice(byte_arr);
+		position = idx_end;
+	}
+	Ok(position)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn exact_and_over_capacity() {
+		let slices: [&[u8]; 3] = [&[1u8], &[], &[2u8, 3u8]];
+		let blob = byte_arrays_to_blob(&slices);
+
+		let (decoded, len) = blob_to_fixed_arrays::<3>(&blob).unwrap();
+		assert_eq!((decoded, len), (slices, 3));
+		assert_eq!(blob_to_fixed_arrays::<2>(&blob), Err(BlobReadError::TooManyEntries(2)));
+		assert_eq!(blob_to_fixed_arrays::<0>(&[]), Ok(([], 0)));
+		assert!(matches!(blob_to_fixed_arrays::<8>(&blob[..3]), Err(BlobReadError::TruncatedIndex(0))));
+
+		let mut exact = [0u8; 15];
+		assert_eq!(byte_arrays_to_buffer(&slices, &mut exact).unwrap(), 15);
+		assert_eq!(exact[..], blob[..]);
+
+		let mut small = [0xFFu8; 14];
+		assert!(matches!(byte_arrays_to_buffer(&slices, &mut small), Err(BlobWriteError::BufferTooSmall { needed: 15, capacity: 14 })));
+		assert_eq!(small, [0xFFu8; 14]);
+	}
+}
@@ -0,0 +1,50 @@
+//! A canonical set of awkward entries and their exact expected encodings, for tests that check encoded bytes never change
+//!
+//! The expected blobs are built here byte by byte rather than with the crate's encoders, so they can catch a change to an encoder.
+//! The same bytes are committed under `tests/data/`, so a change to the format also shows up as a failing test that needs the files updating on purpose.
+use crate::*;
+
+
+/// Returns the canonical entries: empty, 1 byte, 255 bytes, 4 KB, and entries made of high bytes and bytes that look like indexes
+pub fn fixture_entries() -> Vec<Vec<u8>> {
+	vec![
+		Vec::new(),
+		vec![0x42],
+		(0..255).map(|i| i as u8).collect(),
+		(0..4096).map(|i| (i * 7 % 256) as u8).collect(),
+		(0x80..=0xFF).chain((0x80..=0xFF).rev()).collect(),
+		vec![0xFF, 0xFF, 0xFF, 0xFF, 0x04, 0x00, 0x00, 0x00],
+		Vec::new()
+	]
+}
+
+
+/// Returns the expected encoding of `fixture_entries` in `format`
+///
+/// `NativeEndian` gives different bytes on machines with different byte orders.
+pub fn fixture_blob(format: BlobFormat) -> Vec<u8> {
+	let mut blob = Vec::new();
+	for entry in fixture_entries() {
+		let idx_end = blob.len() + format.header_len() + entry.len();
+		match format {
+			BlobFormat::NativeEndian => blob.extend((idx_end as u32).to_ne_bytes()),
+			BlobFormat::LittleEndian => blob.extend((idx_end as u32).to_le_bytes()),
+			BlobFormat::LittleEndian64 => blob.extend((idx_end as u64).to_le_bytes()),
+			BlobFormat::LengthPrefixed => blob.extend((entry.len() as u32).to_le_bytes())
+		}
+		blob.extend(entry);
+	}
+	blob
+}
+
+
+/// Returns the name of the file under `tests/data/` holding the expected encoding for `format`, or `None` for `NativeEndian`,
+/// which matches `LittleEndian` or a big-endian encoding depending on the machine
+pub fn fixture_file_name(format: BlobFormat) -> Option<&'static str> {
+	match format {
+		BlobFormat::NativeEndian => None,
+		BlobFormat::LittleEndian => Some("little-endian.blob"),
+		BlobFormat::LittleEndian64 => Some("little-endian-64.blob"),
+		BlobFormat::LengthPrefixed => Some("length-prefixed.blob")
+	}
+}
@@ -0,0 +1,255 @@
+//! Flagged blobs, where every entry carries a flags byte marking it as sensitive, so it can be redacted before the blob is shared
+//!
+//! # Layout
+//!
+//! Slice 0 is the 4 magic bytes `BABF`. Every later slice is one entry: a flags byte followed by the payload.
+//! Bit 0 of the flags marks the entry as sensitive. Bit 1 marks a sensitive entry whose payload has been removed by redaction, which leaves only the flags byte.
+//! A flagged blob is an ordinary blob, so `blob_to_byte_arrays` reads it with the magic and the flags bytes included.
+use crate::*;
+use std::borrow::Cow;
+
+
+const FLAG_MAGIC: [u8; 4] = *b"BABF";
+const SENSITIVE: u8 = 1;
+const REDACTED: u8 = 2;
+
+
+/// Appends the index and magic slice that start a flagged blob to `bytes`
+pub(crate) fn write_flagged_header(bytes: &mut Vec<u8>) {
+	bytes.extend(((bytes.len() + 4 + FLAG_MAGIC.len()) as u32).to_ne_bytes());
+	bytes.extend(FLAG_MAGIC);
+}
+
+
+/// Returns whether a blob starts with the flagged blob magic
+pub(crate) fn is_flagged(blob: &[u8]) -> bool {
+	matches!(blob_iter(blob).next(), Some(Ok(magic)) if magic == FLAG_MAGIC)
+}
+
+
+/// Returns the flags byte for an entry
+pub(crate) fn entry_flags(sensitive: bool) -> u8 {
+	match sensitive {
+		true => SENSITIVE,
+		false => 0
+	}
+}
+
+
+/// One entry of a flagged blob, read by `blob_to_flagged_entries`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlaggedEntry<'a> {
+	/// The payload, which is empty if the entry was redacted
+	pub data: &'a [u8],
+	/// Whether the entry was pushed with `BlobBuilder::push_sensitive`
+	pub sensitive: bool,
+	/// Whether the payload of this sensitive entry was removed by `blob_redact`
+	pub redacted: bool
+}
+
+
+/// Returns the entries of a flagged blob, or `None` if its first slice isn't the flagged blob magic
+fn flagged_entries(blob: &[u8]) -> Option<impl Iterator<Item = Result<FlaggedEntry<'_>, BlobReadError>>> {
+	let mut iter = blob_iter(blob);
+	match iter.next() {
+		Some(Ok(magic)) if magic == FLAG_MAGIC => {},
+		_ => return None
+	}
+	Some(iter.enumerate().map(|(i, byte_arr)| {
+		let (&flags, data) = byte_arr?.split_first().ok_or(BlobReadError::InvalidMetadata(i))?;
+		let redacted = flags & REDACTED != 0;
+		// Redaction only applies to sensitive entries, and leaves nothing after the flags byte
+		if flags & !(SENSITIVE | REDACTED) != 0 || (redacted && (flags & SENSITIVE == 0 || !data.is_empty())) {
+			return Err(BlobReadError::InvalidMetadata(i));
+		}
+		Ok(FlaggedEntry { data, sensitive: flags & SENSITIVE != 0, redacted })
+	}))
+}
+
+
+/// Converts a flagged blob to a Vec of its entries with their flags, including the payloads of sensitive entries that haven't been redacted
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::NotFlagged)` if the blob isn't a flagged blob, `Err(BlobReadError::InvalidMetadata)` with the entry index if an entry
+/// has no flags byte or unknown flags, otherwise the same as `blob_to_byte_arrays`
+pub fn blob_to_flagged_entries(blob: &[u8]) -> Result<Vec<FlaggedEntry<'_>>, BlobReadError> {
+	match flagged_entries(blob) {
+		Some(entries) => entries.collect(),
+		None => Err(blob_iter(blob).next().and_then(Result::err).unwrap_or(BlobReadError::NotFlagged))
+	}
+}
+
+
+/// What redacting a blob gave back
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redaction<T> {
+	/// The blob was a flagged blob, and every sensitive payload has been removed
+	Redacted(T),
+	/// The blob isn't a flagged blob, so it has no flags to redact by and is passed through unchanged.
+	/// It should only be shared if none of it is sensitive
+	NothingToRedact(T)
+}
+
+
+impl<T> Redaction<T> {
+	/// Returns the redacted or passed through result
+	pub fn into_inner(self) -> T {
+		match self {
+			Redaction::Redacted(inner) | Redaction::NothingToRedact(inner) => inner
+		}
+	}
+
+	/// Returns whether the blob wasn't a flagged blob, so nothing was removed from it
+	pub fn is_nothing_to_redact(&self) -> bool {
+		matches!(self, Redaction::NothingToRedact(_))
+	}
+}
+
+
+/// Returns a copy of a flagged blob with the payload of every sensitive entry removed, keeping every entry at the same index
+///
+/// The result holds no byte of any sensitive payload and is itself a flagged blob, so redacting it again changes nothing.
+/// A blob that isn't a flagged blob is checked and passed through as `Redaction::NothingToRedact`.
+///
+/// # Errors
+///
+/// The same as `blob_to_flagged_entries` for a flagged blob, or `blob_to_byte_arrays` for any other
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+/// use std::borrow::Cow;
+///
+/// let mut builder = BlobBuilder::flagged();
+/// builder.push(b"uptime 12h").unwrap();
+/// builder.push_sensitive(b"api key hunter2").unwrap();
+/// builder.push(b"load 0.3").unwrap();
+/// let blob = builder.finish().unwrap();
+///
+/// let redacted = blob_redact(&blob).unwrap().into_inner();
+/// assert_eq!(blob_to_byte_arrays_redacted(&redacted).unwrap().into_inner(), [&b"uptime 12h"[..], b"", b"load 0.3"]);
+///
+/// let plain = byte_arrays_to_blob(&[b"plain"]);
+/// assert_eq!(blob_redact(&plain), Ok(Redaction::NothingToRedact(Cow::Borrowed(&plain[..]))));
+/// ```
+pub fn blob_redact(blob: &[u8]) -> Result<Redaction<Cow<'_, [u8]>>, BlobReadError> {
+	let Some(entries) = flagged_entries(blob) else {
+		count_arrays(blob)?;
+		return Ok(Redaction::NothingToRedact(Cow::Borrowed(blob)));
+	};
+	let mut builder = BlobBuilder::flagged();
+	for entry in entries {
+		let entry = entry?;
+		// The result is never larger than the blob, so it always fits
+		let pushed = match entry.sensitive {
+			true => builder.push_flags(SENSITIVE | REDACTED, &[]),
+			false => builder.push(entry.data)
+		};
+		pushed.map_err(|_| BlobReadError::TooLarge)?;
+	}
+	builder.finish().map(|redacted| Redaction::Redacted(Cow::Owned(redacted))).map_err(|_| BlobReadError::TooLarge)
+}
+
+
+/// Converts a flagged blob to a Vec of its payloads, with the payload of every sensitive entry replaced by an empty slice
+///
+/// This gives the same entries as decoding the result of `blob_redact`, without making the redacted blob.
+/// A blob that isn't a flagged blob is decoded with `blob_to_byte_arrays` and returned as `Redaction::NothingToRedact`.
+///
+/// # Errors
+///
+/// The same as `blob_redact`
+pub fn blob_to_byte_arrays_redacted(blob: &[u8]) -> Result<Redaction<Vec<&[u8]>>, BlobReadError> {
+	let Some(entries) = flagged_entries(blob) else {
+		return blob_to_byte_arrays(blob).map(Redaction::NothingToRedact);
+	};
+	entries.map(|entry| entry.map(|entry| match entry.sensitive {
+		true => &[][..],
+		false => entry.data
+	})).collect::<Result<_, _>>().map(Redaction::Redacted)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SECRET: &[u8] = b"s3cr3t-t0k3n";
+
+	fn sample() -> Vec<u8> {
+		let mut builder = BlobBuilder::flagged();
+		builder.push(b"public one").unwrap();
+		builder.push_sensitive(SECRET).unwrap();
+		builder.push(&[]).unwrap();
+		builder.push_sensitive(&[]).unwrap();
+		builder.push(b"public two").unwrap();
+		assert_eq!(builder.len(), 5);
+		builder.finish().unwrap()
+	}
+
+	fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+		haystack.windows(needle.len()).any(|window| window == needle)
+	}
+
+	#[test]
+	fn redaction_removes_sensitive_payloads() {
+		let blob = sample();
+		assert!(contains(&blob, SECRET));
+		let entries = blob_to_flagged_entries(&blob).unwrap();
+		assert_eq!(entries[1], FlaggedEntry { data: SECRET, sensitive: true, redacted: false });
+
+		let redacted = blob_redact(&blob).unwrap().into_inner();
+		assert!(!contains(&redacted, SECRET));
+		assert!(!contains(&redacted, b"s3cr"));
+
+		// Every entry keeps its index, and only the sensitive ones change
+		let before = blob_to_byte_arrays_redacted(&blob).unwrap().into_inner();
+		let after = blob_to_byte_arrays_redacted(&redacted).unwrap().into_inner();
+		assert_eq!(before, after);
+		assert_eq!(after, [&b"public one"[..], b"", b"", b"", b"public two"]);
+		let flagged = blob_to_flagged_entries(&redacted).unwrap();
+		assert_eq!(flagged.len(), entries.len());
+		for (old, new) in entries.iter().zip(&flagged) {
+			assert_eq!(new.sensitive, old.sensitive);
+			assert_eq!(new.redacted, old.sensitive);
+			if !old.sensitive {
+				assert_eq!(new.data, old.data);
+			}
+		}
+		assert_eq!(blob_redact(&redacted).unwrap(), Redaction::Redacted(Cow::Borrowed(&redacted[..])));
+	}
+
+	#[test]
+	fn unflagged_blobs_pass_through() {
+		let plain = byte_arrays_to_blob(&[b"BABX", SECRET]);
+		let passed = blob_redact(&plain).unwrap();
+		assert!(passed.is_nothing_to_redact());
+		assert!(matches!(passed.into_inner(), Cow::Borrowed(unchanged) if unchanged == plain));
+		assert_eq!(blob_to_byte_arrays_redacted(&plain), Ok(Redaction::NothingToRedact(vec![&b"BABX"[..], SECRET])));
+		assert_eq!(blob_redact(&[]), Ok(Redaction::NothingToRedact(Cow::Borrowed(&[][..]))));
+		assert_eq!(blob_to_flagged_entries(&plain), Err(BlobReadError::NotFlagged));
+		assert_eq!(BlobReadError::NotFlagged.kind(), BlobErrorKind::SchemaMismatch);
+
+		// Passing through still checks the blob
+		assert!(matches!(blob_redact(&[255u8; 4]), Err(BlobReadError::NotABlob { .. })));
+		assert!(matches!(blob_to_flagged_entries(&[255u8; 4]), Err(BlobReadError::NotABlob { .. })));
+		assert_eq!(blob_redact(&plain[..plain.len() - 1]), Err(BlobReadError::InvalidEncodedIndex(plain.len())));
+		assert!(blob_to_byte_arrays_redacted(&plain[..plain.len() - 1]).is_err());
+
+		// An empty flagged blob is just the magic
+		let empty = BlobBuilder::flagged().finish().unwrap();
+		assert_eq!(blob_to_byte_arrays(&empty).unwrap(), [b"BABF"]);
+		assert_eq!(blob_redact(&empty).unwrap().into_inner(), empty);
+		assert!(!blob_redact(&empty).unwrap().is_nothing_to_redact());
+	}
+
+	#[test]
+	fn malformed_flags() {
+		for entry in [&[][..], &[4u8], &[REDACTED], &[SENSITIVE | REDACTED, 0]] {
+			let blob = byte_arrays_to_blob(&[&FLAG_MAGIC, &[0u8, 1], entry]);
+			assert_eq!(blob_to_flagged_entries(&blob), Err(BlobReadError::InvalidMetadata(1)), "{:?}", entry);
+		}
+	}
+}
@@ -0,0 +1,335 @@
+//! Alternative layouts for the index stored before each slice
+use crate::*;
+use std::borrow::Cow;
+
+
+/// The largest blob 4 byte indexes can describe, which is `u32::MAX` bytes or the whole address space on a target where usize is narrower than u32
+pub(crate) const MAX_U32_LEN: usize = match checked_index(u32::MAX, usize::MAX as u64) {
+	Some(len) => len,
+	None => usize::MAX
+};
+
+
+/// Converts a 4 byte index to a position, or returns `None` if it is larger than `max_usize`.
+/// `max_usize` is `usize::MAX` outside of tests, which inject smaller values to check how a 16-bit target behaves
+pub(crate) const fn checked_index(encoded: u32, max_usize: u64) -> Option<usize> {
+	match encoded as u64 <= max_usize {
+		// The value fits in usize, so the cast is lossless
+		true => Some(encoded as usize),
+		false => None
+	}
+}
+
+
+/// Converts a 4 byte index read from a blob to a position
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::IndexUnrepresentable)` if the index doesn't fit in usize, which can only happen on targets where usize is narrower than u32
+pub(crate) fn index_to_usize(encoded: u32) -> Result<usize, BlobReadError> {
+	checked_index(encoded, usize::MAX as u64).ok_or(BlobReadError::IndexUnrepresentable { encoded })
+}
+
+
+/// The layout of the index stored before each slice in a blob
+///
+/// Most layouts store the position where their slice ends, measured from the start of the blob, and differ only in width and byte order.
+/// `LengthPrefixed` stores the length of each slice instead, so blobs in that layout can be joined just by concatenating them.
+/// `byte_arrays_to_blob` and `blob_to_byte_arrays` use `NativeEndian`, which can't be read correctly on a machine with the opposite byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlobFormat {
+	/// 4 byte indexes in the byte order of the machine. This is the original layout
+	NativeEndian,
+	/// 4 byte little-endian indexes
+	LittleEndian,
+	/// 8 byte little-endian indexes, for blobs larger than `u32::MAX` bytes
+	LittleEndian64,
+	/// 4 byte little-endian lengths of each slice rather than where it ends. This is the common "u32 length then payload" framing, see `FrameWriter`
+	LengthPrefixed
+}
+
+
+impl BlobFormat {
+	/// Every layout
+	pub const ALL: [BlobFormat; 4] = [BlobFormat::NativeEndian, BlobFormat::LittleEndian, BlobFormat::LittleEndian64, BlobFormat::LengthPrefixed];
+
+	/// Every layout, in the order `detect_format` prefers them
+	///
+	/// `NativeEndian` is the layout every default encoder writes, so it comes first, and a blob of the crate's own output is never misread.
+	/// A `LittleEndian64` blob with indexes below `u32::MAX` is often also a valid 4 byte blob (the high bytes of each index are read as payload), so an
+	/// 8 byte blob may be reported as a 4 byte one; pass the layout explicitly when it is known. `LengthPrefixed` comes last since its indexes are the smallest,
+	/// so a random blob is most likely to fit it by accident.
+	pub(crate) const DETECT_ORDER: [BlobFormat; 4] = [BlobFormat::NativeEndian, BlobFormat::LittleEndian, BlobFormat::LittleEndian64, BlobFormat::LengthPrefixed];
+
+	/// Returns the number of bytes taken by each index
+	pub fn header_len(self) -> usize {
+		match self {
+			BlobFormat::NativeEndian | BlobFormat::LittleEndian | BlobFormat::LengthPrefixed => 4,
+			BlobFormat::LittleEndian64 => 8
+		}
+	}
+
+	/// Returns the largest blob the indexes can describe
+	pub fn max_len(self) -> usize {
+		match self {
+			BlobFormat::NativeEndian | BlobFormat::LittleEndian => MAX_U32_LEN,
+			BlobFormat::LittleEndian64 | BlobFormat::LengthPrefixed => usize::MAX
+		}
+	}
+
+	/// Returns the largest blob the indexes can describe, for blobs read through `Read + Seek` that may not fit in memory
+	#[cfg(feature = "fs")]
+	pub(crate) fn max_len_u64(self) -> u64 {
+		match self {
+			BlobFormat::NativeEndian | BlobFormat::LittleEndian => u32::MAX as u64,
+			BlobFormat::LittleEndian64 | BlobFormat::LengthPrefixed => u64::MAX
+		}
+	}
+
+	/// Reads the index at `idx_start` of `blob`, returning where its slice ends.
+	/// An 8 byte index or a length prefixed end too large for usize reads as `usize::MAX`, which is never a valid position
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::TruncatedIndex)` if the blob ends before `header_len` bytes from `idx_start`,
+	/// or `Err(BlobReadError::IndexUnrepresentable)` if a 4 byte index doesn't fit in usize
+	pub(crate) fn read_index(self, blob: &[u8], idx_start: usize) -> Result<usize, BlobReadError> {
+		let truncated = BlobReadError::TruncatedIndex(idx_start);
+		match self {
+			BlobFormat::NativeEndian => index_to_usize(u32::from_ne_bytes(read_header(blob, idx_start).ok_or(truncated)?)),
+			BlobFormat::LittleEndian => index_to_usize(u32::from_le_bytes(read_header(blob, idx_start).ok_or(truncated)?)),
+			BlobFormat::LittleEndian64 => Ok(usize::try_from(u64::from_le_bytes(read_header(blob, idx_start).ok_or(truncated)?)).unwrap_or(usize::MAX)),
+			BlobFormat::LengthPrefixed => {
+				let len = index_to_usize(u32::from_le_bytes(read_header(blob, idx_start).ok_or(truncated)?))?;
+				Ok(len.saturating_add(idx_start.saturating_add(4)))
+			}
+		}
+	}
+
+	/// The same as `read_index` with u64 positions, so an index far into a large file is read correctly on any target
+	#[cfg(feature = "fs")]
+	pub(crate) fn read_index_u64(self, data: &[u8], idx_start: u64) -> u64 {
+		match self {
+			BlobFormat::NativeEndian => u32::from_ne_bytes(data.try_into().unwrap()) as u64,
+			BlobFormat::LittleEndian => u32::from_le_bytes(data.try_into().unwrap()) as u64,
+			BlobFormat::LittleEndian64 => u64::from_le_bytes(data.try_into().unwrap()),
+			BlobFormat::LengthPrefixed => (u32::from_le_bytes(data.try_into().unwrap()) as u64).saturating_add(idx_start.saturating_add(4))
+		}
+	}
+
+	/// Encodes the index at `idx_start` for a slice ending at `idx_end`, returning the bytes in the first `header_len` of the array
+	///
+	/// # Panics
+	///
+	/// Panics if the index doesn't fit in the layout, which callers rule out by checking `max_len` first
+	pub(crate) fn encode_index(self, idx_start: usize, idx_end: usize) -> [u8; 8] {
+		let to_u32 = |value: usize| u32::try_from(value).expect("blob larger than u32::MAX bytes");
+		let mut data = [0u8; 8];
+		match self {
+			BlobFormat::NativeEndian => data[..4].copy_from_slice(&to_u32(idx_end).to_ne_bytes()),
+			BlobFormat::LittleEndian => data[..4].copy_from_slice(&to_u32(idx_end).to_le_bytes()),
+			BlobFormat::LittleEndian64 => data.copy_from_slice(&(idx_end as u64).to_le_bytes()),
+			BlobFormat::LengthPrefixed => data[..4].copy_from_slice(&to_u32(idx_end - idx_start - 4).to_le_bytes())
+		}
+		data
+	}
+}
+
+
+/// Copies the `N` bytes at `start` out of `blob`, or returns `None` if the blob ends first
+fn read_header<const N: usize>(blob: &[u8], start: usize) -> Option<[u8; N]> {
+	blob.get(start..)?.get(..N)?.try_into().ok()
+}
+
+
+/// Returns an iterator over the slices in a blob using the given index layout. See `blob_iter`
+pub fn blob_iter_with_format(blob: &[u8], format: BlobFormat) -> BlobIter<'_> {
+	BlobIter { blob, format, idx_start: 0, entry: 0, done: false }
+}
+
+
+/// Converts a blob using the given index layout to a Vec of u8 slices. See `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob_with_format(&[&[1, 2], &[3]], BlobFormat::LittleEndian64);
+/// assert_eq!(blob.len(), 8 + 2 + 8 + 1);
+/// assert_eq!(blob_to_byte_arrays_with_format(&blob, BlobFormat::LittleEndian64).unwrap(), vec![&[1, 2][..], &[3]]);
+/// ```
+pub fn blob_to_byte_arrays_with_format(blob: &[u8], format: BlobFormat) -> Result<Vec<&[u8]>, BlobReadError> {
+	blob_iter_with_format(blob, format).collect()
+}
+
+
+/// Converts slices to a blob using the given index layout. See `byte_arrays_to_blob`
+///
+/// # Panics
+///
+/// Panics if an index doesn't fit in the layout, such as a 4 byte layout blob larger than `u32::MAX` bytes
+pub fn byte_arrays_to_blob_with_format(bytes_2d: &[&[u8]], format: BlobFormat) -> Vec<u8> {
+	let header_len = format.header_len();
+	let mut idx_end = 0;
+	let mut blob: Vec<u8> = Vec::with_capacity(bytes_2d.iter().map(|byte_arr| header_len + byte_arr.len()).sum());
+
+	for byte_arr in bytes_2d {
+		let idx_start = idx_end;
+		idx_end += header_len + byte_arr.len();
+		blob.extend(&format.encode_index(idx_start, idx_end)[..header_len]);
+		blob.extend(*byte_arr);
+	}
+	blob
+}
+
+
+
+
+/// Returns every layout that `blob` can be read as without error, in the order `detect_format` prefers them
+///
+/// None of the layouts store a magic number, so this is found by reading the whole blob once per layout.
+/// On a little-endian machine `NativeEndian` and `LittleEndian` are identical, and blobs can be valid in several layouts.
+pub fn probe_formats(blob: &[u8]) -> Vec<BlobFormat> {
+	BlobFormat::DETECT_ORDER.into_iter()
+		.filter(|&format| blob_iter_with_format(blob, format).all(|byte_arr| byte_arr.is_ok()))
+		.collect()
+}
+
+
+/// Finds the layout of a blob by checking which layouts it can be read as
+///
+/// When more than one layout fits, `NativeEndian` is preferred, then `LittleEndian`, then `LittleEndian64`, so the crate's default output is always read as it was written.
+/// This is a best guess: a `LittleEndian64` blob whose indexes fit in 4 bytes usually reads as a 4 byte blob too, and is reported as one.
+/// Use `probe_formats` to see every layout that fits. An empty blob is valid in every layout, and is reported as `NativeEndian`.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::UnknownFormat)` if no layout fits
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob_with_format(&[&[1; 300], &[2; 20]], BlobFormat::LengthPrefixed);
+/// assert_eq!(detect_format(&blob).unwrap(), BlobFormat::LengthPrefixed);
+/// assert_eq!(detect_format(&byte_arrays_to_blob(&[&[0; 8]])).unwrap(), BlobFormat::NativeEndian);
+/// ```
+pub fn detect_format(blob: &[u8]) -> Result<BlobFormat, BlobReadError> {
+	if blob.is_empty() {
+		return Ok(BlobFormat::NativeEndian);
+	}
+	probe_formats(blob).first().copied().ok_or(BlobReadError::UnknownFormat)
+}
+
+
+/// Decodes a blob in whichever layout `detect_format` finds
+///
+/// The slices are returned as `Cow` so formats that have to build their entries can be added without changing the signature. Every current layout borrows from `blob`.
+///
+/// # Errors
+///
+/// The same as `detect_format`
+pub fn decode_any(blob: &[u8]) -> Result<Vec<Cow<'_, [u8]>>, BlobReadError> {
+	let format = detect_format(blob)?;
+	blob_iter_with_format(blob, format).map(|byte_arr| byte_arr.map(Cow::Borrowed)).collect()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn index_conversion_on_narrow_targets() {
+		// What a 16-bit and a 32-bit target would make of the same indexes
+		assert_eq!(checked_index(0xFFFF, u16::MAX as u64), Some(0xFFFF));
+		assert_eq!(checked_index(0x1_0000, u16::MAX as u64), None);
+		assert_eq!(checked_index(u32::MAX, u16::MAX as u64), None);
+		assert_eq!(checked_index(u32::MAX, u32::MAX as u64), Some(u32::MAX as usize));
+		assert_eq!(checked_index(0, 0), Some(0));
+
+		assert_eq!(index_to_usize(5), Ok(5));
+		assert_eq!(index_to_usize(u32::MAX).is_ok(), usize::BITS >= 32);
+		assert_eq!(MAX_U32_LEN as u64, (u32::MAX as u64).min(usize::MAX as u64));
+	}
+
+	#[test]
+	fn formats_round_trip() {
+		let byte_arrays: Vec<&[u8]> = vec![&[], &[1u8, 2u8], &[3u8; 100], &[]];
+		for format in BlobFormat::ALL {
+			let blob = byte_arrays_to_blob_with_format(&byte_arrays, format);
+			assert_eq!(blob_to_byte_arrays_with_format(&blob, format).unwrap(), byte_arrays);
+		}
+
+		assert_eq!(byte_arrays_to_blob_with_format(&byte_arrays, BlobFormat::NativeEndian), byte_arrays_to_blob(&byte_arrays));
+	}
+
+	#[test]
+	fn little_endian_byte_order() {
+		let blob = byte_arrays_to_blob_with_format(&[&[0u8; 256]], BlobFormat::LittleEndian);
+		assert_eq!(&blob[..4], &[4u8, 1u8, 0u8, 0u8]);
+
+		let blob = byte_arrays_to_blob_with_format(&[&[0u8; 3], &[0u8; 256]], BlobFormat::LengthPrefixed);
+		assert_eq!(&blob[..4], &[3u8, 0u8, 0u8, 0u8]);
+		assert_eq!(&blob[7..11], &[0u8, 1u8, 0u8, 0u8]);
+	}
+
+	#[test]
+	fn detect_every_format() {
+		let byte_arrays: Vec<&[u8]> = vec![&[1u8; 300], &[], &[2u8; 70]];
+		for format in BlobFormat::ALL {
+			let blob = byte_arrays_to_blob_with_format(&byte_arrays, format);
+			let detected = detect_format(&blob).unwrap();
+			assert!(probe_formats(&blob).contains(&format));
+
+			match format {
+				// Little-endian machines can't tell their native layout from little-endian, which is fine as they decode the same
+				BlobFormat::LittleEndian if cfg!(target_endian = "little") => assert_eq!(detected, BlobFormat::NativeEndian),
+				// Small 8 byte indexes also read as 4 byte ones, and the 4 byte layouts are preferred
+				BlobFormat::LittleEndian64 => {
+					assert_eq!(detected, probe_formats(&blob)[0]);
+					continue;
+				},
+				_ => assert_eq!(detected, format)
+			}
+			assert_eq!(decode_any(&blob).unwrap(), byte_arrays);
+		}
+	}
+
+	#[test]
+	fn default_output_is_read_as_written() {
+		// An entry of zeros is also a valid `LittleEndian64` blob holding 4 zero bytes
+		let blob = byte_arrays_to_blob(&[&[0u8; 8]]);
+		assert_eq!(detect_format(&blob), Ok(BlobFormat::NativeEndian));
+		assert_eq!(decode_any(&blob).unwrap(), [&[0u8; 8][..]]);
+		assert!(probe_formats(&blob).contains(&BlobFormat::LittleEndian64));
+	}
+
+	#[test]
+	fn detect_random_bytes() {
+		let mut rng = XorShift32::default_seed();
+		for len in 0..500usize {
+			let blob: Vec<u8> = (0..len % 40).map(|_| rng.next_u32() as u8).collect();
+
+			// Whatever is detected must actually decode
+			match detect_format(&blob) {
+				Ok(format) => assert!(blob_to_byte_arrays_with_format(&blob, format).is_ok()),
+				Err(e) => {
+					assert_eq!(e, BlobReadError::UnknownFormat);
+					assert!(decode_any(&blob).is_err());
+				}
+			}
+		}
+		assert_eq!(detect_format(&[1u8, 2u8, 3u8]), Err(BlobReadError::UnknownFormat));
+		assert_eq!(detect_format(&[]), Ok(BlobFormat::NativeEndian));
+	}
+
+	#[test]
+	fn wide_truncated_index() {
+		let blob = byte_arrays_to_blob_with_format(&[&[1u8]], BlobFormat::LittleEndian64);
+		assert!(matches!(blob_to_byte_arrays_with_format(&blob[..5], BlobFormat::LittleEndian64), Err(BlobReadError::TruncatedIndex(0))));
+	}
+}
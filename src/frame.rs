@@ -0,0 +1,229 @@
+//! Length prefixed frames, for protocols that send one entry at a time
+//!
+//! A frame is a 4 byte little-endian payload length followed by the payload, which is exactly one entry of a `BlobFormat::LengthPrefixed` blob.
+//! Frames carry no positions, so any sequence of frames written one after another is a valid blob: `cat frame1 frame2 frame3` gives the same bytes
+//! as `byte_arrays_to_blob_with_format` with the three payloads, and a captured stream can be read with `blob_to_byte_arrays_with_format`.
+use crate::*;
+use std::io::{self, Read, Write};
+
+
+/// Errors that can occur when reading frames
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum FrameError {
+	/// An error from the underlying reader
+	Io(io::Error),
+	/// A frame says it is `len` bytes long, more than the reader's limit of `max`
+	FrameTooLarge {
+		len: usize,
+		max: usize
+	},
+	/// The stream ended part way through the frame starting at this position
+	Truncated(usize)
+}
+
+
+impl std::error::Error for FrameError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			FrameError::Io(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for FrameError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FrameError::Io(e) => write!(formatter, "{}", e),
+			FrameError::FrameTooLarge { len, max } => write!(formatter, "frame of {} bytes is over the limit of {}", len, max),
+			FrameError::Truncated(position) => write!(formatter, "stream ended inside the frame at {}", position)
+		}
+	}
+}
+
+impl From<io::Error> for FrameError {
+	fn from(e: io::Error) -> Self {
+		FrameError::Io(e)
+	}
+}
+
+
+/// Writes length prefixed frames to an `io::Write`. See the module documentation
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut writer = FrameWriter::new(Vec::new());
+/// writer.write_frame(b"hello").unwrap();
+/// writer.write_frame(b"").unwrap();
+///
+/// let stream = writer.finish().unwrap();
+/// assert_eq!(stream, byte_arrays_to_blob_with_format(&[b"hello", b""], BlobFormat::LengthPrefixed));
+/// assert_eq!(collect_frames(&stream[..]).unwrap(), vec![b"hello".to_vec(), vec![]]);
+/// ```
+#[derive(Debug)]
+pub struct FrameWriter<W: Write> {
+	writer: W
+}
+
+
+impl<W: Write> FrameWriter<W> {
+	pub fn new(writer: W) -> Self {
+		FrameWriter { writer }
+	}
+
+	/// Writes one frame
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobWriteError::TooLarge)` without writing anything if the payload is longer than `u32::MAX` bytes, or `Err(BlobWriteError::Io)` if the writer fails
+	pub fn write_frame(&mut self, payload: &[u8]) -> Result<(), BlobWriteError> {
+		let len = u32::try_from(payload.len()).map_err(|_| BlobWriteError::TooLarge)?;
+		self.writer.write_all(&len.to_le_bytes())?;
+		self.writer.write_all(payload)?;
+		Ok(())
+	}
+
+	/// Flushes and returns the underlying writer
+	pub fn finish(mut self) -> Result<W, BlobWriteError> {
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+}
+
+
+/// Reads length prefixed frames from an `io::Read`. See the module documentation
+///
+/// A frame's payload is read as it arrives rather than allocated up front, and frames over `max_frame_len` are rejected before any of their payload is read.
+#[derive(Debug)]
+pub struct FrameReader<R: Read> {
+	reader: R,
+	max_frame_len: usize,
+	position: usize
+}
+
+
+impl<R: Read> FrameReader<R> {
+	/// The default limit on the length of one frame, 16 MiB
+	pub const DEFAULT_MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+	pub fn new(reader: R) -> Self {
+		FrameReader { reader, max_frame_len: FrameReader::<R>::DEFAULT_MAX_FRAME_LEN, position: 0 }
+	}
+
+	/// Sets the longest frame payload the reader will accept
+	pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+		self.max_frame_len = max_frame_len;
+		self
+	}
+
+	/// Returns the number of bytes consumed so far
+	pub fn position(&self) -> usize {
+		self.position
+	}
+
+	/// Returns the underlying reader
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	/// Reads the next frame, or returns `None` if the stream ended cleanly between frames
+	///
+	/// # Errors
+	///
+	/// Returns `Err(FrameError::FrameTooLarge)` if the frame is over the limit or its length does not fit in a usize, `Err(FrameError::Truncated)` if the stream ends inside a frame,
+	/// or `Err(FrameError::Io)` if the reader fails
+	pub fn read_frame(&mut self) -> Result<Option<Vec<u8>>, FrameError> {
+		let mut header = [0u8; 4];
+		let mut filled = 0;
+		while filled < 4 {
+			match self.reader.read(&mut header[filled..]) {
+				Ok(0) if filled == 0 => return Ok(None),
+				Ok(0) => return Err(FrameError::Truncated(self.position)),
+				Ok(n) => filled += n,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(e.into())
+			}
+		}
+
+		// A length that doesn't fit in a usize can't be read into memory whatever the limit, so it is reported as too large
+		let Ok(len) = usize::try_from(u32::from_le_bytes(header)) else {
+			return Err(FrameError::FrameTooLarge { len: usize::MAX, max: self.max_frame_len });
+		};
+		if len > self.max_frame_len {
+			return Err(FrameError::FrameTooLarge { len, max: self.max_frame_len });
+		}
+
+		let mut payload = Vec::new();
+		(&mut self.reader).take(len as u64).read_to_end(&mut payload)?;
+		if payload.len() != len {
+			return Err(FrameError::Truncated(self.position));
+		}
+		self.position += 4 + len;
+		Ok(Some(payload))
+	}
+}
+
+
+impl<R: Read> Iterator for FrameReader<R> {
+	type Item = Result<Vec<u8>, FrameError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.read_frame().transpose()
+	}
+}
+
+
+/// Reads every frame from `reader`, using the default frame length limit
+///
+/// # Errors
+///
+/// The same as `FrameReader::read_frame`
+pub fn collect_frames<R: Read>(reader: R) -> Result<Vec<Vec<u8>>, FrameError> {
+	FrameReader::new(reader).collect()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn concatenated_frames_are_a_blob() {
+		let payloads: [&[u8]; 4] = [b"first", b"", &[0xFFu8; 300], b"last"];
+
+		// Each frame written on its own, then joined like `cat`
+		let mut joined = Vec::new();
+		for payload in payloads {
+			let mut writer = FrameWriter::new(Vec::new());
+			writer.write_frame(payload).unwrap();
+			joined.extend(writer.finish().unwrap());
+		}
+
+		assert_eq!(joined, byte_arrays_to_blob_with_format(&payloads, BlobFormat::LengthPrefixed));
+		assert_eq!(blob_to_byte_arrays_with_format(&joined, BlobFormat::LengthPrefixed).unwrap(), payloads);
+		assert_eq!(collect_frames(&joined[..]).unwrap(), payloads.map(|payload| payload.to_vec()));
+	}
+
+	#[test]
+	fn reader_limits_and_truncation() {
+		let stream = byte_arrays_to_blob_with_format(&[&[1u8; 10], &[2u8; 100]], BlobFormat::LengthPrefixed);
+
+		let mut reader = FrameReader::new(&stream[..]).with_max_frame_len(50);
+		assert_eq!(reader.read_frame().unwrap(), Some(vec![1u8; 10]));
+		assert_eq!(reader.position(), 14);
+		assert!(matches!(reader.read_frame(), Err(FrameError::FrameTooLarge { len: 100, max: 50 })));
+
+		assert!(matches!(collect_frames(&stream[..stream.len() - 1]), Err(FrameError::Truncated(14))));
+		assert!(matches!(collect_frames(&stream[..2]), Err(FrameError::Truncated(0))));
+
+		// A hostile length is rejected before anything is allocated, even with no limit on the frame length
+		let hostile = u32::MAX.to_le_bytes();
+		assert!(matches!(collect_frames(&hostile[..]), Err(FrameError::FrameTooLarge { .. })));
+		assert!(matches!(FrameReader::new(&hostile[..]).with_max_frame_len(usize::MAX).read_frame(), Err(FrameError::Truncated(0))));
+	}
+}
@@ -0,0 +1,269 @@
+//! Reports on blob files for people, the kind of thing a small command line tool prints, read by seeking from index to index so files of any size can be inspected
+use crate::*;
+use crate::file::FileHeaders;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+
+/// Options for `describe_file`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let options = DescribeOptions::new().with_entries(true).with_preview_len(8);
+/// assert!(options.entries());
+/// assert_eq!(options.max_rows(), 100);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DescribeOptions {
+	entries: bool,
+	preview_len: usize,
+	max_rows: usize
+}
+
+
+impl DescribeOptions {
+	/// Creates options for a summary without a table of entries, with 16 byte previews and at most 100 rows once the table is turned on
+	pub fn new() -> Self {
+		DescribeOptions { entries: false, preview_len: 16, max_rows: 100 }
+	}
+
+	/// Adds a table with the offset, length and a preview of each entry
+	pub fn with_entries(mut self, entries: bool) -> Self {
+		self.entries = entries;
+		self
+	}
+
+	/// Sets how many bytes of each entry the table shows. Only these bytes are read from the file
+	pub fn with_preview_len(mut self, preview_len: usize) -> Self {
+		self.preview_len = preview_len;
+		self
+	}
+
+	/// Sets how many entries the table lists before it stops with a count of the rest
+	pub fn with_max_rows(mut self, max_rows: usize) -> Self {
+		self.max_rows = max_rows;
+		self
+	}
+
+	pub fn entries(&self) -> bool {
+		self.entries
+	}
+
+	pub fn preview_len(&self) -> usize {
+		self.preview_len
+	}
+
+	pub fn max_rows(&self) -> usize {
+		self.max_rows
+	}
+}
+
+
+impl Default for DescribeOptions {
+	fn default() -> Self {
+		DescribeOptions::new()
+	}
+}
+
+
+/// One entry of a blob file, returned by `list_entries`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntrySummary {
+	/// The index of the entry
+	pub index: usize,
+	/// Where the payload starts in the file, after its index
+	pub offset: u64,
+	/// The length of the payload in bytes
+	pub len: u64
+}
+
+
+/// The indexes read from a file in one layout, up to the end or the first invalid one
+struct Walk {
+	format: BlobFormat,
+	ranges: Vec<Range<u64>>,
+	error: Option<BlobReadError>
+}
+
+
+/// Reads the indexes of a file in one layout, passing each payload range to `visit`, and returns the error that stopped it before the end, if any
+fn walk_with(reader: &mut (impl Read + Seek), format: BlobFormat, mut visit: impl FnMut(Range<u64>)) -> Result<Option<BlobReadError>, BlobFileError> {
+	let mut headers = match FileHeaders::new(reader, format) {
+		Ok(headers) => headers,
+		Err(BlobFileError::Read(e)) => return Ok(Some(e)),
+		Err(e) => return Err(e)
+	};
+	loop {
+		match headers.next_range(reader) {
+			Ok(Some(range)) => visit(range),
+			Ok(None) => return Ok(None),
+			Err(BlobFileError::Read(e)) => return Ok(Some(e)),
+			Err(e) => return Err(e)
+		}
+	}
+}
+
+
+fn walk(reader: &mut (impl Read + Seek), format: BlobFormat) -> Result<Walk, BlobFileError> {
+	let mut ranges = Vec::new();
+	let error = walk_with(reader, format, |range| ranges.push(range))?;
+	Ok(Walk { format, ranges, error })
+}
+
+
+/// Walks the file in each layout in the order `detect_format` prefers them, returning the first that reads the whole file, or every failed walk if none does
+fn detect(reader: &mut (impl Read + Seek)) -> Result<Result<Walk, Vec<Walk>>, BlobFileError> {
+	let mut failed = Vec::new();
+	for format in BlobFormat::DETECT_ORDER {
+		let walk = walk(reader, format)?;
+		match walk.error {
+			// An empty file fits every layout, and `detect_format` calls it native-endian
+			None if walk.ranges.is_empty() => return Ok(Ok(Walk { format: BlobFormat::NativeEndian, ..walk })),
+			None => return Ok(Ok(walk)),
+			Some(_) => failed.push(walk)
+		}
+	}
+	Ok(Err(failed))
+}
+
+
+/// Walks a file that must be a blob in some layout
+fn detect_or_fail(reader: &mut (impl Read + Seek)) -> Result<Walk, BlobFileError> {
+	detect(reader)?.map_err(|_| BlobFileError::Read(BlobReadError::UnknownFormat))
+}
+
+
+/// Returns the layout `detect` would pick, without keeping the ranges of any walk
+fn detect_format_only(reader: &mut (impl Read + Seek)) -> Result<BlobFormat, BlobFileError> {
+	for format in BlobFormat::DETECT_ORDER {
+		if walk_with(reader, format, |_| {})?.is_none() {
+			return Ok(format);
+		}
+	}
+	Err(BlobReadError::UnknownFormat.into())
+}
+
+
+/// Returns the offset and length of every entry of the blob file at `path`, in whichever layout `detect_format` would find
+///
+/// Only the indexes are read. An empty file is an empty blob.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Read(BlobReadError::UnknownFormat))` if no layout reads the whole file, or `Err(BlobFileError::Io)` if reading it fails
+pub fn list_entries(path: &Path) -> Result<Vec<EntrySummary>, BlobFileError> {
+	let walk = detect_or_fail(&mut BufReader::new(File::open(path)?))?;
+	Ok(walk.ranges.into_iter().enumerate().map(|(index, range)| EntrySummary { index, offset: range.start, len: range.end - range.start }).collect())
+}
+
+
+/// Copies entry `index` of the blob file at `path` to a new file at `out_path`, returning its length, or `None` without creating the file if there is no such entry
+///
+/// The payload is copied in chunks, so an entry larger than memory can be extracted. Finding the layout reads every index without keeping
+/// them, and the entry is then found by reading the indexes up to it.
+///
+/// # Errors
+///
+/// The same as `list_entries`, and `Err(BlobFileError::Io)` if writing the output fails
+pub fn extract_entry_to_file(path: &Path, index: usize, out_path: &Path) -> Result<Option<u64>, BlobFileError> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let format = detect_format_only(&mut reader)?;
+	let mut headers = FileHeaders::new(&mut reader, format)?;
+	for _ in 0..index {
+		if headers.next_range(&mut reader)?.is_none() {
+			return Ok(None);
+		}
+	}
+	let Some(range) = headers.next_range(&mut reader)? else {
+		return Ok(None);
+	};
+
+	reader.seek(SeekFrom::Start(range.start))?;
+	let mut writer = BufWriter::new(File::create(out_path)?);
+	let copied = io::copy(&mut (&mut reader).take(range.end - range.start), &mut writer)?;
+	// The ranges were checked against the file size, so a short copy means the file changed while being read
+	if copied != range.end - range.start {
+		return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+	}
+	writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+	Ok(Some(copied))
+}
+
+
+/// Returns a report on the blob file at `path` for reading by a person: its layout, number of entries, sizes and index overhead,
+/// and with `DescribeOptions::with_entries` a table of entries with previews
+///
+/// A file that isn't a valid blob still gets a report, saying how far each layout could read it and why it stopped.
+/// Only the indexes and the previewed bytes are read. The exact wording may change, so parse `list_entries` rather than this.
+///
+/// # Errors
+///
+/// Returns `Err(BlobFileError::Io)` if reading the file fails
+///
+/// # Examples
+/// ```no_run
+/// use byte_array_blob::*;
+/// use std::path::Path;
+///
+/// print!("{}", describe_file(Path::new("assets.blob"), &DescribeOptions::new().with_entries(true)).unwrap());
+/// ```
+pub fn describe_file(path: &Path, options: &DescribeOptions) -> Result<String, BlobFileError> {
+	let mut reader = BufReader::new(File::open(path)?);
+	let size = reader.seek(SeekFrom::End(0))?;
+	let mut report = format!("size:      {} bytes\n", size);
+
+	let walk = match detect(&mut reader)? {
+		Ok(walk) => walk,
+		Err(failed) => {
+			report.push_str("format:    unknown, no layout reads the whole file\n");
+			for walk in failed {
+				let error = walk.error.map(|e| e.to_string()).unwrap_or_default();
+				report.push_str(&format!("  {:<18} {} entries, then {}\n", BlobVariant::Format(walk.format).name(), walk.ranges.len(), error));
+			}
+			return Ok(report);
+		}
+	};
+
+	let lens = walk.ranges.iter().map(|range| range.end - range.start);
+	let payload: u64 = lens.clone().sum();
+	report.push_str(&format!("format:    {}\n", BlobVariant::Format(walk.format).name()));
+	report.push_str(&format!("entries:   {}\n", walk.ranges.len()));
+	report.push_str(&match (lens.clone().min(), lens.max()) {
+		(Some(min), Some(max)) => format!("payload:   {} bytes (smallest {}, largest {})\n", payload, min, max),
+		_ => "payload:   0 bytes\n".to_string()
+	});
+	let overhead = size - payload;
+	report.push_str(&match size {
+		0 => "overhead:  0 bytes of indexes\n".to_string(),
+		_ => format!("overhead:  {} bytes of indexes ({:.1}%)\n", overhead, overhead as f64 * 100.0 / size as f64)
+	});
+
+	if options.entries && !walk.ranges.is_empty() {
+		report.push_str(&format!("{:>8} {:>12} {:>12}  preview\n", "index", "offset", "length"));
+		let mut preview = Vec::new();
+		for (index, range) in walk.ranges.iter().enumerate().take(options.max_rows) {
+			let len = range.end - range.start;
+			preview.clear();
+			reader.seek(SeekFrom::Start(range.start))?;
+			(&mut reader).take(len.min(options.preview_len as u64)).read_to_end(&mut preview)?;
+
+			// Only the previewed bytes were read, so the count of the rest comes from the length rather than from `EntryDisplay`
+			let row = format!("{:>8} {:>12} {:>12}  {:#}", index, range.start, len, EntryDisplay(&preview));
+			report.push_str(row.trim_end());
+			match len - preview.len() as u64 {
+				0 => {},
+				1 => report.push_str("… (+1 byte)"),
+				hidden => report.push_str(&format!("… (+{} bytes)", hidden))
+			}
+			report.push('\n');
+		}
+		if walk.ranges.len() > options.max_rows {
+			report.push_str(&format!("... {} more entries\n", walk.ranges.len() - options.max_rows));
+		}
+	}
+	Ok(report)
+}
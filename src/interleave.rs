@@ -0,0 +1,171 @@
+//! Interleaved blobs, which hold several streams of entries taken in turn so entries recorded at the same time stay next to each other
+//!
+//! # Layout
+//!
+//! Slice 0 holds the number of entries in each stream as little-endian u32s, one per stream.
+//! The entries follow round by round: entry 0 of every stream in stream order, then entry 1 of every stream, and so on.
+//! A stream that has run out is left out of the rounds after it, so streams of different lengths need no padding and come back exactly as they went in.
+use crate::*;
+
+
+/// Errors that can occur when interleaving streams into a blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InterleaveError {
+	/// There were no streams to interleave
+	NoChannels,
+	/// The blob would be larger than `u32::MAX` bytes
+	TooLarge
+}
+
+
+impl std::error::Error for InterleaveError {}
+
+impl fmt::Display for InterleaveError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// Interleaves streams of entries into one blob, taking one entry from each stream in turn. See the module layout above
+///
+/// Streams may have different lengths. Once a stream runs out the others carry on without it, and `blob_deinterleave` gives back each stream as it was.
+///
+/// # Errors
+///
+/// Returns `Err(InterleaveError::NoChannels)` if `streams` is empty, or `Err(InterleaveError::TooLarge)` if the blob would be larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let left: [&[u8]; 3] = [b"l0", b"l1", b"l2"];
+/// let right: [&[u8]; 2] = [b"r0", b"r1"];
+/// let blob = interleave_to_blob(&[&left, &right]).unwrap();
+///
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap()[1..], [b"l0", b"r0", b"l1", b"r1", b"l2"]);
+/// assert_eq!(blob_deinterleave(&blob, 2).unwrap(), [&left[..], &right[..]]);
+/// ```
+pub fn interleave_to_blob(streams: &[&[&[u8]]]) -> Result<Vec<u8>, InterleaveError> {
+	if streams.is_empty() {
+		return Err(InterleaveError::NoChannels);
+	}
+	let mut counts = Vec::with_capacity(streams.len() * 4);
+	for stream in streams {
+		counts.extend(u32::try_from(stream.len()).map_err(|_| InterleaveError::TooLarge)?.to_le_bytes());
+	}
+
+	let mut builder = BlobBuilder::new();
+	builder.push(&counts).map_err(|_| InterleaveError::TooLarge)?;
+	// Only streams with entries left take part in a round, so the work is linear in the number of entries
+	let mut live: Vec<&[&[u8]]> = streams.iter().copied().filter(|stream| !stream.is_empty()).collect();
+	let mut round = 0;
+	while !live.is_empty() {
+		for stream in &live {
+			builder.push(stream[round]).map_err(|_| InterleaveError::TooLarge)?;
+		}
+		round += 1;
+		live.retain(|stream| round < stream.len());
+	}
+	builder.finish().map_err(|_| InterleaveError::TooLarge)
+}
+
+
+/// Splits an interleaved blob back into its streams, borrowing every entry from the blob
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InvalidMetadata(0))` if slice 0 doesn't hold the lengths of exactly `channels` streams, which is always so when `channels` is 0,
+/// or if those lengths don't add up to the number of entries in the blob. Otherwise the same as `blob_to_byte_arrays`
+pub fn blob_deinterleave(blob: &[u8], channels: usize) -> Result<Vec<Vec<&[u8]>>, BlobReadError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	let (header, entries) = byte_arrays.split_first().ok_or(BlobReadError::InvalidMetadata(0))?;
+	if channels == 0 || header.len() / 4 != channels || header.len() % 4 != 0 {
+		return Err(BlobReadError::InvalidMetadata(0));
+	}
+
+	let counts: Vec<usize> = header.chunks_exact(4).map(|count| u32::from_le_bytes(count.try_into().unwrap()) as usize).collect();
+	if counts.iter().try_fold(0usize, |total, &count| total.checked_add(count)) != Some(entries.len()) {
+		return Err(BlobReadError::InvalidMetadata(0));
+	}
+
+	// The counts add up to the entries in the blob, so they are safe to allocate for
+	let mut streams: Vec<Vec<&[u8]>> = counts.iter().map(|&count| Vec::with_capacity(count)).collect();
+	let mut entries = entries.iter().copied();
+	// The positions of the streams with entries left, so each round only visits those
+	let mut live: Vec<usize> = (0..channels).filter(|&channel| counts[channel] > 0).collect();
+	let mut round = 0;
+	while !live.is_empty() {
+		for &channel in &live {
+			streams[channel].extend(entries.next());
+		}
+		round += 1;
+		live.retain(|&channel| round < counts[channel]);
+	}
+	Ok(streams)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	fn round_trip(streams: &[&[&[u8]]]) {
+		let blob = interleave_to_blob(streams).unwrap();
+		assert_eq!(blob_deinterleave(&blob, streams.len()).unwrap(), streams);
+		assert_eq!(blob_to_byte_arrays(&blob).unwrap().len(), 1 + streams.iter().map(|stream| stream.len()).sum::<usize>());
+	}
+
+	#[test]
+	fn equal_lengths() {
+		let a: [&[u8]; 3] = [b"a0", b"a1", b"a2"];
+		let b: [&[u8]; 3] = [b"b0", &[], b"b2"];
+		let c: [&[u8]; 3] = [b"c0", b"c1", b"c2"];
+		round_trip(&[&a, &b, &c]);
+		round_trip(&[&a]);
+		round_trip(&[&[], &[]]);
+
+		let blob = interleave_to_blob(&[&a, &b, &c]).unwrap();
+		assert_eq!(blob_to_byte_arrays(&blob).unwrap()[1..4], [b"a0", b"b0", b"c0"]);
+	}
+
+	#[test]
+	fn unequal_lengths() {
+		let long: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; i as usize % 4]).collect();
+		let long = as_slices(&long);
+		let short: [&[u8]; 2] = [b"s0", b"s1"];
+		round_trip(&[&long, &short, &[], &long[..7]]);
+		round_trip(&[&[], &short]);
+
+		let blob = interleave_to_blob(&[&short, &long[..1]]).unwrap();
+		assert_eq!(blob_to_byte_arrays(&blob).unwrap()[1..], [&b"s0"[..], &[], b"s1"]);
+
+		// One long stream among many empty ones doesn't cost a round for each of them
+		let mut streams: Vec<&[&[u8]]> = vec![&[]; 10_000];
+		let many: Vec<&[u8]> = vec![b"x"; 10_000];
+		streams[1234] = &many;
+		round_trip(&streams);
+	}
+
+	#[test]
+	fn mismatched_channels() {
+		let a: [&[u8]; 2] = [b"a0", b"a1"];
+		let blob = interleave_to_blob(&[&a, &a, &a]).unwrap();
+		assert_eq!(blob_deinterleave(&blob, 0), Err(BlobReadError::InvalidMetadata(0)));
+		assert_eq!(blob_deinterleave(&blob, 2), Err(BlobReadError::InvalidMetadata(0)));
+		assert_eq!(blob_deinterleave(&blob, 4), Err(BlobReadError::InvalidMetadata(0)));
+		assert_eq!(blob_deinterleave(&[], 1), Err(BlobReadError::InvalidMetadata(0)));
+		assert_eq!(interleave_to_blob(&[]), Err(InterleaveError::NoChannels));
+
+		// Lengths that claim more entries than the blob holds, or fewer
+		let mut byte_arrays = blob_to_byte_arrays(&blob).unwrap();
+		let extra = byte_arrays_to_blob(&[byte_arrays.as_slice(), &[b"extra"]].concat());
+		assert_eq!(blob_deinterleave(&extra, 3), Err(BlobReadError::InvalidMetadata(0)));
+		byte_arrays.pop();
+		assert_eq!(blob_deinterleave(&byte_arrays_to_blob(&byte_arrays), 3), Err(BlobReadError::InvalidMetadata(0)));
+	}
+}
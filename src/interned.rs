@@ -0,0 +1,156 @@
+//! Interned string blobs, which store each distinct string once for lists with many repeats
+//!
+//! # Layout
+//!
+//! The first slice is the index stream: one unsigned LEB128 number per position in the list, giving the table slot of the string there.
+//! Every slice after it is one slot of the table, a distinct UTF-8 string, in the order the strings first appear.
+//! An empty blob, with no index stream, is also read as an empty list.
+use crate::*;
+use std::collections::HashMap;
+
+
+fn push_leb128(stream: &mut Vec<u8>, mut value: u32) {
+	while value >= 0x80 {
+		stream.push(value as u8 | 0x80);
+		value >>= 7;
+	}
+	stream.push(value as u8);
+}
+
+
+/// Reads one LEB128 number from the front of `stream`, or returns `None` if it is cut short or doesn't fit in a u32
+fn read_leb128(stream: &mut &[u8]) -> Option<u32> {
+	let mut value = 0u32;
+	for (i, &byte) in stream.iter().enumerate().take(5) {
+		let bits = u32::from(byte & 0x7F);
+		// The fifth byte only has room for the top 4 bits
+		if i == 4 && bits > 0x0F {
+			return None;
+		}
+		value |= bits << (7 * i);
+		if byte & 0x80 == 0 {
+			*stream = &stream[i + 1..];
+			return Some(value);
+		}
+	}
+	None
+}
+
+
+/// Converts a list of strings to an interned string blob, storing each distinct string once. See the module layout above
+///
+/// # Panics
+///
+/// Panics if the blob would be larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let strs = ["en-GB", "en-US", "en-GB", "en-GB"];
+/// let blob = intern_strs_to_blob(&strs);
+///
+/// assert_eq!(blob_to_interned_strs(&blob).unwrap(), strs);
+/// assert_eq!(count_arrays(&blob).unwrap(), 3);
+/// ```
+pub fn intern_strs_to_blob(strs: &[&str]) -> Vec<u8> {
+	let mut slots: HashMap<&str, u32> = HashMap::new();
+	let mut table = Vec::new();
+	let mut stream = Vec::with_capacity(strs.len());
+	for &s in strs {
+		let slot = *slots.entry(s).or_insert_with(|| {
+			table.push(s.as_bytes());
+			(table.len() - 1) as u32
+		});
+		push_leb128(&mut stream, slot);
+	}
+
+	let mut blob = Blob::with_capacity(4 + stream.len() + encoded_len(&table), table.len() + 1);
+	blob.push(&stream);
+	for s in table {
+		blob.push(s);
+	}
+	blob.into_bytes()
+}
+
+
+/// Converts an interned string blob back to the full list of strings, borrowed from the table. See the module layout above
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InvalidUtf8)` with the index of the slice if a table string is not UTF-8, or `Err(BlobReadError::InvalidMetadata)`
+/// with the position in the list if its slot number is malformed or past the end of the table. Otherwise the same as `blob_to_byte_arrays`
+pub fn blob_to_interned_strs(blob: &[u8]) -> Result<Vec<&str>, BlobReadError> {
+	let mut iter = blob_iter(blob);
+	let mut stream = match iter.next() {
+		Some(stream) => stream?,
+		None => return Ok(Vec::new())
+	};
+	let table = iter.enumerate().map(|(slot, byte_arr)| {
+		std::str::from_utf8(byte_arr?).map_err(|_| BlobReadError::InvalidUtf8(slot + 1))
+	}).collect::<Result<Vec<&str>, BlobReadError>>()?;
+
+	// Every position takes at least one byte of the stream, so this never reserves more than the blob could hold
+	let mut strs = Vec::with_capacity(stream.len());
+	while !stream.is_empty() {
+		let position = strs.len();
+		let s = read_leb128(&mut stream).and_then(|slot| table.get(slot as usize)).ok_or(BlobReadError::InvalidMetadata(position))?;
+		strs.push(*s);
+	}
+	Ok(strs)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn many_repeats() {
+		let names: Vec<String> = (0..50).map(|i| format!("ENUM_VARIANT_{}", i)).collect();
+		let mut rng = XorShift32::default_seed();
+		let strs: Vec<&str> = (0..100_000).map(|_| names[rng.next_u32() as usize % names.len()].as_str()).collect();
+
+		let blob = intern_strs_to_blob(&strs);
+		assert_eq!(blob_to_interned_strs(&blob).unwrap(), strs);
+		assert_eq!(count_arrays(&blob).unwrap(), 51);
+
+		let plain = byte_arrays_to_blob(&strs.iter().map(|s| s.as_bytes()).collect::<Vec<_>>());
+		// One byte per position plus the table, against at least 4 index bytes and the string at every position
+		assert!(blob.len() < 101_000, "{} bytes", blob.len());
+		assert!(blob.len() * 15 < plain.len(), "{} bytes against {}", blob.len(), plain.len());
+	}
+
+	#[test]
+	fn slot_numbers() {
+		let distinct: Vec<String> = (0..300).map(|i| i.to_string()).collect();
+		let mut strs: Vec<&str> = distinct.iter().map(String::as_str).collect();
+		strs.extend(["299", "128", "0", ""]);
+		assert_eq!(blob_to_interned_strs(&intern_strs_to_blob(&strs)).unwrap(), strs);
+		assert_eq!(blob_to_interned_strs(&intern_strs_to_blob(&[])).unwrap(), Vec::<&str>::new());
+		assert_eq!(blob_to_interned_strs(&[]).unwrap(), Vec::<&str>::new());
+
+		for (value, bytes) in [(0u32, &[0u8][..]), (127, &[0x7F]), (128, &[0x80, 0x01]), (u32::MAX, &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F])] {
+			let mut stream = Vec::new();
+			push_leb128(&mut stream, value);
+			assert_eq!(stream, bytes);
+			assert_eq!(read_leb128(&mut &stream[..]), Some(value));
+		}
+		assert_eq!(read_leb128(&mut &[0xFF, 0xFF, 0xFF, 0xFF, 0x1F][..]), None);
+	}
+
+	#[test]
+	fn bad_references() {
+		let past_table = byte_arrays_to_blob(&[&[0, 1, 2], b"a", b"b"]);
+		assert_eq!(blob_to_interned_strs(&past_table), Err(BlobReadError::InvalidMetadata(2)));
+		let cut_short = byte_arrays_to_blob(&[&[0, 0x80], b"a"]);
+		assert_eq!(blob_to_interned_strs(&cut_short), Err(BlobReadError::InvalidMetadata(1)));
+		let not_utf8 = byte_arrays_to_blob(&[&[0], b"a", &[0xFF]]);
+		assert_eq!(blob_to_interned_strs(&not_utf8), Err(BlobReadError::InvalidUtf8(2)));
+		let no_table = byte_arrays_to_blob(&[&[0]]);
+		assert_eq!(blob_to_interned_strs(&no_table).unwrap_err().entry_number(), Some(0));
+	}
+}
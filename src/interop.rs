@@ -0,0 +1,354 @@
+//! A canonical JSON form of a blob, for checking other implementations of the format against this one
+//!
+//! The JSON is an object with exactly these keys, written in this order with no whitespace:
+//!
+//! - `"version"`: the number 1
+//! - `"format"`: the string `"native-endian"`, the format of the blob the JSON was made from and will be turned back into
+//! - `"byte_order"`: `"little"` or `"big"`, the byte order of the machine that wrote the JSON, and so of the indexes in its blob
+//! - `"entries"`: an array of strings, each entry's bytes in standard base64 (RFC 4648, `+` and `/`, with `=` padding)
+//!
+//! For example, the entries `[b"hi", b""]` on a little endian machine give `{"version":1,"format":"native-endian","byte_order":"little","entries":["aGk=",""]}`.
+//!
+//! `canonical_json_to_blob` also accepts whitespace between tokens and keys in any order, so JSON from serializers with other defaults can be read back.
+//! Strings may not contain escapes, since none of the values above need them.
+use crate::*;
+
+
+/// The standard base64 alphabet
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The `"format"` of every canonical JSON blob
+const CANONICAL_FORMAT: &str = "native-endian";
+
+/// The byte order of this machine, as written in `"byte_order"`
+const NATIVE_BYTE_ORDER: &str = if cfg!(target_endian = "little") { "little" } else { "big" };
+
+
+/// Errors that can occur when turning canonical JSON back into a blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InteropError {
+	/// The text is not JSON of the expected shape. The enum yields the byte position of the problem
+	InvalidJson(usize),
+	/// A required key is missing
+	MissingKey(&'static str),
+	/// A key is not one of the canonical keys, or appears more than once
+	UnexpectedKey(String),
+	/// The `"version"` is not 1
+	UnsupportedVersion(u64),
+	/// The `"format"` is not `"native-endian"`
+	UnsupportedFormat(String),
+	/// The `"byte_order"` is not `"little"` or `"big"`
+	UnsupportedByteOrder(String),
+	/// Entry `n` is not valid padded base64
+	InvalidBase64(usize),
+	/// The entries would make a blob larger than `u32::MAX` bytes
+	TooLarge
+}
+
+
+impl std::error::Error for InteropError {}
+
+impl fmt::Display for InteropError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// Dumps a blob as canonical JSON, described in the module docs
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"hi", b""]);
+/// let json = blob_to_canonical_json(&blob).unwrap();
+///
+/// assert!(json.ends_with(r#""entries":["aGk=",""]}"#));
+/// assert_eq!(canonical_json_to_blob(&json).unwrap(), blob);
+/// ```
+pub fn blob_to_canonical_json(blob: &[u8]) -> Result<String, BlobReadError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	let mut json = format!(r#"{{"version":1,"format":"{}","byte_order":"{}","entries":["#, CANONICAL_FORMAT, NATIVE_BYTE_ORDER);
+	for (i, byte_arr) in byte_arrays.iter().enumerate() {
+		if i > 0 {
+			json.push(',');
+		}
+		json.push('"');
+		base64_encode(byte_arr, &mut json);
+		json.push('"');
+	}
+	json.push_str("]}");
+	Ok(json)
+}
+
+
+/// Reads canonical JSON back into a blob, in this machine's byte order
+///
+/// A blob dumped with `blob_to_canonical_json` on a machine with the same byte order comes back byte for byte. The `"byte_order"` only
+/// records where the JSON came from, since the entries themselves don't depend on it.
+///
+/// # Errors
+///
+/// Returns the `InteropError` describing the first problem found in the text
+pub fn canonical_json_to_blob(json: &str) -> Result<Vec<u8>, InteropError> {
+	let mut parser = JsonParser { text: json.as_bytes(), position: 0 };
+	let mut version = None;
+	let mut format = None;
+	let mut byte_order = None;
+	let mut entries: Option<Vec<Vec<u8>>> = None;
+
+	parser.expect(b'{')?;
+	if !parser.eat(b'}') {
+		loop {
+			let key = parser.string()?;
+			parser.expect(b':')?;
+			let duplicate = match key {
+				"version" => version.replace(parser.number()?).is_some(),
+				"format" => format.replace(parser.string()?).is_some(),
+				"byte_order" => byte_order.replace(parser.string()?).is_some(),
+				"entries" => entries.replace(parser.base64_array()?).is_some(),
+				_ => true
+			};
+			if duplicate {
+				return Err(InteropError::UnexpectedKey(key.to_string()));
+			}
+			if parser.eat(b'}') {
+				break;
+			}
+			parser.expect(b',')?;
+		}
+	}
+	parser.end()?;
+
+	match version {
+		Some(1) => {},
+		Some(version) => return Err(InteropError::UnsupportedVersion(version)),
+		None => return Err(InteropError::MissingKey("version"))
+	}
+	match format {
+		Some(CANONICAL_FORMAT) => {},
+		Some(format) => return Err(InteropError::UnsupportedFormat(format.to_string())),
+		None => return Err(InteropError::MissingKey("format"))
+	}
+	match byte_order {
+		Some("little" | "big") => {},
+		Some(byte_order) => return Err(InteropError::UnsupportedByteOrder(byte_order.to_string())),
+		None => return Err(InteropError::MissingKey("byte_order"))
+	}
+	let entries = entries.ok_or(InteropError::MissingKey("entries"))?;
+
+	let byte_arrays: Vec<&[u8]> = entries.iter().map(Vec::as_slice).collect();
+	if encoded_len(&byte_arrays) > MAX_U32_LEN {
+		return Err(InteropError::TooLarge);
+	}
+	Ok(byte_arrays_to_blob(&byte_arrays))
+}
+
+
+/// Appends `data` to `out` as padded standard base64
+fn base64_encode(data: &[u8], out: &mut String) {
+	for chunk in data.chunks(3) {
+		let bits = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+		for i in 0..4 {
+			match i <= chunk.len() {
+				true => out.push(BASE64_ALPHABET[(bits >> (18 - 6 * i) & 0x3F) as usize] as char),
+				false => out.push('=')
+			}
+		}
+	}
+}
+
+
+/// Decodes padded standard base64, rejecting any other spelling of the same bytes
+fn base64_decode(text: &[u8]) -> Option<Vec<u8>> {
+	if !text.len().is_multiple_of(4) {
+		return None;
+	}
+	let sextet = |c: u8| BASE64_ALPHABET.iter().position(|&a| a == c).map(|i| i as u32);
+
+	let mut data = Vec::with_capacity(text.len() / 4 * 3);
+	for (n, chunk) in text.chunks_exact(4).enumerate() {
+		let last = n == text.len() / 4 - 1;
+		let padding = match (chunk[2], chunk[3]) {
+			(b'=', b'=') if last => 2,
+			(_, b'=') if last => 1,
+			_ => 0
+		};
+
+		let mut bits = 0;
+		for &c in &chunk[..4 - padding] {
+			bits = bits << 6 | sextet(c)?;
+		}
+		bits <<= 6 * padding;
+		let bytes = [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8];
+		// The bits after the last byte must be zero, so each byte string has only one encoding
+		if bytes[3 - padding..].iter().any(|&byte| byte != 0) {
+			return None;
+		}
+		data.extend_from_slice(&bytes[..3 - padding]);
+	}
+	Some(data)
+}
+
+
+/// Just enough of a JSON reader for the canonical form
+struct JsonParser<'a> {
+	text: &'a [u8],
+	position: usize
+}
+
+
+impl<'a> JsonParser<'a> {
+	fn skip_whitespace(&mut self) {
+		while matches!(self.text.get(self.position), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+			self.position += 1;
+		}
+	}
+
+	fn invalid(&self) -> InteropError {
+		InteropError::InvalidJson(self.position)
+	}
+
+	/// Consumes `byte` if it is the next token
+	fn eat(&mut self, byte: u8) -> bool {
+		self.skip_whitespace();
+		let found = self.text.get(self.position) == Some(&byte);
+		if found {
+			self.position += 1;
+		}
+		found
+	}
+
+	fn expect(&mut self, byte: u8) -> Result<(), InteropError> {
+		match self.eat(byte) {
+			true => Ok(()),
+			false => Err(self.invalid())
+		}
+	}
+
+	fn end(&mut self) -> Result<(), InteropError> {
+		self.skip_whitespace();
+		match self.position == self.text.len() {
+			true => Ok(()),
+			false => Err(self.invalid())
+		}
+	}
+
+	fn string(&mut self) -> Result<&'a str, InteropError> {
+		self.expect(b'"')?;
+		let start = self.position;
+		loop {
+			match self.text.get(self.position) {
+				Some(b'"') => break,
+				Some(b'\\') | Some(0..=0x1F) | None => return Err(self.invalid()),
+				Some(_) => self.position += 1
+			}
+		}
+		self.position += 1;
+		// The text came from a str and the string ends at an ASCII quote, so it is valid UTF-8
+		Ok(std::str::from_utf8(&self.text[start..self.position - 1]).unwrap())
+	}
+
+	fn number(&mut self) -> Result<u64, InteropError> {
+		self.skip_whitespace();
+		let start = self.position;
+		while self.text.get(self.position).is_some_and(u8::is_ascii_digit) {
+			self.position += 1;
+		}
+		let digits = &self.text[start..self.position];
+		if digits.is_empty() || (digits.len() > 1 && digits[0] == b'0') {
+			self.position = start;
+			return Err(self.invalid());
+		}
+		std::str::from_utf8(digits).unwrap().parse().map_err(|_| InteropError::InvalidJson(start))
+	}
+
+	fn base64_array(&mut self) -> Result<Vec<Vec<u8>>, InteropError> {
+		self.expect(b'[')?;
+		let mut entries = Vec::new();
+		if self.eat(b']') {
+			return Ok(entries);
+		}
+		loop {
+			let text = self.string()?;
+			entries.push(base64_decode(text.as_bytes()).ok_or(InteropError::InvalidBase64(entries.len()))?);
+			if self.eat(b']') {
+				return Ok(entries);
+			}
+			self.expect(b',')?;
+		}
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn base64_vectors() {
+		// The test vectors from RFC 4648
+		let vectors: [(&[u8], &str); 7] = [(b"", ""), (b"f", "Zg=="), (b"fo", "Zm8="), (b"foo", "Zm9v"), (b"foob", "Zm9vYg=="), (b"fooba", "Zm9vYmE="), (b"foobar", "Zm9vYmFy")];
+		for (data, text) in vectors {
+			let mut encoded = String::new();
+			base64_encode(data, &mut encoded);
+			assert_eq!(encoded, text);
+			assert_eq!(base64_decode(text.as_bytes()).as_deref(), Some(data));
+		}
+
+		let mut encoded = String::new();
+		base64_encode(&[0xFB, 0xFF, 0xBF], &mut encoded);
+		assert_eq!(encoded, "+/+/");
+
+		for invalid in ["Zg", "Zg=", "Zh==", "Zm9=", "Z===", "Zg==Zg==", "Zm9v-A==", "Zm 9"] {
+			assert_eq!(base64_decode(invalid.as_bytes()), None, "{}", invalid);
+		}
+	}
+
+	#[test]
+	fn snapshots() {
+		let json = |entries: &[&[u8]]| blob_to_canonical_json(&byte_arrays_to_blob(entries)).unwrap();
+		let expected = |entries: &str| format!(r#"{{"version":1,"format":"native-endian","byte_order":"{}","entries":[{}]}}"#, NATIVE_BYTE_ORDER, entries);
+
+		assert_eq!(json(&[]), expected(""));
+		assert_eq!(json(&[&[]]), expected(r#""""#));
+		assert_eq!(json(&[b"hi", b"", &[0, 255, 128, 4]]), expected(r#""aGk=","","AP+ABA==""#));
+		assert!(matches!(blob_to_canonical_json(&[1, 2, 3]), Err(BlobReadError::TruncatedIndex(_))));
+	}
+
+	#[test]
+	fn reads_other_spellings() {
+		let blob = byte_arrays_to_blob(&[b"hi", b""]);
+		let spaced = "{\n  \"entries\": [\"aGk=\", \"\"],\n  \"byte_order\": \"big\",\n  \"format\": \"native-endian\",\n  \"version\": 1\n}\n";
+		assert_eq!(canonical_json_to_blob(spaced).unwrap(), blob);
+		assert_eq!(canonical_json_to_blob(&blob_to_canonical_json(&blob).unwrap()).unwrap(), blob);
+	}
+
+	#[test]
+	fn rejects_invalid_json() {
+		let with = |replace: &str, by: &str| canonical_json_to_blob(&r#"{"version":1,"format":"native-endian","byte_order":"little","entries":["aGk="]}"#.replace(replace, by));
+
+		assert_eq!(with("aGk=", "aGk"), Err(InteropError::InvalidBase64(0)));
+		assert_eq!(with(r#""aGk=""#, r#""","aGk""#), Err(InteropError::InvalidBase64(1)));
+		assert_eq!(with(":1", ":2"), Err(InteropError::UnsupportedVersion(2)));
+		assert_eq!(with(":1", ":01"), Err(InteropError::InvalidJson(11)));
+		assert_eq!(with("native-endian", "little-endian"), Err(InteropError::UnsupportedFormat("little-endian".to_string())));
+		assert_eq!(with(r#""little""#, r#""middle""#), Err(InteropError::UnsupportedByteOrder("middle".to_string())));
+		assert_eq!(with(r#""version":1,"#, ""), Err(InteropError::MissingKey("version")));
+		assert_eq!(with(r#""version":1,"#, r#""version":1,"version":1,"#), Err(InteropError::UnexpectedKey("version".to_string())));
+		assert_eq!(with(r#""version""#, r#""extra":1,"version""#), Err(InteropError::UnexpectedKey("extra".to_string())));
+		assert!(matches!(with("aGk=", r"aG\u006b="), Err(InteropError::InvalidJson(_))));
+		assert!(matches!(with("]}", "]},"), Err(InteropError::InvalidJson(_))));
+		assert!(matches!(with("]}", "]"), Err(InteropError::InvalidJson(_))));
+		assert_eq!(canonical_json_to_blob("{}"), Err(InteropError::MissingKey("version")));
+		assert!(matches!(canonical_json_to_blob(""), Err(InteropError::InvalidJson(0))));
+	}
+}
@@ -0,0 +1,221 @@
+//! Iterator adapters shared by the views: `enumerate_with_sizes`, and an exact length for iterators whose count is known but not visible to the compiler
+use std::iter::{Enumerate, FusedIterator};
+
+
+/// Wraps an iterator that is known to yield exactly `remaining` items, so its `size_hint` is exact
+///
+/// Used for iterators built from `filter_map` or `flat_map` over a view that already counted its entries.
+pub(crate) struct ExactLen<I> {
+	iter: I,
+	remaining: usize
+}
+
+
+impl<I> ExactLen<I> {
+	pub(crate) fn new(iter: I, remaining: usize) -> Self {
+		ExactLen { iter, remaining }
+	}
+}
+
+
+impl<I: Iterator> Iterator for ExactLen<I> {
+	type Item = I::Item;
+
+	fn next(&mut self) -> Option<I::Item> {
+		let item = self.iter.next();
+		self.remaining = match item {
+			Some(_) => self.remaining.saturating_sub(1),
+			None => 0
+		};
+		item
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
+	}
+}
+
+
+impl<I: Iterator> ExactSizeIterator for ExactLen<I> {}
+
+
+/// Adds `enumerate_with_sizes` to every iterator over entries
+pub trait EntryIterExt<'a>: Iterator<Item = &'a [u8]> + Sized {
+	/// Yields `(index, len, entry)` for each entry, so a consumer such as a progress display doesn't need to work out the index and length itself
+	///
+	/// The size hint is the same as the iterator's, so it is exact for every view.
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::*;
+	///
+	/// let blob = byte_arrays_to_blob(&[b"ab", b"", b"cde"]);
+	/// let view = BlobView::new(&blob).unwrap();
+	///
+	/// let sizes: Vec<(usize, usize)> = view.iter().enumerate_with_sizes().map(|(index, len, _)| (index, len)).collect();
+	/// assert_eq!(sizes, [(0, 2), (1, 0), (2, 3)]);
+	/// ```
+	fn enumerate_with_sizes(self) -> EnumerateWithSizes<Self> {
+		EnumerateWithSizes(self.enumerate())
+	}
+}
+
+
+impl<'a, I: Iterator<Item = &'a [u8]>> EntryIterExt<'a> for I {}
+
+
+/// The iterator returned by `EntryIterExt::enumerate_with_sizes`
+#[derive(Debug, Clone)]
+pub struct EnumerateWithSizes<I>(Enumerate<I>);
+
+
+impl<'a, I: Iterator<Item = &'a [u8]>> Iterator for EnumerateWithSizes<I> {
+	type Item = (usize, usize, &'a [u8]);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.0.next().map(|(index, byte_arr)| (index, byte_arr.len(), byte_arr))
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.0.size_hint()
+	}
+}
+
+
+impl<'a, I: ExactSizeIterator<Item = &'a [u8]>> ExactSizeIterator for EnumerateWithSizes<I> {}
+
+impl<'a, I: ExactSizeIterator<Item = &'a [u8]> + DoubleEndedIterator> DoubleEndedIterator for EnumerateWithSizes<I> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.0.next_back().map(|(index, byte_arr)| (index, byte_arr.len(), byte_arr))
+	}
+}
+
+impl<'a, I: FusedIterator<Item = &'a [u8]>> FusedIterator for EnumerateWithSizes<I> {}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::*;
+
+	/// Collects into a Vec sized from the lower bound of the size hint, recording whether it had to grow
+	struct Presized<T> {
+		items: Vec<T>,
+		grew: bool
+	}
+
+	impl<T> FromIterator<T> for Presized<T> {
+		fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+			let iter = iter.into_iter();
+			let mut items = Vec::with_capacity(iter.size_hint().0);
+			let capacity = items.capacity();
+			items.extend(iter);
+			Presized { grew: items.capacity() != capacity, items }
+		}
+	}
+
+	/// Checks the iterators from `iter` report exactly how many items are left before, part way through and at the end, and collect without growing
+	fn assert_exact<I: ExactSizeIterator>(iter: impl Fn() -> I, len: usize) {
+		let collected: Presized<_> = iter().collect();
+		assert_eq!(collected.items.len(), len);
+		assert!(!collected.grew);
+
+		let mut iter = iter();
+		assert_eq!(iter.size_hint(), (len, Some(len)));
+		for remaining in (0..len).rev() {
+			assert!(iter.next().is_some());
+			assert_eq!(iter.size_hint(), (remaining, Some(remaining)));
+			assert_eq!(iter.len(), remaining);
+		}
+		assert!(iter.next().is_none());
+		assert_eq!(iter.size_hint(), (0, Some(0)));
+	}
+
+	const ENTRIES: [&[u8]; 4] = [b"ab", b"", b"cde", b"f"];
+
+	#[test]
+	fn borrowed_views_are_exact() {
+		let blob = byte_arrays_to_blob(&ENTRIES);
+		let view = BlobView::new(&blob).unwrap();
+		assert_exact(|| view.iter(), 4);
+		let validated = ValidatedBlob::validate(&blob).unwrap();
+		assert_exact(|| validated.iter(), 4);
+		let owned = Blob::from_byte_arrays(&ENTRIES);
+		assert_exact(|| owned.iter(), 4);
+		let arena = blob_to_arena(&blob).unwrap();
+		assert_exact(|| arena.iter(), 4);
+		let multi = MultiBlobView::new(vec![&blob, &[], &blob]).unwrap();
+		assert_exact(|| multi.iter(), 8);
+
+		let named = named_byte_arrays_to_blob(&[("a", b"1"), ("b", b"22")]);
+		let named = NamedBlobView::new(&named).unwrap();
+		assert_exact(|| named.iter(), 2);
+
+		let compressed = byte_arrays_to_compressed_blob(&ENTRIES);
+		let compressed = CompressedBlobView::new(&compressed).unwrap();
+		assert_exact(|| compressed.iter(), 4);
+
+		let split = split_by_access(&blob, |i| i % 2 == 0).unwrap().to_blob();
+		let split = SplitBlobView::from_blob(&split).unwrap();
+		assert_exact(|| split.iter(), 4);
+
+		let mut builder = SectionsBuilder::new();
+		builder.put(SectionId(3), b"three").unwrap();
+		builder.put(SectionId(1), b"one").unwrap();
+		let sections = builder.finish();
+		let sections = Sections::parse(&sections).unwrap();
+		assert_exact(|| sections.iter(), 2);
+	}
+
+	#[test]
+	fn mutable_views_are_exact() {
+		let mut slab = SlabBlob::new();
+		let ids: Vec<EntryId> = ENTRIES.iter().map(|byte_arr| slab.insert(byte_arr.to_vec())).collect();
+		slab.remove(ids[1]);
+		assert_exact(|| slab.iter(), 3);
+
+		let blob = byte_arrays_to_blob(&ENTRIES);
+		let mut overlay = BlobOverlay::new(&blob).unwrap();
+		overlay.set(0, b"new".to_vec());
+		overlay.delete(2);
+		assert_exact(|| overlay.iter(), overlay.len());
+	}
+
+	#[test]
+	fn enumerate_with_sizes() {
+		let blob = byte_arrays_to_blob(&ENTRIES);
+		let view = BlobView::new(&blob).unwrap();
+		assert_exact(|| view.iter().enumerate_with_sizes(), 4);
+
+		let mut iter = view.iter().enumerate_with_sizes();
+		assert_eq!(iter.next(), Some((0, 2, &b"ab"[..])));
+		assert_eq!(iter.next_back(), Some((3, 1, &b"f"[..])));
+		assert_eq!(iter.len(), 2);
+		assert_eq!(iter.collect::<Vec<_>>(), [(1, 0, &b""[..]), (2, 3, &b"cde"[..])]);
+	}
+
+	#[test]
+	fn unvalidated_iterators_have_bounds() {
+		let blob = byte_arrays_to_blob(&ENTRIES);
+		// Each entry takes at least its 4 byte index, and a trailing partial index is one more item for its error
+		let mut iter = blob_iter(&blob);
+		assert_eq!(iter.size_hint(), (1, Some(blob.len().div_ceil(4))));
+		iter.next();
+		iter.next();
+		let (lower, upper) = iter.size_hint();
+		assert!(lower <= 2 && upper.unwrap() >= 2);
+		assert_eq!(iter.by_ref().count(), 2);
+		assert_eq!(iter.size_hint(), (0, Some(0)));
+		assert_eq!(blob_iter(&[]).size_hint(), (0, Some(0)));
+
+		let mut iter = blob_iter(&blob);
+		let budget = iter.by_budget(3);
+		assert!(budget.size_hint().1.unwrap() <= 3);
+		assert_eq!(budget.count(), 3);
+
+		// Readers can't know how much is left without reading it
+		assert_eq!(BlobReader::new(&blob[..]).size_hint(), (0, None));
+	}
+}
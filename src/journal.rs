@@ -0,0 +1,486 @@
+//! `JournaledBlobFile`, which appends and replaces entries of a blob file through a write-ahead journal so a crash never leaves it invalid
+//!
+//! # Journal layout
+//!
+//! The journal is a file next to the blob file, named after it with `.journal` on the end. It holds the bytes the blob file should
+//! have from some offset to its end once the changes are made:
+//!
+//! - 4 bytes `BABJ`
+//! - the offset, as a little-endian u64
+//! - the length of the new bytes, as a little-endian u64
+//! - the new bytes, which become the end of the blob file
+//! - the CRC-32 of everything before it, as a little-endian u32
+//!
+//! Writing the new bytes at the offset and cutting the file to end after them gives the same file however many times it is done,
+//! so a journal that was written in full can always be applied again. A journal that was not written in full fails its CRC and is thrown away,
+//! which leaves the blob file as it was, since the blob file is only changed once the journal is safely on disk.
+use crate::*;
+use crate::crc32::Crc32;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+
+const JOURNAL_MAGIC: [u8; 4] = *b"BABJ";
+/// The magic, the offset and the length of the new bytes
+const JOURNAL_HEADER_LEN: usize = 4 + 8 + 8;
+
+
+/// What `JournaledBlobFile::open` found left over from an earlier session
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalRecovery {
+	/// There was no journal, so the last commit finished or there wasn't one
+	Clean,
+	/// A complete journal was found and applied, finishing a commit that was interrupted after its journal was written
+	Replayed,
+	/// A partly written journal was found and thrown away, undoing a commit that was interrupted before its journal was written
+	RolledBack
+}
+
+
+/// The changes of one commit, ready to be journaled and applied
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct JournalRecord {
+	/// Where in the blob file the new bytes start
+	offset: u64,
+	/// The new end of the blob file
+	tail: Vec<u8>
+}
+
+
+impl JournalRecord {
+	fn encode(&self) -> Vec<u8> {
+		let mut journal = Vec::with_capacity(JOURNAL_HEADER_LEN + self.tail.len() + 4);
+		journal.extend_from_slice(&JOURNAL_MAGIC);
+		journal.extend_from_slice(&self.offset.to_le_bytes());
+		journal.extend_from_slice(&(self.tail.len() as u64).to_le_bytes());
+		journal.extend_from_slice(&self.tail);
+		let mut crc = Crc32::new();
+		crc.update(&journal);
+		journal.extend_from_slice(&crc.finish().to_le_bytes());
+		journal
+	}
+
+	/// Reads a journal, or returns `None` if it was not written in full
+	fn decode(journal: &[u8]) -> Option<Self> {
+		let (body, crc) = journal.split_last_chunk::<4>()?;
+		let mut expected = Crc32::new();
+		expected.update(body);
+		if body.len() < JOURNAL_HEADER_LEN || body[..4] != JOURNAL_MAGIC || expected.finish() != u32::from_le_bytes(*crc) {
+			return None;
+		}
+		let offset = u64::from_le_bytes(body[4..12].try_into().unwrap());
+		let tail_len = u64::from_le_bytes(body[12..20].try_into().unwrap());
+		let tail = &body[JOURNAL_HEADER_LEN..];
+		(tail.len() as u64 == tail_len).then(|| JournalRecord { offset, tail: tail.to_vec() })
+	}
+
+	/// Writes the new bytes into the blob file and cuts it to end after them, then waits for the file to reach the disk
+	fn apply(&self, file: &mut File) -> Result<(), BlobFileError> {
+		if self.offset > file.metadata()?.len() {
+			return Err(io::Error::new(io::ErrorKind::InvalidData, "journal starts past the end of the blob file it belongs to").into());
+		}
+		file.seek(SeekFrom::Start(self.offset))?;
+		file.write_all(&self.tail)?;
+		file.set_len(self.offset + self.tail.len() as u64)?;
+		file.sync_all()?;
+		Ok(())
+	}
+}
+
+
+/// Makes the creation or removal of a file in `path`'s directory durable. Only needed, and only possible, on unix
+fn sync_parent(path: &Path) -> Result<(), BlobFileError> {
+	#[cfg(unix)]
+	{
+		let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+		File::open(parent)?.sync_all()?;
+	}
+	#[cfg(not(unix))]
+	let _ = path;
+	Ok(())
+}
+
+
+/// A blob file whose changes are staged in memory and then committed through a journal, so a crash at any point leaves a valid blob
+///
+/// A commit writes the journal and waits for it to reach the disk, then changes the blob file and waits for that, then deletes the journal.
+/// `open` finishes a commit whose journal was written, and undoes one whose journal wasn't. See the module docs for the journal layout.
+///
+/// A commit rewrites the blob file from the first entry it changes to the end, and holds those bytes in memory,
+/// so replacing an entry near the start of a large file costs as much as the rest of the file. Appends only write the new entries.
+///
+/// # Examples
+/// ```no_run
+/// use byte_array_blob::*;
+/// use std::path::Path;
+///
+/// let mut pack = JournaledBlobFile::open(Path::new("saves.blob")).unwrap();
+/// pack.append(b"new save");
+/// pack.replace(0, b"updated index");
+/// pack.commit().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct JournaledBlobFile {
+	file: File,
+	journal_path: PathBuf,
+	/// The payload range of each committed entry
+	ranges: Vec<Range<u64>>,
+	replaced: BTreeMap<usize, Vec<u8>>,
+	appended: Vec<Vec<u8>>,
+	recovery: JournalRecovery,
+	/// Set when a commit failed after its journal was written, so the blob file may no longer match `ranges`
+	poisoned: bool
+}
+
+
+impl JournaledBlobFile {
+	/// Opens the blob file at `path`, creating an empty one if it doesn't exist, after finishing or undoing any interrupted commit
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobFileError::Read)` if the blob file is invalid once any journal has been dealt with,
+	/// or `Err(BlobFileError::Io)` if reading or writing either file fails
+	pub fn open(path: &Path) -> Result<Self, BlobFileError> {
+		let mut file = File::options().read(true).write(true).create(true).truncate(false).open(path)?;
+		let journal_path = temp_path_for(path, ".journal");
+
+		let recovery = match fs::read(&journal_path) {
+			Err(e) if e.kind() == io::ErrorKind::NotFound => JournalRecovery::Clean,
+			Err(e) => return Err(e.into()),
+			Ok(journal) => {
+				let recovery = match JournalRecord::decode(&journal) {
+					Some(record) => {
+						record.apply(&mut file)?;
+						JournalRecovery::Replayed
+					},
+					None => JournalRecovery::RolledBack
+				};
+				fs::remove_file(&journal_path)?;
+				sync_parent(&journal_path)?;
+				recovery
+			}
+		};
+
+		let ranges = entry_ranges(&mut file, BlobFormat::NativeEndian)?;
+		Ok(JournaledBlobFile { file, journal_path, ranges, replaced: BTreeMap::new(), appended: Vec::new(), recovery, poisoned: false })
+	}
+
+	/// Returns what `open` found left over from an earlier session
+	pub fn recovery(&self) -> JournalRecovery {
+		self.recovery
+	}
+
+	/// Returns the number of entries, including appends that haven't been committed
+	pub fn len(&self) -> usize {
+		self.ranges.len() + self.appended.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns true if there are changes that haven't been committed
+	pub fn has_pending(&self) -> bool {
+		!self.replaced.is_empty() || !self.appended.is_empty()
+	}
+
+	/// Returns entry `index` as it will be once the pending changes are committed, or `None` if it is out of range
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobFileError::Io)` if reading the blob file fails, or if an earlier commit failed part way, as for `commit`
+	pub fn get(&mut self, index: usize) -> Result<Option<Vec<u8>>, BlobFileError> {
+		self.check_poisoned()?;
+		if let Some(byte_arr) = self.replaced.get(&index).or_else(|| self.appended.get(index.wrapping_sub(self.ranges.len()))) {
+			return Ok(Some(byte_arr.clone()));
+		}
+		match self.ranges.get(index).cloned() {
+			Some(range) => self.read_range(range).map(Some),
+			None => Ok(None)
+		}
+	}
+
+	/// Stages an entry to be added to the end of the blob file
+	pub fn append(&mut self, byte_arr: &[u8]) {
+		self.appended.push(byte_arr.to_vec());
+	}
+
+	/// Stages a new payload for entry `index`, which may be a pending append
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is not less than `len()`
+	pub fn replace(&mut self, index: usize, byte_arr: &[u8]) {
+		assert!(index < self.len(), "replace index {} is out of range for a blob file of {} entries", index, self.len());
+		match index.checked_sub(self.ranges.len()) {
+			Some(appended) => self.appended[appended] = byte_arr.to_vec(),
+			None => {
+				self.replaced.insert(index, byte_arr.to_vec());
+			}
+		}
+	}
+
+	/// Throws away the changes that haven't been committed
+	pub fn discard(&mut self) {
+		self.replaced.clear();
+		self.appended.clear();
+	}
+
+	/// Writes the pending changes to the blob file through the journal. Nothing is written if there are none
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobFileError::Read(BlobReadError::TooLarge))` without writing anything if the blob file would grow past `u32::MAX` bytes,
+	/// or `Err(BlobFileError::Io)` if reading or writing fails. If the error happens after the journal was written, the blob file may be part way
+	/// through being changed, so this handle is poisoned: `commit` and `get` return an error from then on, and opening the file again makes the changes
+	/// from the journal.
+	pub fn commit(&mut self) -> Result<(), BlobFileError> {
+		self.check_poisoned()?;
+		let record = match self.prepare()? {
+			Some(record) => record,
+			None => return Ok(())
+		};
+		self.write_journal(&record)?;
+
+		let applied = record.apply(&mut self.file).and_then(|()| self.clear_journal()).and_then(|()| self.finish_commit());
+		self.poisoned = applied.is_err();
+		applied
+	}
+
+	fn check_poisoned(&self) -> Result<(), BlobFileError> {
+		if self.poisoned {
+			return Err(io::Error::other("an earlier commit failed after writing its journal, so the blob file must be opened again to finish it").into());
+		}
+		Ok(())
+	}
+
+	fn read_range(&mut self, range: Range<u64>) -> Result<Vec<u8>, BlobFileError> {
+		let len = usize::try_from(range.end - range.start).map_err(|_| BlobReadError::TooLarge)?;
+		let mut byte_arr = vec![0u8; len];
+		self.file.seek(SeekFrom::Start(range.start))?;
+		self.file.read_exact(&mut byte_arr)?;
+		Ok(byte_arr)
+	}
+
+	/// Encodes the blob file from the first changed entry onwards, which is what the journal holds
+	fn prepare(&mut self) -> Result<Option<JournalRecord>, BlobFileError> {
+		if !self.has_pending() {
+			return Ok(None);
+		}
+		let first = self.replaced.keys().next().copied().unwrap_or(self.ranges.len());
+		let offset = match self.ranges.get(first) {
+			Some(range) => range.start - 4,
+			None => self.ranges.last().map_or(0, |range| range.end)
+		};
+
+		let mut tail = Vec::new();
+		for index in first..self.len() {
+			let byte_arr = match self.replaced.get(&index).or_else(|| self.appended.get(index.wrapping_sub(self.ranges.len()))) {
+				Some(byte_arr) => byte_arr.clone(),
+				None => self.read_range(self.ranges[index].clone())?
+			};
+			let idx_end = offset + (tail.len() + 4 + byte_arr.len()) as u64;
+			let idx_end = u32::try_from(idx_end).map_err(|_| BlobReadError::TooLarge)?;
+			tail.extend_from_slice(&idx_end.to_ne_bytes());
+			tail.extend_from_slice(&byte_arr);
+		}
+		Ok(Some(JournalRecord { offset, tail }))
+	}
+
+	fn write_journal(&self, record: &JournalRecord) -> Result<(), BlobFileError> {
+		let mut journal = File::create(&self.journal_path)?;
+		journal.write_all(&record.encode())?;
+		journal.sync_all()?;
+		sync_parent(&self.journal_path)
+	}
+
+	fn clear_journal(&self) -> Result<(), BlobFileError> {
+		fs::remove_file(&self.journal_path)?;
+		sync_parent(&self.journal_path)
+	}
+
+	/// Forgets the pending changes now they are in the blob file, and reads its indexes again
+	fn finish_commit(&mut self) -> Result<(), BlobFileError> {
+		self.discard();
+		self.ranges = entry_ranges(&mut self.file, BlobFormat::NativeEndian)?;
+		Ok(())
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn temp_path(name: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("byte-array-blob-{}-journal-{}", std::process::id(), name));
+		let _ = fs::remove_file(temp_path_for(&path, ".journal"));
+		path
+	}
+
+	fn original() -> Vec<u8> {
+		byte_arrays_to_blob(&[b"zero", b"one", &[2u8; 1000], b"three"])
+	}
+
+	fn expected() -> Vec<u8> {
+		byte_arrays_to_blob(&[b"zero", b"ONE!", &[2u8; 1000], b"3", b"four"])
+	}
+
+	/// Opens a copy of `original` and stages the changes that give `expected`
+	fn staged(path: &Path) -> JournaledBlobFile {
+		fs::write(path, original()).unwrap();
+		let mut file = JournaledBlobFile::open(path).unwrap();
+		file.replace(1, b"ONE!");
+		file.append(b"5");
+		file.replace(3, b"3");
+		file.replace(4, b"four");
+		file
+	}
+
+	fn cleanup(path: &Path) {
+		assert!(!temp_path_for(path, ".journal").exists());
+		fs::remove_file(path).unwrap();
+	}
+
+	#[test]
+	fn commit_and_reopen() {
+		let path = temp_path("commit");
+		let mut file = staged(&path);
+		assert_eq!(file.len(), 5);
+		assert_eq!(file.get(1).unwrap(), Some(b"ONE!".to_vec()));
+		assert_eq!(file.get(2).unwrap(), Some(vec![2u8; 1000]));
+		assert_eq!(file.get(5).unwrap(), None);
+		assert_eq!(fs::read(&path).unwrap(), original());
+
+		file.commit().unwrap();
+		assert!(!file.has_pending());
+		assert_eq!(fs::read(&path).unwrap(), expected());
+		assert_eq!(file.get(4).unwrap(), Some(b"four".to_vec()));
+
+		// Only appends, so nothing before them is rewritten
+		file.append(b"five");
+		assert_eq!(file.prepare().unwrap().unwrap().offset, expected().len() as u64);
+		file.commit().unwrap();
+		drop(file);
+
+		let mut file = JournaledBlobFile::open(&path).unwrap();
+		assert_eq!(file.recovery(), JournalRecovery::Clean);
+		assert_eq!(file.len(), 6);
+		assert_eq!(file.get(5).unwrap(), Some(b"five".to_vec()));
+		file.commit().unwrap();
+		cleanup(&path);
+	}
+
+	#[test]
+	fn crash_before_journal_is_synced() {
+		let path = temp_path("torn-journal");
+		let mut file = staged(&path);
+		let journal = file.prepare().unwrap().unwrap().encode();
+		drop(file);
+
+		// Every prefix of the journal is what a crash part way through writing it could leave
+		for len in [0, 3, JOURNAL_HEADER_LEN, journal.len() / 2, journal.len() - 1] {
+			fs::write(temp_path_for(&path, ".journal"), &journal[..len]).unwrap();
+			let file = JournaledBlobFile::open(&path).unwrap();
+			assert_eq!(file.recovery(), JournalRecovery::RolledBack);
+			assert_eq!(file.len(), 4);
+			assert_eq!(fs::read(&path).unwrap(), original());
+		}
+		cleanup(&path);
+	}
+
+	#[test]
+	fn crash_after_journal_is_synced() {
+		let path = temp_path("synced-journal");
+		let mut file = staged(&path);
+		let record = file.prepare().unwrap().unwrap();
+		file.write_journal(&record).unwrap();
+		drop(file);
+
+		let file = JournaledBlobFile::open(&path).unwrap();
+		assert_eq!(file.recovery(), JournalRecovery::Replayed);
+		assert_eq!(file.len(), 5);
+		assert_eq!(fs::read(&path).unwrap(), expected());
+		cleanup(&path);
+	}
+
+	#[test]
+	fn crash_while_applying() {
+		let path = temp_path("half-applied");
+		let mut file = staged(&path);
+		let record = file.prepare().unwrap().unwrap();
+		file.write_journal(&record).unwrap();
+		drop(file);
+
+		// Half of the new bytes reached the blob file, which is now invalid until the journal is replayed
+		let mut half_written = original();
+		half_written.truncate(record.offset as usize);
+		half_written.extend_from_slice(&record.tail[..record.tail.len() / 2]);
+		fs::write(&path, &half_written).unwrap();
+		assert!(blob_to_byte_arrays(&half_written).is_err());
+
+		let file = JournaledBlobFile::open(&path).unwrap();
+		assert_eq!(file.recovery(), JournalRecovery::Replayed);
+		assert_eq!(fs::read(&path).unwrap(), expected());
+		cleanup(&path);
+	}
+
+	#[test]
+	fn crash_before_journal_is_cleared() {
+		let path = temp_path("applied");
+		let mut file = staged(&path);
+		let record = file.prepare().unwrap().unwrap();
+		file.write_journal(&record).unwrap();
+		record.apply(&mut file.file).unwrap();
+		drop(file);
+
+		// Applying the journal a second time changes nothing
+		let file = JournaledBlobFile::open(&path).unwrap();
+		assert_eq!(file.recovery(), JournalRecovery::Replayed);
+		assert_eq!(fs::read(&path).unwrap(), expected());
+		cleanup(&path);
+	}
+
+	#[test]
+	fn failed_apply_poisons_the_handle() {
+		let path = temp_path("failed-apply");
+		let mut file = staged(&path);
+
+		// A read-only handle fails to write the new bytes once the journal is on disk
+		file.file = File::open(&path).unwrap();
+		assert!(matches!(file.commit(), Err(BlobFileError::Io(_))));
+		assert!(temp_path_for(&path, ".journal").exists());
+
+		// The indexes in memory may no longer match, so nothing more is read or committed through this handle
+		assert!(matches!(file.commit(), Err(BlobFileError::Io(_))));
+		assert!(matches!(file.get(0), Err(BlobFileError::Io(_))));
+		drop(file);
+
+		let mut file = JournaledBlobFile::open(&path).unwrap();
+		assert_eq!(file.recovery(), JournalRecovery::Replayed);
+		assert_eq!(fs::read(&path).unwrap(), expected());
+		assert_eq!(file.get(1).unwrap(), Some(b"ONE!".to_vec()));
+		cleanup(&path);
+	}
+
+	#[test]
+	fn shrinking_replace() {
+		let path = temp_path("shrink");
+		fs::write(&path, original()).unwrap();
+		let mut file = JournaledBlobFile::open(&path).unwrap();
+		file.replace(2, b"");
+		file.commit().unwrap();
+		assert_eq!(blob_to_byte_arrays(&fs::read(&path).unwrap()).unwrap(), [&b"zero"[..], b"one", b"", b"three"]);
+
+		file.replace(0, b"x");
+		file.discard();
+		file.commit().unwrap();
+		assert_eq!(file.get(0).unwrap(), Some(b"zero".to_vec()));
+		cleanup(&path);
+	}
+}
@@ -19,32 +19,329 @@
 //! assert_eq!(slices[0], data1);
 //! assert_eq!(slices[1], data2);
 //! ```
+#![allow(clippy::tabs_in_doc_comments)]
 use std::fmt;
 
+mod decode;
+pub use decode::*;
+mod reorder;
+pub use reorder::*;
+mod typed;
+pub use typed::*;
+mod archive;
+pub use archive::*;
+mod stream;
+pub use stream::*;
+mod decoder;
+pub use decoder::*;
+mod view;
+pub use view::*;
+mod blob;
+pub use blob::*;
+mod combine;
+pub use combine::*;
+mod format;
+pub use format::*;
+mod dedup;
+pub use dedup::*;
+mod error;
+pub use error::Error;
+mod packbits;
+mod compressed;
+pub use compressed::*;
+mod progress;
+pub use progress::*;
+mod recover;
+pub use recover::*;
+mod padded;
+pub use padded::*;
+mod numeric;
+pub use numeric::*;
+mod validated;
+pub use validated::*;
+mod crc32;
+mod verify;
+pub use verify::*;
+mod cursor;
+pub use cursor::*;
+mod options;
+pub use options::*;
+mod sparse;
+pub use sparse::*;
+mod named;
+pub use named::*;
+mod multi;
+pub use multi::*;
+mod fixed;
+pub use fixed::*;
+mod sidecar;
+pub use sidecar::*;
+mod frame;
+pub use frame::*;
+mod builder;
+pub use builder::*;
+mod overlay;
+pub use overlay::*;
+mod slab;
+pub use slab::*;
+mod filter;
+pub use filter::*;
+mod parse;
+pub use parse::*;
+mod payload;
+pub use payload::*;
+mod sniff;
+pub use sniff::*;
+mod sections;
+pub use sections::*;
+mod display;
+pub use display::*;
+mod versioned;
+pub use versioned::*;
+mod const_decode;
+pub use const_decode::*;
+mod channel;
+pub use channel::*;
+mod interned;
+pub use interned::*;
+mod sha256;
+mod digest;
+pub use digest::*;
+mod checkpoint;
+pub use checkpoint::*;
+mod arena;
+pub use arena::*;
+mod codec;
+pub use codec::*;
+mod interleave;
+pub use interleave::*;
+mod split;
+pub use split::*;
+mod aligned;
+pub use aligned::*;
+mod record;
+pub use record::*;
+mod normalize;
+pub use normalize::*;
+mod tagged;
+pub use tagged::*;
+mod variant;
+pub use variant::*;
+mod flagged;
+pub use flagged::*;
+mod shared;
+pub use shared::*;
+mod transform;
+pub use transform::*;
+mod iter;
+pub use iter::*;
+mod encrypted;
+pub use encrypted::*;
+mod coalesce;
+pub use coalesce::*;
+mod provenance;
+pub use provenance::*;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+#[cfg(feature = "testutil")]
+pub mod testutil;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "interop")]
+mod interop;
+#[cfg(feature = "interop")]
+pub use interop::*;
+#[cfg(feature = "fs")]
+mod file;
+#[cfg(feature = "fs")]
+pub use file::*;
+#[cfg(feature = "fs")]
+mod journal;
+#[cfg(feature = "fs")]
+pub use journal::*;
+#[cfg(feature = "fs")]
+mod inspect;
+#[cfg(feature = "fs")]
+pub use inspect::*;
+
 
 /// Errors that can occur when reading a blob u8 slice
-#[derive(Debug)]
+///
+/// New variants and fields may be added as the diagnostics improve. To branch on an error, match on `kind()` and read its details
+/// through the accessor methods, which keep working when the variants change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum BlobReadError {
 	/// Occurs when a given index is invalid, either being outside the size of the slice, or pointing to earlier data.
 	/// The enum yields the offending index
 	///
+	/// The first index of a blob is reported as `NotABlob` instead, since a first index that doesn't fit almost always means the data is something else.
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::{blob_to_byte_arrays, byte_arrays_to_blob, BlobErrorKind};
+	///
+	/// // Make the second index point to u32::MAX, which is larger than the slice size
+	/// let mut blob = byte_arrays_to_blob(&[b"first", b"second"]);
+	/// blob[9..13].copy_from_slice(&u32::MAX.to_ne_bytes());
+	/// let error = blob_to_byte_arrays(&blob).unwrap_err();
+	/// assert_eq!(error.kind(), BlobErrorKind::InvalidIndex);
+	/// assert_eq!(error.offending_index(), Some(u32::MAX as usize));
+	/// ```
+	InvalidEncodedIndex(usize),
+	/// Occurs when the slice ends part way through a 4 byte index.
+	/// The enum yields the position of the truncated index
+	///
 	/// # Examples
 	/// ```
-	/// use byte_array_blob::{blob_to_byte_arrays, BlobReadError};
+	/// use byte_array_blob::{blob_to_byte_arrays, BlobErrorKind};
+	///
+	/// // One valid entry, followed by 2 bytes of a second index
+	/// let blob = [5u8, 0u8, 0u8, 0u8, 1u8, 9u8, 0u8];
+	/// let error = blob_to_byte_arrays(&blob).unwrap_err();
+	/// assert_eq!(error.kind(), BlobErrorKind::Truncated);
+	/// assert_eq!(error.byte_offset(), Some(5));
+	/// ```
+	TruncatedIndex(usize),
+	/// Occurs when a formatted blob (such as a typed blob) contains a name or tag that is not valid UTF-8.
+	/// The enum yields the index of the offending entry
+	InvalidUtf8(usize),
+	/// Occurs when a formatted blob that stores several slices per entry (such as a typed blob) ends with an incomplete entry.
+	/// The enum yields the index of the incomplete entry
+	UnpairedEntry(usize),
+	/// Occurs when the metadata of an entry in a formatted blob (such as an archive or compressed blob) is malformed.
+	/// The enum yields the index of the offending entry
+	InvalidMetadata(usize),
+	/// Occurs when a compressed entry can not be decompressed, or decompresses to a different length than was recorded.
+	/// The enum yields the index of the offending entry
+	InvalidCompressedData(usize),
+	/// Occurs when a bounded decode would copy more bytes than its budget allows.
+	/// `needed` is the total that copying entry `at_entry` would have required
+	BudgetExceeded {
+		at_entry: usize,
+		needed: usize,
+		budget: usize
+	},
+	/// Occurs when a blob would decode to more entries than `DecodeOptions` allow.
+	/// The enum yields the limit
+	TooManyEntries(usize),
+	/// Occurs when one of several blobs read together (such as the parts of a `MultiBlobView`) is invalid.
+	/// The enum yields the index of the part and the error reading it
+	InPart {
+		part: usize,
+		error: Box<BlobReadError>
+	},
+	/// Occurs when a blob can not be read in any known format
+	UnknownFormat,
+	/// Occurs when the first index of a blob points before the end of the index or past the end of the blob, so the data is very likely not a blob at all.
+	/// The enum yields where the first index says its slice ends, or `u32::MAX` if that is larger
+	///
+	/// This is reported instead of `InvalidEncodedIndex` for the first index only, to tell a file of some other kind apart from a damaged blob.
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::{blob_to_byte_arrays, BlobErrorKind, BlobReadError};
 	///
 	/// // The first four bytes point to u32::MAX, which is larger than the slice size
 	/// let blob = [255u8, 255u8, 255u8, 255u8];
-	/// match blob_to_byte_arrays(&blob).unwrap_err() {
-	/// 	BlobReadError::InvalidEncodedIndex(idx) => assert_eq!(idx, u32::MAX as usize),
-	/// 	_ => panic!("This should not happen")
-	/// }
+	/// let error = blob_to_byte_arrays(&blob).unwrap_err();
+	/// assert_eq!(error, BlobReadError::NotABlob { first_header: u32::MAX });
+	/// assert_eq!(error.kind(), BlobErrorKind::UnknownFormat);
+	/// assert_eq!(error.offending_index(), Some(u32::MAX as usize));
 	/// ```
-	InvalidEncodedIndex(usize),
+	NotABlob {
+		first_header: u32
+	},
+	/// Occurs when a tagged blob was written with a different schema tag than the reader expected, before any entry is read.
+	/// The enum yields the expected tag and the tag in the blob
+	SchemaTagMismatch {
+		expected: u64,
+		found: u64
+	},
+	/// Occurs when a blob read as a tagged blob has no schema tag header
+	MissingSchemaTag,
+	/// Occurs when a blob read as a flagged blob doesn't start with the flagged blob magic
+	NotFlagged,
+	/// Occurs when a flagged blob is given to a function that would treat its header and flags bytes as payload, such as the provenance repacks
+	UnexpectedFlagged,
+	/// Occurs when a fallible decode such as `blob_to_byte_vecs_try` can't allocate memory for its result.
+	/// `requested` is the size in bytes of the allocation that failed
+	AllocationFailed {
+		requested: usize
+	},
+	/// Occurs when an index is too large for the platform's usize, which can only happen on targets where usize is narrower than u32 (such as 16-bit microcontrollers).
+	/// The blob may be valid on a wider target. The enum yields the index as it was encoded
+	IndexUnrepresentable {
+		encoded: u32
+	},
 	/// Occurs when the given slice is larger than `u32::MAX`. This is highly unlikely to occur
 	TooLarge
 }
 
 
+impl BlobReadError {
+	/// Returns the broad category of the error, which stays the same however the detailed variants change
+	///
+	/// An error in one part of several blobs read together has the kind of the error in that part.
+	pub fn kind(&self) -> BlobErrorKind {
+		match self {
+			BlobReadError::InvalidEncodedIndex(_) | BlobReadError::IndexUnrepresentable { .. } => BlobErrorKind::InvalidIndex,
+			BlobReadError::TruncatedIndex(_) => BlobErrorKind::Truncated,
+			BlobReadError::InvalidUtf8(_)
+			| BlobReadError::UnpairedEntry(_)
+			| BlobReadError::InvalidMetadata(_)
+			| BlobReadError::InvalidCompressedData(_) => BlobErrorKind::InvalidEntry,
+			BlobReadError::BudgetExceeded { .. } | BlobReadError::TooManyEntries(_) => BlobErrorKind::LimitExceeded,
+			BlobReadError::InPart { error, .. } => error.kind(),
+			BlobReadError::UnknownFormat | BlobReadError::NotABlob { .. } => BlobErrorKind::UnknownFormat,
+			BlobReadError::SchemaTagMismatch { .. } | BlobReadError::MissingSchemaTag | BlobReadError::NotFlagged | BlobReadError::UnexpectedFlagged => BlobErrorKind::SchemaMismatch,
+			BlobReadError::AllocationFailed { .. } => BlobErrorKind::OutOfMemory,
+			BlobReadError::TooLarge => BlobErrorKind::TooLarge
+		}
+	}
+
+	/// Returns the invalid index that was read from the blob, if the error is an invalid index that fits in usize
+	pub fn offending_index(&self) -> Option<usize> {
+		match self {
+			BlobReadError::InvalidEncodedIndex(idx) => Some(*idx),
+			BlobReadError::NotABlob { first_header } => usize::try_from(*first_header).ok(),
+			BlobReadError::InPart { error, .. } => error.offending_index(),
+			_ => None
+		}
+	}
+
+	/// Returns the position in the blob of an index that was cut short, if the error is a truncated index
+	pub fn byte_offset(&self) -> Option<usize> {
+		match self {
+			BlobReadError::TruncatedIndex(pos) => Some(*pos),
+			BlobReadError::InPart { error, .. } => error.byte_offset(),
+			_ => None
+		}
+	}
+
+	/// Returns the index of the entry the error happened at, if the error is tied to one entry
+	pub fn entry_number(&self) -> Option<usize> {
+		match self {
+			BlobReadError::InvalidUtf8(entry)
+			| BlobReadError::UnpairedEntry(entry)
+			| BlobReadError::InvalidMetadata(entry)
+			| BlobReadError::InvalidCompressedData(entry) => Some(*entry),
+			BlobReadError::BudgetExceeded { at_entry, .. } => Some(*at_entry),
+			BlobReadError::InPart { error, .. } => error.entry_number(),
+			_ => None
+		}
+	}
+
+	/// Returns the index of the part the error happened in, if several blobs were being read together
+	pub fn part(&self) -> Option<usize> {
+		match self {
+			BlobReadError::InPart { part, .. } => Some(*part),
+			_ => None
+		}
+	}
+}
+
+
 impl std::error::Error for BlobReadError {}
 
 impl fmt::Display for BlobReadError {
@@ -54,57 +351,80 @@ impl fmt::Display for BlobReadError {
 }
 
 
-/// Converts a blob u8 slice to a Vec of u8 slices
-///
-/// # Errors
-///
-/// The function returns `Err(BlobReadError)` when an index in the data is invalid, or when the length of the data is more than `u32::MAX`
-///
-/// # Examples
-///
-/// ```
-/// use byte_array_blob::blob_to_byte_arrays;
+/// The broad category of a `BlobReadError`, returned by `BlobReadError::kind`
 ///
-/// // The first four bytes in the blob point to the end of the slice (index 8)
-/// let blob = [8u8, 0u8, 0u8, 0u8, 255u8, 0u8, 0u8, 0u8];
-/// let read_blob = byte_array_blob::blob_to_byte_arrays(&blob);
-///
-/// assert!(read_blob.is_ok());
-/// if let Ok(data) = read_blob {
-/// 	assert_eq!(data[0], &[255, 0, 0, 0]);
-/// }
-/// ```
-pub fn blob_to_byte_arrays(blob: &[u8]) -> Result<Vec<&[u8]>, BlobReadError> {
-	// It is difficult for this case to occur
-	if blob.len() > u32::MAX as usize {
-		return Err(BlobReadError::TooLarge);
-	}
+/// Kinds only describe what went wrong, with no details attached, so they can be matched on without breaking when `BlobReadError` changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BlobErrorKind {
+	/// An index points outside the blob or before the data it follows
+	InvalidIndex,
+	/// The blob ends part way through an index
+	Truncated,
+	/// The indexes are valid but an entry of a formatted blob is malformed
+	InvalidEntry,
+	/// A limit set by `DecodeOptions` or a byte budget was reached
+	LimitExceeded,
+	/// The blob is not in any known format
+	UnknownFormat,
+	/// The blob has no schema tag, a different one than was expected, or is or isn't a flagged blob when the other was expected
+	SchemaMismatch,
+	/// Memory for the decoded entries couldn't be allocated
+	OutOfMemory,
+	/// The blob is larger than `u32::MAX` bytes
+	TooLarge
+}
 
-	let mut idx_end = 0;
-	let mut idx_start = 0;
 
-	let mut byte_arrays = Vec::new();
+/// Errors that can occur when reading or writing blobs on the filesystem
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BlobFileError {
+	/// An error from the filesystem
+	Io(std::io::Error),
+	/// The blob being read is invalid
+	Read(BlobReadError),
+	/// A file name can't be stored in an archive on this platform, or a path would escape the directory it is being unpacked to.
+	/// The enum yields the offending path, lossily converted to UTF-8
+	UnsafePath(String)
+}
 
-	// Read slices while the index is within bounds
-	while idx_end < blob.len() {
-		// Get encoded index as 4 bytes
-		let idx_end_data = &blob[idx_start..idx_start+4];
-		let idx_end_data: [u8; 4] = idx_end_data.try_into().unwrap();
 
-		// Transform 4 bytes into u32, then usize
-		idx_end = u32::from_ne_bytes(idx_end_data) as usize;
-		idx_start += 4;
+#[cfg(feature = "fs")]
+impl std::error::Error for BlobFileError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			BlobFileError::Io(e) => Some(e),
+			BlobFileError::Read(e) => Some(e),
+			BlobFileError::UnsafePath(_) => None
+		}
+	}
+}
 
-		// If the index end is invalid (more than the blob length, or less than the slice start) then return Err
-		if idx_end > blob.len() || idx_end < idx_start {
-			return Err(BlobReadError::InvalidEncodedIndex(idx_end));
+#[cfg(feature = "fs")]
+impl fmt::Display for BlobFileError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BlobFileError::Io(e) => write!(formatter, "{}", e),
+			BlobFileError::Read(e) => write!(formatter, "{}", e),
+			BlobFileError::UnsafePath(path) => write!(formatter, "unsafe path {:?}", path)
 		}
+	}
+}
+
+#[cfg(feature = "fs")]
+impl From<std::io::Error> for BlobFileError {
+	fn from(e: std::io::Error) -> Self {
+		BlobFileError::Io(e)
+	}
+}
 
-		// Push slice to byte_arrays
-		byte_arrays.push(&blob[idx_start..idx_end]);
-		idx_start = idx_end;
+#[cfg(feature = "fs")]
+impl From<BlobReadError> for BlobFileError {
+	fn from(e: BlobReadError) -> Self {
+		BlobFileError::Read(e)
 	}
-	Ok(byte_arrays)
 }
 
 
@@ -113,6 +433,10 @@ pub fn blob_to_byte_arrays(blob: &[u8]) -> Result<Vec<&[u8]>, BlobReadError> {
 /// An additional 4 bytes of data are allocated to the Vec for each slice, and are used to keep track of the indexes.
 /// So the length of the Vec will be equal to the sum of the slices' lengths + 4*N, where N is the number of slices.
 ///
+/// # Panics
+///
+/// Panics if the blob would be larger than `u32::MAX` bytes, since its indexes could not be stored
+///
 /// # Examples
 /// ```
 /// use byte_array_blob::*;
@@ -128,24 +452,42 @@ pub fn blob_to_byte_arrays(blob: &[u8]) -> Result<Vec<&[u8]>, BlobReadError> {
 /// assert_eq!(blob.len(), checksum_encoded);
 pub fn byte_arrays_to_blob(bytes_2d: &[&[u8]]) -> Vec<u8> {
 	let mut idx_end = 0;
-	let mut blob: Vec<u8> = Vec::new();
+	let mut blob: Vec<u8> = Vec::with_capacity(encoded_len(bytes_2d));
 
 	for byte_arr in bytes_2d {
 		idx_end += 4 + byte_arr.len();
-		let write_end_idx: [u8; 4] = (idx_end as u32).to_ne_bytes();
+		let write_end_idx: [u8; 4] = u32::try_from(idx_end).expect("blob larger than u32::MAX bytes").to_ne_bytes();
 		blob.extend(write_end_idx);
-		blob.extend(byte_arr.into_iter());
+		blob.extend(*byte_arr);
 	}
 	blob
 }
 
 
+/// Returns the length of the blob that `byte_arrays_to_blob` would produce for the given slices, without encoding them.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let data1 = [1, 2, 3, 4, 5];
+/// let data2 = [8; 64];
+///
+/// let len = encoded_len(&[&data1, &data2]);
+/// assert_eq!(len, byte_arrays_to_blob(&[&data1, &data2]).len());
+/// ```
+pub fn encoded_len(bytes_2d: &[&[u8]]) -> usize {
+	bytes_2d.iter().map(|byte_arr| 4 + byte_arr.len()).sum()
+}
+
+
 
 
 #[cfg(test)]
 #[allow(unused)]
 mod tests {
     use super::*;
+    use crate::test_support::*;
 
 	#[test]
 	fn bytes_blob_test() {
@@ -159,20 +501,318 @@ mod tests {
 		assert_eq!(blob_to_byte_arrays(&blob).unwrap().len(), 2);
 	}
 
+	#[test]
+	fn other_formats_are_not_blobs() {
+		let headers: [&[u8]; 3] = [b"\x89PNG\r\n\x1a\n", b"PK\x03\x04", b"\x7fELF\x02\x01\x01\x00"];
+		for header in headers {
+			let file = [header, &[0u8; 64]].concat();
+			let first_header = u32::from_ne_bytes(file[..4].try_into().unwrap());
+			assert!(!looks_like_blob(&file));
+			assert_eq!(blob_to_byte_arrays(&file), Err(BlobReadError::NotABlob { first_header }));
+			assert_eq!(count_arrays(&file), Err(BlobReadError::NotABlob { first_header }));
+		}
+
+		assert!(looks_like_blob(&[]));
+		assert!(!looks_like_blob(&[4, 0, 0]));
+		assert!(!looks_like_blob(&0u32.to_ne_bytes()));
+		assert!(looks_like_blob(&byte_arrays_to_blob(&[&[], b"one"])));
+
+		// Only the first index is reported as NotABlob
+		let mut blob = byte_arrays_to_blob(&[b"one", b"two"]);
+		blob[7..11].copy_from_slice(&0u32.to_ne_bytes());
+		assert!(looks_like_blob(&blob));
+		assert_eq!(blob_to_byte_arrays(&blob), Err(BlobReadError::InvalidEncodedIndex(0)));
+	}
+
 	#[test]
 	fn blob_read_invalid_index() {
 		let blob = vec![255u8, 255u8, 255u8, 255u8];
 		let read = blob_to_byte_arrays(&blob);
 
 		if let Err(e) = blob_to_byte_arrays(&blob) {
-			match e {
-				BlobReadError::InvalidEncodedIndex(idx) => { assert_eq!(idx, u32::MAX as usize) },
-				_ => { panic!("Error should be BlobReadError::InvalidEncodedIndex") }
-			}
+			assert_eq!(e.kind(), BlobErrorKind::UnknownFormat);
+			assert_eq!(e.offending_index(), Some(u32::MAX as usize));
 		}
 
 		else {
 			panic!("blob_to_byte_arrays() should return Err for invalid data");
 		}
 	}
+
+	#[test]
+	fn blob_read_truncated_index() {
+		let mut blob = byte_arrays_to_blob(&[&[1u8, 2u8, 3u8]]);
+
+		// Every cut through the second index must error instead of panicking
+		for extra in 1..4 {
+			blob.push(0u8);
+			let error = blob_to_byte_arrays(&blob).unwrap_err();
+			assert_eq!(error.kind(), BlobErrorKind::Truncated, "for {} extra bytes", extra);
+			assert_eq!(error.byte_offset(), Some(7));
+		}
+	}
+
+	#[test]
+	fn budgeted_iteration_matches_full_decode() {
+		let entries: Vec<Vec<u8>> = (0..10_000u32).map(|i| i.to_le_bytes()[..i as usize % 5].to_vec()).collect();
+		let blob = entries_to_blob(&entries);
+		let full = blob_to_byte_arrays(&blob).unwrap();
+
+		for budget in [1, 7, 1000] {
+			let mut iter = blob_iter(&blob);
+			let mut decoded = Vec::new();
+			let mut calls = 0;
+			while !iter.is_finished() {
+				let before = decoded.len();
+				decoded.extend(iter.by_budget(budget).map(|byte_arr| byte_arr.unwrap()));
+				assert!(decoded.len() - before <= budget);
+				calls += 1;
+			}
+			assert_eq!(decoded, full);
+			assert_eq!(calls, full.len().div_ceil(budget));
+		}
+
+		// A budget that reaches an error stops there, and the iterator is finished
+		let mut iter = blob_iter(&blob[..blob.len() - 1]);
+		while iter.by_budget(1000).all(|byte_arr| byte_arr.is_ok()) {}
+		assert!(iter.is_finished());
+		assert_eq!(iter.by_budget(1000).count(), 0);
+	}
+
+	#[test]
+	fn blob_iter_stops_after_error() {
+		let blob = [5u8, 0u8, 0u8, 0u8, 1u8, 255u8, 0u8, 0u8, 0u8];
+		let mut iter = blob_iter(&blob);
+
+		assert_eq!(iter.next().unwrap().unwrap(), &[1u8]);
+		assert!(iter.next().unwrap().is_err());
+		assert!(iter.next().is_none());
+	}
+
+	#[test]
+	fn position_of_prefix_does_not_match() {
+		let blob = byte_arrays_to_blob(&[&[1u8, 2u8, 3u8], &[9u8], &[1u8, 2u8]]);
+
+		assert_eq!(blob_position_of(&blob, &[1u8, 2u8]).unwrap(), Some(2));
+		assert_eq!(blob_position_of(&blob, &[1u8]).unwrap(), None);
+		assert!(!blob_contains(&blob, &[2u8, 3u8]).unwrap());
+		assert!(blob_contains(&blob, &[9u8]).unwrap());
+	}
+
+	#[test]
+	fn position_of_zero_length_needle() {
+		let blob = byte_arrays_to_blob(&[&[1u8], &[], &[2u8], &[]]);
+		assert_eq!(blob_position_of(&blob, &[]).unwrap(), Some(1));
+		assert_eq!(blob_positions_of(&blob, &[]).unwrap(), vec![1, 3]);
+
+		let no_empty = byte_arrays_to_blob(&[&[1u8], &[2u8]]);
+		assert_eq!(blob_position_of(&no_empty, &[]).unwrap(), None);
+	}
+
+	#[test]
+	fn positions_of_reports_errors_after_matches() {
+		let mut blob = byte_arrays_to_blob(&[&[1u8], &[1u8]]);
+		blob.push(0u8);
+
+		assert_eq!(blob_position_of(&blob, &[1u8]).unwrap(), Some(0));
+		assert!(blob_positions_of(&blob, &[1u8]).is_err());
+	}
+
+	#[test]
+	fn bounded_decode_trips_on_third_entry() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 4], &[2u8; 4], &[3u8; 100], &[4u8; 1]]);
+
+		let error = blob_to_byte_vecs_bounded(&blob, 10).unwrap_err();
+		assert_eq!(error.kind(), BlobErrorKind::LimitExceeded);
+		assert_eq!(error.entry_number(), Some(2));
+		assert_eq!(error, BlobReadError::BudgetExceeded { at_entry: 2, needed: 108, budget: 10 });
+
+		assert_eq!(blob_to_byte_vecs_bounded(&blob, 109).unwrap(), blob_to_byte_vecs(&blob).unwrap());
+	}
+
+	#[test]
+	fn fallible_decodes_report_failed_allocations() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 4], &[2u8; 300], &[]]);
+		assert_eq!(blob_to_byte_vecs_try(&blob).unwrap(), blob_to_byte_vecs(&blob).unwrap());
+
+		// The limit makes any reservation over 256 bytes fail, as a real allocator would when memory runs out
+		decode::ALLOCATION_LIMIT.set(256);
+		let error = blob_to_byte_vecs_try(&blob).unwrap_err();
+		assert_eq!(error, BlobReadError::AllocationFailed { requested: 300 });
+		assert_eq!(error.kind(), BlobErrorKind::OutOfMemory);
+		assert_eq!(blob_to_byte_vecs_try(&blob[..blob.len() - 1]), Err(BlobReadError::TruncatedIndex(blob.len() - 4)));
+
+		let many = byte_arrays_to_blob(&[&[][..]; 100]);
+		let slices_len = 100 * size_of::<&[u8]>();
+		assert_eq!(blob_to_byte_arrays(&many), Err(BlobReadError::AllocationFailed { requested: slices_len }));
+		decode::ALLOCATION_LIMIT.set(usize::MAX);
+		assert_eq!(blob_to_byte_arrays(&many).unwrap().len(), 100);
+
+		// A reservation no allocator could make fails without the limit
+		let mut huge = Vec::<u64>::new();
+		assert_eq!(decode::try_reserve_exact(&mut huge, usize::MAX / 2), Err(BlobReadError::AllocationFailed { requested: usize::MAX }));
+		assert!(decode::try_reserve_exact(&mut huge, isize::MAX as usize / 8 - 1).is_err());
+	}
+
+	#[test]
+	fn truncate_to_each_count() {
+		let slices: [&[u8]; 4] = [b"one", &[], &[9u8; 50], b"four"];
+		let blob = byte_arrays_to_blob(&slices);
+
+		for keep in 0..7 {
+			let mut truncated = blob.clone();
+			let len = blob_truncate_entries(&mut truncated, keep).unwrap();
+			assert_eq!(len, truncated.len());
+			assert_eq!(blob_to_byte_arrays(&truncated).unwrap(), slices[..keep.min(4)]);
+		}
+
+		// Damage after the cut is dropped, damage before it is reported and leaves the blob alone
+		let mut damaged = blob.clone();
+		damaged.extend([255u8; 6]);
+		assert_eq!(blob_truncate_entries(&mut damaged.clone(), 4), Ok(blob.len()));
+		assert_eq!(blob_truncate_entries(&mut damaged.clone(), 5).unwrap_err().kind(), BlobErrorKind::InvalidIndex);
+		let before = damaged.clone();
+		assert!(blob_truncate_entries(&mut damaged, 9).is_err());
+		assert_eq!(damaged, before);
+	}
+
+	#[test]
+	fn error_kinds_and_accessors() {
+		let nested = BlobReadError::InPart { part: 3, error: Box::new(BlobReadError::InvalidMetadata(8)) };
+		assert_eq!(nested.kind(), BlobErrorKind::InvalidEntry);
+		assert_eq!((nested.part(), nested.entry_number()), (Some(3), Some(8)));
+		assert_eq!((nested.offending_index(), nested.byte_offset()), (None, None));
+
+		assert_eq!(BlobReadError::TooManyEntries(5).kind(), BlobErrorKind::LimitExceeded);
+		assert_eq!(BlobReadError::TooManyEntries(5).entry_number(), None);
+		assert_eq!(BlobReadError::UnknownFormat.kind(), BlobErrorKind::UnknownFormat);
+		assert_eq!(BlobReadError::TooLarge.kind(), BlobErrorKind::TooLarge);
+		assert_eq!(BlobReadError::TooLarge.part(), None);
+	}
+
+	/// A stack only collection of up to 4 slices, standing in for `ArrayVec` or `heapless::Vec`
+	#[derive(Default)]
+	struct FourSlices<'a> {
+		items: [&'a [u8]; 4],
+		len: usize
+	}
+
+	impl<'a> Extend<&'a [u8]> for FourSlices<'a> {
+		fn extend<I: IntoIterator<Item = &'a [u8]>>(&mut self, iter: I) {
+			for item in iter {
+				self.items[self.len] = item;
+				self.len += 1;
+			}
+		}
+	}
+
+	#[test]
+	fn decode_into_collections() {
+		let slices: [&[u8]; 3] = [&[1u8], &[], &[2u8, 2u8]];
+		let blob = byte_arrays_to_blob(&slices);
+
+		let vec: Vec<&[u8]> = blob_to_collection(&blob).unwrap();
+		assert_eq!(vec, slices);
+
+		let queue: std::collections::VecDeque<&[u8]> = blob_to_collection(&blob).unwrap();
+		assert_eq!(queue, slices);
+
+		let fixed: FourSlices = blob_to_collection(&blob).unwrap();
+		assert_eq!(&fixed.items[..fixed.len], slices);
+
+		let set: std::collections::BTreeSet<&[u8]> = blob_to_collection(&blob).unwrap();
+		assert_eq!(set.len(), 3);
+
+		assert!(blob_to_collection::<Vec<&[u8]>>(&blob[..blob.len() - 1]).is_err());
+	}
+
+	#[test]
+	fn zero_length_boundaries() {
+		let one: &[u8] = &[7u8];
+		let mut cases: Vec<Vec<&[u8]>> = vec![vec![]];
+		for n in 1..6 {
+			cases.push(vec![&[]; n]);
+			cases.push([vec![&[][..]; n], vec![one]].concat());
+			cases.push([vec![one], vec![&[][..]; n]].concat());
+			cases.push([vec![&[][..]; n], vec![one], vec![&[][..]; n]].concat());
+		}
+
+		for slices in cases {
+			let blob = byte_arrays_to_blob(&slices);
+			assert_eq!(blob.len(), encoded_len(&slices));
+			assert_eq!(blob.is_empty(), slices.is_empty());
+
+			// Every decoder agrees on the exact number of slices
+			assert_eq!(blob_to_byte_arrays(&blob).unwrap(), slices);
+			assert_eq!(count_arrays(&blob).unwrap(), slices.len());
+			assert_eq!(blob_iter(&blob).count(), slices.len());
+			assert_eq!(blob_to_byte_vecs(&blob).unwrap().len(), slices.len());
+			assert_eq!(BlobView::new(&blob).unwrap().len(), slices.len());
+			assert_eq!(Blob::from_bytes(blob.clone()).unwrap().len(), slices.len());
+			assert_eq!(BlobReader::new(&blob[..]).count(), slices.len());
+
+			// Cutting the blob at a slice boundary keeps exactly the slices before the cut
+			let view = BlobView::new(&blob).unwrap();
+			for (i, &end) in view.ends().iter().enumerate() {
+				assert_eq!(count_arrays(&blob[..end]).unwrap(), i + 1);
+			}
+		}
+	}
+
+	#[test]
+	fn entry_cap() {
+		// Nothing but empty slices, the most entries a blob of this size can hold
+		let many_empty = |count: usize| -> Vec<u8> {
+			let mut blob = vec![0u8; count * 4];
+			for (i, index) in blob.chunks_exact_mut(4).enumerate() {
+				index.copy_from_slice(&((i + 1) as u32 * 4).to_ne_bytes());
+			}
+			blob
+		};
+
+		let blob = many_empty(1000);
+		assert_eq!(blob_to_byte_arrays_with_options(&blob, &DecodeOptions::new().with_max_entries(1000)).unwrap().len(), 1000);
+		assert_eq!(blob_to_byte_arrays_with_options(&blob, &DecodeOptions::new().with_max_entries(999)), Err(BlobReadError::TooManyEntries(999)));
+		assert_eq!(blob_to_byte_arrays_with_options(&blob, &DecodeOptions::new().with_max_entries(0)), Err(BlobReadError::TooManyEntries(0)));
+		assert_eq!(blob_to_byte_arrays_with_options(&[], &DecodeOptions::new().with_max_entries(0)).unwrap().len(), 0);
+
+		// A corrupt index is reported the same way whether or not the cap is reached first
+		let mut corrupt = blob.clone();
+		corrupt[400] = 0xFF;
+		assert_eq!(blob_to_byte_arrays_with_options(&corrupt, &DecodeOptions::new().with_max_entries(usize::MAX)), blob_to_byte_arrays(&corrupt));
+		assert_eq!(blob_to_byte_arrays_with_options(&corrupt, &DecodeOptions::new().with_max_entries(10)), Err(BlobReadError::TooManyEntries(10)));
+
+		// blob_to_byte_arrays stops at the default cap, one slice past it
+		let over_default = many_empty(DecodeOptions::DEFAULT_MAX_ENTRIES + 1);
+		assert_eq!(blob_to_byte_arrays(&over_default), Err(BlobReadError::TooManyEntries(DecodeOptions::DEFAULT_MAX_ENTRIES)));
+	}
+
+	#[test]
+	fn encoded_len_matches_blob() {
+		let empty: &[&[u8]] = &[];
+		assert_eq!(encoded_len(empty), 0);
+		assert_eq!(encoded_len(&[&[], &[1u8]]), byte_arrays_to_blob(&[&[], &[1u8]]).len());
+	}
+
+	#[test]
+	fn get_many_keeps_caller_order() {
+		let entries: Vec<Vec<u8>> = (0..200u32).map(|i| i.to_le_bytes()[..(i % 4) as usize].to_vec()).collect();
+		let blob = entries_to_blob(&entries);
+
+		let indices = [150, 3, 199, 3, 0, 200, 77, usize::MAX, 150];
+		let found = blob_get_many(&blob, &indices).unwrap();
+		for (&index, found) in indices.iter().zip(&found) {
+			assert_eq!(*found, blob_iter(&blob).nth(index).map(Result::unwrap), "index {}", index);
+		}
+		assert_eq!(blob_get_many(&blob, &[]).unwrap(), Vec::<Option<&[u8]>>::new());
+		assert_eq!(blob_get_many(&[], &[0, 1]).unwrap(), [None, None]);
+
+		// Slices past the largest index asked for aren't read, unless an index is out of range
+		let mut corrupt = blob.clone();
+		let cut = blob_iter(&blob).take(100).map(Result::unwrap).map(|e| e.len() + 4).sum::<usize>();
+		corrupt[cut..cut + 4].copy_from_slice(&u32::MAX.to_ne_bytes());
+		assert_eq!(blob_get_many(&corrupt, &[99, 5]).unwrap(), [Some(&entries[99][..]), Some(&entries[5][..])]);
+		assert!(blob_get_many(&corrupt, &[100]).is_err());
+		assert!(blob_get_many(&corrupt, &[5, 1000]).is_err());
+	}
 }
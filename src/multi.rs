@@ -0,0 +1,140 @@
+//! `MultiBlobView`, several blobs read as one sequence of slices
+use crate::*;
+
+
+/// Several borrowed blobs treated as one sequence of slices, without copying them together
+///
+/// Each part is validated as it is added. Slice numbers run through the parts in order, so the first slice of part 1 comes straight after
+/// the last slice of part 0. Finding which part holds a slice is a binary search over the parts.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let base = byte_arrays_to_blob(&[b"a", b"b"]);
+/// let extra = byte_arrays_to_blob(&[b"c"]);
+///
+/// let mut view = MultiBlobView::new(vec![&base]).unwrap();
+/// view.push_part(&extra).unwrap();
+///
+/// assert_eq!(view.len(), 3);
+/// assert_eq!(view.get(2), Some(&b"c"[..]));
+/// assert_eq!(view.source_of(2), Some((1, 0)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MultiBlobView<'a> {
+	parts: Vec<BlobView<'a>>,
+	/// `starts[i]` is the number of slices before part `i`
+	starts: Vec<usize>,
+	len: usize
+}
+
+
+impl<'a> MultiBlobView<'a> {
+	/// Reads every part, returning an error if any are invalid
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InPart)` with the index of the first invalid part and the error `blob_to_byte_arrays` gives for it
+	pub fn new(parts: Vec<&'a [u8]>) -> Result<Self, BlobReadError> {
+		let mut view = MultiBlobView::default();
+		for part in parts {
+			view.push_part(part)?;
+		}
+		Ok(view)
+	}
+
+	/// Reads another part and adds its slices to the end of the sequence. If it is invalid the view is left unchanged
+	///
+	/// # Errors
+	///
+	/// The same as `new`, with the index the part would have had
+	pub fn push_part(&mut self, part: &'a [u8]) -> Result<(), BlobReadError> {
+		let view = BlobView::new(part).map_err(|e| BlobReadError::InPart { part: self.parts.len(), error: Box::new(e) })?;
+		self.starts.push(self.len);
+		self.len += view.len();
+		self.parts.push(view);
+		Ok(())
+	}
+
+	/// Returns the total number of slices in every part
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the number of parts
+	pub fn part_count(&self) -> usize {
+		self.parts.len()
+	}
+
+	/// Returns which part holds slice `index`, and its index within that part, or `None` if it is out of range
+	pub fn source_of(&self, index: usize) -> Option<(usize, usize)> {
+		if index >= self.len {
+			return None;
+		}
+		// The last part starting at or before `index`. Empty parts share a start with the next part, so they are skipped over
+		let part = self.starts.partition_point(|&start| start <= index) - 1;
+		Some((part, index - self.starts[part]))
+	}
+
+	/// Returns slice `index`, or `None` if it is out of range
+	pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+		let (part, local) = self.source_of(index)?;
+		self.parts[part].get(local)
+	}
+
+	/// Returns an iterator over the slices of every part in order
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = &'a [u8]> + '_ {
+		ExactLen::new(self.parts.iter().flat_map(|part| part.iter()), self.len)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn indexes_cross_part_boundaries() {
+		let parts = [
+			byte_arrays_to_blob(&[&[0u8], &[1u8]]),
+			Vec::new(),
+			byte_arrays_to_blob(&[&[2u8]]),
+			byte_arrays_to_blob(&[&[], &[4u8]])
+		];
+		let view = MultiBlobView::new(parts.iter().map(|part| part.as_slice()).collect()).unwrap();
+
+		assert_eq!(view.len(), 5);
+		assert_eq!(view.part_count(), 4);
+		let sources: Vec<_> = (0..5).map(|i| view.source_of(i).unwrap()).collect();
+		assert_eq!(sources, vec![(0, 0), (0, 1), (2, 0), (3, 0), (3, 1)]);
+		assert_eq!(view.source_of(5), None);
+		assert_eq!(view.get(5), None);
+
+		let expected: Vec<&[u8]> = vec![&[0u8], &[1u8], &[2u8], &[], &[4u8]];
+		assert_eq!((0..5).map(|i| view.get(i).unwrap()).collect::<Vec<_>>(), expected);
+		assert_eq!(view.iter().collect::<Vec<_>>(), expected);
+	}
+
+	#[test]
+	fn invalid_part_is_reported() {
+		let good = byte_arrays_to_blob(&[&[1u8]]);
+		let bad = [255u8, 0u8, 0u8, 0u8];
+		assert_eq!(
+			MultiBlobView::new(vec![&good, &bad, &good]).unwrap_err(),
+			BlobReadError::InPart { part: 1, error: Box::new(BlobReadError::NotABlob { first_header: u32::from_ne_bytes(bad) }) }
+		);
+
+		let mut view = MultiBlobView::new(vec![&good]).unwrap();
+		assert!(matches!(view.push_part(&bad), Err(BlobReadError::InPart { part: 1, .. })));
+		assert_eq!(view.part_count(), 1);
+		view.push_part(&good).unwrap();
+		assert_eq!(view.source_of(1), Some((1, 0)));
+	}
+}
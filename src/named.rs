@@ -0,0 +1,289 @@
+//! Blobs of named entries, sorted by name so one can be found with a binary search
+//!
+//! # Layout
+//!
+//! A named blob has the same layout as a typed blob: two slices per entry, the UTF-8 name followed by the payload.
+//! Entries are sorted by name in byte order, and entries with the same name keep the order they were given in.
+use crate::*;
+
+
+/// Errors that can occur when reading a named blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NamedBlobError {
+	/// The blob is not a valid typed blob
+	Read(BlobReadError),
+	/// Entry `second_index` has a name that sorts before the name of entry `first_index`, the entry just before it
+	OutOfOrder {
+		first_index: usize,
+		second_index: usize,
+		first_key: String,
+		second_key: String
+	}
+}
+
+
+impl std::error::Error for NamedBlobError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			NamedBlobError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for NamedBlobError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for NamedBlobError {
+	fn from(e: BlobReadError) -> Self {
+		NamedBlobError::Read(e)
+	}
+}
+
+
+/// Converts a list of `(name, payload)` pairs to a named blob, sorting them by name. See the module layout above
+pub fn named_byte_arrays_to_blob(entries: &[(&str, &[u8])]) -> Vec<u8> {
+	let mut sorted = entries.to_vec();
+	sorted.sort_by_key(|(name, _)| *name);
+	typed_byte_arrays_to_blob(&sorted)
+}
+
+
+/// Reads (name, payload) pairs one at a time, checking each name is UTF-8 and each name has a payload
+fn named_pairs(blob: &[u8]) -> impl Iterator<Item = Result<(&str, &[u8]), BlobReadError>> {
+	let mut iter = blob_iter(blob);
+	let mut entry = 0;
+	std::iter::from_fn(move || {
+		let name = match iter.next()? {
+			Ok(name) => name,
+			Err(e) => return Some(Err(e))
+		};
+		let pair = match iter.next() {
+			Some(Ok(data)) => std::str::from_utf8(name).map(|name| (name, data)).map_err(|_| BlobReadError::InvalidUtf8(entry)),
+			Some(Err(e)) => Err(e),
+			None => Err(BlobReadError::UnpairedEntry(entry))
+		};
+		entry += 1;
+		Some(pair)
+	})
+}
+
+
+/// Finds the payload of the entry called `name` by reading the blob from the start, without building a view or a map
+///
+/// The first entry with the name wins, in blob order, whether or not the blob is sorted. Every entry up to the match is checked as it is read,
+/// so a corrupt blob returns an error rather than pairing a name with the wrong payload. Entries after the match are not read.
+///
+/// # Errors
+///
+/// The same as `blob_to_typed_byte_arrays`, for the entries up to the match
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = named_byte_arrays_to_blob(&[("config", b"{}"), ("icon", b"png")]);
+/// assert_eq!(blob_find_named(&blob, "icon").unwrap(), Some(&b"png"[..]));
+/// assert_eq!(blob_find_named(&blob, "missing").unwrap(), None);
+/// ```
+pub fn blob_find_named<'a>(blob: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, BlobReadError> {
+	for pair in named_pairs(blob) {
+		let (entry_name, data) = pair?;
+		if entry_name == name {
+			return Ok(Some(data));
+		}
+	}
+	Ok(None)
+}
+
+
+/// Returns an iterator over the names in a named blob, in blob order
+///
+/// The whole blob is checked first, without allocating, so the iterator itself can't fail.
+///
+/// # Errors
+///
+/// The same as `blob_to_typed_byte_arrays`
+pub fn blob_named_keys(blob: &[u8]) -> Result<impl Iterator<Item = &str>, BlobReadError> {
+	for pair in named_pairs(blob) {
+		pair?;
+	}
+	Ok(named_pairs(blob).map(|pair| pair.unwrap().0))
+}
+
+
+/// A borrowed named blob, for looking up entries by name
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = named_byte_arrays_to_blob(&[("zebra", b"z"), ("apple", b"a")]);
+/// let view = NamedBlobView::new(&blob).unwrap();
+///
+/// assert_eq!(view.get("apple"), Some(&b"a"[..]));
+/// assert_eq!(view.get("mango"), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NamedBlobView<'a> {
+	entries: Vec<(&'a str, &'a [u8])>
+}
+
+
+impl<'a> NamedBlobView<'a> {
+	/// Reads a named blob, checking that its entries are sorted by name
+	///
+	/// # Errors
+	///
+	/// Returns `Err(NamedBlobError::OutOfOrder)` for the first pair of entries that are out of order, or `Err(NamedBlobError::Read)` for the same errors as `blob_to_typed_byte_arrays`
+	pub fn new(blob: &'a [u8]) -> Result<Self, NamedBlobError> {
+		let view = NamedBlobView::new_unchecked_order(blob)?;
+		view.verify_sorted()?;
+		Ok(view)
+	}
+
+	/// Reads a named blob without checking that its entries are sorted
+	///
+	/// If they are not, `get` can miss entries that are present. Use `verify_sorted` or `resort` to check or fix the order later.
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_typed_byte_arrays`
+	pub fn new_unchecked_order(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		Ok(NamedBlobView { entries: blob_to_typed_byte_arrays(blob)? })
+	}
+
+	/// Returns the number of entries
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Returns the payload of the first entry called `name`, found with a binary search
+	pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+		let index = self.entries.partition_point(|(entry_name, _)| *entry_name < name);
+		match self.entries.get(index) {
+			Some((entry_name, data)) if *entry_name == name => Some(data),
+			_ => None
+		}
+	}
+
+	/// Returns an iterator over the `(name, payload)` pairs, in blob order
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&'a str, &'a [u8])> + ExactSizeIterator + '_ {
+		self.entries.iter().copied()
+	}
+
+	/// Checks that the entries are sorted by name. Repeated names are allowed
+	///
+	/// # Errors
+	///
+	/// Returns `Err(NamedBlobError::OutOfOrder)` for the first pair of entries that are out of order
+	pub fn verify_sorted(&self) -> Result<(), NamedBlobError> {
+		match self.entries.windows(2).position(|pair| pair[0].0 > pair[1].0) {
+			Some(first_index) => Err(NamedBlobError::OutOfOrder {
+				first_index,
+				second_index: first_index + 1,
+				first_key: self.entries[first_index].0.to_string(),
+				second_key: self.entries[first_index + 1].0.to_string()
+			}),
+			None => Ok(())
+		}
+	}
+
+	/// Returns true if the entries are sorted by name
+	pub fn is_sorted(&self) -> bool {
+		self.verify_sorted().is_ok()
+	}
+
+	/// Returns a new named blob with the entries sorted by name, and the permutation that was applied, in the same way as `blob_sort_by_key`
+	pub fn resort(&self) -> (Vec<u8>, Vec<usize>) {
+		let mut permutation: Vec<usize> = (0..self.entries.len()).collect();
+		permutation.sort_by_key(|&i| self.entries[i].0);
+		let sorted: Vec<(&str, &[u8])> = permutation.iter().map(|&i| self.entries[i]).collect();
+		(typed_byte_arrays_to_blob(&sorted), permutation)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn unsorted_blob() -> Vec<u8> {
+		// Built as a typed blob, which keeps the order it is given
+		typed_byte_arrays_to_blob(&[("b", b"2"), ("d", b"4"), ("c", b"3"), ("a", b"1"), ("e", b"5")])
+	}
+
+	#[test]
+	fn detects_unsorted_names() {
+		let blob = unsorted_blob();
+		assert_eq!(NamedBlobView::new(&blob).unwrap_err(), NamedBlobError::OutOfOrder {
+			first_index: 1,
+			second_index: 2,
+			first_key: "d".to_string(),
+			second_key: "c".to_string()
+		});
+
+		let view = NamedBlobView::new_unchecked_order(&blob).unwrap();
+		assert!(!view.is_sorted());
+		assert!(["a", "b", "c", "d", "e"].iter().any(|name| view.get(name).is_none()));
+	}
+
+	#[test]
+	fn resort_finds_every_name() {
+		let blob = unsorted_blob();
+		let unsorted = NamedBlobView::new_unchecked_order(&blob).unwrap();
+		let (sorted, permutation) = unsorted.resort();
+		assert_eq!(permutation, vec![3, 0, 2, 1, 4]);
+
+		let view = NamedBlobView::new(&sorted).unwrap();
+		assert!(view.is_sorted());
+		for (name, data) in unsorted.iter() {
+			assert_eq!(view.get(name), Some(data));
+		}
+		assert_eq!(view.get("f"), None);
+	}
+
+	#[test]
+	fn repeated_names() {
+		let blob = named_byte_arrays_to_blob(&[("x", b"first"), ("a", b""), ("x", b"second")]);
+		let view = NamedBlobView::new(&blob).unwrap();
+		assert_eq!(view.len(), 3);
+		assert_eq!(view.get("x"), Some(&b"first"[..]));
+		assert_eq!(view.get("a"), Some(&b""[..]));
+		assert!(NamedBlobView::new(&[]).unwrap().is_empty());
+	}
+
+	#[test]
+	fn find_named_first_match_wins() {
+		let blob = typed_byte_arrays_to_blob(&[("a", b"1"), ("dup", b"first"), ("b", b"2"), ("dup", b"second")]);
+		assert_eq!(blob_find_named(&blob, "dup").unwrap(), Some(&b"first"[..]));
+		assert_eq!(blob_find_named(&blob, "b").unwrap(), Some(&b"2"[..]));
+		assert_eq!(blob_find_named(&blob, "c").unwrap(), None);
+		assert_eq!(blob_named_keys(&blob).unwrap().collect::<Vec<_>>(), vec!["a", "dup", "b", "dup"]);
+	}
+
+	#[test]
+	fn find_named_checks_structure() {
+		let unpaired = byte_arrays_to_blob(&[b"a", b"1", b"b"]);
+		assert_eq!(blob_find_named(&unpaired, "b"), Err(BlobReadError::UnpairedEntry(1)));
+		assert_eq!(blob_find_named(&unpaired, "a"), Ok(Some(&b"1"[..])));
+		assert!(blob_named_keys(&unpaired).is_err());
+
+		let invalid_name = byte_arrays_to_blob(&[b"a", b"1", &[0xFFu8], b"2"]);
+		assert_eq!(blob_find_named(&invalid_name, "z"), Err(BlobReadError::InvalidUtf8(1)));
+
+		let truncated = named_byte_arrays_to_blob(&[("a", b"1"), ("b", b"2")]);
+		assert!(blob_find_named(&truncated[..truncated.len() - 1], "b").is_err());
+		assert_eq!(blob_named_keys(&[]).unwrap().count(), 0);
+	}
+}
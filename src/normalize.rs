@@ -0,0 +1,170 @@
+//! Padding and truncating entries to one fixed size, for code that wants every record the same length
+use crate::*;
+
+
+/// What to do with an entry longer than the fixed size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizeMode {
+	/// Keep the first `size` bytes
+	Truncate,
+	/// Return `NormalizeError::TooLong`
+	Error
+}
+
+
+/// Errors that can occur when normalizing entries to a fixed size
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NormalizeError {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// Entry `entry` is `len` bytes long, which is longer than `size`, and the mode is `NormalizeMode::Error`
+	TooLong {
+		entry: usize,
+		len: usize,
+		size: usize
+	},
+	/// The normalized blob would be larger than `u32::MAX` bytes
+	TooLarge
+}
+
+
+impl std::error::Error for NormalizeError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			NormalizeError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for NormalizeError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for NormalizeError {
+	fn from(e: BlobReadError) -> Self {
+		NormalizeError::Read(e)
+	}
+}
+
+
+/// Returns the part of entry `entry` that is kept, which is all of it unless it is longer than `size`
+fn kept(entry: usize, byte_arr: &[u8], size: usize, mode: NormalizeMode) -> Result<&[u8], NormalizeError> {
+	match byte_arr.get(..size) {
+		Some(kept) if kept.len() < byte_arr.len() && mode == NormalizeMode::Error => Err(NormalizeError::TooLong { entry, len: byte_arr.len(), size }),
+		Some(kept) => Ok(kept),
+		None => Ok(byte_arr)
+	}
+}
+
+
+/// Re-encodes a blob so that every entry is exactly `size` bytes, padding shorter entries with `fill` and handling longer ones as `mode` says
+///
+/// # Errors
+///
+/// Returns `Err(NormalizeError::TooLong)` with the first entry longer than `size` in `NormalizeMode::Error`, `Err(NormalizeError::TooLarge)` if the padding
+/// would make the blob larger than `u32::MAX` bytes, or `Err(NormalizeError::Read)` with the error `blob_to_byte_arrays` gives
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"ab", b"abcdef"]);
+/// let normalized = blob_normalize_entries(&blob, 4, 0, NormalizeMode::Truncate).unwrap();
+/// assert_eq!(blob_to_byte_arrays(&normalized).unwrap(), [b"ab\0\0", b"abcd"]);
+///
+/// let error = blob_normalize_entries(&blob, 4, 0, NormalizeMode::Error).unwrap_err();
+/// assert_eq!(error, NormalizeError::TooLong { entry: 1, len: 6, size: 4 });
+/// ```
+pub fn blob_normalize_entries(blob: &[u8], size: usize, fill: u8, mode: NormalizeMode) -> Result<Vec<u8>, NormalizeError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	let encoded_len = match size.checked_add(4).and_then(|entry_len| entry_len.checked_mul(byte_arrays.len())) {
+		Some(len) if len <= MAX_U32_LEN => len,
+		_ => return Err(NormalizeError::TooLarge)
+	};
+
+	let mut normalized = Vec::with_capacity(encoded_len);
+	for (i, byte_arr) in byte_arrays.into_iter().enumerate() {
+		let kept = kept(i, byte_arr, size, mode)?;
+		// The whole blob fits in u32, so every index in it does
+		let idx_end = normalized.len() + 4 + size;
+		normalized.extend((idx_end as u32).to_ne_bytes());
+		normalized.extend(kept);
+		normalized.resize(idx_end, fill);
+	}
+	Ok(normalized)
+}
+
+
+/// Converts a blob u8 slice to owned records of exactly `size` bytes each, normalized the same way as `blob_normalize_entries` but without re-encoding
+///
+/// # Errors
+///
+/// The same as `blob_normalize_entries`. `Err(NormalizeError::TooLarge)` is returned if the records together would be larger than `u32::MAX` bytes,
+/// so the records always fit in a blob
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"ab", b"abcdef"]);
+/// let records = blob_to_fixed_records(&blob, 4, b'-', NormalizeMode::Truncate).unwrap();
+/// assert_eq!(records, [b"ab--", b"abcd"]);
+/// ```
+pub fn blob_to_fixed_records(blob: &[u8], size: usize, fill: u8, mode: NormalizeMode) -> Result<Vec<Vec<u8>>, NormalizeError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	if size.checked_mul(byte_arrays.len()).is_none_or(|len| len > MAX_U32_LEN) {
+		return Err(NormalizeError::TooLarge);
+	}
+	byte_arrays.into_iter().enumerate().map(|(i, byte_arr)| {
+		let mut record = kept(i, byte_arr, size, mode)?.to_vec();
+		record.resize(size, fill);
+		Ok(record)
+	}).collect()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entries(blob: &[u8]) -> Vec<Vec<u8>> {
+		blob_to_byte_vecs(blob).unwrap()
+	}
+
+	#[test]
+	fn exact_short_and_long_entries() {
+		let exact = [7u8; 8];
+		let blob = byte_arrays_to_blob(&[&exact, b"abc", &[], &[1u8; 11]]);
+
+		let expected = vec![exact.to_vec(), b"abc\xff\xff\xff\xff\xff".to_vec(), vec![0xff; 8], vec![1u8; 8]];
+		let normalized = blob_normalize_entries(&blob, 8, 0xff, NormalizeMode::Truncate).unwrap();
+		assert_eq!(entries(&normalized), expected);
+		assert_eq!(normalized.len(), 4 * 12);
+		assert_eq!(blob_to_fixed_records(&blob, 8, 0xff, NormalizeMode::Truncate).unwrap(), expected);
+
+		// Entries that are already the right size come back unchanged
+		let same = byte_arrays_to_blob(&[&exact, &exact]);
+		assert_eq!(blob_normalize_entries(&same, 8, 0, NormalizeMode::Error).unwrap(), same);
+		assert!(blob_normalize_entries(&[], 8, 0, NormalizeMode::Error).unwrap().is_empty());
+	}
+
+	#[test]
+	fn long_entries_rejected() {
+		let blob = byte_arrays_to_blob(&[b"ok", b"fine", b"too long"]);
+		let error = NormalizeError::TooLong { entry: 2, len: 8, size: 4 };
+		assert_eq!(blob_normalize_entries(&blob, 4, 0, NormalizeMode::Error), Err(error.clone()));
+		assert_eq!(blob_to_fixed_records(&blob, 4, 0, NormalizeMode::Error), Err(error));
+
+		assert_eq!(blob_normalize_entries(&blob, usize::MAX, 0, NormalizeMode::Truncate), Err(NormalizeError::TooLarge));
+		assert_eq!(blob_to_fixed_records(&blob, usize::MAX, 0, NormalizeMode::Truncate), Err(NormalizeError::TooLarge));
+		assert_eq!(blob_to_fixed_records(&blob, MAX_U32_LEN / 2, 0, NormalizeMode::Truncate), Err(NormalizeError::TooLarge));
+		assert_eq!(blob_to_fixed_records(&[], usize::MAX, 0, NormalizeMode::Truncate), Ok(Vec::new()));
+		assert!(matches!(blob_to_fixed_records(&[255u8; 4], 4, 0, NormalizeMode::Truncate), Err(NormalizeError::Read(_))));
+	}
+}
@@ -0,0 +1,244 @@
+//! Converting slices of numbers to and from entries
+use crate::*;
+
+
+/// The byte order numbers are stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+	Little,
+	Big
+}
+
+
+impl Endianness {
+	/// The byte order of the target platform
+	#[cfg(target_endian = "little")]
+	pub const NATIVE: Endianness = Endianness::Little;
+	/// The byte order of the target platform
+	#[cfg(target_endian = "big")]
+	pub const NATIVE: Endianness = Endianness::Big;
+}
+
+
+/// Errors that can occur when reading numbers from entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NumericEntryError {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// Entry `entry` is `len` bytes long, which is not a whole number of `element_size` byte numbers.
+	/// `entry` is always 0 for the single entry functions
+	InvalidLength {
+		entry: usize,
+		len: usize,
+		element_size: usize
+	}
+}
+
+
+impl std::error::Error for NumericEntryError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			NumericEntryError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for NumericEntryError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for NumericEntryError {
+	fn from(e: BlobReadError) -> Self {
+		NumericEntryError::Read(e)
+	}
+}
+
+
+mod sealed {
+	pub trait Sealed {}
+}
+
+
+/// A number that can be stored in an entry. Implemented for u16, u32, u64, i16, i32, i64, f32 and f64
+///
+/// Floats are stored by their bit pattern, so every NaN payload survives a round trip.
+pub trait NumericElement: Copy + sealed::Sealed {
+	/// Size of one number in bytes
+	const SIZE: usize;
+
+	#[doc(hidden)]
+	fn extend_bytes(self, endianness: Endianness, out: &mut Vec<u8>);
+
+	/// `bytes` is always exactly `SIZE` long
+	#[doc(hidden)]
+	fn from_bytes(bytes: &[u8], endianness: Endianness) -> Self;
+}
+
+
+macro_rules! numeric_element {
+	($t:ty, $to_entry:ident, $from_entry:ident) => {
+		impl sealed::Sealed for $t {}
+
+		impl NumericElement for $t {
+			const SIZE: usize = std::mem::size_of::<$t>();
+
+			fn extend_bytes(self, endianness: Endianness, out: &mut Vec<u8>) {
+				match endianness {
+					Endianness::Little => out.extend(self.to_le_bytes()),
+					Endianness::Big => out.extend(self.to_be_bytes())
+				}
+			}
+
+			fn from_bytes(bytes: &[u8], endianness: Endianness) -> Self {
+				let bytes = bytes.try_into().unwrap();
+				match endianness {
+					Endianness::Little => <$t>::from_le_bytes(bytes),
+					Endianness::Big => <$t>::from_be_bytes(bytes)
+				}
+			}
+		}
+
+		#[doc = concat!("Converts a slice of `", stringify!($t), "` to the bytes of an entry")]
+		pub fn $to_entry(numbers: &[$t], endianness: Endianness) -> Vec<u8> {
+			numbers_to_entry(numbers, endianness)
+		}
+
+		#[doc = concat!("Reads the bytes of an entry as `", stringify!($t), "`s")]
+		///
+		/// # Errors
+		///
+		/// Returns `Err(NumericEntryError::InvalidLength)` with `entry` 0 if the entry's length is not a multiple of the number's size
+		pub fn $from_entry(entry: &[u8], endianness: Endianness) -> Result<Vec<$t>, NumericEntryError> {
+			entry_to_numbers(0, entry, endianness)
+		}
+	};
+}
+
+numeric_element!(u16, u16s_to_entry, entry_to_u16s);
+numeric_element!(u32, u32s_to_entry, entry_to_u32s);
+numeric_element!(u64, u64s_to_entry, entry_to_u64s);
+numeric_element!(i16, i16s_to_entry, entry_to_i16s);
+numeric_element!(i32, i32s_to_entry, entry_to_i32s);
+numeric_element!(i64, i64s_to_entry, entry_to_i64s);
+numeric_element!(f32, f32s_to_entry, entry_to_f32s);
+numeric_element!(f64, f64s_to_entry, entry_to_f64s);
+
+
+fn numbers_to_entry<T: NumericElement>(numbers: &[T], endianness: Endianness) -> Vec<u8> {
+	let mut entry = Vec::with_capacity(numbers.len() * T::SIZE);
+	for &number in numbers {
+		number.extend_bytes(endianness, &mut entry);
+	}
+	entry
+}
+
+
+fn entry_to_numbers<T: NumericElement>(index: usize, entry: &[u8], endianness: Endianness) -> Result<Vec<T>, NumericEntryError> {
+	if !entry.len().is_multiple_of(T::SIZE) {
+		return Err(NumericEntryError::InvalidLength { entry: index, len: entry.len(), element_size: T::SIZE });
+	}
+	Ok(entry.chunks_exact(T::SIZE).map(|bytes| T::from_bytes(bytes, endianness)).collect())
+}
+
+
+/// Encodes slices of numbers into a blob, one entry per slice
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = numeric_arrays_to_blob(&[&[1.5f32, -2.0][..], &[]], Endianness::Little);
+/// let arrays: Vec<Vec<f32>> = blob_to_numeric_arrays(&blob, Endianness::Little).unwrap();
+/// assert_eq!(arrays, vec![vec![1.5, -2.0], vec![]]);
+/// ```
+pub fn numeric_arrays_to_blob<T: NumericElement>(arrays: &[&[T]], endianness: Endianness) -> Vec<u8> {
+	let mut blob = Blob::new();
+	for numbers in arrays {
+		blob.push(&numbers_to_entry(numbers, endianness));
+	}
+	blob.into_bytes()
+}
+
+
+/// Converts a blob u8 slice to a Vec of numbers for each entry
+///
+/// # Errors
+///
+/// Returns `Err(NumericEntryError::Read)` if the blob is invalid, or `Err(NumericEntryError::InvalidLength)` with the index of the
+/// first entry whose length is not a multiple of the number's size
+pub fn blob_to_numeric_arrays<T: NumericElement>(blob: &[u8], endianness: Endianness) -> Result<Vec<Vec<T>>, NumericEntryError> {
+	blob_iter(blob)
+		.enumerate()
+		.map(|(index, entry)| entry_to_numbers(index, entry?, endianness))
+		.collect()
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const BOTH: [Endianness; 2] = [Endianness::Little, Endianness::Big];
+
+	#[test]
+	fn integer_round_trips() {
+		for endianness in BOTH {
+			let numbers = [0u16, 1, 0x1234, u16::MAX];
+			assert_eq!(entry_to_u16s(&u16s_to_entry(&numbers, endianness), endianness).unwrap(), numbers);
+			let numbers = [0u32, 0xDEAD_BEEF, u32::MAX];
+			assert_eq!(entry_to_u32s(&u32s_to_entry(&numbers, endianness), endianness).unwrap(), numbers);
+			let numbers = [0u64, 0x0102_0304_0506_0708, u64::MAX];
+			assert_eq!(entry_to_u64s(&u64s_to_entry(&numbers, endianness), endianness).unwrap(), numbers);
+			let numbers = [i16::MIN, -1, 0, i16::MAX];
+			assert_eq!(entry_to_i16s(&i16s_to_entry(&numbers, endianness), endianness).unwrap(), numbers);
+			let numbers = [i32::MIN, -1, 0, i32::MAX];
+			assert_eq!(entry_to_i32s(&i32s_to_entry(&numbers, endianness), endianness).unwrap(), numbers);
+			let numbers = [i64::MIN, -1, 0, i64::MAX];
+			assert_eq!(entry_to_i64s(&i64s_to_entry(&numbers, endianness), endianness).unwrap(), numbers);
+		}
+
+		assert_eq!(u16s_to_entry(&[0x1234], Endianness::Little), vec![0x34, 0x12]);
+		assert_eq!(u16s_to_entry(&[0x1234], Endianness::Big), vec![0x12, 0x34]);
+		assert_eq!(u32s_to_entry(&[1], Endianness::NATIVE), 1u32.to_ne_bytes());
+	}
+
+	#[test]
+	fn float_bit_patterns_survive() {
+		let f32_bits = [0x7FC0_1234u32, 0xFF80_0001, 0x8000_0000, 1.5f32.to_bits(), f32::INFINITY.to_bits()];
+		let f64_bits = [0x7FF8_0000_DEAD_BEEFu64, 0x8000_0000_0000_0000, (-0.25f64).to_bits()];
+		for endianness in BOTH {
+			let numbers: Vec<f32> = f32_bits.iter().map(|&bits| f32::from_bits(bits)).collect();
+			let decoded = entry_to_f32s(&f32s_to_entry(&numbers, endianness), endianness).unwrap();
+			assert_eq!(decoded.iter().map(|number| number.to_bits()).collect::<Vec<_>>(), f32_bits);
+
+			let numbers: Vec<f64> = f64_bits.iter().map(|&bits| f64::from_bits(bits)).collect();
+			let decoded = entry_to_f64s(&f64s_to_entry(&numbers, endianness), endianness).unwrap();
+			assert_eq!(decoded.iter().map(|number| number.to_bits()).collect::<Vec<_>>(), f64_bits);
+		}
+	}
+
+	#[test]
+	fn blob_round_trip_and_errors() {
+		for endianness in BOTH {
+			let arrays: [&[i32]; 3] = [&[1, -2, 3], &[], &[i32::MIN]];
+			let blob = numeric_arrays_to_blob(&arrays, endianness);
+			assert_eq!(blob_to_numeric_arrays::<i32>(&blob, endianness).unwrap(), arrays);
+		}
+
+		assert_eq!(entry_to_u32s(&[1, 2, 3], Endianness::Little), Err(NumericEntryError::InvalidLength { entry: 0, len: 3, element_size: 4 }));
+
+		let blob = byte_arrays_to_blob(&[&[0u8; 8], &[0u8; 4], &[0u8; 6]]);
+		assert_eq!(blob_to_numeric_arrays::<u16>(&blob, Endianness::Big).unwrap().len(), 3);
+		assert_eq!(
+			blob_to_numeric_arrays::<u64>(&blob, Endianness::Big),
+			Err(NumericEntryError::InvalidLength { entry: 1, len: 4, element_size: 8 })
+		);
+		assert!(matches!(blob_to_numeric_arrays::<u16>(&[1u8], Endianness::Big), Err(NumericEntryError::Read(_))));
+	}
+}
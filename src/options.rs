@@ -0,0 +1,45 @@
+//! `DecodeOptions`, limits on what decoding a blob may allocate
+
+
+/// Limits applied while decoding, to stop a small hostile blob from making the decoder allocate a huge amount of memory
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let options = DecodeOptions::new().with_max_entries(1000);
+/// assert_eq!(options.max_entries(), 1000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+	max_entries: usize
+}
+
+
+impl Default for DecodeOptions {
+	fn default() -> Self {
+		DecodeOptions::new()
+	}
+}
+
+
+impl DecodeOptions {
+	/// The default limit on the number of entries, 16 Mi
+	pub const DEFAULT_MAX_ENTRIES: usize = 16 * 1024 * 1024;
+
+	/// Creates options with the default limits
+	pub fn new() -> Self {
+		DecodeOptions { max_entries: DecodeOptions::DEFAULT_MAX_ENTRIES }
+	}
+
+	/// Sets the most entries a decode may produce. Use `usize::MAX` for no limit
+	pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+		self.max_entries = max_entries;
+		self
+	}
+
+	/// Returns the most entries a decode may produce
+	pub fn max_entries(&self) -> usize {
+		self.max_entries
+	}
+}
@@ -0,0 +1,296 @@
+//! Copy-on-write overlays, which patch single entries of a read-only base blob without rewriting it
+use crate::*;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+
+/// Record tag for an entry replaced by new data
+const TAG_SET: u8 = 1;
+/// Record tag for a deleted entry
+const TAG_DELETE: u8 = 0;
+
+
+/// Errors that can occur when loading a saved overlay
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OverlayError {
+	/// The base blob or the saved overlay is not a valid blob
+	Read(BlobReadError),
+	/// Record `n` of the saved overlay is malformed, or does not come after the record before it
+	InvalidRecord(usize),
+	/// A record patches an entry the base blob does not have
+	IndexOutOfRange {
+		index: usize,
+		len: usize
+	}
+}
+
+
+impl std::error::Error for OverlayError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			OverlayError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for OverlayError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for OverlayError {
+	fn from(e: BlobReadError) -> Self {
+		OverlayError::Read(e)
+	}
+}
+
+
+/// A read-only base blob with a set of entry replacements and deletions on top
+///
+/// Indexes always refer to entries of the base, so deleting an entry does not move the ones after it. Reads of unmodified entries borrow from the base.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let base = byte_arrays_to_blob(&[b"a", b"b", b"c"]);
+/// let mut overlay = BlobOverlay::new(&base).unwrap();
+/// overlay.set(0, b"patched".to_vec());
+/// overlay.delete(1);
+///
+/// assert_eq!(overlay.get(0).as_deref(), Some(&b"patched"[..]));
+/// assert_eq!(overlay.get(1), None);
+/// assert_eq!(overlay.materialize(), byte_arrays_to_blob(&[b"patched", b"c"]));
+///
+/// let saved = overlay.save_overlay();
+/// assert_eq!(BlobOverlay::load_overlay(&base, &saved).unwrap().materialize(), overlay.materialize());
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlobOverlay<'a> {
+	base: BlobView<'a>,
+	/// `Some` for a replaced entry and `None` for a deleted one
+	changes: BTreeMap<usize, Option<Vec<u8>>>
+}
+
+
+impl<'a> BlobOverlay<'a> {
+	/// Creates an overlay with no changes on top of `base`
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`
+	pub fn new(base: &'a [u8]) -> Result<Self, BlobReadError> {
+		Ok(BlobOverlay {
+			base: BlobView::new(base)?,
+			changes: BTreeMap::new()
+		})
+	}
+
+	/// Returns the number of entries in the base
+	pub fn base_len(&self) -> usize {
+		self.base.len()
+	}
+
+	/// Returns the number of entries that haven't been deleted
+	pub fn len(&self) -> usize {
+		self.base.len() - self.changes.values().filter(|change| change.is_none()).count()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns true if entry `index` has been replaced or deleted
+	pub fn is_modified(&self, index: usize) -> bool {
+		self.changes.contains_key(&index)
+	}
+
+	/// Replaces entry `index`, which also brings it back if it was deleted
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is out of range for the base
+	pub fn set(&mut self, index: usize, byte_arr: Vec<u8>) {
+		self.check_index(index);
+		self.changes.insert(index, Some(byte_arr));
+	}
+
+	/// Deletes entry `index`, so it is skipped by `iter` and `materialize`
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is out of range for the base
+	pub fn delete(&mut self, index: usize) {
+		self.check_index(index);
+		self.changes.insert(index, None);
+	}
+
+	/// Drops any change to entry `index`, so it reads from the base again
+	pub fn revert(&mut self, index: usize) {
+		self.changes.remove(&index);
+	}
+
+	/// Returns entry `index`, borrowed from the base if it is unmodified or from the overlay if it was replaced, or `None` if it was deleted or is out of range
+	pub fn get(&self, index: usize) -> Option<Cow<'_, [u8]>> {
+		match self.changes.get(&index) {
+			Some(Some(byte_arr)) => Some(Cow::Borrowed(byte_arr.as_slice())),
+			Some(None) => None,
+			None => self.base.get(index).map(Cow::Borrowed)
+		}
+	}
+
+	/// Returns an iterator over the base index and data of every entry that hasn't been deleted
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = (usize, Cow<'_, [u8]>)> + '_ {
+		let kept = (0..self.base.len()).filter_map(|index| self.get(index).map(|byte_arr| (index, byte_arr)));
+		ExactLen::new(kept, self.len())
+	}
+
+	/// Encodes the entries that haven't been deleted as a new blob, with the changes applied
+	///
+	/// # Panics
+	///
+	/// Panics if the blob would become larger than `u32::MAX` bytes
+	pub fn materialize(&self) -> Vec<u8> {
+		let mut blob = Blob::with_capacity(self.base.as_bytes().len(), self.len());
+		for (_, byte_arr) in self.iter() {
+			blob.push(&byte_arr);
+		}
+		blob.into_bytes()
+	}
+
+	/// Encodes just the changes as their own blob, to be applied to the same base with `load_overlay`
+	///
+	/// Each change is a record of two entries: a header holding a tag byte and the u32 little endian index, then the new data, which is empty for a deletion.
+	pub fn save_overlay(&self) -> Vec<u8> {
+		let mut blob = Blob::new();
+		for (&index, change) in &self.changes {
+			let tag = if change.is_some() { TAG_SET } else { TAG_DELETE };
+			let mut header = [tag, 0, 0, 0, 0];
+			// Base indexes always fit, since a base blob is at most u32::MAX bytes
+			header[1..].copy_from_slice(&(index as u32).to_le_bytes());
+			blob.push(&header);
+			blob.push(change.as_deref().unwrap_or_default());
+		}
+		blob.into_bytes()
+	}
+
+	/// Rebuilds an overlay on top of `base` from the output of `save_overlay`
+	///
+	/// # Errors
+	///
+	/// Returns `Err(OverlayError::Read)` if either blob is invalid or a record's index does not fit in a usize, `Err(OverlayError::InvalidRecord)` if a record is malformed or out of order,
+	/// or `Err(OverlayError::IndexOutOfRange)` if a record patches an entry `base` does not have
+	pub fn load_overlay(base: &'a [u8], overlay: &[u8]) -> Result<Self, OverlayError> {
+		let mut loaded = BlobOverlay::new(base)?;
+		let entries = blob_to_byte_arrays(overlay)?;
+		if !entries.len().is_multiple_of(2) {
+			return Err(OverlayError::InvalidRecord(entries.len() / 2));
+		}
+
+		let mut previous = None;
+		for (record, pair) in entries.chunks_exact(2).enumerate() {
+			let (header, byte_arr) = (pair[0], pair[1]);
+			let &[tag, b0, b1, b2, b3] = header else {
+				return Err(OverlayError::InvalidRecord(record));
+			};
+			let index = index_to_usize(u32::from_le_bytes([b0, b1, b2, b3]))?;
+			if previous.is_some_and(|previous| index <= previous) {
+				return Err(OverlayError::InvalidRecord(record));
+			}
+			if index >= loaded.base.len() {
+				return Err(OverlayError::IndexOutOfRange { index, len: loaded.base.len() });
+			}
+
+			let change = match tag {
+				TAG_SET => Some(byte_arr.to_vec()),
+				TAG_DELETE if byte_arr.is_empty() => None,
+				_ => return Err(OverlayError::InvalidRecord(record))
+			};
+			loaded.changes.insert(index, change);
+			previous = Some(index);
+		}
+		Ok(loaded)
+	}
+
+	fn check_index(&self, index: usize) {
+		assert!(index < self.base.len(), "overlay index {} is out of range for a base of {} entries", index, self.base.len());
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn matches_reference_encode() {
+		let mut rng = XorShift32::default_seed();
+		let base_entries: Vec<Vec<u8>> = (0..100).map(|_| {
+			let random = rng.next_u32();
+			vec![(random >> 8) as u8; random as usize % 24]
+		}).collect();
+		let base = entries_to_blob(&base_entries);
+
+		let mut overlay = BlobOverlay::new(&base).unwrap();
+		let mut reference: Vec<Option<Vec<u8>>> = base_entries.iter().cloned().map(Some).collect();
+		for (index, byte_arr) in [(3, b"three".to_vec()), (50, Vec::new()), (99, vec![9; 40])] {
+			overlay.set(index, byte_arr.clone());
+			reference[index] = Some(byte_arr);
+		}
+		for index in [0, 10, 11, 98] {
+			overlay.delete(index);
+			reference[index] = None;
+		}
+		// Deleting and then setting again brings the entry back with the new data
+		overlay.delete(20);
+		overlay.set(20, b"back".to_vec());
+		reference[20] = Some(b"back".to_vec());
+
+		let expected: Vec<&[u8]> = reference.iter().flatten().map(Vec::as_slice).collect();
+		assert_eq!(overlay.len(), 96);
+		assert_eq!(overlay.materialize(), byte_arrays_to_blob(&expected));
+		assert!(matches!(overlay.get(1), Some(Cow::Borrowed(_))));
+		assert_eq!(overlay.get(1).as_deref(), Some(&base_entries[1][..]));
+		assert_eq!(overlay.get(10), None);
+		assert_eq!(overlay.get(100), None);
+
+		let loaded = BlobOverlay::load_overlay(&base, &overlay.save_overlay()).unwrap();
+		assert_eq!(loaded.materialize(), overlay.materialize());
+		assert_eq!(loaded.iter().map(|(index, _)| index).collect::<Vec<_>>(), overlay.iter().map(|(index, _)| index).collect::<Vec<_>>());
+
+		overlay.revert(10);
+		assert_eq!(overlay.get(10).as_deref(), Some(&base_entries[10][..]));
+	}
+
+	#[test]
+	fn invalid_saved_overlays() {
+		let base = byte_arrays_to_blob(&[b"a", b"b"]);
+		let unpaired = byte_arrays_to_blob(&[&[TAG_SET, 0, 0, 0, 0]]);
+		assert_eq!(BlobOverlay::load_overlay(&base, &unpaired).err(), Some(OverlayError::InvalidRecord(0)));
+
+		let out_of_range = byte_arrays_to_blob(&[&[TAG_DELETE, 2, 0, 0, 0], &[]]);
+		assert_eq!(BlobOverlay::load_overlay(&base, &out_of_range).err(), Some(OverlayError::IndexOutOfRange { index: 2, len: 2 }));
+
+		let repeated = byte_arrays_to_blob(&[&[TAG_SET, 1, 0, 0, 0], b"x", &[TAG_SET, 1, 0, 0, 0], b"y"]);
+		assert_eq!(BlobOverlay::load_overlay(&base, &repeated).err(), Some(OverlayError::InvalidRecord(1)));
+
+		let bad_tag = byte_arrays_to_blob(&[&[7, 0, 0, 0, 0], b"x"]);
+		assert_eq!(BlobOverlay::load_overlay(&base, &bad_tag).err(), Some(OverlayError::InvalidRecord(0)));
+
+		assert!(matches!(BlobOverlay::load_overlay(&base, &[1, 2]), Err(OverlayError::Read(_))));
+		assert_eq!(BlobOverlay::load_overlay(&base, &[]).unwrap().materialize(), base);
+	}
+
+	#[test]
+	#[should_panic]
+	fn set_out_of_range() {
+		let base = byte_arrays_to_blob(&[b"a"]);
+		BlobOverlay::new(&base).unwrap().set(1, Vec::new());
+	}
+}
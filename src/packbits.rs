@@ -0,0 +1,177 @@
+//! PackBits run length encoding, used to compress entries without any dependencies
+//!
+//! Each run starts with a header byte `n`. For `n` from 0 to 127, the next `n + 1` bytes are copied literally.
+//! For `n` from 129 to 255, the next byte is repeated `257 - n` times. A header of 128 is ignored.
+use std::io;
+
+
+/// Compresses `data`. Runs of 3 or more equal bytes are encoded as repeats, everything else as literals
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+	let mut out = Vec::with_capacity(data.len() / 2);
+	let mut i = 0;
+	let mut literal_start = 0;
+
+	while i < data.len() {
+		let mut run = 1;
+		while i + run < data.len() && data[i + run] == data[i] && run < 128 {
+			run += 1;
+		}
+
+		if run >= 3 {
+			push_literals(&mut out, &data[literal_start..i]);
+			out.push((257 - run) as u8);
+			out.push(data[i]);
+			i += run;
+			literal_start = i;
+		}
+		else {
+			i += run;
+		}
+	}
+	push_literals(&mut out, &data[literal_start..]);
+	out
+}
+
+
+fn push_literals(out: &mut Vec<u8>, literals: &[u8]) {
+	for chunk in literals.chunks(128) {
+		out.push((chunk.len() - 1) as u8);
+		out.extend(chunk);
+	}
+}
+
+
+#[derive(Debug, Clone, Copy)]
+enum Run {
+	Header,
+	Literal(usize),
+	Repeat(u8, usize)
+}
+
+
+/// Decompresses PackBits data as it is read, checking it produces exactly `expected_len` bytes
+#[derive(Debug, Clone)]
+pub(crate) struct Decompressor<'a> {
+	input: &'a [u8],
+	run: Run,
+	remaining: usize
+}
+
+
+impl<'a> Decompressor<'a> {
+	pub(crate) fn new(input: &'a [u8], expected_len: usize) -> Self {
+		Decompressor { input, run: Run::Header, remaining: expected_len }
+	}
+
+	/// Fills `buf` with decompressed bytes, returning how many were written. Returns `Err(())` for malformed data
+	pub(crate) fn decompress_into(&mut self, buf: &mut [u8]) -> Result<usize, ()> {
+		let mut written = 0;
+		while written < buf.len() && self.remaining > 0 {
+			match self.run {
+				Run::Header => {
+					let (&header, rest) = self.input.split_first().ok_or(())?;
+					self.input = rest;
+					self.run = match header {
+						0..=127 => Run::Literal(header as usize + 1),
+						128 => Run::Header,
+						_ => {
+							let (&byte, rest) = self.input.split_first().ok_or(())?;
+							self.input = rest;
+							Run::Repeat(byte, 257 - header as usize)
+						}
+					};
+				},
+				Run::Literal(n) => {
+					let take = n.min(buf.len() - written).min(self.remaining);
+					let literals = self.input.get(..take).ok_or(())?;
+					buf[written..written+take].copy_from_slice(literals);
+					self.input = &self.input[take..];
+					self.finish_run(take, n)?;
+					written += take;
+				},
+				Run::Repeat(byte, n) => {
+					let take = n.min(buf.len() - written).min(self.remaining);
+					buf[written..written+take].fill(byte);
+					self.finish_run(take, n)?;
+					written += take;
+				}
+			}
+		}
+
+		// Once the expected length is reached, any data left over means the length was wrong
+		if self.remaining == 0 && (!self.input.is_empty() || !matches!(self.run, Run::Header)) {
+			return Err(());
+		}
+		Ok(written)
+	}
+
+	fn finish_run(&mut self, take: usize, n: usize) -> Result<(), ()> {
+		self.remaining -= take;
+		self.run = match (self.run, n - take) {
+			(_, 0) => Run::Header,
+			(Run::Literal(_), left) => Run::Literal(left),
+			(Run::Repeat(byte, _), left) => Run::Repeat(byte, left),
+			(Run::Header, _) => return Err(())
+		};
+		Ok(())
+	}
+
+	/// Returns the number of decompressed bytes still to come
+	pub(crate) fn remaining(&self) -> usize {
+		self.remaining
+	}
+}
+
+
+/// Decompresses all of `input`, which must produce exactly `expected_len` bytes
+pub(crate) fn decompress(input: &[u8], expected_len: usize) -> Result<Vec<u8>, ()> {
+	// A 2 byte repeat expands to at most 128 bytes, so a larger length can't be right and is not worth allocating
+	if expected_len > input.len().saturating_mul(64) {
+		return Err(());
+	}
+	let mut out = vec![0u8; expected_len];
+	let mut decompressor = Decompressor::new(input, expected_len);
+	let written = decompressor.decompress_into(&mut out)?;
+	if written != expected_len {
+		return Err(());
+	}
+	Ok(out)
+}
+
+
+impl io::Read for Decompressor<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.decompress_into(buf).map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "malformed compressed entry"))
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let mut mixed = vec![1u8, 2u8, 3u8];
+		mixed.extend([7u8; 300]);
+		mixed.extend(0..=255u8);
+		mixed.extend([9u8, 9u8, 1u8, 1u8, 1u8]);
+
+		for data in [vec![], vec![5u8], vec![0u8; 1000], mixed] {
+			let compressed = compress(&data);
+			assert_eq!(decompress(&compressed, data.len()).unwrap(), data);
+		}
+		assert!(compress(&[0u8; 1000]).len() < 20);
+	}
+
+	#[test]
+	fn malformed() {
+		let compressed = compress(&[1u8, 2u8, 3u8, 4u8]);
+		assert!(decompress(&compressed[..3], 4).is_err());
+		assert!(decompress(&compressed, 3).is_err());
+		assert!(decompress(&compressed, 5).is_err());
+		assert!(decompress(&[129u8], 128).is_err());
+	}
+}
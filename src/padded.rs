@@ -0,0 +1,121 @@
+//! Aligning payloads by inserting padding slices, readable by any decoder
+use crate::*;
+
+
+/// The byte every padding slice is filled with
+pub const PADDING_FILLER: u8 = 0xAA;
+
+
+/// Encodes slices into a blob so that every payload starts at a multiple of `align` bytes from the start of the blob
+///
+/// Alignment comes from inserting padding slices filled with `PADDING_FILLER`, so the result is an ordinary blob: `blob_to_byte_arrays` reads it,
+/// just with the padding slices included, and `blob_skip_padding` drops them. Also returns where each input slice ended up, so `physical[i]` is
+/// the index `bytes_2d[i]` has when the padding slices are counted.
+///
+/// Compared to aligning in the format itself, each padding slice costs an extra 4 byte index and at least one filler byte,
+/// and a slice that is itself non-empty and entirely `PADDING_FILLER` would be mistaken for padding. In exchange, nothing reading the blob needs to change.
+/// Payloads are only aligned in memory if the blob's own buffer is.
+///
+/// # Panics
+///
+/// Panics if `align` is 0, or if the blob would be larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let (blob, physical) = byte_arrays_to_blob_padded(&[&[1, 2, 3], &[4]], 8);
+///
+/// // The padding slice between them is visible to plain decoding
+/// assert_eq!(physical, vec![1, 3]);
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap().len(), 4);
+/// assert_eq!(blob_skip_padding(&blob).unwrap(), vec![&[1, 2, 3][..], &[4]]);
+/// ```
+pub fn byte_arrays_to_blob_padded(bytes_2d: &[&[u8]], align: usize) -> (Vec<u8>, Vec<usize>) {
+	assert!(align != 0, "alignment must not be 0");
+
+	let mut blob = Blob::new();
+	let mut physical = Vec::with_capacity(bytes_2d.len());
+	for byte_arr in bytes_2d {
+		let payload_start = blob.as_bytes().len() + 4;
+		if !payload_start.is_multiple_of(align) {
+			// The padding slice's index and filler push the payload along, and the filler can't be empty or it couldn't be told apart
+			let filler_len = match (align - (payload_start + 4) % align) % align {
+				0 => align,
+				len => len
+			};
+			blob.push(&vec![PADDING_FILLER; filler_len]);
+		}
+		physical.push(blob.len());
+		blob.push(byte_arr);
+	}
+	(blob.into_bytes(), physical)
+}
+
+
+fn is_padding(byte_arr: &[u8]) -> bool {
+	!byte_arr.is_empty() && byte_arr.iter().all(|&byte| byte == PADDING_FILLER)
+}
+
+
+/// Converts a blob u8 slice to a Vec of u8 slices, leaving out padding slices inserted by `byte_arrays_to_blob_padded`
+///
+/// Any non-empty slice made only of `PADDING_FILLER` bytes is treated as padding.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+pub fn blob_skip_padding(blob: &[u8]) -> Result<Vec<&[u8]>, BlobReadError> {
+	let mut byte_arrays = Vec::new();
+	for byte_arr in blob_iter(blob) {
+		let byte_arr = byte_arr?;
+		if !is_padding(byte_arr) {
+			byte_arrays.push(byte_arr);
+		}
+	}
+	Ok(byte_arrays)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn padded_entries_are_aligned() {
+		let entries: Vec<Vec<u8>> = (0..20).map(|i| (0..i as u8 * 3).collect()).collect();
+		let slices = as_slices(&entries);
+
+		for align in [1, 2, 3, 4, 8, 16, 64] {
+			let (blob, physical) = byte_arrays_to_blob_padded(&slices, align);
+
+			// Old decoding still works and sees the logical entries at their physical indexes
+			let all = blob_to_byte_arrays(&blob).unwrap();
+			assert_eq!(physical.len(), slices.len());
+			for (logical, &index) in physical.iter().enumerate() {
+				assert_eq!(all[index], slices[logical]);
+			}
+			assert!(all.len() >= slices.len());
+			if align == 1 {
+				assert_eq!(all.len(), slices.len());
+			}
+
+			let logical = blob_skip_padding(&blob).unwrap();
+			assert_eq!(logical, slices);
+			// Offsets rather than addresses, since a Vec<u8> buffer is only aligned to 1
+			for byte_arr in logical {
+				assert_eq!((byte_arr.as_ptr() as usize - blob.as_ptr() as usize) % align, 0);
+			}
+		}
+	}
+
+	#[test]
+	fn empty_entries_are_kept() {
+		let (blob, _) = byte_arrays_to_blob_padded(&[&[], &[1u8], &[]], 8);
+		assert_eq!(blob_skip_padding(&blob).unwrap(), vec![&[][..], &[1u8], &[]]);
+		assert!(blob_skip_padding(&[1u8]).is_err());
+	}
+}
@@ -0,0 +1,232 @@
+//! `FromEntry`, for turning the slices of a blob into values of your own types with the entry index attached to any failure
+use crate::*;
+use std::convert::Infallible;
+
+
+/// A type that can be parsed from one slice of a blob
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// struct Point { x: u8, y: u8 }
+///
+/// impl FromEntry for Point {
+/// 	type Error = usize;
+///
+/// 	fn from_entry(_index: usize, bytes: &[u8]) -> Result<Self, usize> {
+/// 		match bytes {
+/// 			&[x, y] => Ok(Point { x, y }),
+/// 			_ => Err(bytes.len())
+/// 		}
+/// 	}
+/// }
+///
+/// let blob = byte_arrays_to_blob(&[&[1, 2], &[3, 4, 5]]);
+/// assert_eq!(blob_parse_entries::<Point>(&blob).err(), Some(EntryParseError::Parse { entry: 1, error: 3 }));
+/// ```
+pub trait FromEntry: Sized {
+	type Error;
+
+	/// Parses slice `index` of a blob
+	fn from_entry(index: usize, bytes: &[u8]) -> Result<Self, Self::Error>;
+}
+
+
+impl FromEntry for Vec<u8> {
+	type Error = Infallible;
+
+	fn from_entry(_index: usize, bytes: &[u8]) -> Result<Self, Self::Error> {
+		Ok(bytes.to_vec())
+	}
+}
+
+impl FromEntry for String {
+	type Error = std::str::Utf8Error;
+
+	fn from_entry(_index: usize, bytes: &[u8]) -> Result<Self, Self::Error> {
+		std::str::from_utf8(bytes).map(str::to_string)
+	}
+}
+
+impl<const N: usize> FromEntry for [u8; N] {
+	type Error = std::array::TryFromSliceError;
+
+	fn from_entry(_index: usize, bytes: &[u8]) -> Result<Self, Self::Error> {
+		bytes.try_into()
+	}
+}
+
+
+/// Errors that can occur when parsing the slices of a blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum EntryParseError<E> {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// Slice `entry` could not be parsed
+	Parse {
+		entry: usize,
+		error: E
+	},
+	/// The blob holds `actual` slices but the layout being parsed has `expected`
+	CountMismatch {
+		expected: usize,
+		actual: usize
+	}
+}
+
+
+impl<E: std::error::Error + 'static> std::error::Error for EntryParseError<E> {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			EntryParseError::Read(e) => Some(e),
+			EntryParseError::Parse { error, .. } => Some(error),
+			EntryParseError::CountMismatch { .. } => None
+		}
+	}
+}
+
+impl<E: fmt::Debug> fmt::Display for EntryParseError<E> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl<E> From<BlobReadError> for EntryParseError<E> {
+	fn from(e: BlobReadError) -> Self {
+		EntryParseError::Read(e)
+	}
+}
+
+
+/// Parses every slice of a blob as a `T`
+///
+/// # Errors
+///
+/// Returns `Err(EntryParseError::Parse)` with the index of the first slice that fails to parse, otherwise the same as `blob_to_byte_arrays`
+/// for the slices up to it
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"one", b"two"]);
+/// assert_eq!(blob_parse_entries::<String>(&blob).unwrap(), vec!["one", "two"]);
+/// ```
+pub fn blob_parse_entries<T: FromEntry>(blob: &[u8]) -> Result<Vec<T>, EntryParseError<T::Error>> {
+	blob_iter(blob).enumerate().map(|(entry, byte_arr)| {
+		T::from_entry(entry, byte_arr?).map_err(|error| EntryParseError::Parse { entry, error })
+	}).collect()
+}
+
+
+/// A tuple of `FromEntry` types parsed from a blob with exactly one slice per element, created by `blob_parse_tuple`
+///
+/// The elements have different error types, so a failure is boxed.
+pub trait FromEntryTuple: Sized {
+	/// The number of slices the tuple is parsed from
+	const LEN: usize;
+
+	/// Parses the tuple from exactly `LEN` slices
+	fn from_entries(byte_arrays: &[&[u8]]) -> Result<Self, EntryParseError<Box<dyn std::error::Error + Send + Sync>>>;
+}
+
+
+macro_rules! impl_from_entry_tuple {
+	($len:literal; $($name:ident $index:tt),+) => {
+		impl<$($name: FromEntry),+> FromEntryTuple for ($($name,)+)
+		where $($name::Error: Into<Box<dyn std::error::Error + Send + Sync>>),+ {
+			const LEN: usize = $len;
+
+			fn from_entries(byte_arrays: &[&[u8]]) -> Result<Self, EntryParseError<Box<dyn std::error::Error + Send + Sync>>> {
+				if byte_arrays.len() != Self::LEN {
+					return Err(EntryParseError::CountMismatch { expected: Self::LEN, actual: byte_arrays.len() });
+				}
+				Ok(($(
+					$name::from_entry($index, byte_arrays[$index]).map_err(|error| EntryParseError::Parse { entry: $index, error: error.into() })?,
+				)+))
+			}
+		}
+	};
+}
+
+impl_from_entry_tuple!(1; A 0);
+impl_from_entry_tuple!(2; A 0, B 1);
+impl_from_entry_tuple!(3; A 0, B 1, C 2);
+impl_from_entry_tuple!(4; A 0, B 1, C 2, D 3);
+impl_from_entry_tuple!(5; A 0, B 1, C 2, D 3, E 4);
+impl_from_entry_tuple!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+
+
+/// Parses a blob with a fixed layout, where each slice has its own type, into a tuple of up to 6 elements
+///
+/// # Errors
+///
+/// Returns `Err(EntryParseError::CountMismatch)` if the blob doesn't hold exactly one slice per element,
+/// `Err(EntryParseError::Parse)` with the index and boxed error of the first slice that fails to parse, or `Err(EntryParseError::Read)` if the blob is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"header", &[1, 0], b"payload"]);
+/// let (name, version, payload) = blob_parse_tuple::<(String, [u8; 2], Vec<u8>)>(&blob).unwrap();
+///
+/// assert_eq!(name, "header");
+/// assert_eq!(version, [1, 0]);
+/// assert_eq!(payload, b"payload");
+/// ```
+pub fn blob_parse_tuple<T: FromEntryTuple>(blob: &[u8]) -> Result<T, EntryParseError<Box<dyn std::error::Error + Send + Sync>>> {
+	let mut byte_arrays = Vec::with_capacity(T::LEN);
+	let mut count = 0;
+	// Only the first `LEN` slices are kept, so a blob with far too many can't make this allocate
+	for byte_arr in blob_iter(blob) {
+		let byte_arr = byte_arr?;
+		if count < T::LEN {
+			byte_arrays.push(byte_arr);
+		}
+		count += 1;
+	}
+	if count != T::LEN {
+		return Err(EntryParseError::CountMismatch { expected: T::LEN, actual: count });
+	}
+	T::from_entries(&byte_arrays)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn failing_middle_entry() {
+		let blob = byte_arrays_to_blob(&[b"fine", &[0xFF, 0xFE], b"also fine"]);
+		match blob_parse_entries::<String>(&blob) {
+			Err(EntryParseError::Parse { entry: 1, error }) => assert_eq!(error.valid_up_to(), 0),
+			other => panic!("Expected a parse error at entry 1, got {:?}", other)
+		}
+		assert_eq!(blob_parse_entries::<Vec<u8>>(&blob).unwrap()[1], vec![0xFF, 0xFE]);
+		assert!(matches!(blob_parse_entries::<[u8; 4]>(&blob), Err(EntryParseError::Parse { entry: 1, .. })));
+
+		let mut corrupt = blob.clone();
+		corrupt.pop();
+		assert!(matches!(blob_parse_entries::<Vec<u8>>(&corrupt), Err(EntryParseError::Read(_))));
+		assert_eq!(blob_parse_entries::<String>(&[]).unwrap(), Vec::<String>::new());
+	}
+
+	#[test]
+	fn tuple_arity() {
+		let blob = byte_arrays_to_blob(&[b"name", &[7, 7]]);
+		assert_eq!(blob_parse_tuple::<(String, [u8; 2])>(&blob).unwrap(), ("name".to_string(), [7, 7]));
+		assert!(matches!(blob_parse_tuple::<(String, [u8; 2], Vec<u8>)>(&blob), Err(EntryParseError::CountMismatch { expected: 3, actual: 2 })));
+		assert!(matches!(blob_parse_tuple::<(String,)>(&blob), Err(EntryParseError::CountMismatch { expected: 1, actual: 2 })));
+
+		match blob_parse_tuple::<(String, [u8; 3])>(&blob) {
+			Err(EntryParseError::Parse { entry: 1, error }) => assert!(error.is::<std::array::TryFromSliceError>()),
+			other => panic!("Expected a parse error at entry 1, got {:?}", other)
+		}
+	}
+}
@@ -0,0 +1,224 @@
+//! Readers that stream the payloads of a blob one after another as a single `io::Read`, leaving out the indexes
+use crate::*;
+use std::io::{self, BufRead, Read};
+
+
+/// Where a payload reader has got to, shared by the borrowed and owned readers
+#[derive(Debug, Clone, Default)]
+struct PayloadCursor {
+	entry: usize,
+	/// How far into payload `entry` has been read
+	offset: usize
+}
+
+
+impl PayloadCursor {
+	/// Returns the unread rest of the current payload, moving past any payloads that are finished or empty
+	fn fill<'b>(&mut self, view: &BlobView<'b>) -> &'b [u8] {
+		while let Some(byte_arr) = view.get(self.entry) {
+			if self.offset < byte_arr.len() {
+				return &byte_arr[self.offset..];
+			}
+			self.entry += 1;
+			self.offset = 0;
+		}
+		&[]
+	}
+
+	fn consume(&mut self, amt: usize) {
+		self.offset += amt;
+	}
+}
+
+
+/// Returns the total length of the payloads in a view, which is its bytes without the 4 byte indexes
+fn payload_len(view: &BlobView) -> usize {
+	(0..view.len()).filter_map(|index| view.range(index)).map(|range| range.len()).sum()
+}
+
+
+/// Reads the payloads of a borrowed blob in order as one stream, skipping the indexes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+/// use std::io::Read;
+///
+/// let blob = byte_arrays_to_blob(&[b"hello ", b"", b"world"]);
+/// let mut reader = BlobPayloadReader::new(&blob).unwrap();
+/// let mut text = String::with_capacity(reader.total_len());
+///
+/// reader.read_to_string(&mut text).unwrap();
+/// assert_eq!(text, "hello world");
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlobPayloadReader<'a> {
+	view: BlobView<'a>,
+	cursor: PayloadCursor,
+	total_len: usize
+}
+
+
+impl<'a> BlobPayloadReader<'a> {
+	/// Reads every index in `blob` before anything is streamed, so reading can't fail part way through
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`
+	pub fn new(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		Ok(BlobPayloadReader::from_view(BlobView::new(blob)?))
+	}
+
+	/// Streams the payloads of an existing view without reading the blob again
+	pub fn from_view(view: BlobView<'a>) -> Self {
+		let total_len = payload_len(&view);
+		BlobPayloadReader { view, cursor: PayloadCursor::default(), total_len }
+	}
+
+	/// Returns the total length of all the payloads, whatever has been read so far
+	pub fn total_len(&self) -> usize {
+		self.total_len
+	}
+}
+
+
+impl Read for BlobPayloadReader<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let available = self.fill_buf()?;
+		let n = available.len().min(buf.len());
+		buf[..n].copy_from_slice(&available[..n]);
+		self.consume(n);
+		Ok(n)
+	}
+}
+
+
+impl BufRead for BlobPayloadReader<'_> {
+	/// Returns the unread rest of the current payload, so one call never returns bytes from two payloads
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		Ok(self.cursor.fill(&self.view))
+	}
+
+	fn consume(&mut self, amt: usize) {
+		self.cursor.consume(amt);
+	}
+}
+
+
+/// The same as `BlobPayloadReader` but owning its `Blob`, so it can be returned or sent elsewhere
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+/// use std::io::Read;
+///
+/// let blob = Blob::from_byte_arrays(&[&[1, 2], &[3]]);
+/// let mut payloads = Vec::new();
+/// OwnedBlobPayloadReader::new(blob).read_to_end(&mut payloads).unwrap();
+/// assert_eq!(payloads, [1, 2, 3]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct OwnedBlobPayloadReader {
+	blob: Blob,
+	cursor: PayloadCursor,
+	total_len: usize
+}
+
+
+impl OwnedBlobPayloadReader {
+	pub fn new(blob: Blob) -> Self {
+		let total_len = payload_len(&blob.view());
+		OwnedBlobPayloadReader { blob, cursor: PayloadCursor::default(), total_len }
+	}
+
+	/// Returns the total length of all the payloads, whatever has been read so far
+	pub fn total_len(&self) -> usize {
+		self.total_len
+	}
+
+	/// Returns the blob being read
+	pub fn into_inner(self) -> Blob {
+		self.blob
+	}
+}
+
+
+impl Read for OwnedBlobPayloadReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let available = self.fill_buf()?;
+		let n = available.len().min(buf.len());
+		buf[..n].copy_from_slice(&available[..n]);
+		self.consume(n);
+		Ok(n)
+	}
+}
+
+
+impl BufRead for OwnedBlobPayloadReader {
+	/// Returns the unread rest of the current payload, so one call never returns bytes from two payloads
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		Ok(self.cursor.fill(&self.blob.view()))
+	}
+
+	fn consume(&mut self, amt: usize) {
+		self.cursor.consume(amt);
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Reads `amounts` bytes at a time, cycling through them, so reads start and stop at every kind of place
+	fn read_in_steps(mut reader: impl Read, amounts: &[usize]) -> Vec<u8> {
+		let mut out = Vec::new();
+		for &amount in amounts.iter().cycle() {
+			let mut buf = vec![0u8; amount];
+			let n = reader.read(&mut buf).unwrap();
+			if n == 0 && amount > 0 {
+				return out;
+			}
+			out.extend_from_slice(&buf[..n]);
+		}
+		unreachable!()
+	}
+
+	#[test]
+	fn matches_concatenated_entries() {
+		let slices: [&[u8]; 7] = [&[], b"first", &[], &[], &[0u8; 300], b"x", &[]];
+		let expected = slices.concat();
+		let blob = Blob::from_byte_arrays(&slices);
+
+		let mut reader = BlobPayloadReader::new(blob.as_bytes()).unwrap();
+		assert_eq!(reader.total_len(), expected.len());
+		let mut copied = Vec::new();
+		assert_eq!(io::copy(&mut reader, &mut copied).unwrap(), expected.len() as u64);
+		assert_eq!(copied, expected);
+		assert_eq!(reader.read(&mut [0u8; 4]).unwrap(), 0);
+
+		assert_eq!(read_in_steps(BlobPayloadReader::new(blob.as_bytes()).unwrap(), &[1, 0, 7, 3]), expected);
+		assert_eq!(read_in_steps(OwnedBlobPayloadReader::new(blob.clone()), &[64, 2]), expected);
+		assert_eq!(read_in_steps(BlobPayloadReader::from_view(blob.slice(2..5)), &[5]), slices[2..5].concat());
+		assert_eq!(BlobPayloadReader::from_view(blob.slice(2..5)).total_len(), 300);
+
+		let mut owned = OwnedBlobPayloadReader::new(blob.clone());
+		assert_eq!(owned.fill_buf().unwrap(), b"first");
+		owned.consume(5);
+		assert_eq!(owned.fill_buf().unwrap(), &[0u8; 300][..]);
+		assert_eq!(owned.into_inner(), blob);
+	}
+
+	#[test]
+	fn empty_and_invalid() {
+		let mut reader = BlobPayloadReader::new(&[]).unwrap();
+		assert_eq!(reader.total_len(), 0);
+		assert_eq!(reader.fill_buf().unwrap(), &[][..]);
+
+		let blob = byte_arrays_to_blob(&[&[], &[]]);
+		assert_eq!(read_in_steps(BlobPayloadReader::new(&blob).unwrap(), &[8]), Vec::<u8>::new());
+		assert!(BlobPayloadReader::new(&blob[..6]).is_err());
+	}
+}
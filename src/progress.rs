@@ -0,0 +1,84 @@
+//! Progress reporting for decoding large blobs
+use crate::*;
+
+
+/// How far a decode has got. Passed to the callback of the `_with_progress` functions after each entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+	/// Number of entries decoded so far
+	pub entries_done: usize,
+	/// Total number of entries, if it is known before decoding finishes
+	pub entries_total: Option<usize>,
+	/// Number of bytes of the blob consumed so far
+	pub bytes_done: usize,
+	/// Total length of the blob, if it is known
+	pub bytes_total: Option<usize>
+}
+
+
+/// Converts a blob u8 slice to a Vec of u8 slices, calling `on_progress` after each slice is read
+///
+/// The callback only receives copies of the counters, so it can't affect decoding. It is generic rather than a trait object,
+/// so passing a closure that does nothing compiles down to the same code as `blob_to_byte_arrays`.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`. Progress is reported for every slice read before the error
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1; 100], &[2; 100]]);
+///
+/// let mut last = None;
+/// blob_to_byte_arrays_with_progress(&blob, |progress| last = Some(progress)).unwrap();
+/// assert_eq!(last.unwrap().bytes_done, blob.len());
+/// ```
+pub fn blob_to_byte_arrays_with_progress(blob: &[u8], mut on_progress: impl FnMut(Progress)) -> Result<Vec<&[u8]>, BlobReadError> {
+	let mut byte_arrays = Vec::new();
+	let mut iter = blob_iter(blob);
+	while let Some(byte_arr) = iter.next() {
+		byte_arrays.push(byte_arr?);
+		on_progress(Progress {
+			entries_done: byte_arrays.len(),
+			entries_total: None,
+			bytes_done: iter.position(),
+			bytes_total: Some(blob.len())
+		});
+	}
+	Ok(byte_arrays)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn ten_entry_progress() {
+		let entries: Vec<Vec<u8>> = (0..10).map(|i| vec![i as u8; i * 10]).collect();
+		let blob = entries_to_blob(&entries);
+
+		let mut events = Vec::new();
+		blob_to_byte_arrays_with_progress(&blob, |progress| events.push(progress)).unwrap();
+
+		assert_eq!(events.len(), 10);
+		for (i, pair) in events.windows(2).enumerate() {
+			assert!(pair[0].bytes_done < pair[1].bytes_done);
+			assert_eq!(pair[1].entries_done, i + 2);
+		}
+		assert_eq!(events.last().unwrap().bytes_done, blob.len());
+		assert!(events.iter().all(|progress| progress.bytes_total == Some(blob.len())));
+
+		// The stream reader reports the same counts, without knowing the total up front
+		let mut stream_events = Vec::new();
+		let read = BlobReader::new(&blob[..]).read_all_with_progress(|progress| stream_events.push(progress)).unwrap();
+		assert_eq!(read, entries);
+		assert_eq!(stream_events.iter().map(|p| p.bytes_done).collect::<Vec<_>>(), events.iter().map(|p| p.bytes_done).collect::<Vec<_>>());
+		assert!(stream_events.iter().all(|progress| progress.bytes_total.is_none()));
+	}
+}
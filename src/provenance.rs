@@ -0,0 +1,408 @@
+//! Provenance, a record of where each entry of a blob came from through every stage of a pipeline that repacked it
+//!
+//! # Layout
+//!
+//! The records are stored as one trailing entry after the payloads: the 4 magic bytes `BABP` followed by a nested blob with one slice per payload entry.
+//! Each of those slices is itself a nested blob of three slices per step, oldest first: the source label, the entry's index in that source
+//! as a little-endian u64, and the name of the transform applied.
+//! Every index is absolute, so the bytes before the trailing entry are a valid blob of the payloads alone, which `blob_without_provenance` returns.
+//! The plain decoder sees the trailing entry as one more entry, so a blob whose last payload starts with `BABP` can't carry provenance unambiguously.
+use crate::*;
+use crate::compressed::compress_entry;
+
+
+const PROVENANCE_MAGIC: [u8; 4] = *b"BABP";
+
+
+/// One stage an entry passed through: it was entry `index` of `source`, and `transform` was applied to it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProvenanceStep {
+	/// The label of the blob the entry was read from, such as its file name
+	pub source: String,
+	/// The index of the entry in that blob
+	pub index: usize,
+	/// The name of what the stage did to the entry, such as `retain` or `compress`
+	pub transform: String
+}
+
+
+/// Where one entry of a blob came from, read by `blob_provenance`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ProvenanceRecord {
+	/// Every stage the entry passed through, oldest first
+	pub steps: Vec<ProvenanceStep>
+}
+
+
+impl ProvenanceRecord {
+	/// Returns the first stage, which names the blob the entry originally came from and its index there
+	pub fn origin(&self) -> Option<&ProvenanceStep> {
+		self.steps.first()
+	}
+
+	/// Returns the record of an entry that passed through one more stage after this one
+	fn then(&self, step: ProvenanceStep) -> Self {
+		let mut record = self.clone();
+		record.steps.push(step);
+		record
+	}
+
+	fn encode(&self) -> Vec<u8> {
+		let mut blob = Blob::new();
+		for step in &self.steps {
+			blob.push(step.source.as_bytes());
+			blob.push(&(step.index as u64).to_le_bytes());
+			blob.push(step.transform.as_bytes());
+		}
+		blob.into_bytes()
+	}
+
+	fn decode(bytes: &[u8]) -> Option<Self> {
+		let parts = blob_to_byte_arrays(bytes).ok()?;
+		if !parts.len().is_multiple_of(3) {
+			return None;
+		}
+		let steps = parts.chunks_exact(3).map(|step| {
+			Some(ProvenanceStep {
+				source: std::str::from_utf8(step[0]).ok()?.to_string(),
+				index: usize::try_from(u64::from_le_bytes(step[1].try_into().ok()?)).ok()?,
+				transform: std::str::from_utf8(step[2]).ok()?.to_string()
+			})
+		});
+		Some(ProvenanceRecord { steps: steps.collect::<Option<_>>()? })
+	}
+}
+
+
+/// Builds a blob with a provenance record for every entry, made by `with_provenance`
+#[derive(Debug, Clone)]
+pub struct ProvenanceBuilder {
+	builder: BlobBuilder,
+	source_label: String,
+	records: Vec<ProvenanceRecord>
+}
+
+
+/// Wraps `builder` so every entry pushed records that it came from `source_label`, and where it was before that if the source had provenance
+///
+/// A flagged builder works too, and its records cover the entries after its header. The repacks below refuse flagged blobs, since they would drop the flags.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut builder = with_provenance(BlobBuilder::new(), "textures.blob");
+/// builder.push(4, "resize", b"small texture").unwrap();
+/// let blob = builder.finish().unwrap();
+///
+/// let records = blob_provenance(&blob).unwrap().unwrap();
+/// assert_eq!(records[0].origin().map(|step| (step.source.as_str(), step.index)), Some(("textures.blob", 4)));
+/// assert_eq!(blob_to_byte_arrays(blob_without_provenance(&blob).unwrap()).unwrap(), [b"small texture"]);
+/// ```
+pub fn with_provenance(builder: BlobBuilder, source_label: &str) -> ProvenanceBuilder {
+	ProvenanceBuilder { builder, source_label: source_label.to_string(), records: Vec::new() }
+}
+
+
+impl ProvenanceBuilder {
+	/// Appends a slice that was entry `source_index` of the source, changed by `transform`
+	///
+	/// # Errors
+	///
+	/// The same as `BlobBuilder::push`
+	pub fn push(&mut self, source_index: usize, transform: &str, byte_arr: &[u8]) -> Result<(), BlobWriteError> {
+		self.push_derived(&ProvenanceRecord::default(), source_index, transform, byte_arr)
+	}
+
+	/// Appends a slice like `push`, for an entry of a source that had provenance, so the new record continues `earlier`
+	///
+	/// # Errors
+	///
+	/// The same as `BlobBuilder::push`
+	pub fn push_derived(&mut self, earlier: &ProvenanceRecord, source_index: usize, transform: &str, byte_arr: &[u8]) -> Result<(), BlobWriteError> {
+		self.builder.push(byte_arr)?;
+		let step = ProvenanceStep { source: self.source_label.clone(), index: source_index, transform: transform.to_string() };
+		self.records.push(earlier.then(step));
+		Ok(())
+	}
+
+	/// Returns the number of entries pushed
+	pub fn len(&self) -> usize {
+		self.records.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.records.is_empty()
+	}
+
+	/// Returns the encoded blob with the provenance entry after the payloads
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobWriteError::TooLarge)` if the provenance entry doesn't fit within `u32::MAX` bytes, otherwise the same as `BlobBuilder::finish`
+	pub fn finish(self) -> Result<Vec<u8>, BlobWriteError> {
+		let mut records = Blob::new();
+		for record in &self.records {
+			records.push(&record.encode());
+		}
+		let records = records.into_bytes();
+
+		let mut blob = self.builder.finish()?;
+		let idx_end = u32::try_from(blob.len() + 4 + PROVENANCE_MAGIC.len() + records.len()).map_err(|_| BlobWriteError::TooLarge)?;
+		blob.extend(idx_end.to_ne_bytes());
+		blob.extend(PROVENANCE_MAGIC);
+		blob.extend(records);
+		Ok(blob)
+	}
+}
+
+
+/// The trailing entry of a blob with provenance
+struct ProvenanceEntry<'a> {
+	/// The index of the entry, which is the number of slices before it
+	index: usize,
+	/// Where the entry's index starts in the blob
+	idx_start: usize,
+	/// The nested blob of records, after the magic
+	records: &'a [u8]
+}
+
+
+/// Returns the provenance entry of a blob, or `None` if its last slice isn't one
+fn provenance_entry(blob: &[u8]) -> Result<Option<ProvenanceEntry<'_>>, BlobReadError> {
+	let mut last = None;
+	let mut idx_start = 0;
+	for (index, byte_arr) in blob_iter(blob).enumerate() {
+		let byte_arr = byte_arr?;
+		last = Some((ProvenanceEntry { index, idx_start, records: &[] }, byte_arr));
+		idx_start += 4 + byte_arr.len();
+	}
+	Ok(last.and_then(|(entry, byte_arr)| Some(ProvenanceEntry { records: byte_arr.strip_prefix(&PROVENANCE_MAGIC)?, ..entry })))
+}
+
+
+/// Reads the provenance records of a blob made by `with_provenance` or one of the repacks that keep provenance, one per payload entry,
+/// or returns `Ok(None)` if the blob has no provenance
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InvalidMetadata)` with the index of the provenance entry if it is malformed or holds more records than there are payloads,
+/// otherwise the same as `blob_to_byte_arrays`
+pub fn blob_provenance(blob: &[u8]) -> Result<Option<Vec<ProvenanceRecord>>, BlobReadError> {
+	let Some(entry) = provenance_entry(blob)? else {
+		return Ok(None);
+	};
+	let records: Option<Vec<_>> = blob_iter(entry.records).map(|record| ProvenanceRecord::decode(record.ok()?)).collect();
+	match records {
+		Some(records) if records.len() <= entry.index => Ok(Some(records)),
+		_ => Err(BlobReadError::InvalidMetadata(entry.index))
+	}
+}
+
+
+/// Returns the part of a blob before its provenance entry, which is a valid blob of the payloads alone, or the whole blob if it has no provenance
+///
+/// Formatted blobs such as compressed blobs should be read from this, so the provenance entry isn't mistaken for one of their entries.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+pub fn blob_without_provenance(blob: &[u8]) -> Result<&[u8], BlobReadError> {
+	Ok(match provenance_entry(blob)? {
+		Some(entry) => &blob[..entry.idx_start],
+		None => blob
+	})
+}
+
+
+/// The payloads of a blob being repacked, with the record of each if it has provenance
+struct Source<'a> {
+	payloads: Vec<&'a [u8]>,
+	records: Option<Vec<ProvenanceRecord>>
+}
+
+
+impl<'a> Source<'a> {
+	fn read(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		let payloads = blob_without_provenance(blob)?;
+		// The header slice and flags bytes of a flagged blob would be repacked as payload, and its sensitive entries would lose their flags
+		if is_flagged(payloads) {
+			return Err(BlobReadError::UnexpectedFlagged);
+		}
+		Ok(Source { records: blob_provenance(blob)?, payloads: blob_to_byte_arrays(payloads)? })
+	}
+}
+
+
+/// Pushes `byte_arr`, which was entry `index` of the source, continuing its earlier record if the source had provenance
+fn push_from(builder: &mut ProvenanceBuilder, records: Option<&[ProvenanceRecord]>, index: usize, transform: &str, byte_arr: &[u8]) -> Result<(), BlobReadError> {
+	let pushed = match records.and_then(|records| records.get(index)) {
+		Some(earlier) => builder.push_derived(earlier, index, transform, byte_arr),
+		None => builder.push(index, transform, byte_arr)
+	};
+	// The output holds no more than the inputs and the records, so only provenance that has grown past `u32::MAX` bytes fails
+	pushed.map_err(|_| BlobReadError::TooLarge)
+}
+
+
+/// Repacks a blob with each entry replaced by `f(index, entry)`, recording `transform` as the stage for every entry
+///
+/// If the blob already has provenance, each entry's record is continued, so it names every stage before this one.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::UnexpectedFlagged)` for a flagged blob, which would lose its flags, `Err(BlobReadError::TooLarge)` if the result
+/// would be larger than `u32::MAX` bytes, otherwise the same as `blob_provenance`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"keep", b"drop", b"keep too"]);
+/// let kept = blob_retain_with_provenance(&blob, "raw.blob", |_, entry| entry.starts_with(b"keep")).unwrap();
+/// let upper = blob_map_with_provenance(&kept, "kept.blob", "uppercase", |_, entry| entry.to_ascii_uppercase()).unwrap();
+///
+/// let record = &blob_provenance(&upper).unwrap().unwrap()[1];
+/// let stages: Vec<_> = record.steps.iter().map(|step| (step.source.as_str(), step.index, step.transform.as_str())).collect();
+/// assert_eq!(stages, [("raw.blob", 2, "retain"), ("kept.blob", 1, "uppercase")]);
+/// ```
+pub fn blob_map_with_provenance(blob: &[u8], source_label: &str, transform: &str, mut f: impl FnMut(usize, &[u8]) -> Vec<u8>) -> Result<Vec<u8>, BlobReadError> {
+	let Source { payloads, records } = Source::read(blob)?;
+	let mut builder = with_provenance(BlobBuilder::with_expected_entries(payloads.len()), source_label);
+	for (index, byte_arr) in payloads.into_iter().enumerate() {
+		push_from(&mut builder, records.as_deref(), index, transform, &f(index, byte_arr))?;
+	}
+	builder.finish().map_err(|_| BlobReadError::TooLarge)
+}
+
+
+/// Repacks a blob with only the entries `keep` returns true for, recording the stage as `retain`
+///
+/// # Errors
+///
+/// The same as `blob_map_with_provenance`
+pub fn blob_retain_with_provenance(blob: &[u8], source_label: &str, mut keep: impl FnMut(usize, &[u8]) -> bool) -> Result<Vec<u8>, BlobReadError> {
+	let Source { payloads, records } = Source::read(blob)?;
+	let mut builder = with_provenance(BlobBuilder::new(), source_label);
+	for (index, byte_arr) in payloads.into_iter().enumerate() {
+		if keep(index, byte_arr) {
+			push_from(&mut builder, records.as_deref(), index, "retain", byte_arr)?;
+		}
+	}
+	builder.finish().map_err(|_| BlobReadError::TooLarge)
+}
+
+
+/// Repacks a blob with every entry compressed the same way as `byte_arrays_to_compressed_blob`, recording the stage as `compress`
+///
+/// The payloads are a compressed blob, which `CompressedBlobView` reads from `blob_without_provenance` of the result.
+///
+/// # Errors
+///
+/// The same as `blob_map_with_provenance`
+pub fn blob_compress_with_provenance(blob: &[u8], source_label: &str) -> Result<Vec<u8>, BlobReadError> {
+	blob_map_with_provenance(blob, source_label, "compress", |_, byte_arr| compress_entry(byte_arr))
+}
+
+
+/// Concatenates blobs like `blob_concat`, each given with its source label, recording the stage as `merge`
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InPart)` with the position of the first invalid blob and its error, or `Err(BlobReadError::TooLarge)` if the result would be
+/// larger than `u32::MAX` bytes
+pub fn blob_concat_with_provenance(sources: &[(&str, &[u8])]) -> Result<Vec<u8>, BlobReadError> {
+	let mut builder = with_provenance(BlobBuilder::new(), "");
+	for (part, &(source_label, blob)) in sources.iter().enumerate() {
+		let Source { payloads, records } = Source::read(blob).map_err(|error| BlobReadError::InPart { part, error: Box::new(error) })?;
+		builder.source_label = source_label.to_string();
+		for (index, byte_arr) in payloads.into_iter().enumerate() {
+			push_from(&mut builder, records.as_deref(), index, "merge", byte_arr)?;
+		}
+	}
+	builder.finish().map_err(|_| BlobReadError::TooLarge)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn step(source: &str, index: usize, transform: &str) -> ProvenanceStep {
+		ProvenanceStep { source: source.to_string(), index, transform: transform.to_string() }
+	}
+
+	#[test]
+	fn chained_stages_keep_the_original_indices() {
+		let raw = byte_arrays_to_blob(&[b"keep: a", b"drop", b"keep: b", &[0u8; 64]]);
+		assert_eq!(blob_provenance(&raw), Ok(None));
+
+		let retained = blob_retain_with_provenance(&raw, "raw.blob", |_, entry| entry != b"drop").unwrap();
+		let compressed = blob_compress_with_provenance(&retained, "retained.blob").unwrap();
+
+		let records = blob_provenance(&compressed).unwrap().unwrap();
+		assert_eq!(records.len(), 3);
+		for (record, original) in records.iter().zip([0, 2, 3]) {
+			assert_eq!(record.origin(), Some(&step("raw.blob", original, "retain")));
+		}
+		assert_eq!(records[2].steps, [step("raw.blob", 3, "retain"), step("retained.blob", 2, "compress")]);
+
+		// The payloads alone are a compressed blob of the entries that were kept
+		let view = CompressedBlobView::new(blob_without_provenance(&compressed).unwrap()).unwrap();
+		assert_eq!(view.get(1).unwrap().read_to_vec().unwrap(), b"keep: b");
+		assert_eq!(view.get(2).unwrap().read_to_vec().unwrap(), [0u8; 64]);
+	}
+
+	#[test]
+	fn merge_names_each_source() {
+		let first = blob_map_with_provenance(&byte_arrays_to_blob(&[b"x", b"y"]), "a.blob", "copy", |_, entry| entry.to_vec()).unwrap();
+		let plain = byte_arrays_to_blob(&[b"z"]);
+		let merged = blob_concat_with_provenance(&[("first.blob", &first), ("b.blob", &plain)]).unwrap();
+
+		assert_eq!(blob_to_byte_arrays(blob_without_provenance(&merged).unwrap()).unwrap(), [b"x", b"y", b"z"]);
+		let records = blob_provenance(&merged).unwrap().unwrap();
+		assert_eq!(records[1].steps, [step("a.blob", 1, "copy"), step("first.blob", 1, "merge")]);
+		assert_eq!(records[2].steps, [step("b.blob", 0, "merge")]);
+
+		let error = blob_concat_with_provenance(&[("ok", &plain), ("bad", &plain[..3])]).unwrap_err();
+		assert_eq!(error.part(), Some(1));
+	}
+
+	#[test]
+	fn builders_and_malformed_records() {
+		let mut builder = with_provenance(BlobBuilder::flagged(), "secrets.blob");
+		builder.push(7, "copy", b"public").unwrap();
+		assert_eq!(builder.len(), 1);
+		let blob = builder.finish().unwrap();
+		assert_eq!(blob_provenance(&blob).unwrap().unwrap()[0].steps, [step("secrets.blob", 7, "copy")]);
+		assert_eq!(blob_to_flagged_entries(blob_without_provenance(&blob).unwrap()).unwrap()[0].data, b"public");
+
+		// Repacking would read the flagged header as an entry and drop the flags, so flagged blobs are refused, with provenance or without
+		let mut flagged = BlobBuilder::flagged();
+		flagged.push(b"a").unwrap();
+		flagged.push_sensitive(b"secret").unwrap();
+		let flagged = flagged.finish().unwrap();
+		for source in [&blob, &flagged] {
+			assert_eq!(blob_retain_with_provenance(source, "src.blob", |_, _| true), Err(BlobReadError::UnexpectedFlagged));
+			assert_eq!(blob_map_with_provenance(source, "src.blob", "copy", |_, entry| entry.to_vec()), Err(BlobReadError::UnexpectedFlagged));
+			assert_eq!(blob_compress_with_provenance(source, "src.blob"), Err(BlobReadError::UnexpectedFlagged));
+			let error = blob_concat_with_provenance(&[("plain", &byte_arrays_to_blob(&[b"x"])), ("src.blob", source)]).unwrap_err();
+			assert_eq!(error, BlobReadError::InPart { part: 1, error: Box::new(BlobReadError::UnexpectedFlagged) });
+		}
+
+		let empty = with_provenance(BlobBuilder::new(), "none").finish().unwrap();
+		assert_eq!(blob_provenance(&empty), Ok(Some(Vec::new())));
+		assert_eq!(blob_without_provenance(&empty), Ok(&[][..]));
+		assert_eq!(blob_without_provenance(&[]), Ok(&[][..]));
+
+		// A record that doesn't decode, and more records than there are entries
+		let bad_record = byte_arrays_to_blob(&[b"entry", &[&PROVENANCE_MAGIC[..], &byte_arrays_to_blob(&[b"not a record"])].concat()]);
+		assert_eq!(blob_provenance(&bad_record), Err(BlobReadError::InvalidMetadata(1)));
+		let records = byte_arrays_to_blob(&[&ProvenanceRecord::default().encode(), &ProvenanceRecord::default().encode()]);
+		let too_many = byte_arrays_to_blob(&[b"entry", &[&PROVENANCE_MAGIC[..], &records].concat()]);
+		assert_eq!(blob_provenance(&too_many), Err(BlobReadError::InvalidMetadata(1)));
+		assert!(blob_provenance(&too_many[..too_many.len() - 1]).is_err());
+	}
+}
@@ -0,0 +1,431 @@
+//! `RecordLayout`, a description of a fixed layout record such as a C struct, for reading entries of them without unsafe casts
+use crate::*;
+
+
+/// The type of one field of a record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldType {
+	U8,
+	U16,
+	U32,
+	U64,
+	F32,
+	F64
+}
+
+
+impl FieldType {
+	/// Returns the number of bytes the field takes
+	pub fn size(self) -> usize {
+		match self {
+			FieldType::U8 => 1,
+			FieldType::U16 => 2,
+			FieldType::U32 | FieldType::F32 => 4,
+			FieldType::U64 | FieldType::F64 => 8
+		}
+	}
+}
+
+
+/// The value of one field of a record, read by `RecordLayout::read`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue {
+	U8(u8),
+	U16(u16),
+	U32(u32),
+	U64(u64),
+	F32(f32),
+	F64(f64)
+}
+
+
+/// One field of a `RecordLayout`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordField {
+	name: String,
+	field_type: FieldType,
+	endianness: Endianness,
+	offset: usize
+}
+
+
+impl RecordField {
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	pub fn field_type(&self) -> FieldType {
+		self.field_type
+	}
+
+	pub fn endianness(&self) -> Endianness {
+		self.endianness
+	}
+
+	/// Returns where the field starts within a record
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+}
+
+
+/// Errors that can occur when reading records from an entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LayoutError {
+	/// The entry is `len` bytes long, which ends before field `field` of record `record`, which needs `needed` bytes
+	TooShort {
+		record: usize,
+		field: String,
+		needed: usize,
+		len: usize
+	},
+	/// There are `len` bytes left over at `at` after the last whole record
+	TrailingBytes {
+		at: usize,
+		len: usize
+	},
+	/// The layout has no fields, so an entry can't be split into records of it
+	EmptyLayout
+}
+
+
+impl std::error::Error for LayoutError {}
+
+impl fmt::Display for LayoutError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// The fields of a fixed layout record, in order and with no padding between them
+///
+/// Fields are added one at a time, each starting where the last one ended. Records are read with plain byte conversions, so any entry is safe
+/// to read and a malformed one is an error. A layout is usually built once and kept, since reading looks fields up by name.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let layout = RecordLayout::new().u32_le("id").u32_le("offset").u32_le("len");
+/// let entry = [7, 0, 0, 0, 64, 0, 0, 0, 12, 0, 0, 0];
+///
+/// let record = layout.read(&entry).unwrap();
+/// assert_eq!(record.get_u32("id"), Some(7));
+/// assert_eq!(record.value(2), Some(FieldValue::U32(12)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordLayout {
+	fields: Vec<RecordField>,
+	size: usize
+}
+
+
+impl RecordLayout {
+	/// Returns a layout with no fields
+	pub fn new() -> Self {
+		RecordLayout { fields: Vec::new(), size: 0 }
+	}
+
+	/// Adds a field after the current last field
+	pub fn with_field(mut self, name: &str, field_type: FieldType, endianness: Endianness) -> Self {
+		self.fields.push(RecordField { name: name.to_string(), field_type, endianness, offset: self.size });
+		self.size += field_type.size();
+		self
+	}
+
+	pub fn u8(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U8, Endianness::Little)
+	}
+
+	pub fn u16_le(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U16, Endianness::Little)
+	}
+
+	pub fn u16_be(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U16, Endianness::Big)
+	}
+
+	pub fn u32_le(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U32, Endianness::Little)
+	}
+
+	pub fn u32_be(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U32, Endianness::Big)
+	}
+
+	pub fn u64_le(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U64, Endianness::Little)
+	}
+
+	pub fn u64_be(self, name: &str) -> Self {
+		self.with_field(name, FieldType::U64, Endianness::Big)
+	}
+
+	pub fn f32_le(self, name: &str) -> Self {
+		self.with_field(name, FieldType::F32, Endianness::Little)
+	}
+
+	pub fn f32_be(self, name: &str) -> Self {
+		self.with_field(name, FieldType::F32, Endianness::Big)
+	}
+
+	pub fn f64_le(self, name: &str) -> Self {
+		self.with_field(name, FieldType::F64, Endianness::Little)
+	}
+
+	pub fn f64_be(self, name: &str) -> Self {
+		self.with_field(name, FieldType::F64, Endianness::Big)
+	}
+
+	/// Returns the number of bytes in one record
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
+	pub fn fields(&self) -> &[RecordField] {
+		&self.fields
+	}
+
+	/// Returns the position of the first field called `name`
+	pub fn index_of(&self, name: &str) -> Option<usize> {
+		self.fields.iter().position(|field| field.name == name)
+	}
+
+	/// Reads an entry holding exactly one record
+	///
+	/// # Errors
+	///
+	/// Returns `Err(LayoutError::TooShort)` naming the first field the entry ends before, or `Err(LayoutError::TrailingBytes)` if it is longer than one record
+	pub fn read(&self, entry: &[u8]) -> Result<RecordValues<'_>, LayoutError> {
+		let record = self.read_record(entry, 0, entry.len())?;
+		if entry.len() > self.size {
+			return Err(LayoutError::TrailingBytes { at: self.size, len: entry.len() - self.size });
+		}
+		Ok(record)
+	}
+
+	/// Reads an entry holding an array of records, one after another
+	///
+	/// # Errors
+	///
+	/// Returns `Err(LayoutError::TrailingBytes)` if the entry isn't a whole number of records, or `Err(LayoutError::EmptyLayout)` if the layout has no fields
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::*;
+	///
+	/// let layout = RecordLayout::new().u8("kind").u16_be("value");
+	/// let records = layout.read_all(&[1, 0, 5, 2, 1, 0]).unwrap();
+	///
+	/// assert_eq!(records.len(), 2);
+	/// assert_eq!(records[1].get_u16("value"), Some(256));
+	/// assert_eq!(layout.read_all(&[1, 0, 5, 2]), Err(LayoutError::TrailingBytes { at: 3, len: 1 }));
+	/// ```
+	pub fn read_all(&self, entry: &[u8]) -> Result<Vec<RecordValues<'_>>, LayoutError> {
+		if self.size == 0 {
+			return Err(LayoutError::EmptyLayout);
+		}
+		let chunks = entry.chunks_exact(self.size);
+		let remainder = chunks.remainder().len();
+		if remainder != 0 {
+			return Err(LayoutError::TrailingBytes { at: entry.len() - remainder, len: remainder });
+		}
+		chunks.enumerate().map(|(i, record)| self.read_record(record, i, entry.len())).collect()
+	}
+
+	/// Reads record number `index` from `record`, which is the part of an entry `len` bytes long that it starts at
+	fn read_record(&self, record: &[u8], index: usize, len: usize) -> Result<RecordValues<'_>, LayoutError> {
+		let values = self.fields.iter().map(|field| {
+			let end = field.offset + field.field_type.size();
+			let bytes = record.get(field.offset..end).ok_or_else(|| LayoutError::TooShort {
+				record: index,
+				field: field.name.clone(),
+				needed: index * self.size + end,
+				len
+			})?;
+			Ok(read_value(bytes, field.field_type, field.endianness))
+		}).collect::<Result<_, _>>()?;
+		Ok(RecordValues { layout: self, values })
+	}
+}
+
+
+/// Converts exactly `field_type.size()` bytes to a value
+fn read_value(bytes: &[u8], field_type: FieldType, endianness: Endianness) -> FieldValue {
+	fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+		let mut array = [0u8; N];
+		array.copy_from_slice(bytes);
+		array
+	}
+
+	macro_rules! number {
+		($t:ty) => {
+			match endianness {
+				Endianness::Little => <$t>::from_le_bytes(array(bytes)),
+				Endianness::Big => <$t>::from_be_bytes(array(bytes))
+			}
+		};
+	}
+
+	match field_type {
+		FieldType::U8 => FieldValue::U8(number!(u8)),
+		FieldType::U16 => FieldValue::U16(number!(u16)),
+		FieldType::U32 => FieldValue::U32(number!(u32)),
+		FieldType::U64 => FieldValue::U64(number!(u64)),
+		FieldType::F32 => FieldValue::F32(number!(f32)),
+		FieldType::F64 => FieldValue::F64(number!(f64))
+	}
+}
+
+
+/// The fields of one record read with a `RecordLayout`, by name or by position
+///
+/// The typed getters return `None` if there is no field with that name or if it has a different type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordValues<'l> {
+	layout: &'l RecordLayout,
+	values: Vec<FieldValue>
+}
+
+
+impl RecordValues<'_> {
+	/// Returns the value of the first field called `name`
+	pub fn get(&self, name: &str) -> Option<FieldValue> {
+		self.value(self.layout.index_of(name)?)
+	}
+
+	/// Returns the value of field `index`, in layout order
+	pub fn value(&self, index: usize) -> Option<FieldValue> {
+		self.values.get(index).copied()
+	}
+
+	/// Returns every value, in layout order
+	pub fn values(&self) -> &[FieldValue] {
+		&self.values
+	}
+
+	pub fn get_u8(&self, name: &str) -> Option<u8> {
+		match self.get(name)? {
+			FieldValue::U8(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn get_u16(&self, name: &str) -> Option<u16> {
+		match self.get(name)? {
+			FieldValue::U16(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn get_u32(&self, name: &str) -> Option<u32> {
+		match self.get(name)? {
+			FieldValue::U32(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn get_u64(&self, name: &str) -> Option<u64> {
+		match self.get(name)? {
+			FieldValue::U64(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn get_f32(&self, name: &str) -> Option<f32> {
+		match self.get(name)? {
+			FieldValue::F32(value) => Some(value),
+			_ => None
+		}
+	}
+
+	pub fn get_f64(&self, name: &str) -> Option<f64> {
+		match self.get(name)? {
+			FieldValue::F64(value) => Some(value),
+			_ => None
+		}
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn mixed(endianness: Endianness) -> RecordLayout {
+		let layout = RecordLayout::new().u8("flags");
+		match endianness {
+			Endianness::Little => layout.u16_le("kind").u32_le("id").u64_le("offset").f32_le("scale"),
+			Endianness::Big => layout.u16_be("kind").u32_be("id").u64_be("offset").f32_be("scale")
+		}
+	}
+
+	fn encode(endianness: Endianness, id: u32) -> Vec<u8> {
+		let mut record = vec![0x81];
+		match endianness {
+			Endianness::Little => {
+				record.extend(513u16.to_le_bytes());
+				record.extend(id.to_le_bytes());
+				record.extend((u64::from(id) << 32).to_le_bytes());
+				record.extend(1.5f32.to_le_bytes());
+			},
+			Endianness::Big => {
+				record.extend(513u16.to_be_bytes());
+				record.extend(id.to_be_bytes());
+				record.extend((u64::from(id) << 32).to_be_bytes());
+				record.extend(1.5f32.to_be_bytes());
+			}
+		}
+		record
+	}
+
+	#[test]
+	fn mixed_fields_in_both_byte_orders() {
+		for endianness in [Endianness::Little, Endianness::Big] {
+			let layout = mixed(endianness);
+			assert_eq!(layout.size(), 19);
+			assert_eq!(layout.fields()[3].offset(), 7);
+
+			let record = layout.read(&encode(endianness, 9)).unwrap();
+			assert_eq!(record.get_u8("flags"), Some(0x81));
+			assert_eq!(record.get_u16("kind"), Some(513));
+			assert_eq!(record.get_u32("id"), Some(9));
+			assert_eq!(record.get_u64("offset"), Some(9 << 32));
+			assert_eq!(record.get_f32("scale"), Some(1.5));
+			assert_eq!(record.value(1), Some(FieldValue::U16(513)));
+			assert_eq!(record.values().len(), 5);
+
+			// Wrong type or no such field
+			assert_eq!(record.get_u64("id"), None);
+			assert_eq!(record.get("missing"), None);
+			assert_eq!(record.value(5), None);
+
+			let entry: Vec<u8> = (0..4).flat_map(|id| encode(endianness, id)).collect();
+			let records = layout.read_all(&entry).unwrap();
+			assert_eq!(records.iter().map(|record| record.get_u32("id").unwrap()).collect::<Vec<_>>(), [0, 1, 2, 3]);
+		}
+
+		let layout = RecordLayout::new().f64_be("x").f64_le("y");
+		let entry = [2.25f64.to_be_bytes(), (-4.0f64).to_le_bytes()].concat();
+		assert_eq!(layout.read(&entry).unwrap().values(), [FieldValue::F64(2.25), FieldValue::F64(-4.0)]);
+	}
+
+	#[test]
+	fn short_and_long_entries_are_rejected() {
+		let layout = mixed(Endianness::Little);
+		let record = encode(Endianness::Little, 1);
+
+		assert_eq!(layout.read(&record[..5]), Err(LayoutError::TooShort { record: 0, field: "id".to_string(), needed: 7, len: 5 }));
+		assert_eq!(layout.read(&[]), Err(LayoutError::TooShort { record: 0, field: "flags".to_string(), needed: 1, len: 0 }));
+		assert_eq!(layout.read(&[record.as_slice(), &[0]].concat()), Err(LayoutError::TrailingBytes { at: 19, len: 1 }));
+
+		let entry = [record.as_slice(), &record[..18]].concat();
+		assert_eq!(layout.read_all(&entry), Err(LayoutError::TrailingBytes { at: 19, len: 18 }));
+		assert!(layout.read_all(&[]).unwrap().is_empty());
+		assert_eq!(RecordLayout::new().read_all(&[1]), Err(LayoutError::EmptyLayout));
+	}
+}
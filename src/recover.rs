@@ -0,0 +1,241 @@
+//! Best effort recovery of entries from a damaged blob
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+
+/// A resynchronised run of indexes must be at least this long to be trusted, unless it ends exactly at the end of the blob
+const MIN_PARTIAL_CHAIN: usize = 3;
+
+
+/// How much a recovered region can be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryConfidence {
+	/// Read from the start of the blob before any damage, exactly as `blob_to_byte_arrays` would
+	Prefix,
+	/// Found by scanning past damage, and its indexes lead exactly to the end of the blob. Very unlikely to be a false match
+	ResyncedToEnd,
+	/// Found by scanning past damage, but its indexes stop at more damage before the end of the blob. This could be a false match inside a payload
+	ResyncedPartial
+}
+
+
+/// A run of consecutive recovered entries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredRegion {
+	/// Indexes into `RecoveryReport::recovered`
+	pub entries: Range<usize>,
+	/// Bytes of the blob covered by the region, including indexes
+	pub bytes: Range<usize>,
+	pub confidence: RecoveryConfidence
+}
+
+
+/// The result of `recover_blob`
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RecoveryReport<'a> {
+	/// The byte range of each recovered payload (not including its index) and the payload itself, in blob order
+	pub recovered: Vec<(Range<usize>, &'a [u8])>,
+	/// Byte ranges that could not be explained as entries
+	pub skipped: Vec<Range<usize>>,
+	/// The runs of entries in `recovered`, with how much each can be trusted
+	pub regions: Vec<RecoveredRegion>
+}
+
+
+impl RecoveryReport<'_> {
+	/// Returns true if the blob was undamaged, so everything was recovered with `RecoveryConfidence::Prefix`
+	pub fn is_complete(&self) -> bool {
+		self.skipped.is_empty() && self.regions.iter().all(|region| region.confidence == RecoveryConfidence::Prefix)
+	}
+}
+
+
+/// Follows indexes from `start`, returning where each payload starts and ends, and whether the chain ended exactly at the end of the blob
+fn follow_chain(blob: &[u8], start: usize) -> (Vec<Range<usize>>, bool) {
+	let mut payloads = Vec::new();
+	let mut iter = BlobIter { blob, format: BlobFormat::NativeEndian, idx_start: start, entry: 0, done: false };
+	while let Some(byte_arr) = iter.next() {
+		match byte_arr {
+			Ok(byte_arr) => payloads.push(iter.position() - byte_arr.len()..iter.position()),
+			Err(_) => return (payloads, false)
+		}
+	}
+	(payloads, true)
+}
+
+
+/// Returns the length of the chain starting at `start` and whether it ends cleanly, remembering results so each position is only followed once
+fn chain_len(blob: &[u8], start: usize, memo: &mut HashMap<usize, (usize, bool)>) -> (usize, bool) {
+	let mut path = Vec::new();
+	let mut position = start;
+	let mut result = (0, false);
+
+	loop {
+		if let Some(&known) = memo.get(&position) {
+			result = known;
+			break;
+		}
+		if position == blob.len() {
+			result = (0, true);
+			break;
+		}
+		match BlobFormat::NativeEndian.read_index(blob, position) {
+			Ok(idx_end) if idx_end <= blob.len() && idx_end >= position + 4 => {
+				path.push(position);
+				position = idx_end;
+			},
+			_ => break
+		}
+	}
+
+	for &position in path.iter().rev() {
+		result = (result.0 + 1, result.1);
+		memo.insert(position, result);
+	}
+	result
+}
+
+
+/// Recovers as many entries as possible from a damaged blob
+///
+/// The valid prefix is read first. From the first bad index, every later position is tried as the start of a new run of indexes,
+/// and the first run that either ends exactly at the end of the blob or is at least 3 entries long is taken. This repeats until the end of the blob.
+/// The result is deterministic and never panics, but payload bytes can happen to look like indexes, so check each region's confidence.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let entries: Vec<Vec<u8>> = (0..5).map(|i| vec![0xAB; 10 + i]).collect();
+/// let mut blob = byte_arrays_to_blob(&entries.iter().map(|e| e.as_slice()).collect::<Vec<_>>());
+///
+/// // Destroy the index of the second entry
+/// blob[14..18].fill(0);
+///
+/// let report = recover_blob(&blob);
+/// assert_eq!(report.recovered.len(), 4);
+/// assert_eq!(report.skipped, vec![14..29]);
+/// ```
+pub fn recover_blob(blob: &[u8]) -> RecoveryReport<'_> {
+	let mut report = RecoveryReport::default();
+	let mut memo = HashMap::new();
+
+	let (prefix, clean) = follow_chain(blob, 0);
+	let mut position = prefix.last().map_or(0, |payload| payload.end);
+	push_region(&mut report, blob, 0, prefix, RecoveryConfidence::Prefix);
+	if clean {
+		return report;
+	}
+
+	let mut skipped_start = position;
+	while position < blob.len() {
+		position += 1;
+		let (len, clean) = chain_len(blob, position, &mut memo);
+		if len == 0 || (!clean && len < MIN_PARTIAL_CHAIN) {
+			continue;
+		}
+
+		report.skipped.push(skipped_start..position);
+		let (payloads, _) = follow_chain(blob, position);
+		let confidence = match clean {
+			true => RecoveryConfidence::ResyncedToEnd,
+			false => RecoveryConfidence::ResyncedPartial
+		};
+		let start = position;
+		position = payloads.last().map_or(position, |payload| payload.end);
+		push_region(&mut report, blob, start, payloads, confidence);
+		skipped_start = position;
+	}
+
+	if skipped_start < blob.len() {
+		report.skipped.push(skipped_start..blob.len());
+	}
+	report
+}
+
+
+fn push_region<'a>(report: &mut RecoveryReport<'a>, blob: &'a [u8], start: usize, payloads: Vec<Range<usize>>, confidence: RecoveryConfidence) {
+	let Some(end) = payloads.last().map(|payload| payload.end) else {
+		return;
+	};
+	let first = report.recovered.len();
+	// The payloads were read from the blob by `follow_chain`, so every range is in it
+	report.recovered.extend(payloads.into_iter().filter_map(|payload| Some((payload.clone(), blob.get(payload)?))));
+	report.regions.push(RecoveredRegion { entries: first..report.recovered.len(), bytes: start..end, confidence });
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	fn ten_entries() -> Vec<Vec<u8>> {
+		(0..10).map(|i| vec![0xC0 | i as u8; 20 + i * 3]).collect()
+	}
+
+	#[test]
+	fn undamaged_blob() {
+		let entries = ten_entries();
+		let blob = entries_to_blob(&entries);
+		let report = recover_blob(&blob);
+
+		assert!(report.is_complete());
+		assert_eq!(report.recovered.iter().map(|(_, data)| data.to_vec()).collect::<Vec<_>>(), entries);
+		assert_eq!(recover_blob(&[]), RecoveryReport::default());
+	}
+
+	#[test]
+	fn zeroed_middle_header() {
+		let entries = ten_entries();
+		let mut blob = entries_to_blob(&entries);
+
+		// Zero the index of entry 5
+		let view = BlobView::new(&blob).unwrap();
+		let damaged = view.ends()[4];
+		let next = view.ends()[5];
+		blob[damaged..damaged+4].fill(0);
+
+		let report = recover_blob(&blob);
+		let recovered: Vec<Vec<u8>> = report.recovered.iter().map(|(_, data)| data.to_vec()).collect();
+		let mut expected = entries.clone();
+		expected.remove(5);
+		assert_eq!(recovered, expected);
+
+		assert_eq!(report.skipped, vec![damaged..next]);
+		assert_eq!(report.regions.len(), 2);
+		assert_eq!(report.regions[0].confidence, RecoveryConfidence::Prefix);
+		assert_eq!(report.regions[1].confidence, RecoveryConfidence::ResyncedToEnd);
+		assert_eq!(report.regions[1].entries, 5..9);
+		assert!(!report.is_complete());
+
+		for (range, data) in &report.recovered {
+			assert_eq!(&blob[range.clone()], *data);
+		}
+	}
+
+	#[test]
+	fn never_panics_and_is_deterministic() {
+		let mut blob = entries_to_blob(&ten_entries());
+		let mut rng = XorShift32::new(0x1234_5678);
+		for _ in 0..300 {
+			let random = rng.next_u32();
+			let i = random as usize % blob.len();
+			blob[i] = (random >> 8) as u8;
+
+			let report = recover_blob(&blob);
+			assert_eq!(report, recover_blob(&blob));
+
+			// Recovered entries and skipped ranges never overlap and stay in order
+			let mut covered: Vec<Range<usize>> = report.regions.iter().map(|region| region.bytes.clone()).chain(report.skipped.iter().cloned()).collect();
+			covered.sort_by_key(|range| range.start);
+			for pair in covered.windows(2) {
+				assert!(pair[0].end <= pair[1].start);
+			}
+		}
+	}
+}
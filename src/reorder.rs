@@ -0,0 +1,272 @@
+//! Functions that change the order of the slices in a blob
+use crate::*;
+
+
+/// Errors that can occur when moving the slices of a blob around
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BlobEditError {
+	/// The blob is invalid
+	Read(BlobReadError),
+	/// Slice `index` was asked for but the blob only has `len`
+	IndexOutOfRange {
+		index: usize,
+		len: usize
+	},
+	/// The two slices have different lengths, so swapping them would move every index between them. Use `blob_reorder` instead
+	UnequalLengthsRequireReencode {
+		a_len: usize,
+		b_len: usize
+	},
+	/// The permutation has `actual` indexes but the blob has `expected` slices
+	PermutationLength {
+		expected: usize,
+		actual: usize
+	},
+	/// The permutation uses slice `index` more than once
+	DuplicateIndex(usize)
+}
+
+
+impl std::error::Error for BlobEditError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			BlobEditError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for BlobEditError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for BlobEditError {
+	fn from(e: BlobReadError) -> Self {
+		BlobEditError::Read(e)
+	}
+}
+
+
+/// Swaps slices `a` and `b` of a blob in place, which is only possible when they have the same length
+///
+/// The payload bytes are swapped and no index changes. Only the indexes up to the later of the two slices are read.
+///
+/// # Errors
+///
+/// Returns `Err(BlobEditError::UnequalLengthsRequireReencode)` without changing the blob if the slices have different lengths,
+/// `Err(BlobEditError::IndexOutOfRange)` if the blob doesn't have both slices, or `Err(BlobEditError::Read)` if an index before them is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut blob = byte_arrays_to_blob(&[b"abc", b"-", b"xyz"]);
+/// blob_swap_entries(&mut blob, 0, 2).unwrap();
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap(), [&b"xyz"[..], b"-", b"abc"]);
+///
+/// assert_eq!(blob_swap_entries(&mut blob, 0, 1), Err(BlobEditError::UnequalLengthsRequireReencode { a_len: 3, b_len: 1 }));
+/// ```
+pub fn blob_swap_entries(blob: &mut [u8], a: usize, b: usize) -> Result<(), BlobEditError> {
+	let (first, second) = (a.min(b), a.max(b));
+	let mut first_range = 0..0;
+	let mut iter = blob_iter(blob);
+	for index in 0..=second {
+		let idx_start = iter.position();
+		let byte_arr = iter.next().ok_or(BlobEditError::IndexOutOfRange { index: second, len: index })??;
+		let range = idx_start + 4..idx_start + 4 + byte_arr.len();
+		if index == first {
+			first_range = range.clone();
+		}
+		if index == second {
+			if first == second {
+				return Ok(());
+			}
+			if first_range.len() != range.len() {
+				let (a_len, b_len) = if a < b { (first_range.len(), range.len()) } else { (range.len(), first_range.len()) };
+				return Err(BlobEditError::UnequalLengthsRequireReencode { a_len, b_len });
+			}
+			let (head, tail) = blob.split_at_mut(range.start);
+			head[first_range].swap_with_slice(&mut tail[..range.len()]);
+			return Ok(());
+		}
+	}
+	unreachable!()
+}
+
+
+/// Returns a copy of a blob with its slices in a new order. `permutation[i]` is the current index of the slice that moves to `i`,
+/// the same as the permutation returned by `blob_sort_by_key`
+///
+/// Every index is read once up front, then each payload is copied straight into its new place, so the only allocations are the
+/// new blob and one table of indexes.
+///
+/// # Errors
+///
+/// Returns `Err(BlobEditError::PermutationLength)` if the permutation doesn't have one index per slice, `Err(BlobEditError::IndexOutOfRange)`
+/// or `Err(BlobEditError::DuplicateIndex)` if it doesn't use each slice exactly once, or `Err(BlobEditError::Read)` if the blob is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"a", b"bb", b"ccc"]);
+/// let reordered = blob_reorder(&blob, &[2, 0, 1]).unwrap();
+/// assert_eq!(blob_to_byte_arrays(&reordered).unwrap(), [&b"ccc"[..], b"a", b"bb"]);
+///
+/// assert_eq!(blob_reorder(&blob, &[0, 0, 1]), Err(BlobEditError::DuplicateIndex(0)));
+/// ```
+pub fn blob_reorder(blob: &[u8], permutation: &[usize]) -> Result<Vec<u8>, BlobEditError> {
+	let view = BlobView::new(blob)?;
+	if permutation.len() != view.len() {
+		return Err(BlobEditError::PermutationLength { expected: view.len(), actual: permutation.len() });
+	}
+	let mut used = vec![false; view.len()];
+	for &index in permutation {
+		match used.get_mut(index) {
+			None => return Err(BlobEditError::IndexOutOfRange { index, len: view.len() }),
+			Some(true) => return Err(BlobEditError::DuplicateIndex(index)),
+			Some(seen) => *seen = true
+		}
+	}
+
+	// The same slices in a different order take the same number of bytes, so no index can overflow
+	let mut reordered = Vec::with_capacity(blob.len());
+	for &index in permutation {
+		let byte_arr = view.get(index).unwrap();
+		reordered.extend(((reordered.len() + 4 + byte_arr.len()) as u32).to_ne_bytes());
+		reordered.extend_from_slice(byte_arr);
+	}
+	Ok(reordered)
+}
+
+
+/// Sorts the slices in a blob by a key, returning the sorted blob and the permutation that was applied.
+///
+/// The key function is given the original index and the slice. The sort is stable, so slices with equal keys keep their relative order.
+/// The permutation maps new index to old index, so `permutation[i]` is the original position of slice `i` in the sorted blob.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` if the blob can not be read
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[3], &[1], &[2]]);
+/// let (sorted, permutation) = blob_sort_by_key(&blob, |_, data| data[0]).unwrap();
+///
+/// assert_eq!(blob_to_byte_arrays(&sorted).unwrap(), vec![&[1], &[2], &[3]]);
+/// assert_eq!(permutation, vec![1, 2, 0]);
+/// ```
+pub fn blob_sort_by_key<K: Ord>(blob: &[u8], mut key: impl FnMut(usize, &[u8]) -> K) -> Result<(Vec<u8>, Vec<usize>), BlobReadError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+
+	// Keys are computed once per slice, then sorted alongside the original index
+	let mut keyed: Vec<(K, usize)> = byte_arrays.iter().enumerate().map(|(i, byte_arr)| (key(i, byte_arr), i)).collect();
+	keyed.sort_by(|a, b| a.0.cmp(&b.0));
+
+	let permutation: Vec<usize> = keyed.into_iter().map(|(_, i)| i).collect();
+	let sorted: Vec<&[u8]> = permutation.iter().map(|&i| byte_arrays[i]).collect();
+
+	Ok((byte_arrays_to_blob(&sorted), permutation))
+}
+
+
+/// Sorts the slices in a blob by length, shortest first. See `blob_sort_by_key`
+pub fn blob_sort_by_len(blob: &[u8]) -> Result<(Vec<u8>, Vec<usize>), BlobReadError> {
+	blob_sort_by_key(blob, |_, byte_arr| byte_arr.len())
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sort_is_stable() {
+		let blob = byte_arrays_to_blob(&[&[1u8, 0u8], &[2u8], &[3u8, 0u8], &[4u8]]);
+		let (sorted, permutation) = blob_sort_by_len(&blob).unwrap();
+
+		// Equal lengths keep the order they had in the original blob
+		assert_eq!(permutation, vec![1, 3, 0, 2]);
+		assert_eq!(blob_to_byte_arrays(&sorted).unwrap(), vec![&[2u8][..], &[4u8], &[1u8, 0u8], &[3u8, 0u8]]);
+	}
+
+	#[test]
+	fn permutation_round_trips() {
+		let original: Vec<&[u8]> = vec![&[5u8, 5u8], &[], &[9u8], &[1u8], &[5u8, 5u8]];
+		let blob = byte_arrays_to_blob(&original);
+		let (sorted, permutation) = blob_sort_by_key(&blob, |_, byte_arr| byte_arr.to_vec()).unwrap();
+		let sorted = blob_to_byte_arrays(&sorted).unwrap();
+
+		for (new_idx, &old_idx) in permutation.iter().enumerate() {
+			assert_eq!(sorted[new_idx], original[old_idx]);
+		}
+
+		// Inverting the permutation restores the original order
+		let mut restored = vec![&[][..]; sorted.len()];
+		for (new_idx, &old_idx) in permutation.iter().enumerate() {
+			restored[old_idx] = sorted[new_idx];
+		}
+		assert_eq!(restored, original);
+	}
+
+	#[test]
+	fn swap_in_place() {
+		let mut blob = byte_arrays_to_blob(&[b"left", b"-", b"", b"rght", b"x"]);
+		let headers: Vec<usize> = (0..5).map(|index| BlobView::new(&blob).unwrap().range(index).unwrap().start - 4).collect();
+
+		blob_swap_entries(&mut blob, 3, 0).unwrap();
+		assert_eq!(blob_to_byte_arrays(&blob).unwrap(), [&b"rght"[..], b"-", b"", b"left", b"x"]);
+		// Only payload bytes moved
+		for (index, &header) in headers.iter().enumerate() {
+			assert_eq!(BlobView::new(&blob).unwrap().range(index).unwrap().start - 4, header);
+		}
+
+		let before = blob.clone();
+		blob_swap_entries(&mut blob, 1, 1).unwrap();
+		assert_eq!(blob, before);
+		assert_eq!(blob_swap_entries(&mut blob, 1, 4), Ok(()));
+		assert_eq!(blob_to_byte_arrays(&blob).unwrap(), [&b"rght"[..], b"x", b"", b"left", b"-"]);
+	}
+
+	#[test]
+	fn swap_errors() {
+		let mut blob = byte_arrays_to_blob(&[b"long entry", b"short", b""]);
+		let before = blob.clone();
+		assert_eq!(blob_swap_entries(&mut blob, 1, 0), Err(BlobEditError::UnequalLengthsRequireReencode { a_len: 5, b_len: 10 }));
+		assert_eq!(blob_swap_entries(&mut blob, 2, 0), Err(BlobEditError::UnequalLengthsRequireReencode { a_len: 0, b_len: 10 }));
+		assert_eq!(blob_swap_entries(&mut blob, 0, 3), Err(BlobEditError::IndexOutOfRange { index: 3, len: 3 }));
+		assert_eq!(blob, before);
+
+		let mut corrupt = before.clone();
+		corrupt[0] = 0xFF;
+		assert!(matches!(blob_swap_entries(&mut corrupt, 1, 2), Err(BlobEditError::Read(_))));
+	}
+
+	#[test]
+	fn reorder_permutations() {
+		let original: [&[u8]; 5] = [b"zero", &[], b"two", &[3u8; 300], b"4"];
+		let blob = byte_arrays_to_blob(&original);
+
+		assert_eq!(blob_reorder(&blob, &[0, 1, 2, 3, 4]).unwrap(), blob);
+		let reversed = blob_reorder(&blob, &[4, 3, 2, 1, 0]).unwrap();
+		assert_eq!(blob_to_byte_arrays(&reversed).unwrap(), original.iter().rev().copied().collect::<Vec<_>>());
+		assert_eq!(reversed.len(), blob.len());
+
+		// The permutation from a sort reproduces the sorted blob
+		let (sorted, permutation) = blob_sort_by_len(&blob).unwrap();
+		assert_eq!(blob_reorder(&blob, &permutation).unwrap(), sorted);
+
+		assert_eq!(blob_reorder(&blob, &[0, 1, 2, 2, 4]), Err(BlobEditError::DuplicateIndex(2)));
+		assert_eq!(blob_reorder(&blob, &[0, 1, 2, 3, 5]), Err(BlobEditError::IndexOutOfRange { index: 5, len: 5 }));
+		assert_eq!(blob_reorder(&blob, &[0, 1]), Err(BlobEditError::PermutationLength { expected: 5, actual: 2 }));
+		assert_eq!(blob_reorder(&[], &[]).unwrap(), Vec::<u8>::new());
+	}
+}
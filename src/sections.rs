@@ -0,0 +1,220 @@
+//! Blobs of sections with stable numeric IDs, for file formats whose set of sections grows over time
+//!
+//! # Layout
+//!
+//! Two slices per section: the `SectionId` as a little-endian u16, followed by the section data.
+//! Sections are stored in the order they were put, and no ID appears twice.
+//! A reader only looks up the IDs it knows about, so a blob written with more sections can still be read by older code.
+use crate::*;
+
+
+/// The ID of a section. IDs are stored in the blob, so once an ID has been used for a section it should never be given to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SectionId(pub u16);
+
+
+/// Errors that can occur when building a sectioned blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SectionError {
+	/// A section with this ID has already been put
+	Duplicate(SectionId)
+}
+
+
+impl std::error::Error for SectionError {}
+
+impl fmt::Display for SectionError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// Builds a sectioned blob. See the module layout above
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// const HEADER: SectionId = SectionId(1);
+/// const WORLD: SectionId = SectionId(2);
+///
+/// let mut builder = SectionsBuilder::new();
+/// builder.put(HEADER, b"save v3").unwrap();
+/// builder.put(WORLD, &[0; 64]).unwrap();
+/// assert_eq!(builder.put(HEADER, b"again"), Err(SectionError::Duplicate(HEADER)));
+///
+/// let blob = builder.finish();
+/// let sections = Sections::parse(&blob).unwrap();
+/// assert_eq!(sections.get(HEADER), Some(&b"save v3"[..]));
+/// assert_eq!(sections.get(SectionId(3)), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SectionsBuilder {
+	blob: Blob,
+	ids: Vec<SectionId>
+}
+
+
+impl SectionsBuilder {
+	pub fn new() -> Self {
+		SectionsBuilder::default()
+	}
+
+	/// Adds a section
+	///
+	/// # Errors
+	///
+	/// Returns `Err(SectionError::Duplicate)` without adding anything if a section with `id` has already been put
+	///
+	/// # Panics
+	///
+	/// Panics if the blob would become larger than `u32::MAX` bytes
+	pub fn put(&mut self, id: SectionId, data: &[u8]) -> Result<(), SectionError> {
+		if self.ids.contains(&id) {
+			return Err(SectionError::Duplicate(id));
+		}
+		self.ids.push(id);
+		self.blob.push(&id.0.to_le_bytes());
+		self.blob.push(data);
+		Ok(())
+	}
+
+	/// Returns the encoded blob
+	pub fn finish(self) -> Vec<u8> {
+		self.blob.into_bytes()
+	}
+}
+
+
+/// A parsed sectioned blob, borrowing the data of each section. See the module layout above
+///
+/// Every section is kept, including ones with IDs the reader doesn't know about, so they can be listed with `iter` or copied into a new blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sections<'a> {
+	sections: Vec<(SectionId, &'a [u8])>
+}
+
+
+impl<'a> Sections<'a> {
+	/// Reads every section of a blob
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InvalidMetadata)` with the index of the section if its ID is not 2 bytes or appears twice,
+	/// or `Err(BlobReadError::UnpairedEntry)` if the blob ends with an ID and no data. Otherwise the same as `blob_to_byte_arrays`
+	pub fn parse(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		let mut sections: Vec<(SectionId, &'a [u8])> = Vec::new();
+		let mut iter = blob_iter(blob);
+		while let Some(id) = iter.next() {
+			let index = sections.len();
+			let id = <[u8; 2]>::try_from(id?).map_err(|_| BlobReadError::InvalidMetadata(index))?;
+			let id = SectionId(u16::from_le_bytes(id));
+			let data = iter.next().ok_or(BlobReadError::UnpairedEntry(index))??;
+			if sections.iter().any(|&(seen, _)| seen == id) {
+				return Err(BlobReadError::InvalidMetadata(index));
+			}
+			sections.push((id, data));
+		}
+		Ok(Sections { sections })
+	}
+
+	/// Returns the data of the section with `id`, or `None` if the blob has no such section
+	pub fn get(&self, id: SectionId) -> Option<&'a [u8]> {
+		self.sections.iter().find(|&&(seen, _)| seen == id).map(|&(_, data)| data)
+	}
+
+	pub fn contains(&self, id: SectionId) -> bool {
+		self.get(id).is_some()
+	}
+
+	/// Returns the number of sections, including any with unknown IDs
+	pub fn len(&self) -> usize {
+		self.sections.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.sections.is_empty()
+	}
+
+	/// Returns an iterator over every section in the order they are stored
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = (SectionId, &'a [u8])> + ExactSizeIterator + '_ {
+		self.sections.iter().copied()
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const HEADER: SectionId = SectionId(0);
+	const WORLD: SectionId = SectionId(1);
+	const ENTITIES: SectionId = SectionId(2);
+	const INVENTORY: SectionId = SectionId(3);
+	const THUMBNAIL: SectionId = SectionId(4);
+
+	fn save_file(extra: &[(SectionId, &[u8])]) -> Vec<u8> {
+		let mut builder = SectionsBuilder::new();
+		builder.put(HEADER, b"v1").unwrap();
+		builder.put(WORLD, &[7u8; 500]).unwrap();
+		builder.put(ENTITIES, &[]).unwrap();
+		builder.put(INVENTORY, b"sword").unwrap();
+		builder.put(THUMBNAIL, &[0xFF; 16]).unwrap();
+		for &(id, data) in extra {
+			builder.put(id, data).unwrap();
+		}
+		builder.finish()
+	}
+
+	#[test]
+	fn round_trip() {
+		let blob = save_file(&[]);
+		let sections = Sections::parse(&blob).unwrap();
+		assert_eq!(sections.len(), 5);
+		assert_eq!(sections.get(HEADER), Some(&b"v1"[..]));
+		assert_eq!(sections.get(WORLD), Some(&[7u8; 500][..]));
+		assert_eq!(sections.get(ENTITIES), Some(&[][..]));
+		assert_eq!(sections.get(INVENTORY), Some(&b"sword"[..]));
+		assert_eq!(sections.get(THUMBNAIL), Some(&[0xFF; 16][..]));
+		assert!(!sections.contains(SectionId(5)));
+		assert!(Sections::parse(&[]).unwrap().is_empty());
+
+		let mut builder = SectionsBuilder::new();
+		builder.put(WORLD, b"a").unwrap();
+		assert_eq!(builder.put(WORLD, b"b"), Err(SectionError::Duplicate(WORLD)));
+		assert_eq!(Sections::parse(&builder.finish()).unwrap().iter().collect::<Vec<_>>(), [(WORLD, &b"a"[..])]);
+	}
+
+	#[test]
+	fn old_reader_skips_new_sections() {
+		// Written by a newer version that added a sixth section, in the middle of the others
+		let mut builder = SectionsBuilder::new();
+		builder.put(HEADER, b"v2").unwrap();
+		builder.put(SectionId(500), b"achievements").unwrap();
+		builder.put(WORLD, b"world").unwrap();
+		let blob = builder.finish();
+
+		let sections = Sections::parse(&blob).unwrap();
+		assert_eq!(sections.get(HEADER), Some(&b"v2"[..]));
+		assert_eq!(sections.get(WORLD), Some(&b"world"[..]));
+		assert_eq!(sections.get(THUMBNAIL), None);
+		assert_eq!(sections.iter().map(|(id, _)| id).collect::<Vec<_>>(), [HEADER, SectionId(500), WORLD]);
+	}
+
+	#[test]
+	fn invalid_layouts() {
+		let blob = save_file(&[]);
+		let unpaired = blob_concat(&[&blob, &byte_arrays_to_blob(&[&[9, 0]])]).unwrap();
+		assert_eq!(Sections::parse(&unpaired), Err(BlobReadError::UnpairedEntry(5)));
+
+		let bad_id = byte_arrays_to_blob(&[&[0, 0], b"a", &[1, 0, 0], b"b"]);
+		assert_eq!(Sections::parse(&bad_id), Err(BlobReadError::InvalidMetadata(1)));
+		let repeated = byte_arrays_to_blob(&[&[3, 0], b"a", &[3, 0], b"b"]);
+		assert_eq!(Sections::parse(&repeated), Err(BlobReadError::InvalidMetadata(1)));
+		assert!(Sections::parse(&blob[..blob.len() - 1]).is_err());
+	}
+}
@@ -0,0 +1,169 @@
+//! SHA-256 (FIPS 180-4), for digests that stay the same across platforms, builds and versions of the crate
+
+const K: [u32; 64] = [
+	0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+	0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+	0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+	0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+	0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+	0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+	0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+	0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2
+];
+
+const INITIAL_STATE: [u32; 8] = [
+	0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19
+];
+
+
+/// A running SHA-256
+#[derive(Debug, Clone)]
+pub(crate) struct Sha256 {
+	state: [u32; 8],
+	/// Bytes that don't yet fill a 64 byte block
+	block: [u8; 64],
+	block_len: usize,
+	/// The total number of bytes hashed
+	len: u64
+}
+
+
+impl Sha256 {
+	pub(crate) fn new() -> Self {
+		Sha256 { state: INITIAL_STATE, block: [0; 64], block_len: 0, len: 0 }
+	}
+
+	pub(crate) fn update(&mut self, mut bytes: &[u8]) {
+		self.len = self.len.wrapping_add(bytes.len() as u64);
+		while !bytes.is_empty() {
+			let n = bytes.len().min(64 - self.block_len);
+			self.block[self.block_len..self.block_len + n].copy_from_slice(&bytes[..n]);
+			self.block_len += n;
+			bytes = &bytes[n..];
+			if self.block_len == 64 {
+				compress(&mut self.state, &self.block);
+				self.block_len = 0;
+			}
+		}
+	}
+
+	pub(crate) fn finish(mut self) -> [u8; 32] {
+		let bit_len = self.len.wrapping_mul(8);
+		self.update(&[0x80]);
+		while self.block_len != 56 {
+			self.update(&[0]);
+		}
+		self.update(&bit_len.to_be_bytes());
+
+		let mut digest = [0u8; 32];
+		for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+			chunk.copy_from_slice(&word.to_be_bytes());
+		}
+		digest
+	}
+}
+
+
+/// Returns HMAC-SHA256 (RFC 2104) of the concatenation of `parts` under `key`
+pub(crate) fn hmac_sha256(key: &[u8], parts: &[&[u8]]) -> [u8; 32] {
+	let mut block_key = [0u8; 64];
+	match key.len() > 64 {
+		true => {
+			let mut hasher = Sha256::new();
+			hasher.update(key);
+			block_key[..32].copy_from_slice(&hasher.finish());
+		},
+		false => block_key[..key.len()].copy_from_slice(key)
+	}
+
+	let mut inner = Sha256::new();
+	inner.update(&block_key.map(|byte| byte ^ 0x36));
+	for part in parts {
+		inner.update(part);
+	}
+	let mut outer = Sha256::new();
+	outer.update(&block_key.map(|byte| byte ^ 0x5c));
+	outer.update(&inner.finish());
+	outer.finish()
+}
+
+
+fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+	let mut w = [0u32; 64];
+	for (word, chunk) in w.iter_mut().zip(block.chunks_exact(4)) {
+		*word = u32::from_be_bytes(chunk.try_into().unwrap());
+	}
+	for i in 16..64 {
+		let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+		let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+		w[i] = w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1);
+	}
+
+	let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+	for i in 0..64 {
+		let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+		let ch = (e & f) ^ (!e & g);
+		let t1 = h.wrapping_add(s1).wrapping_add(ch).wrapping_add(K[i]).wrapping_add(w[i]);
+		let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+		let maj = (a & b) ^ (a & c) ^ (b & c);
+		let t2 = s0.wrapping_add(maj);
+		h = g;
+		g = f;
+		f = e;
+		e = d.wrapping_add(t1);
+		d = c;
+		c = b;
+		b = a;
+		a = t1.wrapping_add(t2);
+	}
+	for (word, new) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+		*word = word.wrapping_add(new);
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sha256(bytes: &[u8]) -> [u8; 32] {
+		let mut hasher = Sha256::new();
+		hasher.update(bytes);
+		hasher.finish()
+	}
+
+	fn hex(digest: [u8; 32]) -> String {
+		digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+	}
+
+	#[test]
+	fn known_values() {
+		assert_eq!(hex(sha256(b"")), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+		assert_eq!(hex(sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+		assert_eq!(
+			hex(sha256(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq")),
+			"248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+		);
+
+		// Fed in uneven chunks that cross block boundaries
+		let million = vec![b'a'; 1_000_000];
+		let mut hasher = Sha256::new();
+		for chunk in million.chunks(37) {
+			hasher.update(chunk);
+		}
+		assert_eq!(hex(hasher.finish()), "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0");
+	}
+
+	#[test]
+	fn hmac_known_values() {
+		// RFC 4231 test cases 1, 2 and 6, the last with a key longer than a block
+		assert_eq!(hex(hmac_sha256(&[0x0b; 20], &[b"Hi There"])), "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7");
+		assert_eq!(hex(hmac_sha256(b"Jefe", &[b"what do ya ", b"want for nothing?"])), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+		assert_eq!(
+			hex(hmac_sha256(&[0xaa; 131], &[b"Test Using Larger Than Block-Size Key - Hash Key First"])),
+			"60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+		);
+	}
+}
@@ -0,0 +1,269 @@
+//! Publishing a blob into memory shared with other threads or processes, with a generation counter so readers never use a half written blob
+//!
+//! # Layout
+//!
+//! The region is a slice of `AtomicU64` words. Word 0 is the generation and word 1 the length of the blob in bytes, together the 16 byte `SharedBlobHeader`.
+//! The blob follows from word 2, 8 bytes per word in native byte order, with the last word padded with zeros.
+//!
+//! # Protocol
+//!
+//! This is a seqlock. The generation is even while the region holds a whole blob and odd while one is being written.
+//! `publish_blob` moves the generation to the next odd number, writes the length and the data, then moves it to the next even number.
+//! `snapshot_blob` reads the generation, copies the length and the data, then reads the generation again. If it changed, or was odd, the copy may be torn and is thrown away.
+//!
+//! Every access to the region is atomic, so a reader racing with a writer only ever copies stale or mixed words, and never causes undefined behaviour.
+//! The orderings match each other in pairs:
+//!
+//! - The writer's odd generation is followed by a `Release` fence, and the reader's second generation load is preceded by an `Acquire` fence.
+//!   If the reader copied any word the writer stored after the fence, the two fences synchronize, so the second load sees the odd generation or a later one.
+//! - The writer stores the even generation with `Release` after the data, and the reader loads the first generation with `Acquire` before copying.
+//!   If the reader sees that generation, it also sees every word written before it.
+//!
+//! The data words themselves are read and written with `Relaxed`, since the fences and the generation order them.
+//! Shared memory from another process can be used by casting the mapped pointer to `*const AtomicU64`, as long as it is 8 byte aligned and the other process follows the same protocol.
+use crate::*;
+use std::hint;
+use std::sync::atomic::{self, AtomicU64, Ordering};
+
+
+/// How many times `snapshot_blob` copies the blob again after a torn read before giving up
+const SNAPSHOT_RETRIES: usize = 16;
+
+
+/// Errors that can occur when publishing a blob to a shared region
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PublishError {
+	/// The region holds `capacity` bytes after the header, and the blob needs `needed`
+	RegionTooSmall {
+		needed: usize,
+		capacity: usize
+	},
+	/// Another writer is part way through publishing to the region. Nothing was written
+	Busy,
+	/// The blob would be larger than `u32::MAX` bytes
+	TooLarge
+}
+
+
+impl std::error::Error for PublishError {}
+
+impl fmt::Display for PublishError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+
+/// The 16 byte prefix of a shared region: the generation and the length of the blob after it. See the module layout above
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SharedBlobHeader {
+	/// Counts the writes to the region, going up by 2 with each blob published. It is odd while a blob is being written
+	pub generation: u64,
+	/// The length of the blob in bytes
+	pub len: u64
+}
+
+
+impl SharedBlobHeader {
+	/// The size of the header in bytes
+	pub const LEN: usize = 16;
+
+	/// Reads the header of `region` as it is now, or returns `None` if the region is too short to hold one.
+	/// The two words are read separately, so the length may belong to a different generation while a blob is being written
+	pub fn load(region: &[AtomicU64]) -> Option<Self> {
+		let [generation, len, ..] = region else {
+			return None;
+		};
+		Some(SharedBlobHeader { generation: generation.load(Ordering::Acquire), len: len.load(Ordering::Relaxed) })
+	}
+
+	/// Returns true if a blob was being written when the header was read
+	pub fn is_writing(&self) -> bool {
+		!self.generation.is_multiple_of(2)
+	}
+}
+
+
+/// Returns the number of words a region needs to hold a blob of `blob_len` bytes, including the header
+pub fn shared_region_words(blob_len: usize) -> usize {
+	SharedBlobHeader::LEN / 8 + blob_len.div_ceil(8)
+}
+
+
+/// Encodes `entries` as a blob and publishes it to `region`, replacing the blob there
+///
+/// Readers see either the old blob or the new one. Only one writer can publish at a time, and a second one that tries while the first is writing gets `Busy`.
+///
+/// # Errors
+///
+/// Returns `Err(PublishError::RegionTooSmall)` if the blob doesn't fit after the header, `Err(PublishError::Busy)` if another write is in progress,
+/// or `Err(PublishError::TooLarge)` if the blob would be larger than `u32::MAX` bytes. The region is unchanged after an error
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+/// use std::sync::atomic::AtomicU64;
+///
+/// let region: Vec<AtomicU64> = (0..shared_region_words(64)).map(|_| AtomicU64::new(0)).collect();
+/// publish_blob(&region, &[b"frame", b"7"]).unwrap();
+///
+/// assert_eq!(snapshot_blob(&region).unwrap(), Some(vec![b"frame".to_vec(), b"7".to_vec()]));
+/// assert_eq!(SharedBlobHeader::load(&region).unwrap().generation, 2);
+/// ```
+pub fn publish_blob(region: &[AtomicU64], entries: &[&[u8]]) -> Result<(), PublishError> {
+	let mut builder = BlobBuilder::with_expected_entries(entries.len());
+	for byte_arr in entries {
+		builder.push(byte_arr).map_err(|_| PublishError::TooLarge)?;
+	}
+	let blob = builder.finish().map_err(|_| PublishError::TooLarge)?;
+
+	let [generation, len, data @ ..] = region else {
+		return Err(PublishError::RegionTooSmall { needed: blob.len(), capacity: 0 });
+	};
+	if blob.len().div_ceil(8) > data.len() {
+		return Err(PublishError::RegionTooSmall { needed: blob.len(), capacity: data.len() * 8 });
+	}
+
+	let start = generation.load(Ordering::Relaxed);
+	if !start.is_multiple_of(2) || generation.compare_exchange(start, start + 1, Ordering::Acquire, Ordering::Relaxed).is_err() {
+		return Err(PublishError::Busy);
+	}
+	// Orders the odd generation before the data stores, for readers that copy any of them
+	atomic::fence(Ordering::Release);
+
+	len.store(blob.len() as u64, Ordering::Relaxed);
+	for (word, chunk) in data.iter().zip(blob.chunks(8)) {
+		let mut bytes = [0u8; 8];
+		bytes[..chunk.len()].copy_from_slice(chunk);
+		word.store(u64::from_ne_bytes(bytes), Ordering::Relaxed);
+	}
+	generation.store(start + 2, Ordering::Release);
+	Ok(())
+}
+
+
+/// Copies the blob in `region` and decodes it, or returns `Ok(None)` if a writer kept changing it for every attempt
+///
+/// Each attempt checks the generation before and after copying, and a torn copy is retried a few times. A region that was never published to holds an empty blob.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InvalidEncodedIndex)` with the length if the header says the blob is longer than the region, otherwise the same as `blob_to_byte_vecs`.
+/// Only a whole, untorn copy is decoded, so these mean the region holds bad data rather than a write in progress
+pub fn snapshot_blob(region: &[AtomicU64]) -> Result<Option<Vec<Vec<u8>>>, BlobReadError> {
+	let [generation, len, data @ ..] = region else {
+		return Ok(Some(Vec::new()));
+	};
+
+	let mut blob = Vec::new();
+	for _ in 0..=SNAPSHOT_RETRIES {
+		let start = generation.load(Ordering::Acquire);
+		if !start.is_multiple_of(2) {
+			hint::spin_loop();
+			continue;
+		}
+
+		let blob_len = len.load(Ordering::Relaxed);
+		let fits = usize::try_from(blob_len).ok().filter(|blob_len| blob_len.div_ceil(8) <= data.len());
+		blob.clear();
+		for word in data.iter().take(fits.map_or(0, |blob_len| blob_len.div_ceil(8))) {
+			blob.extend(word.load(Ordering::Relaxed).to_ne_bytes());
+		}
+		// Orders the copy before the second generation load, so a copy of any word from a newer write is seen as torn
+		atomic::fence(Ordering::Acquire);
+		if generation.load(Ordering::Relaxed) != start {
+			hint::spin_loop();
+			continue;
+		}
+
+		let Some(blob_len) = fits else {
+			return Err(BlobReadError::InvalidEncodedIndex(usize::try_from(blob_len).unwrap_or(usize::MAX)));
+		};
+		blob.truncate(blob_len);
+		return blob_to_byte_vecs(&blob).map(Some);
+	}
+	Ok(None)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::atomic::AtomicBool;
+	use std::thread;
+
+	fn region(words: usize) -> Vec<AtomicU64> {
+		(0..words).map(|_| AtomicU64::new(0)).collect()
+	}
+
+	#[test]
+	fn publish_and_snapshot() {
+		let region = region(shared_region_words(32));
+		assert_eq!(snapshot_blob(&region), Ok(Some(Vec::new())));
+
+		publish_blob(&region, &[b"abc", &[]]).unwrap();
+		publish_blob(&region, &[b"longer entry"]).unwrap();
+		assert_eq!(snapshot_blob(&region), Ok(Some(vec![b"longer entry".to_vec()])));
+		assert_eq!(SharedBlobHeader::load(&region), Some(SharedBlobHeader { generation: 4, len: 16 }));
+
+		// A blob that doesn't fit leaves the last one in place
+		assert_eq!(publish_blob(&region, &[&[0u8; 40]]), Err(PublishError::RegionTooSmall { needed: 44, capacity: 32 }));
+		assert_eq!(publish_blob(&region[..1], &[]), Err(PublishError::RegionTooSmall { needed: 0, capacity: 0 }));
+		assert_eq!(snapshot_blob(&region), Ok(Some(vec![b"longer entry".to_vec()])));
+	}
+
+	#[test]
+	fn writes_in_progress() {
+		let region = region(8);
+		publish_blob(&region, &[b"one"]).unwrap();
+
+		// A writer that stopped part way, holding the generation odd
+		region[0].store(3, Ordering::Relaxed);
+		assert!(SharedBlobHeader::load(&region).unwrap().is_writing());
+		assert_eq!(snapshot_blob(&region), Ok(None));
+		assert_eq!(publish_blob(&region, &[b"two"]), Err(PublishError::Busy));
+
+		region[0].store(4, Ordering::Relaxed);
+		region[1].store(1000, Ordering::Relaxed);
+		assert_eq!(snapshot_blob(&region), Err(BlobReadError::InvalidEncodedIndex(1000)));
+	}
+
+	#[test]
+	fn concurrent_readers_never_see_torn_blobs() {
+		let region = region(shared_region_words(4 * 68));
+		let done = AtomicBool::new(false);
+
+		thread::scope(|scope| {
+			scope.spawn(|| {
+				// Every blob has four equal entries, all filled with one byte and sized from it, so a mix of two writes is easy to spot
+				for n in 0..20_000u32 {
+					let fill = (n % 251) as u8;
+					let entry = vec![fill; fill as usize % 64];
+					publish_blob(&region, &[&entry, &entry, &entry, &entry]).unwrap();
+				}
+				done.store(true, Ordering::Relaxed);
+			});
+			for _ in 0..3 {
+				scope.spawn(|| {
+					let mut snapshots = 0;
+					while !done.load(Ordering::Relaxed) {
+						let Some(entries) = snapshot_blob(&region).unwrap() else {
+							continue;
+						};
+						if let Some(first) = entries.first() {
+							assert_eq!(entries.len(), 4);
+							assert!(entries.iter().all(|entry| entry == first));
+							assert!(first.iter().all(|&byte| byte as usize % 64 == first.len()));
+						}
+						snapshots += 1;
+					}
+					snapshots
+				});
+			}
+		});
+		assert_eq!(SharedBlobHeader::load(&region).unwrap().generation, 40_000);
+	}
+}
@@ -0,0 +1,104 @@
+//! Sidecar blobs, which hold extra data about each entry of another blob in a parallel blob with the same number of entries
+use crate::*;
+
+
+/// Errors that can occur when reading two blobs side by side
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ZipError {
+	/// One of the blobs is invalid
+	Read(BlobReadError),
+	/// The blobs hold different numbers of entries
+	LengthMismatch {
+		a: usize,
+		b: usize
+	}
+}
+
+
+impl std::error::Error for ZipError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			ZipError::Read(e) => Some(e),
+			_ => None
+		}
+	}
+}
+
+impl fmt::Display for ZipError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl From<BlobReadError> for ZipError {
+	fn from(e: BlobReadError) -> Self {
+		ZipError::Read(e)
+	}
+}
+
+
+/// Builds a sidecar blob holding `f(index, entry)` for every entry of `blob`, in the same order
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`. `f` is not called if the blob is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"hello", b"hi"]);
+/// let lengths = build_sidecar(&blob, |_, entry| vec![entry.len() as u8]).unwrap();
+///
+/// let pairs: Vec<_> = zip_blobs(&blob, &lengths).unwrap().collect();
+/// assert_eq!(pairs, vec![(&b"hello"[..], &[5][..]), (&b"hi"[..], &[2][..])]);
+/// ```
+pub fn build_sidecar(blob: &[u8], mut f: impl FnMut(usize, &[u8]) -> Vec<u8>) -> Result<Vec<u8>, BlobReadError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	let mut sidecar = Blob::new();
+	for (index, byte_arr) in byte_arrays.into_iter().enumerate() {
+		sidecar.push(&f(index, byte_arr));
+	}
+	Ok(sidecar.into_bytes())
+}
+
+
+/// Returns an iterator over the entries of two blobs in pairs, such as a blob and its sidecar
+///
+/// Both blobs are read in full first, so the iterator itself can't fail.
+///
+/// # Errors
+///
+/// Returns `Err(ZipError::LengthMismatch)` with both entry counts if they differ, or `Err(ZipError::Read)` if either blob is invalid
+pub fn zip_blobs<'a>(a: &'a [u8], b: &'a [u8]) -> Result<impl Iterator<Item = (&'a [u8], &'a [u8])>, ZipError> {
+	let a = blob_to_byte_arrays(a)?;
+	let b = blob_to_byte_arrays(b)?;
+	if a.len() != b.len() {
+		return Err(ZipError::LengthMismatch { a: a.len(), b: b.len() });
+	}
+	Ok(a.into_iter().zip(b))
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn mismatched_and_empty() {
+		let blob = byte_arrays_to_blob(&[&[1u8], &[2u8], &[]]);
+		let sidecar = build_sidecar(&blob, |index, _| vec![index as u8; index]).unwrap();
+		assert_eq!(blob_to_byte_arrays(&sidecar).unwrap(), vec![&[][..], &[1u8], &[2u8, 2u8]]);
+
+		let short = byte_arrays_to_blob(&[&[1u8]]);
+		assert_eq!(zip_blobs(&blob, &short).err(), Some(ZipError::LengthMismatch { a: 3, b: 1 }));
+		assert_eq!(zip_blobs(&short, &blob[..5]).unwrap().count(), 1);
+		assert!(matches!(zip_blobs(&blob, &sidecar[..3]).err(), Some(ZipError::Read(_))));
+
+		assert_eq!(zip_blobs(&[], &[]).unwrap().count(), 0);
+		assert_eq!(build_sidecar(&[], |_, _| unreachable!()).unwrap(), Vec::<u8>::new());
+	}
+}
@@ -0,0 +1,219 @@
+//! `SlabBlob`, a set of entries with IDs that stay valid while other entries are added and removed
+use crate::*;
+use std::collections::HashMap;
+
+
+/// The ID of an entry in a `SlabBlob`
+///
+/// An ID is a slot and the generation of that slot when the entry was added. Removing an entry moves its slot on to the next generation,
+/// so once an entry is gone its ID never matches the entry that reuses the slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EntryId {
+	slot: u32,
+	generation: u32
+}
+
+
+impl EntryId {
+	/// Returns the slot the entry is stored in
+	pub fn slot(self) -> u32 {
+		self.slot
+	}
+
+	/// Returns the generation of the slot the entry was added in
+	pub fn generation(self) -> u32 {
+		self.generation
+	}
+}
+
+
+#[derive(Debug, Clone)]
+struct Slot {
+	generation: u32,
+	byte_arr: Option<Vec<u8>>
+}
+
+
+/// Entries addressed by `EntryId` rather than by index, so removing one entry doesn't change how the others are found
+///
+/// Freed slots are reused by later inserts with a new generation. A slot whose generation reaches `u32::MAX` is retired rather than reused,
+/// so a stale ID can never come back around to a live entry.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut slab = SlabBlob::new();
+/// let a = slab.insert(b"a".to_vec());
+/// let b = slab.insert(b"b".to_vec());
+///
+/// assert_eq!(slab.remove(a), Some(b"a".to_vec()));
+/// let c = slab.insert(b"c".to_vec());
+///
+/// assert_eq!(slab.get(a), None);
+/// assert_eq!(slab.get(b), Some(&b"b"[..]));
+/// assert_eq!(slab.get(c), Some(&b"c"[..]));
+///
+/// let (blob, indexes) = slab.compact();
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap()[indexes[&b]], b"b");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SlabBlob {
+	slots: Vec<Slot>,
+	free: Vec<u32>,
+	len: usize
+}
+
+
+impl SlabBlob {
+	pub fn new() -> Self {
+		SlabBlob::default()
+	}
+
+	/// Returns the number of live entries
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Adds an entry and returns its ID
+	///
+	/// # Panics
+	///
+	/// Panics if every one of the `u32::MAX + 1` slots is in use or retired
+	pub fn insert(&mut self, byte_arr: Vec<u8>) -> EntryId {
+		self.len += 1;
+		if let Some(slot) = self.free.pop() {
+			let entry = &mut self.slots[slot as usize];
+			entry.byte_arr = Some(byte_arr);
+			return EntryId { slot, generation: entry.generation };
+		}
+
+		let slot = u32::try_from(self.slots.len()).expect("slab has run out of slots");
+		self.slots.push(Slot { generation: 0, byte_arr: Some(byte_arr) });
+		EntryId { slot, generation: 0 }
+	}
+
+	/// Removes the entry with `id` and returns it, or returns `None` if there is no such entry
+	pub fn remove(&mut self, id: EntryId) -> Option<Vec<u8>> {
+		let entry = self.slots.get_mut(id.slot as usize).filter(|entry| entry.generation == id.generation)?;
+		let byte_arr = entry.byte_arr.take()?;
+		self.len -= 1;
+		if entry.generation < u32::MAX {
+			entry.generation += 1;
+			self.free.push(id.slot);
+		}
+		Some(byte_arr)
+	}
+
+	/// Returns the entry with `id`, or `None` if it has been removed
+	pub fn get(&self, id: EntryId) -> Option<&[u8]> {
+		self.slots.get(id.slot as usize)
+			.filter(|entry| entry.generation == id.generation)
+			.and_then(|entry| entry.byte_arr.as_deref())
+	}
+
+	pub fn contains(&self, id: EntryId) -> bool {
+		self.get(id).is_some()
+	}
+
+	/// Returns an iterator over the live entries and their IDs, in slot order
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = (EntryId, &[u8])> + '_ {
+		let live = self.slots.iter().enumerate().filter_map(|(slot, entry)| {
+			let id = EntryId { slot: slot as u32, generation: entry.generation };
+			entry.byte_arr.as_deref().map(|byte_arr| (id, byte_arr))
+		});
+		ExactLen::new(live, self.len)
+	}
+
+	/// Encodes the live entries as a plain blob in slot order, and returns it with the index each ID ended up at
+	///
+	/// # Panics
+	///
+	/// Panics if the blob would become larger than `u32::MAX` bytes
+	pub fn compact(&self) -> (Vec<u8>, HashMap<EntryId, usize>) {
+		let mut blob = Blob::new();
+		let mut indexes = HashMap::with_capacity(self.len);
+		for (index, (id, byte_arr)) in self.iter().enumerate() {
+			blob.push(byte_arr);
+			indexes.insert(id, index);
+		}
+		(blob.into_bytes(), indexes)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn ids_survive_interleaved_changes() {
+		let mut slab = SlabBlob::new();
+		let mut live: Vec<(EntryId, Vec<u8>)> = Vec::new();
+		let mut removed = Vec::new();
+		let early = slab.insert(b"early".to_vec());
+
+		let mut rng = XorShift32::default_seed();
+		for i in 0..500u32 {
+			let random = rng.next_u32();
+			if random.is_multiple_of(3) && !live.is_empty() {
+				let (id, byte_arr) = live.swap_remove(random as usize % live.len());
+				assert_eq!(slab.remove(id), Some(byte_arr));
+				assert_eq!(slab.remove(id), None);
+				removed.push(id);
+			} else {
+				let byte_arr = i.to_le_bytes().to_vec();
+				live.push((slab.insert(byte_arr.clone()), byte_arr));
+			}
+		}
+
+		assert_eq!(slab.len(), live.len() + 1);
+		assert_eq!(slab.get(early), Some(&b"early"[..]));
+		for (id, byte_arr) in &live {
+			assert_eq!(slab.get(*id), Some(byte_arr.as_slice()));
+		}
+		// Stale IDs miss even though most of their slots hold newer entries
+		for id in &removed {
+			assert_eq!(slab.get(*id), None);
+		}
+		assert!(removed.iter().any(|id| live.iter().any(|(live_id, _)| live_id.slot() == id.slot())));
+
+		let (blob, indexes) = slab.compact();
+		let byte_arrays = blob_to_byte_arrays(&blob).unwrap();
+		assert_eq!(byte_arrays.len(), slab.len());
+		assert_eq!(indexes.len(), slab.len());
+		assert_eq!(byte_arrays[indexes[&early]], b"early");
+		for (id, byte_arr) in &live {
+			assert_eq!(byte_arrays[indexes[id]], byte_arr.as_slice());
+		}
+	}
+
+	#[test]
+	fn stale_and_foreign_ids() {
+		let mut slab = SlabBlob::new();
+		let a = slab.insert(b"a".to_vec());
+		slab.remove(a);
+		let b = slab.insert(b"b".to_vec());
+		assert_eq!((a.slot(), b.slot()), (0, 0));
+		assert_eq!(b.generation(), a.generation() + 1);
+		assert_eq!(slab.get(a), None);
+		assert_eq!(slab.remove(a), None);
+		assert_eq!(slab.get(EntryId { slot: 5, generation: 0 }), None);
+
+		// A slot at the last generation is retired instead of reused
+		slab.slots[0].generation = u32::MAX;
+		let last = EntryId { slot: 0, generation: u32::MAX };
+		assert_eq!(slab.remove(last), Some(b"b".to_vec()));
+		let c = slab.insert(b"c".to_vec());
+		assert_eq!(c.slot(), 1);
+		assert_eq!(slab.get(last), None);
+		assert!(slab.iter().eq([(c, &b"c"[..])]));
+	}
+}
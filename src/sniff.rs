@@ -0,0 +1,105 @@
+//! `blob_sniff`, a cheap check of the first few indexes of a buffer before committing to decode it
+use crate::*;
+
+
+/// What `blob_sniff` found in the indexes it checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SniffResult {
+	/// True if every index checked was valid
+	pub looks_valid: bool,
+	/// Number of valid indexes read before stopping
+	pub entries_checked: usize,
+	/// Number of bytes from the start of the buffer taken up by the valid entries
+	pub bytes_covered: usize,
+	/// The error that stopped the check, if any
+	pub first_error: Option<BlobReadError>
+}
+
+
+/// Reads up to `max_entries` indexes of `blob` to see whether it looks like a blob, without allocating or reading any payload
+///
+/// This is meant for turning away buffers in other formats before paying for a full decode, so a buffer that passes can still fail
+/// further in. It never panics, whatever the input. An empty buffer is a valid blob of no entries, so it looks valid.
+/// Like a decode the indexes are checked against the length of `blob`, so pass the whole buffer rather than the start of it.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"first", b"second", b"third"]);
+/// let sniffed = blob_sniff(&blob, 2);
+/// assert!(sniffed.looks_valid);
+/// assert_eq!((sniffed.entries_checked, sniffed.bytes_covered), (2, 19));
+///
+/// let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+/// assert!(!blob_sniff(png, 2).looks_valid);
+/// ```
+pub fn blob_sniff(blob: &[u8], max_entries: usize) -> SniffResult {
+	let mut iter = blob_iter(blob);
+	let mut entries_checked = 0;
+	let mut first_error = None;
+	for byte_arr in iter.by_ref().take(max_entries) {
+		match byte_arr {
+			Ok(_) => entries_checked += 1,
+			Err(e) => {
+				first_error = Some(e);
+				break;
+			}
+		}
+	}
+	SniffResult { looks_valid: first_error.is_none(), entries_checked, bytes_covered: iter.position(), first_error }
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn valid_blobs() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 10], &[], &[2u8; 3]]);
+		assert_eq!(blob_sniff(&blob, 10), SniffResult { looks_valid: true, entries_checked: 3, bytes_covered: blob.len(), first_error: None });
+		assert_eq!(blob_sniff(&blob, 1), SniffResult { looks_valid: true, entries_checked: 1, bytes_covered: 14, first_error: None });
+		assert_eq!(blob_sniff(&blob, 0), SniffResult { looks_valid: true, entries_checked: 0, bytes_covered: 0, first_error: None });
+		assert!(blob_sniff(&[], 4).looks_valid);
+
+		// Damage past the entries checked isn't seen
+		let mut torn = blob.clone();
+		torn.extend([1u8, 2]);
+		assert!(blob_sniff(&torn, 3).looks_valid);
+		let sniffed = blob_sniff(&torn, 4);
+		assert_eq!((sniffed.looks_valid, sniffed.entries_checked, sniffed.bytes_covered), (false, 3, blob.len()));
+		assert_eq!(sniffed.first_error.map(|e| e.kind()), Some(BlobErrorKind::Truncated));
+	}
+
+	#[test]
+	fn rejects_other_formats() {
+		let headers: [&[u8]; 5] = [
+			b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0",
+			b"\x1f\x8b\x08\0\0\0\0\0\0\x03\xcb\x48\xcd\xc9\xc9\x07\0",
+			b"PK\x03\x04\x14\0\0\0\x08\0",
+			b"%PDF-1.7\n",
+			b"{\"entries\": []}"
+		];
+		for header in headers {
+			assert!(!blob_sniff(header, 4).looks_valid, "{:?}", header);
+		}
+		for len in 1..4 {
+			assert_eq!(blob_sniff(&[0u8; 3][..len], 4).first_error, Some(BlobReadError::TruncatedIndex(0)));
+		}
+
+		let mut rng = XorShift32::default_seed();
+		let mut accepted = 0;
+		for _ in 0..1000 {
+			let mut random = [0u8; 64];
+			random.fill_with(|| rng.next_u32() as u8);
+			if blob_sniff(&random, 4).looks_valid {
+				accepted += 1;
+			}
+		}
+		assert_eq!(accepted, 0);
+	}
+}
@@ -0,0 +1,163 @@
+//! Sparse blobs, where runs of empty slices are stored as a single marker
+use crate::*;
+
+
+/// Runs shorter than this are cheaper as ordinary empty slices, since a marker takes 8 bytes
+const MIN_RUN: usize = 3;
+
+
+/// Encodes slices into a sparse blob, in which each run of 3 or more consecutive empty slices is stored as one 8 byte run marker
+///
+/// Ordinary slices are encoded exactly as `byte_arrays_to_blob` does. A run marker is an index whose value is its own position in the blob,
+/// followed by the number of empty slices in the run as a native endian u32. An ordinary index always points at least 4 bytes past its own position,
+/// so a marker can never be mistaken for one. Plain decoders reject that index, so sparse blobs must be read with `blob_sparse_to_byte_arrays`.
+///
+/// # Panics
+///
+/// Panics if the blob would be larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut slots: Vec<&[u8]> = vec![&[]; 100];
+/// slots[42] = b"answer";
+///
+/// let sparse = byte_arrays_to_blob_sparse(&slots);
+/// assert_eq!(sparse.len(), 8 + 10 + 8);
+/// assert_eq!(blob_sparse_to_byte_arrays(&sparse).unwrap(), slots);
+/// ```
+pub fn byte_arrays_to_blob_sparse(bytes_2d: &[&[u8]]) -> Vec<u8> {
+	let mut blob = Vec::new();
+	let mut rest = bytes_2d;
+	while let Some(byte_arr) = rest.first() {
+		let run = rest.iter().take_while(|byte_arr| byte_arr.is_empty()).count();
+		if run >= MIN_RUN {
+			let run = run.min(MAX_U32_LEN);
+			let position = u32::try_from(blob.len()).expect("blob larger than u32::MAX bytes");
+			blob.extend(position.to_ne_bytes());
+			blob.extend((run as u32).to_ne_bytes());
+			rest = &rest[run..];
+			continue;
+		}
+
+		let idx_end = u32::try_from(blob.len() + 4 + byte_arr.len()).expect("blob larger than u32::MAX bytes");
+		blob.extend(idx_end.to_ne_bytes());
+		blob.extend(*byte_arr);
+		rest = &rest[1..];
+	}
+	blob
+}
+
+
+/// Converts a sparse blob u8 slice to a Vec of u8 slices, with the empty slices of every run in their original positions
+///
+/// # Errors
+///
+/// The same as `blob_sparse_to_byte_arrays_with_options` with the default options
+pub fn blob_sparse_to_byte_arrays(blob: &[u8]) -> Result<Vec<&[u8]>, BlobReadError> {
+	blob_sparse_to_byte_arrays_with_options(blob, &DecodeOptions::default())
+}
+
+
+/// Converts a sparse blob u8 slice to a Vec of u8 slices, within the limits of `options`
+///
+/// A run marker can stand for billions of empty slices in 8 bytes, so the number of slices is checked against `options.max_entries()`
+/// before any run is expanded.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::TooManyEntries)` if the blob holds more slices than `options` allow, `Err(BlobReadError::TruncatedIndex)`
+/// if the blob ends part way through an index or run marker, and otherwise the same as `blob_to_byte_arrays`
+pub fn blob_sparse_to_byte_arrays_with_options<'a>(blob: &'a [u8], options: &DecodeOptions) -> Result<Vec<&'a [u8]>, BlobReadError> {
+	if blob.len() > MAX_U32_LEN {
+		return Err(BlobReadError::TooLarge);
+	}
+
+	let read_u32 = |position: usize| match blob.get(position..position+4) {
+		Some(data) => index_to_usize(u32::from_ne_bytes(data.try_into().unwrap())),
+		None => Err(BlobReadError::TruncatedIndex(position))
+	};
+
+	let mut byte_arrays = Vec::new();
+	let mut position = 0;
+	while position < blob.len() {
+		let idx_end = read_u32(position)?;
+		let count = match idx_end == position {
+			true => read_u32(position + 4)?,
+			false => 1
+		};
+		if byte_arrays.len().saturating_add(count) > options.max_entries() {
+			return Err(BlobReadError::TooManyEntries(options.max_entries()));
+		}
+
+		if idx_end == position {
+			byte_arrays.resize(byte_arrays.len() + count, &blob[..0]);
+			position += 8;
+			continue;
+		}
+
+		if idx_end > blob.len() || idx_end < position + 4 {
+			return Err(BlobReadError::InvalidEncodedIndex(idx_end));
+		}
+		byte_arrays.push(&blob[position+4..idx_end]);
+		position = idx_end;
+	}
+	Ok(byte_arrays)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn mostly_empty_slots() {
+		let mut slots: Vec<Vec<u8>> = vec![Vec::new(); 10_005];
+		for (i, slot) in [0, 17, 5000, 5001, 10_004].into_iter().enumerate() {
+			slots[slot] = vec![i as u8; 10];
+		}
+		let slices = as_slices(&slots);
+
+		let dense = byte_arrays_to_blob(&slices);
+		let sparse = byte_arrays_to_blob_sparse(&slices);
+		assert_eq!(dense.len(), 10_005 * 4 + 50);
+		// 3 runs of empty slots, 5 populated slots
+		assert_eq!(sparse.len(), 3 * 8 + 5 * 14);
+		assert_eq!(blob_sparse_to_byte_arrays(&sparse).unwrap(), slices);
+	}
+
+	#[test]
+	fn short_runs_stay_dense() {
+		let slices: [&[u8]; 6] = [&[], &[], &[1u8], &[], &[], &[]];
+		let sparse = byte_arrays_to_blob_sparse(&slices);
+		assert_eq!(sparse[..8], byte_arrays_to_blob(&slices[..2])[..]);
+		assert_eq!(sparse.len(), 8 + 5 + 8);
+		assert_eq!(blob_sparse_to_byte_arrays(&sparse).unwrap(), slices);
+
+		// Without runs a sparse blob is a plain blob
+		let plain: [&[u8]; 3] = [&[1u8], &[], &[2u8]];
+		assert_eq!(byte_arrays_to_blob_sparse(&plain), byte_arrays_to_blob(&plain));
+		assert!(blob_to_byte_arrays(&sparse).is_err());
+	}
+
+	#[test]
+	fn hostile_run_count_is_bounded() {
+		let mut hostile = 0u32.to_ne_bytes().to_vec();
+		hostile.extend(u32::MAX.to_ne_bytes());
+		assert_eq!(blob_sparse_to_byte_arrays(&hostile), Err(BlobReadError::TooManyEntries(DecodeOptions::DEFAULT_MAX_ENTRIES)));
+
+		let slices: Vec<&[u8]> = vec![&[]; 100];
+		let sparse = byte_arrays_to_blob_sparse(&slices);
+		let options = DecodeOptions::new().with_max_entries(99);
+		assert_eq!(blob_sparse_to_byte_arrays_with_options(&sparse, &options), Err(BlobReadError::TooManyEntries(99)));
+		assert_eq!(blob_sparse_to_byte_arrays_with_options(&sparse, &options.with_max_entries(100)).unwrap().len(), 100);
+
+		// Truncated markers
+		assert_eq!(blob_sparse_to_byte_arrays(&sparse[..6]), Err(BlobReadError::TruncatedIndex(4)));
+		assert_eq!(blob_sparse_to_byte_arrays(&sparse[..2]), Err(BlobReadError::TruncatedIndex(0)));
+	}
+}
@@ -0,0 +1,249 @@
+//! Splitting a blob into a hot blob and a cold blob by how often each entry is read, with a redirect table to find entries by their original index
+//!
+//! # Layout
+//!
+//! The redirect table is stored as one blob entry holding a little-endian u32 per original entry. The top bit is set for an entry in the cold blob,
+//! and the other 31 bits are its index within the blob that holds it. A blob holds at most `u32::MAX / 4` entries, so every index fits.
+//! `SplitBlobs::to_blob` stores the whole arrangement as a blob of three entries: the redirect table, the hot blob and the cold blob.
+use crate::*;
+
+
+const COLD_BIT: u32 = 1 << 31;
+
+
+/// Where an entry of the original blob ended up after `split_by_access`, with its index within that blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntrySlot {
+	Hot(usize),
+	Cold(usize)
+}
+
+
+/// The blobs made by `split_by_access`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitBlobs {
+	/// The entries the predicate chose, in their original order
+	pub hot: Vec<u8>,
+	/// Every other entry, in its original order
+	pub cold: Vec<u8>,
+	/// Where each entry of the original blob is, by original index
+	pub redirect: Vec<EntrySlot>
+}
+
+
+impl SplitBlobs {
+	/// Returns the redirect table as a single blob entry. See the module layout above
+	///
+	/// # Panics
+	///
+	/// Panics if a slot's index doesn't fit in 31 bits, which can only happen if `redirect` was changed by hand
+	pub fn redirect_entry(&self) -> Vec<u8> {
+		let mut entry = Vec::with_capacity(self.redirect.len() * 4);
+		for slot in &self.redirect {
+			let (index, bit) = match *slot {
+				EntrySlot::Hot(index) => (index, 0),
+				EntrySlot::Cold(index) => (index, COLD_BIT)
+			};
+			let index = u32::try_from(index).ok().filter(|index| index & COLD_BIT == 0).expect("redirect index must fit in 31 bits");
+			entry.extend((index | bit).to_le_bytes());
+		}
+		entry
+	}
+
+	/// Stores the redirect table, the hot blob and the cold blob together as one blob, which `SplitBlobView::from_blob` reads back
+	///
+	/// # Panics
+	///
+	/// The same as `redirect_entry`, or if the stored blob would be larger than `u32::MAX` bytes
+	pub fn to_blob(&self) -> Vec<u8> {
+		byte_arrays_to_blob(&[&self.redirect_entry(), &self.hot, &self.cold])
+	}
+}
+
+
+/// Splits a blob into a hot blob holding the entries `hot` returns true for, and a cold blob holding the rest
+///
+/// `hot` is called with each original index in order, so it can look the index up in a table of recorded access counts.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"often", b"rarely", b"often too"]);
+/// let access_counts = [90, 1, 40];
+/// let split = split_by_access(&blob, |i| access_counts[i] > 10).unwrap();
+///
+/// assert_eq!(split.redirect, [EntrySlot::Hot(0), EntrySlot::Cold(0), EntrySlot::Hot(1)]);
+/// assert_eq!(blob_to_byte_arrays(&split.cold).unwrap(), [b"rarely"]);
+///
+/// let view = SplitBlobView::new(&split.hot, &split.cold, &split.redirect_entry()).unwrap();
+/// assert_eq!(view.get(2), Some(&b"often too"[..]));
+/// ```
+pub fn split_by_access(blob: &[u8], hot: impl Fn(usize) -> bool) -> Result<SplitBlobs, BlobReadError> {
+	let mut hot_entries = Vec::new();
+	let mut cold_entries = Vec::new();
+	let mut redirect = Vec::new();
+	for (i, byte_arr) in blob_iter(blob).enumerate() {
+		let byte_arr = byte_arr?;
+		if hot(i) {
+			redirect.push(EntrySlot::Hot(hot_entries.len()));
+			hot_entries.push(byte_arr);
+		}
+		else {
+			redirect.push(EntrySlot::Cold(cold_entries.len()));
+			cold_entries.push(byte_arr);
+		}
+	}
+
+	// Each part is no larger than the blob it came from, so neither can be too large
+	Ok(SplitBlobs {
+		hot: byte_arrays_to_blob(&hot_entries),
+		cold: byte_arrays_to_blob(&cold_entries),
+		redirect
+	})
+}
+
+
+/// A hot blob and a cold blob read together as the blob they were split from
+///
+/// Every slot of the redirect table is checked when the view is made, so `get` only fails for an index past the end.
+#[derive(Debug, Clone)]
+pub struct SplitBlobView<'a> {
+	hot: BlobView<'a>,
+	cold: BlobView<'a>,
+	redirect: Vec<EntrySlot>
+}
+
+
+impl<'a> SplitBlobView<'a> {
+	/// Reads both blobs and a redirect table made by `SplitBlobs::redirect_entry`
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InvalidMetadata(0))` if the redirect table isn't a whole number of slots,
+	/// or `Err(BlobReadError::InvalidMetadata)` with the original index of the first slot pointing past the end of its blob.
+	/// Otherwise the same as `blob_to_byte_arrays` for either blob
+	pub fn new(hot: &'a [u8], cold: &'a [u8], redirect: &[u8]) -> Result<Self, BlobReadError> {
+		if !redirect.len().is_multiple_of(4) {
+			return Err(BlobReadError::InvalidMetadata(0));
+		}
+		let hot = BlobView::new(hot)?;
+		let cold = BlobView::new(cold)?;
+
+		let mut slots = Vec::with_capacity(redirect.len() / 4);
+		for (i, slot) in redirect.chunks_exact(4).enumerate() {
+			let slot = u32::from_le_bytes(slot.try_into().unwrap());
+			let index = index_to_usize(slot & !COLD_BIT)?;
+			let (slot, part_len) = match slot & COLD_BIT {
+				0 => (EntrySlot::Hot(index), hot.len()),
+				_ => (EntrySlot::Cold(index), cold.len())
+			};
+			if index >= part_len {
+				return Err(BlobReadError::InvalidMetadata(i));
+			}
+			slots.push(slot);
+		}
+		Ok(SplitBlobView { hot, cold, redirect: slots })
+	}
+
+	/// Reads a blob made by `SplitBlobs::to_blob`
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobReadError::InvalidMetadata(0))` if the blob doesn't hold exactly three entries, otherwise the same as `new`
+	pub fn from_blob(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		match blob_to_byte_arrays(blob)?[..] {
+			[redirect, hot, cold] => SplitBlobView::new(hot, cold, redirect),
+			_ => Err(BlobReadError::InvalidMetadata(0))
+		}
+	}
+
+	/// Returns the number of entries in the original blob
+	pub fn len(&self) -> usize {
+		self.redirect.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.redirect.is_empty()
+	}
+
+	/// Returns where entry `original_index` of the original blob is, or `None` if it is out of range
+	pub fn slot(&self, original_index: usize) -> Option<EntrySlot> {
+		self.redirect.get(original_index).copied()
+	}
+
+	/// Returns entry `original_index` of the original blob, from whichever blob holds it, or `None` if it is out of range
+	pub fn get(&self, original_index: usize) -> Option<&'a [u8]> {
+		match self.slot(original_index)? {
+			EntrySlot::Hot(index) => self.hot.get(index),
+			EntrySlot::Cold(index) => self.cold.get(index)
+		}
+	}
+
+	/// Returns an iterator over the entries in their original order
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = &'a [u8]> + '_ {
+		// Every slot was checked by `new`, so `get` finds every entry
+		ExactLen::new((0..self.len()).filter_map(move |i| self.get(i)), self.len())
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn alternating_split_resolves_every_index() {
+		let entries: Vec<Vec<u8>> = (0..20u8).map(|i| vec![i; i as usize % 5]).collect();
+		let blob = entries_to_blob(&entries);
+		let split = split_by_access(&blob, |i| i % 2 == 0).unwrap();
+		assert_eq!(blob_to_byte_arrays(&split.hot).unwrap().len(), 10);
+		assert_eq!(blob_to_byte_arrays(&split.cold).unwrap().len(), 10);
+		assert_eq!(split.redirect[7], EntrySlot::Cold(3));
+
+		let stored = split.to_blob();
+		let views = [SplitBlobView::new(&split.hot, &split.cold, &split.redirect_entry()).unwrap(), SplitBlobView::from_blob(&stored).unwrap()];
+		for view in views {
+			assert_eq!(view.len(), 20);
+			for (i, entry) in entries.iter().enumerate() {
+				assert_eq!(view.get(i), Some(entry.as_slice()));
+				assert_eq!(view.slot(i), Some(split.redirect[i]));
+			}
+			assert_eq!(view.get(20), None);
+			assert!(view.iter().eq(entries.iter().map(|e| e.as_slice())));
+		}
+	}
+
+	#[test]
+	fn one_sided_splits() {
+		let blob = byte_arrays_to_blob(&[b"a", b"b"]);
+		let all_hot = split_by_access(&blob, |_| true).unwrap();
+		assert_eq!(all_hot.hot, blob);
+		assert!(all_hot.cold.is_empty());
+
+		let empty = split_by_access(&[], |_| true).unwrap();
+		assert!(SplitBlobView::from_blob(&empty.to_blob()).unwrap().is_empty());
+	}
+
+	#[test]
+	fn invalid_redirects_are_rejected() {
+		let blob = byte_arrays_to_blob(&[b"a", b"b", b"c"]);
+		let split = split_by_access(&blob, |i| i == 1).unwrap();
+		let mut redirect = split.redirect_entry();
+		assert_eq!(SplitBlobView::new(&split.hot, &split.cold, &redirect[..5]).unwrap_err(), BlobReadError::InvalidMetadata(0));
+
+		// Slot 2 points past the end of the cold blob
+		redirect[8..12].copy_from_slice(&(COLD_BIT | 2).to_le_bytes());
+		assert_eq!(SplitBlobView::new(&split.hot, &split.cold, &redirect).unwrap_err(), BlobReadError::InvalidMetadata(2));
+
+		assert_eq!(SplitBlobView::from_blob(&blob).unwrap_err(), BlobReadError::InvalidMetadata(0));
+		assert!(matches!(SplitBlobView::new(&[255u8; 4], &split.cold, &[]), Err(BlobReadError::NotABlob { .. })));
+	}
+}
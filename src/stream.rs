@@ -0,0 +1,477 @@
+//! Reading and writing blobs through `io::Read` and `io::Write` one slice at a time
+use crate::*;
+use std::io::{self, Read, Write};
+
+
+/// Errors that can occur when reading a blob from a stream
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BlobStreamError {
+	/// An error from the underlying reader
+	Io(io::Error),
+	/// The blob being read is invalid
+	Read(BlobReadError)
+}
+
+
+impl std::error::Error for BlobStreamError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			BlobStreamError::Io(e) => Some(e),
+			BlobStreamError::Read(e) => Some(e)
+		}
+	}
+}
+
+impl fmt::Display for BlobStreamError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BlobStreamError::Io(e) => write!(formatter, "{}", e),
+			BlobStreamError::Read(e) => write!(formatter, "{}", e)
+		}
+	}
+}
+
+impl From<io::Error> for BlobStreamError {
+	fn from(e: io::Error) -> Self {
+		BlobStreamError::Io(e)
+	}
+}
+
+impl From<BlobReadError> for BlobStreamError {
+	fn from(e: BlobReadError) -> Self {
+		BlobStreamError::Read(e)
+	}
+}
+
+
+/// Errors that can occur when writing a blob
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BlobWriteError {
+	/// An error from the underlying writer
+	Io(io::Error),
+	/// The blob would become larger than `u32::MAX` bytes, so its indexes could not be stored
+	TooLarge,
+	/// The blob needs `needed` bytes but the buffer it is being written to only has `capacity`
+	BufferTooSmall {
+		needed: usize,
+		capacity: usize
+	},
+	/// A builder expected `expected` slices but `actual` were pushed
+	EntryCountMismatch {
+		expected: usize,
+		actual: usize
+	},
+	/// Writing entry `at_entry` would have gone past the limit `which` set by `WriterLimits`, so nothing of it was written
+	LimitExceeded {
+		which: LimitKind,
+		at_entry: usize
+	}
+}
+
+
+impl std::error::Error for BlobWriteError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			BlobWriteError::Io(e) => Some(e),
+			BlobWriteError::TooLarge | BlobWriteError::BufferTooSmall { .. } | BlobWriteError::EntryCountMismatch { .. } | BlobWriteError::LimitExceeded { .. } => None
+		}
+	}
+}
+
+impl fmt::Display for BlobWriteError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			BlobWriteError::Io(e) => write!(formatter, "{}", e),
+			BlobWriteError::TooLarge => write!(formatter, "TooLarge"),
+			BlobWriteError::BufferTooSmall { needed, capacity } => write!(formatter, "blob needs {} bytes but the buffer holds {}", needed, capacity),
+			BlobWriteError::EntryCountMismatch { expected, actual } => write!(formatter, "expected {} slices but {} were pushed", expected, actual),
+			BlobWriteError::LimitExceeded { which, at_entry } => write!(formatter, "entry {} would go past the {:?} limit", at_entry, which)
+		}
+	}
+}
+
+impl From<io::Error> for BlobWriteError {
+	fn from(e: io::Error) -> Self {
+		BlobWriteError::Io(e)
+	}
+}
+
+
+/// Reads the slices of a blob from an `io::Read` one at a time, so the whole blob never has to be in memory
+///
+/// This is a wrapper around `BlobDecoder`. Bytes are only read from the reader as they are needed, so nothing past the last entry is consumed.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1, 2], &[3]]);
+/// let mut reader = BlobReader::new(&blob[..]);
+///
+/// assert_eq!(reader.read_entry().unwrap(), Some(vec![1, 2]));
+/// assert_eq!(reader.read_entry().unwrap(), Some(vec![3]));
+/// assert_eq!(reader.read_entry().unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct BlobReader<R> {
+	reader: R,
+	decoder: BlobDecoder
+}
+
+
+/// Size of the buffer payloads are read through
+const READ_CHUNK: usize = 8 * 1024;
+
+
+impl<R: Read> BlobReader<R> {
+	pub fn new(reader: R) -> Self {
+		BlobReader { reader, decoder: BlobDecoder::new() }
+	}
+
+	/// Limits the total number of payload bytes the reader will allocate, in the same way as `blob_to_byte_vecs_bounded`
+	pub fn with_budget(mut self, max_bytes: usize) -> Self {
+		self.decoder = self.decoder.with_budget(max_bytes);
+		self
+	}
+
+	/// Returns the number of bytes of the blob consumed so far
+	pub fn position(&self) -> usize {
+		self.decoder.position()
+	}
+
+	/// Returns the underlying reader
+	pub fn into_inner(self) -> R {
+		self.reader
+	}
+
+	/// Reads the next slice, or returns `Ok(None)` if the stream ended cleanly between slices
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobStreamError::Read)` for the same invalid data `blob_to_byte_arrays` rejects (a stream ending inside a slice is treated as an index past the end),
+	/// or when the budget is exceeded. Returns `Err(BlobStreamError::Io)` if the reader fails
+	pub fn read_entry(&mut self) -> Result<Option<Vec<u8>>, BlobStreamError> {
+		let mut chunk = [0u8; READ_CHUNK];
+		loop {
+			if let Some(byte_arr) = self.decoder.next_entry() {
+				return Ok(Some(byte_arr));
+			}
+
+			// Only ask for what the current index or entry still needs, so a hostile index can't make us allocate more than the stream holds
+			let wanted = self.decoder.wanted().clamp(1, READ_CHUNK);
+			let n = match self.reader.read(&mut chunk[..wanted]) {
+				Ok(n) => n,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				Err(e) => return Err(e.into())
+			};
+
+			if n == 0 {
+				self.decoder.check_finished()?;
+				return Ok(None);
+			}
+			self.decoder.push(&chunk[..n])?;
+		}
+	}
+}
+
+
+impl<R: Read> BlobReader<R> {
+	/// Reads every remaining slice, calling `on_progress` after each one. See `blob_to_byte_arrays_with_progress`
+	///
+	/// The length of the stream isn't known, so `bytes_total` and `entries_total` are always `None`.
+	pub fn read_all_with_progress(&mut self, mut on_progress: impl FnMut(Progress)) -> Result<Vec<Vec<u8>>, BlobStreamError> {
+		let mut entries = Vec::new();
+		while let Some(byte_arr) = self.read_entry()? {
+			entries.push(byte_arr);
+			on_progress(Progress { entries_done: entries.len(), entries_total: None, bytes_done: self.decoder.position(), bytes_total: None });
+		}
+		Ok(entries)
+	}
+}
+
+
+impl<R: Read> Iterator for BlobReader<R> {
+	type Item = Result<Vec<u8>, BlobStreamError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.read_entry().transpose()
+	}
+}
+
+
+/// Which of the `WriterLimits` a write would have gone past
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LimitKind {
+	/// The number of entries
+	Entries,
+	/// The total length of the payloads, not counting indexes
+	PayloadBytes,
+	/// The length of the encoded blob, indexes included
+	EncodedBytes
+}
+
+
+/// Limits on what a `BlobWriter` will write, such as to enforce a quota while a blob is streamed in
+///
+/// Every limit is inclusive, so a blob that reaches a limit exactly is allowed.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let limits = WriterLimits::new().with_max_entries(2).with_max_payload_bytes(1024);
+/// let mut writer = BlobWriter::new(Vec::new()).with_limits(limits);
+///
+/// writer.write_entry(b"first").unwrap();
+/// writer.write_entry(b"second").unwrap();
+/// match writer.write_entry(b"third") {
+/// 	Err(BlobWriteError::LimitExceeded { which, at_entry }) => assert_eq!((which, at_entry), (LimitKind::Entries, 2)),
+/// 	_ => panic!("This should not happen")
+/// }
+/// assert_eq!(writer.finish().unwrap(), byte_arrays_to_blob(&[b"first", b"second"]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriterLimits {
+	max_entries: usize,
+	max_payload_bytes: usize,
+	max_encoded_bytes: usize
+}
+
+
+impl Default for WriterLimits {
+	fn default() -> Self {
+		WriterLimits::new()
+	}
+}
+
+
+impl WriterLimits {
+	/// Creates limits that allow anything
+	pub fn new() -> Self {
+		WriterLimits { max_entries: usize::MAX, max_payload_bytes: usize::MAX, max_encoded_bytes: usize::MAX }
+	}
+
+	/// Sets the most entries that may be written
+	pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+		self.max_entries = max_entries;
+		self
+	}
+
+	/// Sets the most payload bytes that may be written, not counting indexes
+	pub fn with_max_payload_bytes(mut self, max_payload_bytes: usize) -> Self {
+		self.max_payload_bytes = max_payload_bytes;
+		self
+	}
+
+	/// Sets the longest the encoded blob may grow, indexes included
+	pub fn with_max_encoded_bytes(mut self, max_encoded_bytes: usize) -> Self {
+		self.max_encoded_bytes = max_encoded_bytes;
+		self
+	}
+
+	/// Returns the most entries that may be written
+	pub fn max_entries(&self) -> usize {
+		self.max_entries
+	}
+
+	/// Returns the most payload bytes that may be written
+	pub fn max_payload_bytes(&self) -> usize {
+		self.max_payload_bytes
+	}
+
+	/// Returns the longest the encoded blob may grow
+	pub fn max_encoded_bytes(&self) -> usize {
+		self.max_encoded_bytes
+	}
+
+	/// Returns the first limit that writing one more entry of `payload_len` bytes would go past
+	fn check(&self, entries: usize, payload_bytes: usize, encoded_bytes: usize, payload_len: usize) -> Option<LimitKind> {
+		let over = |total: usize, added: usize, max: usize| total.checked_add(added).is_none_or(|total| total > max);
+		if over(entries, 1, self.max_entries) {
+			Some(LimitKind::Entries)
+		} else if over(payload_bytes, payload_len, self.max_payload_bytes) {
+			Some(LimitKind::PayloadBytes)
+		} else if over(encoded_bytes, payload_len.saturating_add(4), self.max_encoded_bytes) {
+			Some(LimitKind::EncodedBytes)
+		} else {
+			None
+		}
+	}
+}
+
+
+/// Writes a blob to an `io::Write` one slice at a time. The bytes written are exactly those `byte_arrays_to_blob` produces
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut writer = BlobWriter::new(Vec::new());
+/// writer.write_entry(&[1, 2]).unwrap();
+/// writer.write_entry(&[3]).unwrap();
+///
+/// assert_eq!(writer.finish().unwrap(), byte_arrays_to_blob(&[&[1, 2], &[3]]));
+/// ```
+#[derive(Debug)]
+pub struct BlobWriter<W: Write> {
+	writer: W,
+	position: usize,
+	entries: usize,
+	payload_bytes: usize,
+	limits: WriterLimits
+}
+
+
+impl<W: Write> BlobWriter<W> {
+	pub fn new(writer: W) -> Self {
+		BlobWriter { writer, position: 0, entries: 0, payload_bytes: 0, limits: WriterLimits::new() }
+	}
+
+	/// Sets limits that `write_entry` checks before writing each entry
+	pub fn with_limits(mut self, limits: WriterLimits) -> Self {
+		self.limits = limits;
+		self
+	}
+
+	/// Returns the number of bytes of the blob written so far
+	pub fn position(&self) -> usize {
+		self.position
+	}
+
+	/// Returns the number of entries written so far
+	pub fn entries_written(&self) -> usize {
+		self.entries
+	}
+
+	/// Returns the total length of the payloads written so far, not counting indexes
+	pub fn payload_bytes_written(&self) -> usize {
+		self.payload_bytes
+	}
+
+	/// Returns the number of bytes of the blob written so far, indexes included. The same as `position`
+	pub fn encoded_bytes_written(&self) -> usize {
+		self.position
+	}
+
+	/// Writes one slice and its index
+	///
+	/// # Errors
+	///
+	/// Returns `Err(BlobWriteError::LimitExceeded)` or `Err(BlobWriteError::TooLarge)` without writing anything if the entry would take the blob past
+	/// one of its `WriterLimits` or past `u32::MAX` bytes, so the blob written so far stays valid. Returns `Err(BlobWriteError::Io)` if the writer fails
+	pub fn write_entry(&mut self, byte_arr: &[u8]) -> Result<(), BlobWriteError> {
+		if let Some(which) = self.limits.check(self.entries, self.payload_bytes, self.position, byte_arr.len()) {
+			return Err(BlobWriteError::LimitExceeded { which, at_entry: self.entries });
+		}
+		let idx_end = self.position.checked_add(4 + byte_arr.len()).ok_or(BlobWriteError::TooLarge)?;
+		let write_end_idx = u32::try_from(idx_end).map_err(|_| BlobWriteError::TooLarge)?;
+
+		self.writer.write_all(&write_end_idx.to_ne_bytes())?;
+		self.writer.write_all(byte_arr)?;
+		self.position = idx_end;
+		self.entries += 1;
+		self.payload_bytes += byte_arr.len();
+		Ok(())
+	}
+
+	/// Returns the underlying writer, such as to flush it part way through. Writing to it directly will leave the blob invalid
+	pub fn get_mut(&mut self) -> &mut W {
+		&mut self.writer
+	}
+
+	/// Flushes and returns the underlying writer
+	pub fn finish(mut self) -> Result<W, BlobWriteError> {
+		self.writer.flush()?;
+		Ok(self.writer)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reader_matches_decode() {
+		let blob = byte_arrays_to_blob(&[&[], &[1u8, 2u8, 3u8], &[], &[4u8; 300]]);
+		let read: Vec<Vec<u8>> = BlobReader::new(&blob[..]).collect::<Result<_, _>>().unwrap();
+		assert_eq!(read, blob_to_byte_vecs(&blob).unwrap());
+	}
+
+	#[test]
+	fn reader_truncated() {
+		let blob = byte_arrays_to_blob(&[&[1u8, 2u8, 3u8], &[4u8; 10]]);
+
+		// Cut inside the second index, then inside the second payload
+		let mut reader = BlobReader::new(&blob[..9]);
+		reader.read_entry().unwrap();
+		assert!(matches!(reader.read_entry(), Err(BlobStreamError::Read(BlobReadError::TruncatedIndex(7)))));
+
+		let mut reader = BlobReader::new(&blob[..15]);
+		reader.read_entry().unwrap();
+		assert!(matches!(reader.read_entry(), Err(BlobStreamError::Read(BlobReadError::InvalidEncodedIndex(21)))));
+	}
+
+	#[test]
+	fn writer_matches_encode() {
+		let byte_arrays: Vec<&[u8]> = vec![&[], &[1u8, 2u8], &[3u8; 500], &[]];
+		let mut writer = BlobWriter::new(Vec::new());
+		for byte_arr in &byte_arrays {
+			writer.write_entry(byte_arr).unwrap();
+		}
+		assert_eq!(writer.position(), encoded_len(&byte_arrays));
+		assert_eq!(writer.finish().unwrap(), byte_arrays_to_blob(&byte_arrays));
+	}
+
+	#[test]
+	fn writer_limits() {
+		let byte_arrays: Vec<&[u8]> = vec![&[1u8; 10], &[], &[2u8; 20], &[3u8; 5]];
+		let limited = |limits: WriterLimits| {
+			let mut writer = BlobWriter::new(Vec::new()).with_limits(limits);
+			let error = byte_arrays.iter().find_map(|byte_arr| writer.write_entry(byte_arr).err());
+			assert_eq!(writer.encoded_bytes_written(), writer.position());
+			let totals = (writer.entries_written(), writer.payload_bytes_written(), writer.position());
+			let blob = writer.finish().unwrap();
+			(blob, totals, error)
+		};
+
+		let (blob, totals, error) = limited(WriterLimits::new());
+		assert!(error.is_none());
+		assert_eq!(totals, (4, 35, 51));
+		assert_eq!(blob, byte_arrays_to_blob(&byte_arrays));
+
+		// Each limit is reached exactly by the first two entries and passed by the third, whose bytes must not be in the output
+		let cases = [
+			(WriterLimits::new().with_max_entries(2), LimitKind::Entries),
+			(WriterLimits::new().with_max_payload_bytes(10), LimitKind::PayloadBytes),
+			(WriterLimits::new().with_max_encoded_bytes(18).with_max_payload_bytes(100), LimitKind::EncodedBytes)
+		];
+		for (limits, kind) in cases {
+			let (blob, totals, error) = limited(limits);
+			assert!(matches!(error, Some(BlobWriteError::LimitExceeded { which, at_entry: 2 }) if which == kind), "{:?}", error);
+			assert_eq!(totals, (2, 10, 18));
+			assert_eq!(blob_to_byte_arrays(&blob).unwrap(), byte_arrays[..2]);
+		}
+
+		// The entry count is checked first
+		let (_, _, error) = limited(WriterLimits::new().with_max_entries(0).with_max_payload_bytes(0));
+		assert!(matches!(error, Some(BlobWriteError::LimitExceeded { which: LimitKind::Entries, at_entry: 0 })));
+	}
+
+	#[test]
+	fn reader_budget_trips_on_third_entry() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 4], &[2u8; 4], &[3u8; 100]]);
+		let mut reader = BlobReader::new(&blob[..]).with_budget(10);
+
+		assert!(reader.read_entry().unwrap().is_some());
+		assert!(reader.read_entry().unwrap().is_some());
+		match reader.read_entry() {
+			Err(BlobStreamError::Read(BlobReadError::BudgetExceeded { at_entry, needed, budget })) => assert_eq!((at_entry, needed, budget), (2, 108, 10)),
+			other => panic!("Expected BlobReadError::BudgetExceeded, got {:?}", other)
+		}
+	}
+}
@@ -0,0 +1,123 @@
+//! Blobs stamped with a caller chosen schema tag, so a reader built for a different layout of entries fails instead of misreading them
+//!
+//! # Layout
+//!
+//! Slice 0 is a 12 byte header: the magic bytes `BABS` followed by the tag as a little-endian u64. The entries follow in order.
+//! A tagged blob is an ordinary blob, so `blob_to_byte_arrays` reads it without any check, with the header as its first slice.
+use crate::*;
+
+
+const TAG_MAGIC: [u8; 4] = *b"BABS";
+
+
+/// Converts slices to a blob with `tag` in a header before them. See the module layout above
+///
+/// The tag can be anything both sides agree on, such as a hash of the entry layout or a build constant.
+///
+/// # Panics
+///
+/// Panics if the blob would be larger than `u32::MAX` bytes, the same as `byte_arrays_to_blob`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// const SCHEMA: u64 = 0x5EED_0001;
+/// let blob = byte_arrays_to_blob_tagged(&[b"name", b"score"], SCHEMA);
+///
+/// assert_eq!(blob_to_byte_arrays_tagged(&blob, SCHEMA).unwrap(), [&b"name"[..], b"score"]);
+/// assert_eq!(blob_to_byte_arrays_tagged(&blob, 7), Err(BlobReadError::SchemaTagMismatch { expected: 7, found: SCHEMA }));
+///
+/// // The plain decoder doesn't check the tag, and sees the header as the first slice
+/// assert_eq!(blob_to_byte_arrays(&blob).unwrap().len(), 3);
+/// ```
+pub fn byte_arrays_to_blob_tagged(bytes_2d: &[&[u8]], tag: u64) -> Vec<u8> {
+	let mut header = [0u8; 12];
+	header[..4].copy_from_slice(&TAG_MAGIC);
+	header[4..].copy_from_slice(&tag.to_le_bytes());
+
+	let mut byte_arrays = Vec::with_capacity(bytes_2d.len() + 1);
+	byte_arrays.push(&header[..]);
+	byte_arrays.extend_from_slice(bytes_2d);
+	byte_arrays_to_blob(&byte_arrays)
+}
+
+
+/// Returns the schema tag of a tagged blob, or `None` if its first slice isn't a tag header, without reading past the first slice
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` if the first index is invalid, the same as `blob_to_byte_arrays`
+pub fn blob_schema_tag(blob: &[u8]) -> Result<Option<u64>, BlobReadError> {
+	let header = match blob_iter(blob).next() {
+		Some(header) => header?,
+		None => return Ok(None)
+	};
+	Ok(match header.split_first_chunk::<4>() {
+		Some((magic, tag)) if *magic == TAG_MAGIC => tag.try_into().ok().map(u64::from_le_bytes),
+		_ => None
+	})
+}
+
+
+/// Converts a tagged blob to a Vec of its u8 slices, after checking its tag is `expected_tag`
+///
+/// The tag is checked before any other slice is read, so a blob written for another schema fails the same way whatever its entries hold.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::MissingSchemaTag)` if the blob has no tag header, `Err(BlobReadError::SchemaTagMismatch)` if its tag isn't `expected_tag`,
+/// otherwise the same as `blob_to_byte_arrays`
+pub fn blob_to_byte_arrays_tagged(blob: &[u8], expected_tag: u64) -> Result<Vec<&[u8]>, BlobReadError> {
+	match blob_schema_tag(blob)? {
+		Some(found) if found == expected_tag => {},
+		Some(found) => return Err(BlobReadError::SchemaTagMismatch { expected: expected_tag, found }),
+		None => return Err(BlobReadError::MissingSchemaTag)
+	}
+	let mut byte_arrays = blob_to_byte_arrays(blob)?;
+	byte_arrays.remove(0);
+	Ok(byte_arrays)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn matching_and_mismatched_tags() {
+		let entries: [&[u8]; 3] = [b"a", &[], b"ccc"];
+		let blob = byte_arrays_to_blob_tagged(&entries, u64::MAX - 1);
+		assert_eq!(blob_schema_tag(&blob), Ok(Some(u64::MAX - 1)));
+		assert_eq!(blob_to_byte_arrays_tagged(&blob, u64::MAX - 1).unwrap(), entries);
+		assert_eq!(blob_to_byte_arrays_tagged(&blob, 0), Err(BlobReadError::SchemaTagMismatch { expected: 0, found: u64::MAX - 1 }));
+		assert_eq!(BlobReadError::SchemaTagMismatch { expected: 0, found: 1 }.kind(), BlobErrorKind::SchemaMismatch);
+
+		let empty = byte_arrays_to_blob_tagged(&[], 5);
+		assert!(blob_to_byte_arrays_tagged(&empty, 5).unwrap().is_empty());
+
+		// The plain decoder accepts a tagged blob unchecked
+		let plain = blob_to_byte_arrays(&blob).unwrap();
+		assert_eq!(plain[0].len(), 12);
+		assert_eq!(plain[1..], entries);
+	}
+
+	#[test]
+	fn missing_tags() {
+		assert_eq!(blob_to_byte_arrays_tagged(&[], 1), Err(BlobReadError::MissingSchemaTag));
+		assert_eq!(blob_to_byte_arrays_tagged(&byte_arrays_to_blob(&[b"untagged"]), 1), Err(BlobReadError::MissingSchemaTag));
+		// The magic without a whole tag, and a whole tag without the magic
+		assert_eq!(blob_schema_tag(&byte_arrays_to_blob(&[b"BABS1234"])), Ok(None));
+		assert_eq!(blob_schema_tag(&byte_arrays_to_blob(&[b"XABS12345678"])), Ok(None));
+		assert_eq!(BlobReadError::MissingSchemaTag.kind(), BlobErrorKind::SchemaMismatch);
+
+		// A bad first index is reported as it is, and later entries are only read once the tag matches
+		assert!(matches!(blob_to_byte_arrays_tagged(&[255u8; 4], 1), Err(BlobReadError::NotABlob { .. })));
+		let mut blob = byte_arrays_to_blob_tagged(&[b"entry"], 2);
+		blob.truncate(blob.len() - 1);
+		assert_eq!(blob_to_byte_arrays_tagged(&blob, 3), Err(BlobReadError::SchemaTagMismatch { expected: 3, found: 2 }));
+		assert!(blob_to_byte_arrays_tagged(&blob, 2).is_err());
+	}
+}
@@ -0,0 +1,47 @@
+//! Entries shared by the unit tests of several modules
+use crate::*;
+
+
+/// Returns entries covering the awkward cases: empty ones at the start, middle and end, short ones, a long run that compresses well,
+/// bytes that barely compress, and an entry large enough to span many read buffers
+pub(crate) fn sample_entries() -> Vec<Vec<u8>> {
+	let mut mixed: Vec<u8> = (0..=255u8).collect();
+	mixed.extend([3u8; 5000]);
+	vec![vec![], b"alpha".to_vec(), vec![0u8; 10_000], vec![], b"not very compressible".to_vec(), mixed, vec![2u8; 200_000], vec![5u8], vec![]]
+}
+
+
+/// Borrows each entry as a slice, for the functions that take `&[&[u8]]`
+pub(crate) fn as_slices(entries: &[Vec<u8>]) -> Vec<&[u8]> {
+	entries.iter().map(Vec::as_slice).collect()
+}
+
+
+/// Encodes entries as a plain blob
+pub(crate) fn entries_to_blob(entries: &[Vec<u8>]) -> Vec<u8> {
+	byte_arrays_to_blob(&as_slices(entries))
+}
+
+
+/// A 32 bit xorshift PRNG, so tests that want varied inputs check the same ones on every run
+pub(crate) struct XorShift32(u32);
+
+
+impl XorShift32 {
+	/// Starts from `seed`, which must not be 0
+	pub(crate) fn new(seed: u32) -> Self {
+		XorShift32(seed)
+	}
+
+	/// The seed most tests use
+	pub(crate) fn default_seed() -> Self {
+		XorShift32::new(0x9E37_79B9)
+	}
+
+	pub(crate) fn next_u32(&mut self) -> u32 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 17;
+		self.0 ^= self.0 << 5;
+		self.0
+	}
+}
@@ -0,0 +1,122 @@
+//! Reproducible synthetic blobs of a chosen shape, for benchmarking and testing code that consumes blobs
+//!
+//! The generator is a small PRNG built into the crate, so a seed gives the same entries on every run, platform and version of Rust.
+use crate::*;
+
+
+/// The shape of the entries made by `generate_entries` and `generate_blob`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::testutil::*;
+///
+/// let spec = GenSpec { entries: 100, min_len: 0, max_len: 64, compressible: false };
+/// assert_eq!(generate_entries(7, &spec).len(), 100);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenSpec {
+	/// Number of entries to make
+	pub entries: usize,
+	/// Shortest entry length, inclusive
+	pub min_len: usize,
+	/// Longest entry length, inclusive
+	pub max_len: usize,
+	/// If true payloads are runs of a few repeated bytes, which compress well. Otherwise every byte comes from the PRNG
+	pub compressible: bool
+}
+
+
+/// SplitMix64, which is small, fast, and gives well mixed output even for seeds such as 0
+#[derive(Debug, Clone)]
+struct SplitMix64 {
+	state: u64
+}
+
+
+impl SplitMix64 {
+	fn next(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^ (z >> 31)
+	}
+
+	/// Returns a number from 0 to `bound` inclusive. The small bias from the modulo doesn't matter for test data
+	fn up_to(&mut self, bound: usize) -> usize {
+		match (bound as u64).checked_add(1) {
+			Some(range) => (self.next() % range) as usize,
+			None => self.next() as usize
+		}
+	}
+}
+
+
+fn fill_random(rng: &mut SplitMix64, entry: &mut Vec<u8>, len: usize) {
+	while entry.len() < len {
+		let bytes = rng.next().to_le_bytes();
+		entry.extend_from_slice(&bytes[..bytes.len().min(len - entry.len())]);
+	}
+}
+
+
+fn fill_compressible(rng: &mut SplitMix64, entry: &mut Vec<u8>, len: usize) {
+	while entry.len() < len {
+		// A run of 1 to 64 copies of one of 16 byte values
+		let value = rng.next();
+		let byte = (value & 0x0F) as u8 * 17;
+		let run = ((value >> 8) % 64 + 1) as usize;
+		entry.resize(len.min(entry.len() + run), byte);
+	}
+}
+
+
+/// Returns the entries for `seed` and `spec`. Entry lengths are spread evenly from `spec.min_len` to `spec.max_len`
+///
+/// # Panics
+///
+/// Panics if `spec.min_len` is more than `spec.max_len`
+pub fn generate_entries(seed: u64, spec: &GenSpec) -> Vec<Vec<u8>> {
+	assert!(spec.min_len <= spec.max_len, "GenSpec min_len {} is more than max_len {}", spec.min_len, spec.max_len);
+	let mut rng = SplitMix64 { state: seed };
+	(0..spec.entries).map(|_| {
+		let len = spec.min_len + rng.up_to(spec.max_len - spec.min_len);
+		let mut entry = Vec::with_capacity(len);
+		if spec.compressible {
+			fill_compressible(&mut rng, &mut entry, len);
+		}
+		else {
+			fill_random(&mut rng, &mut entry, len);
+		}
+		entry
+	}).collect()
+}
+
+
+/// Returns a blob of the entries from `generate_entries(seed, spec)`
+///
+/// The entries are the same everywhere, but like every blob the indexes are native-endian,
+/// so the bytes only match between machines with the same byte order.
+///
+/// # Panics
+///
+/// Panics if `spec.min_len` is more than `spec.max_len`, or if the blob would be larger than `u32::MAX` bytes
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+/// use byte_array_blob::testutil::*;
+///
+/// let spec = GenSpec { entries: 10, min_len: 16, max_len: 32, compressible: true };
+/// let blob = generate_blob(42, &spec);
+///
+/// assert_eq!(blob, generate_blob(42, &spec));
+/// assert_eq!(blob_to_byte_vecs(&blob).unwrap(), generate_entries(42, &spec));
+/// ```
+pub fn generate_blob(seed: u64, spec: &GenSpec) -> Vec<u8> {
+	let mut blob = Blob::new();
+	for entry in generate_entries(seed, spec) {
+		blob.push(&entry);
+	}
+	blob.into_bytes()
+}
@@ -0,0 +1,192 @@
+//! `verify_transform`, a dry run of a transform over every entry of a blob that checks it can be undone before anything is rewritten
+use crate::*;
+use std::io::Read;
+
+
+/// The first entry whose payload didn't survive a transform and its inverse, found by `verify_transform`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransformMismatch {
+	/// The index of the entry
+	pub entry: usize,
+	/// The first byte that differs, which is the length of the shorter one if one is a prefix of the other
+	pub offset: usize,
+	/// The length of the original payload
+	pub original_len: usize,
+	/// The length of the payload after the transform and its inverse
+	pub round_trip_len: usize
+}
+
+
+/// What `verify_transform` found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VerifyTransformReport {
+	/// Number of entries checked
+	pub entries: usize,
+	/// Total length of the original payloads
+	pub bytes: u64,
+	/// Total length of the transformed payloads, which is what a repack would write before indexes
+	pub transformed_bytes: u64,
+	/// Number of entries that didn't survive the round trip
+	pub mismatches: usize,
+	/// The first entry that didn't survive the round trip, if any
+	pub first_mismatch: Option<TransformMismatch>
+}
+
+
+impl VerifyTransformReport {
+	/// Returns true if every entry survived the round trip
+	pub fn is_ok(&self) -> bool {
+		self.mismatches == 0
+	}
+
+	/// Returns how many bytes the transformed payloads would take compared to the originals. Negative if they would shrink
+	pub fn size_delta(&self) -> i128 {
+		self.transformed_bytes as i128 - self.bytes as i128
+	}
+}
+
+
+/// Errors that can occur when verifying a transform
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VerifyTransformError<E> {
+	/// The blob couldn't be read
+	Stream(BlobStreamError),
+	/// The transform failed on entry `entry`
+	Forward {
+		entry: usize,
+		error: E
+	},
+	/// The inverse failed on the transformed payload of entry `entry`
+	Inverse {
+		entry: usize,
+		error: E
+	}
+}
+
+
+impl<E: std::error::Error + 'static> std::error::Error for VerifyTransformError<E> {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			VerifyTransformError::Stream(e) => Some(e),
+			VerifyTransformError::Forward { error, .. } | VerifyTransformError::Inverse { error, .. } => Some(error)
+		}
+	}
+}
+
+impl<E: fmt::Debug> fmt::Display for VerifyTransformError<E> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl<E> From<BlobStreamError> for VerifyTransformError<E> {
+	fn from(e: BlobStreamError) -> Self {
+		VerifyTransformError::Stream(e)
+	}
+}
+
+
+/// Applies `forward` and then `inverse` to every entry of the blob read from `reader`, and checks each comes back as it was, without writing anything
+///
+/// Both get the entry index along with the bytes. Entries are read one at a time, so only one entry and its transformed copies are held in memory,
+/// and a blob in memory can be checked by passing it as a `&[u8]`. Every entry is checked even after a mismatch, so the report covers the whole blob.
+///
+/// # Errors
+///
+/// Returns `Err(VerifyTransformError::Forward)` or `Err(VerifyTransformError::Inverse)` with the first error either returns, stopping there,
+/// or `Err(VerifyTransformError::Stream)` with the error `BlobReader::read_entry` gives
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[7u8; 100], b"abc"]);
+/// let compress = |_, byte_arr: &[u8]| Ok::<_, BlobReadError>(blob_to_byte_vecs(&byte_arrays_to_compressed_blob(&[byte_arr]))?.remove(0));
+/// let decompress = |_, entry: &[u8]| {
+/// 	let blob = byte_arrays_to_blob(&[entry]);
+/// 	let view = CompressedBlobView::new(&blob)?;
+/// 	view.get(0).unwrap().read_to_vec()
+/// };
+///
+/// let report = verify_transform(&blob[..], compress, decompress).unwrap();
+/// assert!(report.is_ok());
+/// assert_eq!(report.entries, 2);
+/// assert!(report.size_delta() < 0);
+/// ```
+pub fn verify_transform<R: Read, E>(
+	reader: R,
+	mut forward: impl FnMut(usize, &[u8]) -> Result<Vec<u8>, E>,
+	mut inverse: impl FnMut(usize, &[u8]) -> Result<Vec<u8>, E>
+) -> Result<VerifyTransformReport, VerifyTransformError<E>> {
+	let mut reader = BlobReader::new(reader);
+	let mut report = VerifyTransformReport::default();
+
+	while let Some(byte_arr) = reader.read_entry()? {
+		let entry = report.entries;
+		let transformed = forward(entry, &byte_arr).map_err(|error| VerifyTransformError::Forward { entry, error })?;
+		let round_trip = inverse(entry, &transformed).map_err(|error| VerifyTransformError::Inverse { entry, error })?;
+
+		report.entries += 1;
+		report.bytes += byte_arr.len() as u64;
+		report.transformed_bytes += transformed.len() as u64;
+		if round_trip != byte_arr {
+			report.mismatches += 1;
+			let offset = byte_arr.iter().zip(&round_trip).position(|(a, b)| a != b).unwrap_or(byte_arr.len().min(round_trip.len()));
+			report.first_mismatch.get_or_insert(TransformMismatch { entry, offset, original_len: byte_arr.len(), round_trip_len: round_trip.len() });
+		}
+	}
+	Ok(report)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn xor(_: usize, byte_arr: &[u8]) -> Result<Vec<u8>, String> {
+		Ok(byte_arr.iter().map(|byte| byte ^ 0x5A).collect())
+	}
+
+	#[test]
+	fn lossless_transform() {
+		let blob = byte_arrays_to_blob(&[b"one", &[], &[9u8; 1000]]);
+		let report = verify_transform(&blob[..], xor, xor).unwrap();
+		assert_eq!(report, VerifyTransformReport { entries: 3, bytes: 1003, transformed_bytes: 1003, mismatches: 0, first_mismatch: None });
+		assert!(report.is_ok());
+		assert_eq!(report.size_delta(), 0);
+	}
+
+	#[test]
+	fn lossy_transform_reports_first_mismatch() {
+		let blob = byte_arrays_to_blob(&[b"fine", b"Hello World", b"ok", b"MIXED case"]);
+		// Lowercases as the transform, so only entries with capitals come back changed
+		let lower = |_, byte_arr: &[u8]| Ok::<_, String>(byte_arr.to_ascii_lowercase());
+		let identity = |_, byte_arr: &[u8]| Ok::<_, String>(byte_arr.to_vec());
+
+		let report = verify_transform(&blob[..], lower, identity).unwrap();
+		assert_eq!(report.entries, 4);
+		assert_eq!(report.mismatches, 2);
+		assert_eq!(report.first_mismatch, Some(TransformMismatch { entry: 1, offset: 0, original_len: 11, round_trip_len: 11 }));
+
+		// A transform that keeps only the first 5 bytes makes longer entries differ where it cut them
+		let truncate = |_, byte_arr: &[u8]| Ok::<_, String>(byte_arr[..byte_arr.len().min(5)].to_vec());
+		let report = verify_transform(&blob[..], truncate, identity).unwrap();
+		assert_eq!(report.first_mismatch, Some(TransformMismatch { entry: 1, offset: 5, original_len: 11, round_trip_len: 5 }));
+		assert_eq!(report.size_delta(), -(6 + 5));
+	}
+
+	#[test]
+	fn transform_and_read_errors() {
+		let blob = byte_arrays_to_blob(&[b"a", b"b", b"c"]);
+		let fails_on_second = |entry, byte_arr: &[u8]| match entry {
+			1 => Err(format!("bad entry {}", entry)),
+			_ => Ok(byte_arr.to_vec())
+		};
+		assert!(matches!(verify_transform(&blob[..], fails_on_second, xor), Err(VerifyTransformError::Forward { entry: 1, .. })));
+		assert!(matches!(verify_transform(&blob[..], xor, fails_on_second), Err(VerifyTransformError::Inverse { entry: 1, .. })));
+		assert!(matches!(verify_transform(&blob[..blob.len() - 1], xor, xor), Err(VerifyTransformError::Stream(BlobStreamError::Read(_)))));
+	}
+}
@@ -0,0 +1,95 @@
+//! Blobs where every slice is tagged with a short type string, such as a MIME type
+//!
+//! # Layout
+//!
+//! A typed blob is an ordinary blob holding two slices per entry: the UTF-8 tag, followed by the payload.
+//! Entry `i` is made of slices `2*i` and `2*i + 1`, so `blob_to_byte_arrays` can still read a typed blob.
+use crate::*;
+
+
+/// Converts a list of `(tag, payload)` pairs to a typed blob. See the module layout above
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = typed_byte_arrays_to_blob(&[("text/plain", b"hello"), ("application/json", b"{}")]);
+///
+/// let entries = blob_to_typed_byte_arrays(&blob).unwrap();
+/// assert_eq!(entries[0], ("text/plain", &b"hello"[..]));
+/// assert_eq!(entries[1], ("application/json", &b"{}"[..]));
+/// ```
+pub fn typed_byte_arrays_to_blob(entries: &[(&str, &[u8])]) -> Vec<u8> {
+	let byte_arrays: Vec<&[u8]> = entries.iter().flat_map(|(tag, data)| [tag.as_bytes(), *data]).collect();
+	byte_arrays_to_blob(&byte_arrays)
+}
+
+
+/// Converts a typed blob to a Vec of `(tag, payload)` pairs
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError::InvalidUtf8)` with the entry index if a tag is not valid UTF-8,
+/// `Err(BlobReadError::UnpairedEntry)` if the last tag has no payload, or any error from reading the underlying blob
+pub fn blob_to_typed_byte_arrays(blob: &[u8]) -> Result<Vec<(&str, &[u8])>, BlobReadError> {
+	let byte_arrays = blob_to_byte_arrays(blob)?;
+	if byte_arrays.len() % 2 != 0 {
+		return Err(BlobReadError::UnpairedEntry(byte_arrays.len() / 2));
+	}
+
+	byte_arrays.chunks_exact(2).enumerate().map(|(i, pair)| {
+		let tag = std::str::from_utf8(pair[0]).map_err(|_| BlobReadError::InvalidUtf8(i))?;
+		Ok((tag, pair[1]))
+	}).collect()
+}
+
+
+/// Returns the indexes of the entries in a typed blob whose tag is exactly `tag`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = typed_byte_arrays_to_blob(&[("image/png", b"a"), ("text/plain", b"b"), ("image/png", b"c")]);
+/// assert_eq!(blob_entries_with_type(&blob, "image/png").unwrap(), vec![0, 2]);
+/// ```
+pub fn blob_entries_with_type(blob: &[u8], tag: &str) -> Result<Vec<usize>, BlobReadError> {
+	Ok(blob_to_typed_byte_arrays(blob)?.iter().enumerate()
+		.filter(|(_, (entry_tag, _))| *entry_tag == tag)
+		.map(|(i, _)| i)
+		.collect())
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn empty_and_repeated_tags() {
+		let entries: Vec<(&str, &[u8])> = vec![("", b"untagged"), ("a", b""), ("a", b"x"), ("", b"")];
+		let blob = typed_byte_arrays_to_blob(&entries);
+
+		assert_eq!(blob_to_typed_byte_arrays(&blob).unwrap(), entries);
+		assert_eq!(blob_entries_with_type(&blob, "").unwrap(), vec![0, 3]);
+		assert_eq!(blob_entries_with_type(&blob, "a").unwrap(), vec![1, 2]);
+		assert!(blob_entries_with_type(&blob, "b").unwrap().is_empty());
+	}
+
+	#[test]
+	fn invalid_utf8_tag() {
+		let blob = byte_arrays_to_blob(&[b"ok", b"1", &[0xFFu8, 0xFEu8], b"2"]);
+		match blob_to_typed_byte_arrays(&blob) {
+			Err(BlobReadError::InvalidUtf8(i)) => assert_eq!(i, 1),
+			other => panic!("Expected BlobReadError::InvalidUtf8, got {:?}", other)
+		}
+	}
+
+	#[test]
+	fn unpaired_tag() {
+		let blob = byte_arrays_to_blob(&[b"tag", b"data", b"tag"]);
+		assert!(matches!(blob_to_typed_byte_arrays(&blob), Err(BlobReadError::UnpairedEntry(1))));
+	}
+}
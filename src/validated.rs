@@ -0,0 +1,170 @@
+//! `ValidatedBlob`, a blob whose indexes are checked once so reading slices needs no further checks
+use crate::*;
+
+
+/// A borrowed blob whose every index has been checked, so `get` and `iter` skip all checks except the bounds of the slice number
+///
+/// The only way to make one is `validate` (or converting a `BlobView`, which was validated when it was built), so holding a `ValidatedBlob`
+/// proves the blob is valid. The cached offsets are private and never change, which is what makes skipping the checks sound.
+/// Each slice's start is cached alongside its end, so `get` is two loads and no branches once `index` is known to be in range.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1], &[2, 2]]);
+/// let validated = ValidatedBlob::validate(&blob).unwrap();
+///
+/// assert_eq!(validated.get(1), Some(&[2, 2][..]));
+/// assert!(ValidatedBlob::validate(&blob[..6]).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValidatedBlob<'a> {
+	blob: &'a [u8],
+	/// Slice `i` is `offsets[i] + 4..offsets[i + 1]`, so `offsets` is never empty. `offsets[0]` is 0 unless the blob came from a sliced `BlobView`
+	offsets: Vec<usize>
+}
+
+
+impl<'a> ValidatedBlob<'a> {
+	/// Reads every index in `blob`, returning an error if any are invalid
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`
+	pub fn validate(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		let mut offsets = vec![0];
+		let mut iter = blob_iter(blob);
+		while let Some(byte_arr) = iter.next() {
+			byte_arr?;
+			offsets.push(iter.position());
+		}
+		Ok(ValidatedBlob { blob, offsets })
+	}
+
+	/// Returns the number of slices in the blob
+	pub fn len(&self) -> usize {
+		self.offsets.len() - 1
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Returns the encoded blob
+	pub fn as_bytes(&self) -> &'a [u8] {
+		self.blob
+	}
+
+	/// Returns slice `index`, or `None` if it is out of range
+	pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+		if index >= self.len() {
+			return None;
+		}
+		Some(self.get_in_range(index))
+	}
+
+	fn get_in_range(&self, index: usize) -> &'a [u8] {
+		debug_assert!(index < self.len());
+		// SAFETY: `offsets` has `len() + 1` elements, so both offsets exist
+		let (start, end) = unsafe { (*self.offsets.get_unchecked(index) + 4, *self.offsets.get_unchecked(index + 1)) };
+		debug_assert!(start <= end && end <= self.blob.len());
+		// SAFETY: `offsets` was read from `blob` by `validate` or by `BlobView::new`, which check every slice is inside the blob
+		// and starts after the previous one. Neither `blob` nor `offsets` can be changed afterwards
+		unsafe { self.blob.get_unchecked(start..end) }
+	}
+
+	/// Returns which slice the byte at `offset` in the blob belongs to, with a binary search over the cached offsets. See `BlobView::entry_at_offset`
+	pub fn entry_at_offset(&self, offset: usize) -> Option<EntryLocation> {
+		if offset < self.offsets[0] {
+			return None;
+		}
+		let entry_index = self.offsets[1..].partition_point(|&end| end <= offset);
+		if entry_index >= self.len() {
+			return None;
+		}
+		let payload_range = self.offsets[entry_index] + 4..self.offsets[entry_index + 1];
+		Some(EntryLocation { entry_index, in_header: offset < payload_range.start, payload_range })
+	}
+
+	/// Returns an iterator over the slices, which can also be iterated in reverse
+	pub fn iter(&self) -> impl DoubleEndedIterator<Item = &'a [u8]> + ExactSizeIterator + '_ {
+		(0..self.len()).map(|index| self.get_in_range(index))
+	}
+
+	/// Returns a `BlobView` of the same blob, copying the cached offsets rather than reading the blob again
+	pub fn view(&self) -> BlobView<'a> {
+		BlobView::from_validated_owned(self.blob, self.offsets[0], self.offsets[1..].to_vec())
+	}
+}
+
+
+impl<'a> From<BlobView<'a>> for ValidatedBlob<'a> {
+	fn from(view: BlobView<'a>) -> Self {
+		let mut offsets = Vec::with_capacity(view.len() + 1);
+		offsets.push(view.start());
+		offsets.extend_from_slice(view.ends());
+		ValidatedBlob { blob: view.as_bytes(), offsets }
+	}
+}
+
+
+impl<'a> From<ValidatedBlob<'a>> for BlobView<'a> {
+	fn from(validated: ValidatedBlob<'a>) -> Self {
+		let mut ends = validated.offsets;
+		let start = ends.remove(0);
+		BlobView::from_validated_owned(validated.blob, start, ends)
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn matches_plain_decoder() {
+		let blob = byte_arrays_to_blob(&[&[1u8], &[], &[2u8, 3u8], &[4u8; 30]]);
+		let mut rng = XorShift32::default_seed();
+
+		for _ in 0..2000 {
+			let random = rng.next_u32();
+			let mut corrupt = blob.clone();
+			let i = random as usize % corrupt.len();
+			corrupt[i] = (random >> 8) as u8;
+			corrupt.truncate(corrupt.len() - (random >> 24) as usize % 4);
+
+			match (blob_to_byte_arrays(&corrupt), ValidatedBlob::validate(&corrupt)) {
+				(Ok(expected), Ok(validated)) => {
+					assert_eq!(validated.iter().collect::<Vec<_>>(), expected);
+					assert_eq!(validated.iter().rev().count(), expected.len());
+				},
+				(Err(expected), Err(e)) => assert_eq!(e, expected),
+				(expected, validated) => panic!("decoder gave {:?} but validate gave {:?}", expected, validated)
+			}
+		}
+
+		for corrupt in [&[1u8][..], &[3u8, 0, 0, 0], &[255u8, 0, 0, 0], &[4u8, 0, 0, 0, 1u8]] {
+			assert_eq!(ValidatedBlob::validate(corrupt).unwrap_err(), blob_to_byte_arrays(corrupt).unwrap_err());
+		}
+	}
+
+	#[test]
+	fn view_conversions() {
+		let blob = byte_arrays_to_blob(&[&[1u8], &[], &[2u8, 3u8]]);
+		let validated = ValidatedBlob::validate(&blob).unwrap();
+		assert_eq!(validated.get(3), None);
+		assert!(ValidatedBlob::validate(&[]).unwrap().is_empty());
+
+		let view: BlobView = validated.clone().into();
+		assert_eq!(view.iter().collect::<Vec<_>>(), validated.iter().collect::<Vec<_>>());
+		assert_eq!(validated.view().ends(), view.ends());
+
+		let back = ValidatedBlob::from(view);
+		assert_eq!(back.len(), 3);
+		assert_eq!(back.get(2), Some(&[2u8, 3u8][..]));
+	}
+}
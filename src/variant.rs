@@ -0,0 +1,121 @@
+//! `BlobVariant`, one value for each layout a list of entries can be written in, so the layout can be picked at run time or compared in benchmarks
+use crate::*;
+use std::borrow::Cow;
+
+
+/// A layout a plain list of entries can be written in, with `encode` and `decode` dispatching to the functions for that layout
+///
+/// Layouts that store more than the entries, such as names or types, aren't included since they can't be written from entries alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlobVariant {
+	/// An ordinary blob with the given index layout. See `byte_arrays_to_blob_with_format`
+	Format(BlobFormat),
+	/// Each entry compressed separately. See `byte_arrays_to_compressed_blob`
+	Compressed,
+	/// Runs of empty entries stored as one marker. See `byte_arrays_to_blob_sparse`
+	Sparse,
+	/// Payloads aligned to the given number of bytes by padding slices. See `byte_arrays_to_blob_padded`
+	///
+	/// A non-empty entry made only of `PADDING_FILLER` bytes is dropped by `decode`, the same as `blob_skip_padding`.
+	Padded(usize)
+}
+
+
+impl BlobVariant {
+	/// Every layout, with `Padded` aligning to 8 bytes
+	pub const ALL: [BlobVariant; 7] = [
+		BlobVariant::Format(BlobFormat::NativeEndian),
+		BlobVariant::Format(BlobFormat::LittleEndian),
+		BlobVariant::Format(BlobFormat::LittleEndian64),
+		BlobVariant::Format(BlobFormat::LengthPrefixed),
+		BlobVariant::Compressed,
+		BlobVariant::Sparse,
+		BlobVariant::Padded(8)
+	];
+
+	/// Returns a short name for the layout, for labelling results
+	pub fn name(self) -> &'static str {
+		match self {
+			BlobVariant::Format(BlobFormat::NativeEndian) => "native-endian",
+			BlobVariant::Format(BlobFormat::LittleEndian) => "little-endian",
+			BlobVariant::Format(BlobFormat::LittleEndian64) => "little-endian-64",
+			BlobVariant::Format(BlobFormat::LengthPrefixed) => "length-prefixed",
+			BlobVariant::Compressed => "compressed",
+			BlobVariant::Sparse => "sparse",
+			BlobVariant::Padded(_) => "padded"
+		}
+	}
+
+	/// Converts slices to a blob in this layout
+	///
+	/// # Panics
+	///
+	/// Panics if the blob would be too large for the layout, or if the layout is `Padded(0)`
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::*;
+	///
+	/// for variant in BlobVariant::ALL {
+	/// 	let blob = variant.encode(&[b"one", b"", b"three"]);
+	/// 	assert_eq!(variant.decode(&blob).unwrap(), [&b"one"[..], b"", b"three"], "{}", variant.name());
+	/// }
+	/// ```
+	pub fn encode(self, bytes_2d: &[&[u8]]) -> Vec<u8> {
+		match self {
+			BlobVariant::Format(format) => byte_arrays_to_blob_with_format(bytes_2d, format),
+			BlobVariant::Compressed => byte_arrays_to_compressed_blob(bytes_2d),
+			BlobVariant::Sparse => byte_arrays_to_blob_sparse(bytes_2d),
+			BlobVariant::Padded(align) => byte_arrays_to_blob_padded(bytes_2d, align).0
+		}
+	}
+
+	/// Converts a blob in this layout to its entries, which borrow from the blob unless they had to be decompressed
+	///
+	/// # Errors
+	///
+	/// The same as the decode function for the layout
+	pub fn decode(self, blob: &[u8]) -> Result<Vec<Cow<'_, [u8]>>, BlobReadError> {
+		let borrowed = match self {
+			BlobVariant::Format(format) => blob_to_byte_arrays_with_format(blob, format)?,
+			BlobVariant::Compressed => return CompressedBlobView::new(blob)?.iter().map(|handle| handle.read()).collect(),
+			BlobVariant::Sparse => blob_sparse_to_byte_arrays(blob)?,
+			BlobVariant::Padded(_) => blob_skip_padding(blob)?
+		};
+		Ok(borrowed.into_iter().map(Cow::Borrowed).collect())
+	}
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_support::*;
+
+	#[test]
+	fn every_variant_round_trips() {
+		let entries: Vec<Vec<u8>> = (0..50u8).map(|i| match i % 5 {
+			0 => Vec::new(),
+			1 => vec![i; 300],
+			_ => (0..i).collect()
+		}).collect();
+		let slices = as_slices(&entries);
+
+		for variant in BlobVariant::ALL.into_iter().chain([BlobVariant::Padded(64)]) {
+			let blob = variant.encode(&slices);
+			assert_eq!(variant.decode(&blob).unwrap(), slices, "{:?}", variant);
+			assert!(variant.encode(&[]).is_empty());
+			assert!(variant.decode(&[]).unwrap().is_empty());
+		}
+	}
+
+	#[test]
+	fn names_are_distinct() {
+		let mut names: Vec<&str> = BlobVariant::ALL.iter().map(|variant| variant.name()).collect();
+		names.sort_unstable();
+		names.dedup();
+		assert_eq!(names.len(), BlobVariant::ALL.len());
+	}
+}
@@ -0,0 +1,266 @@
+//! Verifying a blob from a reader in fixed size chunks, without holding any entry in memory
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+use crate::crc32::Crc32;
+use std::io::{self, Read};
+
+
+/// How much of the stream is read at a time
+const VERIFY_CHUNK: usize = 64 * 1024;
+
+
+/// One entry checked by `verify_blob_stream`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryVerification {
+	/// Where the entry's index starts in the stream
+	pub offset: u64,
+	/// Length of the payload
+	pub len: usize,
+	/// CRC-32 of the payload
+	pub crc32: u32
+}
+
+
+/// What `verify_blob_stream` found
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VerifyReport {
+	/// Every entry that was read in full, in order
+	pub entries: Vec<EntryVerification>,
+	/// Number of bytes read from the stream
+	pub total_bytes: u64,
+	/// CRC-32 of every byte read, indexes included
+	pub crc32: u32
+}
+
+
+/// Errors that can occur when verifying a blob stream
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VerifyError {
+	/// An error from the underlying reader
+	Io(io::Error),
+	/// The blob is invalid. `report` holds every entry that passed before the failure, so the failing entry is `report.entries.len()`
+	Invalid {
+		error: BlobReadError,
+		report: VerifyReport
+	}
+}
+
+
+impl std::error::Error for VerifyError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			VerifyError::Io(e) => Some(e),
+			VerifyError::Invalid { error, .. } => Some(error)
+		}
+	}
+}
+
+impl fmt::Display for VerifyError {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			VerifyError::Io(e) => write!(formatter, "{}", e),
+			VerifyError::Invalid { error, report } => write!(formatter, "entry {} failed verification: {}", report.entries.len(), error)
+		}
+	}
+}
+
+impl From<io::Error> for VerifyError {
+	fn from(e: io::Error) -> Self {
+		VerifyError::Io(e)
+	}
+}
+
+
+enum VerifyState {
+	Index {
+		data: [u8; 4],
+		filled: usize
+	},
+	Payload {
+		offset: u64,
+		idx_end: usize,
+		crc: Crc32
+	}
+}
+
+
+/// Reads a whole blob from `reader`, checking its structure and computing a CRC-32 of every entry and of the whole stream
+///
+/// The stream is read once, 64 KB at a time, and payloads are only ever passed through the running digests, so memory use stays the same however large the entries are.
+/// The plain format stores no digests, so this checks the same structure `blob_to_byte_arrays` does, and the CRCs in the report are for comparing against digests kept elsewhere.
+///
+/// # Errors
+///
+/// Returns `Err(VerifyError::Invalid)` for the same invalid data `BlobReader` rejects, along with the report up to that point, or `Err(VerifyError::Io)` if the reader fails
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"first", b"second"]);
+///
+/// let report = verify_blob_stream(&mut &blob[..]).unwrap();
+/// assert_eq!(report.entries.len(), 2);
+/// assert_eq!(report.total_bytes, blob.len() as u64);
+///
+/// match verify_blob_stream(&mut &blob[..12]) {
+/// 	Err(VerifyError::Invalid { report, .. }) => assert_eq!(report.entries.len(), 1),
+/// 	other => panic!("{:?}", other)
+/// }
+/// ```
+pub fn verify_blob_stream<R: Read>(reader: &mut R) -> Result<VerifyReport, VerifyError> {
+	let mut report = VerifyReport::default();
+	let mut whole = Crc32::new();
+	let mut state = VerifyState::Index { data: [0u8; 4], filled: 0 };
+	let mut position = 0usize;
+	let mut chunk = vec![0u8; VERIFY_CHUNK];
+
+	let invalid = |error, mut report: VerifyReport, whole: Crc32, position: usize| {
+		report.total_bytes = position as u64;
+		report.crc32 = whole.finish();
+		VerifyError::Invalid { error, report }
+	};
+
+	loop {
+		let n = match reader.read(&mut chunk) {
+			Ok(n) => n,
+			Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+			Err(e) => return Err(e.into())
+		};
+
+		if n == 0 {
+			let error = match state {
+				VerifyState::Index { filled: 0, .. } => break,
+				VerifyState::Index { filled, .. } => BlobReadError::TruncatedIndex(position - filled),
+				// As in `BlobDecoder`, a stream that ends inside its first entry has a first index past the end
+				VerifyState::Payload { idx_end, .. } if report.entries.is_empty() => BlobReadError::NotABlob { first_header: u32::try_from(idx_end).unwrap_or(u32::MAX) },
+				VerifyState::Payload { idx_end, .. } => BlobReadError::InvalidEncodedIndex(idx_end)
+			};
+			return Err(invalid(error, report, whole, position));
+		}
+
+		// A reader that claims to have read more than the buffer holds is broken, and is reported rather than trusted
+		let mut bytes = chunk.get(..n).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "reader returned more bytes than it was given room for"))?;
+		while !bytes.is_empty() {
+			let (taken, rest) = match &mut state {
+				VerifyState::Index { data, filled } => {
+					let (taken, rest) = split_at_most(bytes, 4 - *filled);
+					fill_from(data, *filled, taken);
+					*filled += taken.len();
+					(taken, rest)
+				},
+				VerifyState::Payload { idx_end, crc, .. } => {
+					let (taken, rest) = split_at_most(bytes, *idx_end - position);
+					crc.update(taken);
+					(taken, rest)
+				}
+			};
+			whole.update(taken);
+			position += taken.len();
+			bytes = rest;
+
+			if let VerifyState::Index { data, filled: 4 } = state {
+				let encoded = u32::from_ne_bytes(data);
+				let idx_end = match index_to_usize(encoded) {
+					Ok(idx_end) => idx_end,
+					Err(e) => return Err(invalid(e, report, whole, position))
+				};
+				if idx_end < position {
+					let error = match report.entries.is_empty() {
+						true => BlobReadError::NotABlob { first_header: encoded },
+						false => BlobReadError::InvalidEncodedIndex(idx_end)
+					};
+					return Err(invalid(error, report, whole, position));
+				}
+				state = VerifyState::Payload { offset: position as u64 - 4, idx_end, crc: Crc32::new() };
+			}
+			if let VerifyState::Payload { offset, idx_end, crc } = state {
+				if idx_end == position {
+					report.entries.push(EntryVerification { offset, len: idx_end - offset as usize - 4, crc32: crc.finish() });
+					state = VerifyState::Index { data: [0u8; 4], filled: 0 };
+				}
+			}
+		}
+	}
+
+	report.total_bytes = position as u64;
+	report.crc32 = whole.finish();
+	Ok(report)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::crc32::crc32;
+	use std::io::{BufReader, Cursor};
+
+	#[test]
+	fn large_entry_through_small_buffer() {
+		let big: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+		let blob = byte_arrays_to_blob(&[b"head", &big, &[]]);
+
+		let mut reader = BufReader::with_capacity(4096, Cursor::new(&blob));
+		let report = verify_blob_stream(&mut reader).unwrap();
+
+		assert_eq!(report.total_bytes, blob.len() as u64);
+		assert_eq!(report.crc32, crc32(&blob));
+		assert_eq!(report.entries, vec![
+			EntryVerification { offset: 0, len: 4, crc32: crc32(b"head") },
+			EntryVerification { offset: 8, len: big.len(), crc32: crc32(&big) },
+			EntryVerification { offset: 12 + big.len() as u64, len: 0, crc32: 0 }
+		]);
+	}
+
+	#[test]
+	fn errors_match_blob_reader() {
+		let blob = byte_arrays_to_blob(&[&[1u8; 10], &[2u8; 10]]);
+
+		let cases = [blob[..blob.len() - 1].to_vec(), blob[..16].to_vec(), [&blob[..14], &[0u8, 0u8, 0u8, 0u8]].concat()];
+		for corrupt in cases {
+			let expected = match BlobReader::new(&corrupt[..]).read_all_with_progress(|_| {}) {
+				Err(BlobStreamError::Read(e)) => e,
+				other => panic!("{:?}", other)
+			};
+			match verify_blob_stream(&mut &corrupt[..]) {
+				Err(VerifyError::Invalid { error, report }) => {
+					assert_eq!(error, expected);
+					assert_eq!(report.entries.len(), 1);
+					assert!(report.total_bytes <= corrupt.len() as u64);
+				},
+				other => panic!("{:?}", other)
+			}
+		}
+
+		// A bad first index is reported as `NotABlob`, whether it points backwards or past the end
+		for corrupt in [&[255u8; 4][..], &2u32.to_ne_bytes(), &[9u32.to_ne_bytes().as_slice(), &[1]].concat()] {
+			let expected = blob_to_byte_arrays(corrupt).unwrap_err();
+			assert!(matches!(expected, BlobReadError::NotABlob { .. }), "{:?}", expected);
+			assert!(matches!(BlobReader::new(corrupt).read_entry(), Err(BlobStreamError::Read(e)) if e == expected));
+			match verify_blob_stream(&mut &corrupt[..]) {
+				Err(VerifyError::Invalid { error, report }) => {
+					assert_eq!(error, expected);
+					assert!(report.entries.is_empty());
+				},
+				other => panic!("{:?}", other)
+			}
+		}
+	}
+
+	struct FailingReader;
+
+	impl Read for FailingReader {
+		fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+			Err(io::Error::other("gone"))
+		}
+	}
+
+	#[test]
+	fn io_errors_are_passed_on() {
+		assert!(matches!(verify_blob_stream(&mut FailingReader), Err(VerifyError::Io(_))));
+		assert_eq!(verify_blob_stream(&mut &[][..]).unwrap(), VerifyReport { entries: vec![], total_bytes: 0, crc32: 0 });
+	}
+}
@@ -0,0 +1,187 @@
+//! Entries tagged with a schema version, and migration of old entries to a newer version
+//!
+//! # Layout
+//!
+//! Each slice is a little-endian u16 version followed by the payload.
+use crate::*;
+
+
+/// Appends `data` to a builder as a slice tagged with `version`. See the module layout above
+///
+/// # Errors
+///
+/// The same as `BlobBuilder::push`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut builder = BlobBuilder::new();
+/// push_versioned(&mut builder, 3, b"payload").unwrap();
+/// let blob = builder.finish().unwrap();
+///
+/// assert_eq!(split_version(blob_to_byte_arrays(&blob).unwrap()[0]), Some((3, &b"payload"[..])));
+/// ```
+pub fn push_versioned(builder: &mut BlobBuilder, version: u16, data: &[u8]) -> Result<(), BlobWriteError> {
+	builder.push_parts(&[&version.to_le_bytes(), data])
+}
+
+
+/// Splits a versioned slice into its version and payload, or returns `None` if it is too short to hold a version
+pub fn split_version(byte_arr: &[u8]) -> Option<(u16, &[u8])> {
+	let (version, data) = byte_arr.split_first_chunk::<2>()?;
+	Some((u16::from_le_bytes(*version), data))
+}
+
+
+/// Errors that can occur when migrating a versioned blob
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MigrateError<E> {
+	/// The blob is invalid, or slice `n` is too short to hold a version, given as `BlobReadError::InvalidMetadata(n)`
+	Read(BlobReadError),
+	/// Slice `entry` has a version later than the target, so it can't be migrated
+	NewerThanTarget {
+		entry: usize,
+		version: u16
+	},
+	/// The callback failed to migrate slice `entry` from version `from` to version `to`
+	Migrate {
+		entry: usize,
+		from: u16,
+		to: u16,
+		error: E
+	},
+	/// The migrated slices would make a blob larger than `u32::MAX` bytes
+	TooLarge
+}
+
+
+impl<E: std::error::Error + 'static> std::error::Error for MigrateError<E> {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			MigrateError::Read(e) => Some(e),
+			MigrateError::Migrate { error, .. } => Some(error),
+			MigrateError::NewerThanTarget { .. } | MigrateError::TooLarge => None
+		}
+	}
+}
+
+impl<E: fmt::Debug> fmt::Display for MigrateError<E> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(formatter, "{:?}", self)
+	}
+}
+
+impl<E> From<BlobReadError> for MigrateError<E> {
+	fn from(e: BlobReadError) -> Self {
+		MigrateError::Read(e)
+	}
+}
+
+
+/// Returns a copy of a versioned blob with every slice brought up to version `target`
+///
+/// `migrate` is given a version and a payload at that version, and returns the payload at the next version. A slice several versions behind
+/// is passed through it once per version, in order. Slices already at `target` are copied through without calling `migrate`,
+/// so migrating a blob a second time gives the same bytes back.
+///
+/// # Errors
+///
+/// Returns `Err(MigrateError::Migrate)` with the index of the slice and the step that failed if `migrate` fails,
+/// `Err(MigrateError::NewerThanTarget)` if a slice has a version after `target`, or `Err(MigrateError::Read)` if the blob is invalid
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let mut builder = BlobBuilder::new();
+/// push_versioned(&mut builder, 1, b"old").unwrap();
+/// push_versioned(&mut builder, 2, b"new").unwrap();
+///
+/// let migrated = blob_migrate_entries(&builder.finish().unwrap(), 2, |_, data| Ok::<_, ()>(data.to_ascii_uppercase())).unwrap();
+/// let entries: Vec<_> = blob_iter(&migrated).map(|byte_arr| split_version(byte_arr.unwrap()).unwrap()).collect();
+/// assert_eq!(entries, [(2, &b"OLD"[..]), (2, &b"new"[..])]);
+/// ```
+pub fn blob_migrate_entries<E>(blob: &[u8], target: u16, mut migrate: impl FnMut(u16, &[u8]) -> Result<Vec<u8>, E>) -> Result<Vec<u8>, MigrateError<E>> {
+	let mut builder = BlobBuilder::new();
+	for (entry, byte_arr) in blob_iter(blob).enumerate() {
+		let (version, data) = split_version(byte_arr?).ok_or(BlobReadError::InvalidMetadata(entry))?;
+		if version > target {
+			return Err(MigrateError::NewerThanTarget { entry, version });
+		}
+
+		let mut migrated = None;
+		for from in version..target {
+			let current = migrated.as_deref().unwrap_or(data);
+			let next = migrate(from, current).map_err(|error| MigrateError::Migrate { entry, from, to: from + 1, error })?;
+			migrated = Some(next);
+		}
+		push_versioned(&mut builder, target, migrated.as_deref().unwrap_or(data)).map_err(|_| MigrateError::TooLarge)?;
+	}
+	builder.finish().map_err(|_| MigrateError::TooLarge)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// v0 is a name, v1 adds a `:` separator and a level, v2 upper-cases the name
+	fn migrate(from: u16, data: &[u8]) -> Result<Vec<u8>, String> {
+		match from {
+			0 => Ok([data, b":1"].concat()),
+			1 => {
+				let split = data.iter().position(|&byte| byte == b':').ok_or("no level")?;
+				Ok([&data[..split].to_ascii_uppercase(), &data[split..]].concat())
+			},
+			_ => Err(format!("no migration from {}", from))
+		}
+	}
+
+	fn versioned_blob(entries: &[(u16, &[u8])]) -> Vec<u8> {
+		let mut builder = BlobBuilder::new();
+		for &(version, data) in entries {
+			push_versioned(&mut builder, version, data).unwrap();
+		}
+		builder.finish().unwrap()
+	}
+
+	fn entries(blob: &[u8]) -> Vec<(u16, &[u8])> {
+		blob_iter(blob).map(|byte_arr| split_version(byte_arr.unwrap()).unwrap()).collect()
+	}
+
+	#[test]
+	fn mixed_versions() {
+		let blob = versioned_blob(&[(0, b"ann"), (1, b"bob:4"), (2, b"CAT:9"), (0, b"")]);
+		let mut calls = Vec::new();
+		let migrated = blob_migrate_entries(&blob, 2, |from, data| {
+			calls.push(from);
+			migrate(from, data)
+		}).unwrap();
+		assert_eq!(entries(&migrated), [(2, &b"ANN:1"[..]), (2, b"BOB:4"), (2, b"CAT:9"), (2, b":1")]);
+		assert_eq!(calls, [0, 1, 1, 0, 1]);
+
+		// A second pass has nothing to do
+		let again = blob_migrate_entries(&migrated, 2, |_, _| -> Result<Vec<u8>, String> { panic!("already migrated") }).unwrap();
+		assert_eq!(again, migrated);
+
+		let older = versioned_blob(&[(0, b"ann"), (1, b"bob:4")]);
+		assert_eq!(entries(&blob_migrate_entries(&older, 1, migrate).unwrap()), [(1, &b"ann:1"[..]), (1, b"bob:4")]);
+		assert_eq!(blob_migrate_entries(&[], 7, migrate).unwrap(), Vec::<u8>::new());
+	}
+
+	#[test]
+	fn failures() {
+		let blob = versioned_blob(&[(2, b"A:1"), (1, b"no separator"), (0, b"c")]);
+		assert_eq!(blob_migrate_entries(&blob, 2, migrate), Err(MigrateError::Migrate { entry: 1, from: 1, to: 2, error: "no level".to_string() }));
+		assert_eq!(blob_migrate_entries(&blob, 3, migrate), Err(MigrateError::Migrate { entry: 0, from: 2, to: 3, error: "no migration from 2".to_string() }));
+		assert_eq!(blob_migrate_entries(&blob, 1, migrate), Err(MigrateError::NewerThanTarget { entry: 0, version: 2 }));
+
+		let short = byte_arrays_to_blob(&[&[0, 0], &[5]]);
+		assert_eq!(blob_migrate_entries(&short, 1, migrate), Err(MigrateError::Read(BlobReadError::InvalidMetadata(1))));
+		assert_eq!(split_version(&[1]), None);
+	}
+}
@@ -0,0 +1,430 @@
+//! `BlobView`, a validated blob with the position of every slice cached for random access
+#![cfg_attr(not(test), deny(clippy::indexing_slicing, clippy::unwrap_used, clippy::expect_used))]
+use crate::*;
+use std::borrow::Cow;
+use std::ops::Range;
+
+
+/// Which slice of a blob a byte offset falls in, returned by `blob_entry_at_offset`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryLocation {
+	pub entry_index: usize,
+	/// The byte range of the slice within the blob, not including its index
+	pub payload_range: Range<usize>,
+	/// True if the offset is in the 4 byte index before the slice rather than in the slice itself
+	pub in_header: bool
+}
+
+
+/// A borrowed blob that has been read once, caching where each slice ends
+///
+/// Building a view reads the whole blob, after which `get`, `len` and iteration in either direction are O(1) per slice.
+/// In comparison the plain free functions (such as `blob_last_array`) must walk every index from the start of the blob,
+/// because each index only says where its own slice ends.
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[&[1], &[2, 2], &[3, 3, 3]]);
+/// let view = BlobView::new(&blob).unwrap();
+///
+/// assert_eq!(view.len(), 3);
+/// assert_eq!(view.get(1), Some(&[2, 2][..]));
+/// assert_eq!(view.iter().rev().next(), Some(&[3, 3, 3][..]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlobView<'a> {
+	blob: &'a [u8],
+	/// Where the index of the first slice starts, which is only past 0 for a view made with `slice`
+	start: usize,
+	ends: Cow<'a, [usize]>
+}
+
+
+impl<'a> BlobView<'a> {
+	/// Reads every index in `blob`, returning an error if any are invalid
+	///
+	/// # Errors
+	///
+	/// The same as `blob_to_byte_arrays`
+	pub fn new(blob: &'a [u8]) -> Result<Self, BlobReadError> {
+		let mut ends = Vec::new();
+		let mut iter = blob_iter(blob);
+		while let Some(byte_arr) = iter.next() {
+			byte_arr?;
+			ends.push(iter.position());
+		}
+		Ok(BlobView { blob, start: 0, ends: Cow::Owned(ends) })
+	}
+
+	/// Builds a view from a blob and slice ends that are already known to be valid, such as those cached by a `Blob`
+	pub(crate) fn from_validated(blob: &'a [u8], ends: &'a [usize]) -> Self {
+		BlobView { blob, start: 0, ends: Cow::Borrowed(ends) }
+	}
+
+	/// The same as `from_validated` but taking ownership of the slice ends, which follow a first index at `start`
+	pub(crate) fn from_validated_owned(blob: &'a [u8], start: usize, ends: Vec<usize>) -> Self {
+		BlobView { blob, start, ends: Cow::Owned(ends) }
+	}
+
+	/// Returns a view of just the slices in `range`, borrowing the same bytes
+	///
+	/// Indexes into the new view count from the start of `range`. Nothing is copied, and the blob isn't read again.
+	///
+	/// # Panics
+	///
+	/// Panics if the range is inverted or ends past `len()`, in the same way as slicing a `[T]`
+	///
+	/// # Examples
+	/// ```
+	/// use byte_array_blob::*;
+	///
+	/// let blob = byte_arrays_to_blob(&[&[0], &[1], &[2], &[3]]);
+	/// let view = BlobView::new(&blob).unwrap();
+	/// let middle = view.slice(1..3);
+	///
+	/// assert_eq!(middle.len(), 2);
+	/// assert_eq!(middle.get(0), Some(&[1][..]));
+	/// assert_eq!(middle.to_blob().as_bytes(), byte_arrays_to_blob(&[&[1], &[2]]));
+	/// ```
+	pub fn slice(&self, range: Range<usize>) -> BlobView<'_> {
+		BlobView::from_validated_range(self.blob, self.start, &self.ends, range)
+	}
+
+	/// Builds a view of the slices in `range` out of the slice ends of a whole view, which follow a first index at `start`.
+	/// This panics on a bad `range` from the caller, as `slice` documents, never on anything read from the blob
+	#[allow(clippy::indexing_slicing)]
+	pub(crate) fn from_validated_range(blob: &'a [u8], start: usize, ends: &'a [usize], range: Range<usize>) -> Self {
+		let start = match range.start {
+			0 => start,
+			_ => ends[range.start - 1]
+		};
+		BlobView { blob, start, ends: Cow::Borrowed(&ends[range]) }
+	}
+
+	/// Copies the slices of the view into a new `Blob`, with the indexes rewritten to start from 0
+	pub fn to_blob(&self) -> Blob {
+		let first = self.start;
+		let last = self.ends.last().copied().unwrap_or(first);
+		let mut blob = Blob::with_capacity(last - first, self.len());
+		for byte_arr in self.iter() {
+			blob.push(byte_arr);
+		}
+		blob
+	}
+
+	/// Returns the number of slices in the blob
+	pub fn len(&self) -> usize {
+		self.ends.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.ends.is_empty()
+	}
+
+	/// Returns where each slice ends in the blob
+	pub(crate) fn ends(&self) -> &[usize] {
+		&self.ends
+	}
+
+	/// Returns where the index of the first slice starts in the blob
+	pub(crate) fn start(&self) -> usize {
+		self.start
+	}
+
+	/// Returns the encoded blob the view was built from
+	///
+	/// For a view made with `slice`, this is still the whole of the original blob, since only that is a valid blob on its own.
+	/// Use `to_blob` to get the encoding of just the sliced slices.
+	pub fn as_bytes(&self) -> &'a [u8] {
+		self.blob
+	}
+
+	/// Returns the byte range of slice `index` within the blob, not including its index
+	pub fn range(&self, index: usize) -> Option<Range<usize>> {
+		let end = *self.ends.get(index)?;
+		let start = match index {
+			0 => self.start + 4,
+			_ => *self.ends.get(index - 1)? + 4
+		};
+		Some(start..end)
+	}
+
+	/// Returns slice `index`, or `None` if it is out of range
+	pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+		self.range(index).and_then(|range| self.blob.get(range))
+	}
+
+	/// Returns the last slice in the blob
+	pub fn last(&self) -> Option<&'a [u8]> {
+		self.len().checked_sub(1).and_then(|index| self.get(index))
+	}
+
+	/// Returns which slice the byte at `offset` in the blob belongs to, or `None` if the offset is outside the slices of the view
+	///
+	/// This is a binary search over the cached slice ends, so O(log n).
+	pub fn entry_at_offset(&self, offset: usize) -> Option<EntryLocation> {
+		if offset < self.start {
+			return None;
+		}
+		let entry_index = self.ends.partition_point(|&end| end <= offset);
+		let payload_range = self.range(entry_index)?;
+		Some(EntryLocation { entry_index, in_header: offset < payload_range.start, payload_range })
+	}
+
+	/// Returns an iterator over the slices, which can also be iterated in reverse
+	pub fn iter(&self) -> BlobViewIter<'_, 'a> {
+		BlobViewIter { view: self, front: 0, back: self.len() }
+	}
+}
+
+
+impl<'v, 'a> IntoIterator for &'v BlobView<'a> {
+	type Item = &'a [u8];
+	type IntoIter = BlobViewIter<'v, 'a>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+
+/// Iterator over the slices of a `BlobView`, created by `BlobView::iter`
+#[derive(Debug, Clone)]
+pub struct BlobViewIter<'v, 'a> {
+	view: &'v BlobView<'a>,
+	front: usize,
+	back: usize
+}
+
+
+impl<'a> Iterator for BlobViewIter<'_, 'a> {
+	type Item = &'a [u8];
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.front >= self.back {
+			return None;
+		}
+		self.front += 1;
+		self.view.get(self.front - 1)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = self.back - self.front;
+		(remaining, Some(remaining))
+	}
+
+	fn nth(&mut self, n: usize) -> Option<Self::Item> {
+		self.front = self.front.saturating_add(n).min(self.back);
+		self.next()
+	}
+}
+
+
+impl<'a> DoubleEndedIterator for BlobViewIter<'_, 'a> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		if self.front >= self.back {
+			return None;
+		}
+		self.back -= 1;
+		self.view.get(self.back)
+	}
+
+	fn nth_back(&mut self, n: usize) -> Option<Self::Item> {
+		self.back = self.back.saturating_sub(n).max(self.front);
+		self.next_back()
+	}
+}
+
+
+impl ExactSizeIterator for BlobViewIter<'_, '_> {}
+
+impl std::iter::FusedIterator for BlobViewIter<'_, '_> {}
+
+
+/// Returns the last slice in a blob, or `None` if the blob is empty
+///
+/// The plain format can only be read forwards, so this walks every index in the blob, but nothing is allocated.
+/// Use a `BlobView` when the last slice is needed repeatedly.
+///
+/// # Errors
+///
+/// The same as `blob_to_byte_arrays`, since every index is checked on the way
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"data", b"manifest"]);
+/// assert_eq!(blob_last_array(&blob).unwrap(), Some(&b"manifest"[..]));
+/// ```
+pub fn blob_last_array(blob: &[u8]) -> Result<Option<&[u8]>, BlobReadError> {
+	let mut last = None;
+	for byte_arr in blob_iter(blob) {
+		last = Some(byte_arr?);
+	}
+	Ok(last)
+}
+
+
+/// Returns which slice the byte at `offset` in a blob belongs to, and whether it is in the slice's index or its payload
+///
+/// The indexes are walked from the start up to the slice holding `offset`, so this is O(n) and nothing after that slice is checked.
+/// Use `BlobView::entry_at_offset` for a binary search when the offsets are already cached.
+///
+/// # Errors
+///
+/// Returns `Err(BlobReadError)` if an invalid index is reached before the slice holding `offset`
+///
+/// # Examples
+/// ```
+/// use byte_array_blob::*;
+///
+/// let blob = byte_arrays_to_blob(&[b"abc", b"de"]);
+/// let location = blob_entry_at_offset(&blob, 8).unwrap().unwrap();
+///
+/// assert_eq!(location, EntryLocation { entry_index: 1, payload_range: 11..13, in_header: true });
+/// assert_eq!(blob_entry_at_offset(&blob, 13).unwrap(), None);
+/// ```
+pub fn blob_entry_at_offset(blob: &[u8], offset: usize) -> Result<Option<EntryLocation>, BlobReadError> {
+	let mut iter = blob_iter(blob);
+	let mut idx_start = 0;
+	let mut entry_index = 0;
+	while let Some(byte_arr) = iter.next() {
+		byte_arr?;
+		let end = iter.position();
+		if offset < end {
+			let payload_range = idx_start + 4..end;
+			return Ok(Some(EntryLocation { entry_index, in_header: offset < payload_range.start, payload_range }));
+		}
+		idx_start = end;
+		entry_index += 1;
+	}
+	Ok(None)
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reverse_matches_forward() {
+		let blob = byte_arrays_to_blob(&[&[1u8], &[], &[2u8, 2u8], &[3u8; 9], &[]]);
+		let view = BlobView::new(&blob).unwrap();
+
+		let mut forward: Vec<&[u8]> = view.iter().collect();
+		forward.reverse();
+		let reverse: Vec<&[u8]> = view.iter().rev().collect();
+		assert_eq!(reverse, forward);
+		assert_eq!(view.iter().collect::<Vec<_>>(), blob_to_byte_arrays(&blob).unwrap());
+	}
+
+	#[test]
+	fn nth_back_and_meeting_ends() {
+		let blob = byte_arrays_to_blob(&[&[0u8], &[1u8], &[2u8], &[3u8], &[4u8]]);
+		let view = BlobView::new(&blob).unwrap();
+
+		let mut iter = view.iter();
+		assert_eq!(iter.nth_back(1), Some(&[3u8][..]));
+		assert_eq!(iter.next(), Some(&[0u8][..]));
+		assert_eq!(iter.len(), 2);
+		assert_eq!(iter.next_back(), Some(&[2u8][..]));
+		assert_eq!(iter.next(), Some(&[1u8][..]));
+		assert_eq!(iter.next(), None);
+		assert_eq!(iter.next_back(), None);
+
+		assert_eq!(view.iter().nth_back(10), None);
+	}
+
+	#[test]
+	fn last_array() {
+		assert_eq!(blob_last_array(&[]).unwrap(), None);
+
+		let blob = byte_arrays_to_blob(&[&[1u8], &[]]);
+		assert_eq!(blob_last_array(&blob).unwrap(), Some(&[][..]));
+		assert_eq!(BlobView::new(&blob).unwrap().last(), Some(&[][..]));
+
+		let mut corrupt = blob.clone();
+		corrupt.push(1u8);
+		assert!(blob_last_array(&corrupt).is_err());
+		assert!(BlobView::new(&corrupt).is_err());
+	}
+
+	#[test]
+	fn slice_boundaries() {
+		let blob = Blob::from_byte_arrays(&[&[0u8], &[], &[2u8, 2u8], &[3u8]]);
+		let view = blob.view();
+
+		assert!(view.slice(0..0).is_empty());
+		assert!(view.slice(4..4).is_empty());
+		assert_eq!(view.slice(4..4).to_blob().as_bytes(), Vec::<u8>::new());
+		assert_eq!(view.slice(0..4).iter().collect::<Vec<_>>(), view.iter().collect::<Vec<_>>());
+		assert_eq!(view.slice(0..4).to_blob(), blob);
+
+		let tail = view.slice(2..4);
+		assert_eq!(tail.iter().rev().collect::<Vec<_>>(), vec![&[3u8][..], &[2u8, 2u8]]);
+		assert_eq!(tail.get(2), None);
+		assert_eq!(tail.slice(1..2).get(0), Some(&[3u8][..]));
+		assert_eq!(blob.slice(1..3).to_blob().as_bytes(), byte_arrays_to_blob(&[&[], &[2u8, 2u8]]));
+		assert_eq!(ValidatedBlob::from(view.slice(1..3)).iter().collect::<Vec<_>>(), vec![&[][..], &[2u8, 2u8]]);
+		assert_eq!(BlobView::from(ValidatedBlob::from(view.slice(3..4))).get(0), Some(&[3u8][..]));
+	}
+
+	#[test]
+	#[should_panic]
+	fn slice_past_end() {
+		let blob = byte_arrays_to_blob(&[&[0u8], &[1u8]]);
+		let _ = BlobView::new(&blob).unwrap().slice(1..3);
+	}
+
+	#[test]
+	#[should_panic]
+	#[allow(clippy::reversed_empty_ranges)]
+	fn slice_inverted() {
+		let blob = byte_arrays_to_blob(&[&[0u8], &[1u8]]);
+		let _ = BlobView::new(&blob).unwrap().slice(2..1);
+	}
+
+	#[test]
+	fn entry_at_offset() {
+		let blob = byte_arrays_to_blob(&[&[1u8, 1u8], &[], &[2u8; 3]]);
+		let view = BlobView::new(&blob).unwrap();
+		let location = |entry_index, payload_range, in_header| Some(EntryLocation { entry_index, payload_range, in_header });
+
+		let expected = [
+			(0, location(0, 4..6, true)),
+			(3, location(0, 4..6, true)),
+			(4, location(0, 4..6, false)),
+			(5, location(0, 4..6, false)),
+			(6, location(1, 10..10, true)),
+			(9, location(1, 10..10, true)),
+			(10, location(2, 14..17, true)),
+			(14, location(2, 14..17, false)),
+			(16, location(2, 14..17, false)),
+			(17, None),
+			(usize::MAX, None)
+		];
+		for (offset, expected) in expected {
+			assert_eq!(blob_entry_at_offset(&blob, offset).unwrap(), expected, "offset {}", offset);
+			assert_eq!(view.entry_at_offset(offset), expected, "offset {}", offset);
+			assert_eq!(ValidatedBlob::from(view.clone()).entry_at_offset(offset), expected, "offset {}", offset);
+		}
+
+		// A sliced view only knows about its own slices, but offsets still count from the start of the blob
+		let tail = view.slice(1..3);
+		assert_eq!(tail.entry_at_offset(5), None);
+		assert_eq!(tail.entry_at_offset(6), location(0, 10..10, true));
+		assert_eq!(view.slice(0..1).entry_at_offset(6), None);
+		assert_eq!(blob_entry_at_offset(&[], 0).unwrap(), None);
+
+		// Only the indexes up to the offset are checked
+		let mut corrupt = blob.clone();
+		corrupt.truncate(15);
+		assert_eq!(blob_entry_at_offset(&corrupt, 7).unwrap(), location(1, 10..10, true));
+		assert!(blob_entry_at_offset(&corrupt, 12).is_err());
+	}
+}
@@ -0,0 +1,46 @@
+//! Counts the allocations an owned decode makes, to check `blob_to_arena` takes two however many entries there are.
+//! This is its own test binary because the counting allocator is global, and it holds only one test so nothing else allocates while it counts.
+use byte_array_blob::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+
+fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+	let before = ALLOCATIONS.load(Ordering::Relaxed);
+	let result = f();
+	(result, ALLOCATIONS.load(Ordering::Relaxed) - before)
+}
+
+
+#[test]
+fn arena_allocates_twice() {
+	const ENTRIES: usize = 100_000;
+	let entries: Vec<[u8; 3]> = (0..ENTRIES as u32).map(|i| [i as u8, (i >> 8) as u8, 0]).collect();
+	let blob = byte_arrays_to_blob(&entries.iter().map(|e| &e[..]).collect::<Vec<_>>());
+
+	let (arena, arena_allocations) = count_allocations(|| blob_to_arena(&blob).unwrap());
+	let (byte_vecs, vec_allocations) = count_allocations(|| blob_to_byte_vecs(&blob).unwrap());
+
+	assert_eq!(arena_allocations, 2);
+	assert!(vec_allocations > ENTRIES);
+	assert!(arena.iter().eq(byte_vecs.iter().map(|e| e.as_slice())));
+}
@@ -0,0 +1,28 @@
+//! The seeded generator shared by the integration tests that check random inputs
+//!
+//! It is a small 64 bit xorshift PRNG with fixed seeds, so every run checks the same cases and a failure can be reproduced by its case number.
+
+
+pub struct Rng(u64);
+
+impl Rng {
+	pub fn new(case: u64) -> Self {
+		// Mix the case number so neighbouring seeds diverge quickly
+		Rng(case.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	pub fn below(&mut self, n: usize) -> usize {
+		(self.next_u64() % n as u64) as usize
+	}
+
+	pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+		(0..len).map(|_| self.next_u64() as u8).collect()
+	}
+}
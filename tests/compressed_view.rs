@@ -0,0 +1,42 @@
+//! Checks that `CompressedBlobView::get` does no decompression work, by counting allocations
+use byte_array_blob::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+struct CountingAllocator;
+
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::SeqCst);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+
+#[test]
+fn get_does_not_decompress() {
+	let big = vec![0u8; 1 << 20];
+	let blob = byte_arrays_to_compressed_blob(&[&big, b"small"]);
+	let view = CompressedBlobView::new(&blob).unwrap();
+
+	let before = ALLOCATED_BYTES.load(Ordering::SeqCst);
+	let handle = view.get(0).unwrap();
+	assert_eq!(handle.decompressed_len(), big.len());
+	assert!(handle.compressed_len() < big.len() / 50);
+	assert_eq!(ALLOCATED_BYTES.load(Ordering::SeqCst), before);
+
+	// Reading it does allocate the full decompressed size
+	let data = handle.read_to_vec().unwrap();
+	assert!(ALLOCATED_BYTES.load(Ordering::SeqCst) - before >= big.len());
+	assert_eq!(data, big);
+}
@@ -0,0 +1,24 @@
+//! Checks the committed fixture blob at compile time. If `tests/data/little-endian.blob` is corrupted, this file no longer compiles
+use byte_array_blob::*;
+use byte_array_blob::fixtures::*;
+
+
+#[cfg(target_endian = "little")]
+const FIXTURE: &[u8] = include_bytes!("data/little-endian.blob");
+
+#[cfg(target_endian = "little")]
+const _: () = assert!(const_validate(FIXTURE));
+
+#[cfg(target_endian = "little")]
+const FIXTURE_ENTRIES: usize = match const_count_entries(FIXTURE) {
+	Ok(count) => count,
+	Err(_) => panic!("fixture blob is invalid")
+};
+
+
+#[test]
+#[cfg(target_endian = "little")]
+fn compile_time_count_matches() {
+	assert_eq!(FIXTURE_ENTRIES, fixture_entries().len());
+	assert_eq!(FIXTURE, fixture_blob(BlobFormat::NativeEndian));
+}
@@ -0,0 +1,44 @@
+//! Checks the encoders still produce exactly the bytes of the committed fixture files, and the decoders still read them
+use byte_array_blob::*;
+use byte_array_blob::fixtures::*;
+use std::path::Path;
+
+
+fn read_fixture(format: BlobFormat) -> Option<Vec<u8>> {
+	let name = fixture_file_name(format)?;
+	let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data").join(name);
+	Some(std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e)))
+}
+
+
+#[test]
+fn encoders_match_fixtures() {
+	let entries = fixture_entries();
+	let slices: Vec<&[u8]> = entries.iter().map(|e| e.as_slice()).collect();
+
+	for format in BlobFormat::ALL {
+		let expected = fixture_blob(format);
+		assert_eq!(byte_arrays_to_blob_with_format(&slices, format), expected, "{:?}", format);
+		if let Some(file) = read_fixture(format) {
+			assert_eq!(file, expected, "{:?} fixture file", format);
+		}
+	}
+	assert_eq!(byte_arrays_to_blob(&slices), fixture_blob(BlobFormat::NativeEndian));
+
+	// The native layout is one of the committed files, whichever byte order this machine has
+	if cfg!(target_endian = "little") {
+		assert_eq!(fixture_blob(BlobFormat::NativeEndian), read_fixture(BlobFormat::LittleEndian).unwrap());
+	}
+}
+
+
+#[test]
+fn decoders_accept_fixtures() {
+	let entries = fixture_entries();
+	for format in BlobFormat::ALL {
+		let Some(file) = read_fixture(format) else { continue };
+		assert_eq!(blob_to_byte_arrays_with_format(&file, format).unwrap(), entries, "{:?}", format);
+		assert!(probe_formats(&file).contains(&format), "{:?}", format);
+	}
+	assert_eq!(blob_to_byte_arrays(&fixture_blob(BlobFormat::NativeEndian)).unwrap(), entries);
+}
@@ -0,0 +1,102 @@
+//! Snapshots of the `describe_file` report for the committed fixture file and a damaged copy of it, and the functions a tool would build on
+#![cfg(feature = "fs")]
+use byte_array_blob::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+
+fn fixture_path() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/little-endian.blob")
+}
+
+
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+	let path = std::env::temp_dir().join(format!("byte-array-blob-{}-inspect-{}", std::process::id(), name));
+	fs::write(&path, contents).unwrap();
+	path
+}
+
+
+// The fixture is little-endian, which is only reported as native-endian on a little-endian machine
+#[cfg(target_endian = "little")]
+#[test]
+fn describe_fixture() {
+	let report = describe_file(&fixture_path(), &DescribeOptions::new().with_entries(true)).unwrap();
+	assert_eq!(report, "\
+size:      4644 bytes
+format:    native-endian
+entries:   7
+payload:   4616 bytes (smallest 0, largest 4096)
+overhead:  28 bytes of indexes (0.6%)
+   index       offset       length  preview
+       0            4            0
+       1            8            1  B
+       2           13          255  \\x00\\x01\\x02\\x03\\x04\\x05\\x06\\x07\\x08\\x09\\x0a\\x0b\\x0c\\x0d\\x0e\\x0f… (+239 bytes)
+       3          272         4096  \\x00\\x07\\x0e\\x15\\x1c#*18?FMT[bi… (+4080 bytes)
+       4         4372          256  \\x80\\x81\\x82\\x83\\x84\\x85\\x86\\x87\\x88\\x89\\x8a\\x8b\\x8c\\x8d\\x8e\\x8f… (+240 bytes)
+       5         4632            8  \\xff\\xff\\xff\\xff\\x04\\x00\\x00\\x00
+       6         4644            0
+");
+
+	let short = describe_file(&fixture_path(), &DescribeOptions::new().with_entries(true).with_max_rows(2).with_preview_len(0)).unwrap();
+	assert!(short.ends_with("\
+   index       offset       length  preview
+       0            4            0
+       1            8            1… (+1 byte)
+... 5 more entries
+"), "{}", short);
+	assert!(!describe_file(&fixture_path(), &DescribeOptions::new()).unwrap().contains("preview"));
+}
+
+
+// The native-endian walk of the damaged file depends on the byte order
+#[cfg(target_endian = "little")]
+#[test]
+fn describe_damaged_fixture() {
+	let mut damaged = fs::read(fixture_path()).unwrap();
+	damaged.truncate(damaged.len() - 3);
+	let path = temp_file("damaged", &damaged);
+
+	assert_eq!(describe_file(&path, &DescribeOptions::new().with_entries(true)).unwrap(), "\
+size:      4641 bytes
+format:    unknown, no layout reads the whole file
+  native-endian      6 entries, then TruncatedIndex(4640)
+  little-endian      6 entries, then TruncatedIndex(4640)
+  little-endian-64   0 entries, then NotABlob { first_header: 4294967295 }
+  length-prefixed    1 entries, then InvalidEncodedIndex(68686)
+");
+	assert!(matches!(list_entries(&path), Err(BlobFileError::Read(BlobReadError::UnknownFormat))));
+	let out = std::env::temp_dir().join(format!("byte-array-blob-{}-inspect-damaged-extracted", std::process::id()));
+	assert!(matches!(extract_entry_to_file(&path, 0, &out), Err(BlobFileError::Read(BlobReadError::UnknownFormat))));
+	assert!(!out.exists());
+	fs::remove_file(path).unwrap();
+}
+
+
+#[test]
+fn list_and_extract() {
+	let entries = list_entries(&fixture_path()).unwrap();
+	assert_eq!(entries.len(), 7);
+	assert_eq!(entries[3], EntrySummary { index: 3, offset: 272, len: 4096 });
+
+	let out = std::env::temp_dir().join(format!("byte-array-blob-{}-inspect-extracted", std::process::id()));
+	assert_eq!(extract_entry_to_file(&fixture_path(), 3, &out).unwrap(), Some(4096));
+	assert_eq!(fs::read(&out).unwrap(), fs::read(fixture_path()).unwrap()[272..272 + 4096]);
+	fs::remove_file(&out).unwrap();
+	assert_eq!(extract_entry_to_file(&fixture_path(), 7, &out).unwrap(), None);
+	assert!(!out.exists());
+
+	// Every entry, including the last, is found by reading the indexes up to it
+	let fixture = fs::read(fixture_path()).unwrap();
+	for entry in &entries {
+		assert_eq!(extract_entry_to_file(&fixture_path(), entry.index, &out).unwrap(), Some(entry.len));
+		let start = entry.offset as usize;
+		assert_eq!(fs::read(&out).unwrap(), fixture[start..start + entry.len as usize]);
+		fs::remove_file(&out).unwrap();
+	}
+
+	let empty = temp_file("empty", &[]);
+	assert!(list_entries(&empty).unwrap().is_empty());
+	assert!(describe_file(&empty, &DescribeOptions::new().with_entries(true)).unwrap().ends_with("format:    native-endian\nentries:   0\npayload:   0 bytes\noverhead:  0 bytes of indexes\n"));
+	fs::remove_file(empty).unwrap();
+}
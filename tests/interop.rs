@@ -0,0 +1,16 @@
+//! Checks the canonical JSON of the fixture entries, which other implementations of the format can test against
+use byte_array_blob::*;
+use byte_array_blob::fixtures::*;
+
+
+#[test]
+fn fixture_round_trip() {
+	let blob = fixture_blob(BlobFormat::NativeEndian);
+	let json = blob_to_canonical_json(&blob).unwrap();
+	assert_eq!(canonical_json_to_blob(&json).unwrap(), blob);
+
+	let byte_order = if cfg!(target_endian = "little") { "little" } else { "big" };
+	let prefix = format!(r#"{{"version":1,"format":"native-endian","byte_order":"{}","entries":["","Qg==","AAECAwQFBgcICQoL"#, byte_order);
+	assert!(json.starts_with(&prefix), "{}", &json[..100]);
+	assert!(json.ends_with(r#"","/////wQAAAA=",""]}"#), "{}", &json[json.len() - 100..]);
+}
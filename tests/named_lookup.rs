@@ -0,0 +1,42 @@
+//! Checks that `blob_find_named` and `blob_named_keys` don't allocate, by counting allocations
+use byte_array_blob::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+
+#[test]
+fn lookup_does_not_allocate() {
+	let names: Vec<String> = (0..1000).map(|i| format!("entry-{:04}", i)).collect();
+	let payloads: Vec<Vec<u8>> = (0..1000).map(|i| vec![i as u8; i % 17]).collect();
+	let entries: Vec<(&str, &[u8])> = names.iter().zip(&payloads).map(|(name, data)| (name.as_str(), data.as_slice())).collect();
+	let blob = named_byte_arrays_to_blob(&entries);
+
+	let before = ALLOCATIONS.load(Ordering::SeqCst);
+	let found = blob_find_named(&blob, "entry-0999").unwrap();
+	let missing = blob_find_named(&blob, "entry-1000").unwrap();
+	let key_count = blob_named_keys(&blob).unwrap().count();
+	assert_eq!(ALLOCATIONS.load(Ordering::SeqCst), before);
+
+	assert_eq!(found, Some(&payloads[999][..]));
+	assert_eq!(missing, None);
+	assert_eq!(key_count, 1000);
+}
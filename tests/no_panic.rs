@@ -0,0 +1,215 @@
+//! Feeds mutated blobs of every layout to every decode function, checking none of them panic.
+//!
+//! This is the same set of calls as the fuzz target, run over inputs from a fixed seed so it needs no fuzzer. Mutating valid blobs of each layout
+//! gets much further into the decoders than random bytes, which rarely make it past the first index. A debug build checks a few thousand cases;
+//! for the full run use `cargo test --release --features all --test no_panic`, which checks a few million. `BLOB_NO_PANIC_CASES` overrides either count.
+use byte_array_blob::*;
+use std::panic;
+
+mod common;
+use common::Rng;
+
+
+fn random_entries(rng: &mut Rng) -> Vec<Vec<u8>> {
+	let count = rng.below(8);
+	(0..count).map(|_| {
+		let len = match rng.below(3) {
+			0 => 0,
+			1 => rng.below(8),
+			_ => rng.below(64)
+		};
+		rng.bytes(len)
+	}).collect()
+}
+
+
+fn cases() -> u64 {
+	match std::env::var("BLOB_NO_PANIC_CASES") {
+		Ok(cases) => cases.parse().expect("BLOB_NO_PANIC_CASES should be a number"),
+		Err(_) if cfg!(debug_assertions) => 20_000,
+		Err(_) => 3_000_000
+	}
+}
+
+
+/// A valid blob in one of the layouts the crate writes
+fn seed(rng: &mut Rng) -> Vec<u8> {
+	let entries = random_entries(rng);
+	let slices: Vec<&[u8]> = entries.iter().map(|e| e.as_slice()).collect();
+	let names: Vec<String> = (0..entries.len()).map(|i| format!("entry{}", i)).collect();
+	let named: Vec<(&str, &[u8])> = names.iter().map(|name| name.as_str()).zip(slices.iter().copied()).collect();
+	match rng.below(14) {
+		0 | 1 => byte_arrays_to_blob(&slices),
+		2 => byte_arrays_to_blob_with_format(&slices, BlobFormat::ALL[rng.below(BlobFormat::ALL.len())]),
+		3 => byte_arrays_to_compressed_blob(&slices),
+		4 => typed_byte_arrays_to_blob(&named),
+		5 => named_byte_arrays_to_blob(&named),
+		6 => byte_arrays_to_blob_sparse(&slices),
+		7 => byte_arrays_to_blob_padded(&slices, 8).0,
+		8 => interleave_to_blob(&[&slices, &slices[..slices.len() / 2], &[]]).unwrap(),
+		9 => split_by_access(&byte_arrays_to_blob(&slices), |i| i % 2 == 0).unwrap().to_blob(),
+		10 => byte_arrays_to_blob_with_codec(&slices, &PackBitsCodec).unwrap(),
+		11 => byte_arrays_to_blob_tagged(&slices, rng.next_u64()),
+		12 => {
+			let mut builder = BlobBuilder::flagged();
+			for (i, slice) in slices.iter().enumerate() {
+				match i % 3 {
+					0 => builder.push_sensitive(slice).unwrap(),
+					_ => builder.push(slice).unwrap()
+				}
+			}
+			builder.finish().unwrap()
+		},
+		_ => intern_strs_to_blob(&names.iter().map(|name| name.as_str()).collect::<Vec<_>>())
+	}
+}
+
+
+/// Damages a blob in one to four places, favouring the indexes since that is where the decoders branch
+fn mutate(rng: &mut Rng, blob: &mut Vec<u8>) {
+	for _ in 0..1 + rng.below(4) {
+		let len = blob.len();
+		match rng.below(6) {
+			0 if len > 0 => blob[rng.below(len)] ^= 1 << rng.below(8),
+			1 if len >= 4 => {
+				let at = rng.below(len - 3);
+				let value = match rng.below(5) {
+					0 => 0,
+					1 => 4,
+					2 => len as u32,
+					3 => u32::MAX,
+					_ => rng.next_u64() as u32 % (len as u32 + 8)
+				};
+				blob[at..at + 4].copy_from_slice(&value.to_ne_bytes());
+			},
+			2 => blob.truncate(rng.below(len + 1)),
+			3 => {
+				let extra = rng.below(9);
+				blob.extend(rng.bytes(extra));
+			},
+			4 if len > 0 => {
+				let start = rng.below(len);
+				let copied = blob[start..start + rng.below(len - start + 1)].to_vec();
+				let at = rng.below(len + 1);
+				blob.splice(at..at, copied);
+			},
+			_ => blob.reverse()
+		}
+	}
+}
+
+
+fn decode_everything(data: &[u8]) {
+	let _ = blob_to_byte_arrays(data);
+	let _ = blob_iter(data).count();
+	let _ = count_arrays(data);
+	let _ = looks_like_blob(data);
+	let _ = const_count_entries(data);
+	let _ = blob_to_byte_vecs(data);
+	let _ = blob_to_byte_vecs_bounded(data, 64);
+	let _ = blob_get_many(data, &[0, 3, 1, usize::MAX]);
+	for entry in BlobReader::new(data).with_budget(1 << 16) {
+		if entry.is_err() {
+			break;
+		}
+	}
+	let mut decoder = BlobDecoder::new().with_budget(1 << 16);
+	for chunk in data.chunks(3) {
+		if decoder.push(chunk).is_err() {
+			break;
+		}
+		while decoder.next_entry().is_some() {}
+	}
+	let _ = decoder.finish();
+	let _ = verify_blob_stream(&mut &data[..]);
+
+	let _ = blob_to_typed_byte_arrays(data);
+	let _ = blob_to_archive(data);
+	if let Ok(view) = BlobView::new(data) {
+		let _ = view.iter().rev().count();
+		let _ = view.get(view.len() / 2);
+	}
+	let _ = blob_last_array(data);
+	let _ = blob_entry_at_offset(data, data.len() / 2);
+	if let Ok(view) = CompressedBlobView::new(data) {
+		for handle in view.iter() {
+			if handle.decompressed_len() <= 1 << 16 {
+				let _ = handle.read();
+			}
+		}
+	}
+	let _ = decode_any(data);
+	for format in BlobFormat::ALL {
+		let _ = blob_to_byte_arrays_with_format(data, format);
+		let _ = blob_content_hash_with_format(data, format);
+	}
+
+	let needle = &data[..data.len().min(2)];
+	let _ = blob_positions_of(data, needle);
+	let _ = recover_blob(data);
+	let _ = collect_frames(data);
+	let _ = blob_find_named(data, "entry1");
+	if let Ok(view) = NamedBlobView::new_unchecked_order(data) {
+		let _ = view.get("entry0");
+	}
+	let _ = blob_to_fixed_arrays::<8>(data);
+	let _ = blob_skip_padding(data);
+	let _ = blob_to_numeric_arrays::<u32>(data, Endianness::Big);
+	let _ = blob_sparse_to_byte_arrays_with_options(data, &DecodeOptions::new().with_max_entries(1 << 16));
+	if let Ok(view) = MultiBlobView::new(vec![data, data]) {
+		let _ = view.get(view.len() / 2);
+	}
+	let mut cursor = BlobCursor::new(data);
+	while let Ok(Some(_)) = cursor.peek_len() {
+		let _ = cursor.skip();
+	}
+	if let Ok(validated) = ValidatedBlob::validate(data) {
+		let _ = validated.iter().rev().count();
+	}
+	if let Ok(overlay) = BlobOverlay::load_overlay(data, data) {
+		let _ = overlay.materialize();
+	}
+	let _ = blob_to_canonical_json(data);
+	if let Ok(sections) = Sections::parse(data) {
+		let _ = sections.get(SectionId(0));
+	}
+	let _ = blob_to_interned_strs(data);
+	let _ = blob_to_arena(data);
+	let _ = EntryArena::try_from_blob(data);
+	let _ = blob_to_byte_vecs_try(data);
+	let _ = blob_provenance(data);
+	if let Ok(payloads) = blob_without_provenance(data) {
+		assert!(blob_to_byte_arrays(payloads).is_ok());
+	}
+	let _ = blob_to_aligned_entries(data, 16);
+	let _ = blob_to_byte_vecs_with_codec(data, &PackBitsCodec);
+	let _ = blob_deinterleave(data, 3);
+	let _ = SplitBlobView::from_blob(data);
+	let _ = blob_sniff(data, 8);
+	if let Ok(Some(tag)) = blob_schema_tag(data) {
+		let _ = blob_to_byte_arrays_tagged(data, tag);
+	}
+	let _ = blob_to_flagged_entries(data);
+	let _ = blob_to_byte_arrays_redacted(data);
+	let _ = blob_redact(data);
+	if let Ok(view) = EncryptedBlobView::new(data) {
+		let _ = view.decrypt_range(0..view.len(), &HmacSha256Cipher::new(b"key"));
+	}
+
+	let mut iter = blob_iter(data);
+	iter.next();
+	let _ = resume_blob_iter(data, iter.checkpoint()).map(|resumed| resumed.count());
+}
+
+
+#[test]
+fn mutated_blobs_never_panic() {
+	for case in 0..cases() {
+		let mut rng = Rng::new(case);
+		let mut blob = seed(&mut rng);
+		mutate(&mut rng, &mut blob);
+		if panic::catch_unwind(|| decode_everything(&blob)).is_err() {
+			panic!("case {} panicked on {:?}", case, blob);
+		}
+	}
+}
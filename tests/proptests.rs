@@ -0,0 +1,105 @@
+//! Property tests over randomly generated inputs.
+//!
+//! Inputs come from the seeded generator in `common`, so every run checks the same cases and a failure can be reproduced by its case number.
+use byte_array_blob::*;
+
+mod common;
+use common::Rng;
+
+
+const CASES: u64 = 2000;
+
+
+/// Entry lists biased towards the awkward cases: no entries, zero-length entries, and small entries
+fn random_entries(rng: &mut Rng) -> Vec<Vec<u8>> {
+	let count = match rng.below(4) {
+		0 => 0,
+		1 => 1,
+		_ => rng.below(20)
+	};
+	(0..count).map(|_| {
+		let len = match rng.below(3) {
+			0 => 0,
+			1 => rng.below(8),
+			_ => rng.below(300)
+		};
+		rng.bytes(len)
+	}).collect()
+}
+
+
+fn as_slices(entries: &[Vec<u8>]) -> Vec<&[u8]> {
+	entries.iter().map(|e| e.as_slice()).collect()
+}
+
+
+/// Checks every returned slice points inside `blob`
+fn assert_within(blob: &[u8], slices: &[&[u8]], case: u64) {
+	let range = blob.as_ptr_range();
+	for slice in slices {
+		let slice_range = slice.as_ptr_range();
+		assert!(range.start <= slice_range.start && slice_range.end <= range.end, "case {}: slice outside of input", case);
+	}
+}
+
+
+#[test]
+fn round_trip() {
+	for case in 0..CASES {
+		let entries = random_entries(&mut Rng::new(case));
+		let slices = as_slices(&entries);
+
+		let blob = byte_arrays_to_blob(&slices);
+		let decoded = blob_to_byte_arrays(&blob).unwrap_or_else(|e| panic!("case {}: {}", case, e));
+		assert_eq!(decoded, slices, "case {}", case);
+		assert_eq!(count_arrays(&blob), Ok(slices.len()), "case {}", case);
+	}
+}
+
+
+#[test]
+fn encoded_len_matches_output() {
+	for case in 0..CASES {
+		let entries = random_entries(&mut Rng::new(case));
+		let slices = as_slices(&entries);
+
+		assert_eq!(encoded_len(&slices), byte_arrays_to_blob(&slices).len(), "case {}", case);
+	}
+}
+
+
+#[test]
+fn decode_random_bytes_never_panics() {
+	for case in 0..CASES {
+		let mut rng = Rng::new(case);
+		let len = rng.below(64);
+		let blob = rng.bytes(len);
+
+		if let Ok(slices) = blob_to_byte_arrays(&blob) {
+			assert_within(&blob, &slices, case);
+		}
+	}
+}
+
+
+#[test]
+fn decode_mutated_blobs_never_panics() {
+	// Random bytes rarely form plausible indexes, so also mutate and cut valid blobs
+	for case in 0..CASES {
+		let mut rng = Rng::new(case);
+		let entries = random_entries(&mut rng);
+		let mut blob = byte_arrays_to_blob(&as_slices(&entries));
+
+		if !blob.is_empty() {
+			let flip = rng.below(blob.len());
+			blob[flip] ^= 1 << rng.below(8);
+
+			let cut = rng.below(blob.len() + 1);
+			blob.truncate(cut);
+		}
+
+		if let Ok(slices) = blob_to_byte_arrays(&blob) {
+			assert_within(&blob, &slices, case);
+		}
+	}
+}
@@ -0,0 +1,44 @@
+//! Checks the synthetic blob generator gives the same entries for the same seed, and that what it makes is valid
+use byte_array_blob::*;
+use byte_array_blob::testutil::*;
+
+
+#[test]
+fn same_seed_same_bytes() {
+	for compressible in [false, true] {
+		let spec = GenSpec { entries: 200, min_len: 0, max_len: 300, compressible };
+		let entries = generate_entries(0xB10B, &spec);
+		assert_eq!(entries, generate_entries(0xB10B, &spec));
+		assert_ne!(entries, generate_entries(0xB10C, &spec));
+
+		let blob = generate_blob(0xB10B, &spec);
+		assert_eq!(blob, generate_blob(0xB10B, &spec));
+		assert_eq!(blob_to_byte_vecs(&blob).unwrap(), entries);
+		assert!(entries.iter().all(|entry| entry.len() <= 300));
+	}
+}
+
+
+#[test]
+fn output_is_pinned() {
+	// These bytes must never change, or benchmarks run against generated data stop being comparable
+	let random = generate_entries(1, &GenSpec { entries: 2, min_len: 3, max_len: 6, compressible: false });
+	let repetitive = generate_entries(1, &GenSpec { entries: 1, min_len: 12, max_len: 12, compressible: true });
+	assert_eq!(random, [vec![103, 236, 142, 101], vec![11, 201, 66, 238, 144]]);
+	assert_eq!(repetitive, [vec![119; 12]]);
+}
+
+
+#[test]
+fn shapes() {
+	let fixed = generate_entries(9, &GenSpec { entries: 50, min_len: 8, max_len: 8, compressible: false });
+	assert!(fixed.iter().all(|entry| entry.len() == 8));
+	assert_eq!(generate_blob(9, &GenSpec { entries: 0, min_len: 0, max_len: 10, compressible: true }), Vec::<u8>::new());
+
+	// Repetitive payloads draw from only 16 byte values and mostly repeat the byte before
+	let spec = GenSpec { entries: 20, min_len: 1000, max_len: 1000, compressible: true };
+	for entry in generate_entries(3, &spec) {
+		assert!(entry.iter().all(|byte| byte % 17 == 0));
+		assert!(entry.windows(2).filter(|pair| pair[0] != pair[1]).count() < 200);
+	}
+}